@@ -110,5 +110,44 @@ export declare class CheckBox extends Widget {
     checked: BoolValue;
     constructor(text: string, checked: BoolValue);
 }
+export interface PluginDependency {
+    uuid: string;
+    versions?: {
+        min: [number, number, number];
+        max?: [number, number, number];
+    };
+}
+export interface PluginInfo {
+    uuid: string;
+    name: string;
+    description?: string;
+    version?: [number, number, number];
+    author?: string;
+    dependencies?: PluginDependency[];
+}
+export type MessageHandler = (args: any, action: string, sender: string) => void;
+/** Declares the script as a plugin, call it once at the top level of the script. */
+export declare function registerPlugin(info: PluginInfo): void;
+/** Publishes a message to the other plugins, `args` must be JSON serializable. */
+export declare function publish(action: string, args?: any): void;
+/** Calls `handler` for every action starting with `prefix` ("*" observes everything), returns a function that removes the subscription. */
+export declare function subscribe(prefix: string, handler: MessageHandler): () => void;
+export interface PortDefinition {
+    id: string;
+    name: string;
+    port_type?: string;
+}
+export interface NodeType {
+    /** Unique across plugins, such as "math.add". */
+    id: string;
+    title: string;
+    category?: string;
+    inputs?: PortDefinition[];
+    outputs?: PortDefinition[];
+}
+/** Defines node types for the graphs of the node editor, call it after registerPlugin. Extensions can list them under `contributes.node_types` instead. */
+export declare function registerNodeTypes(types: NodeType[]): void;
+/** Renders a window contributed in extension.json under `contributes.windows` with the same id. */
+export declare function registerWindow(id: string, render: (ui: Ui) => void): void;
 export {};
 //# sourceMappingURL=designer.d.ts.map
\ No newline at end of file
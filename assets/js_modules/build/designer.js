@@ -157,3 +157,56 @@ globalThis._ui_main = function (ui_object_pointer) {
     const ui = new Ui(ui_object_pointer);
     globalThis.ui_main(ui);
 };
+/////////////////////////////////////////
+const __subscriptions = [];
+let __plugin_info = undefined;
+/** Declares the script as a plugin, call it once at the top level of the script. */
+export function registerPlugin(info) {
+    __plugin_info = info;
+}
+/** Publishes a message to the other plugins, `args` must be JSON serializable. */
+export function publish(action, args = null) {
+    //implemented in rust
+    globalThis.__designer_publish(action, JSON.stringify(args !== null && args !== void 0 ? args : null));
+}
+/** Calls `handler` for every action starting with `prefix` ("*" observes everything), returns a function that removes the subscription. */
+export function subscribe(prefix, handler) {
+    const subscription = { pattern: prefix.endsWith("*") ? prefix : prefix + "*", handler };
+    __subscriptions.push(subscription);
+    return () => {
+        const index = __subscriptions.indexOf(subscription);
+        if (index >= 0)
+            __subscriptions.splice(index, 1);
+    };
+}
+/** Defines node types for the graphs of the node editor, call it after registerPlugin. Extensions can list them under `contributes.node_types` instead. */
+export function registerNodeTypes(types) {
+    var _a;
+    publish("designer.node_graph.management", { RegisterNodeTypes: { owner: (_a = __plugin_info === null || __plugin_info === void 0 ? void 0 : __plugin_info.uuid) !== null && _a !== void 0 ? _a : "", types } });
+}
+const __windows = new Map();
+/** Renders a window contributed in extension.json under `contributes.windows` with the same id. */
+export function registerWindow(id, render) {
+    __windows.set(id, render);
+}
+globalThis.__designer_render_window = function (id, ui_object_pointer) {
+    const render = __windows.get(id);
+    if (render === undefined)
+        throw Error(`Window "${id}" is not registered, call registerWindow("${id}", ...) in the entry module.`);
+    render(new Ui(ui_object_pointer));
+};
+globalThis.__designer_plugin_info = function () {
+    if (__plugin_info === undefined)
+        return null;
+    const subscriptions = [...new Set(__subscriptions.map((subscription) => subscription.pattern))];
+    return JSON.stringify(Object.assign(Object.assign({}, __plugin_info), { subscriptions }));
+};
+globalThis.__designer_handle_message = function (sender, action, args_json) {
+    const args = JSON.parse(args_json);
+    for (const subscription of [...__subscriptions]) {
+        const prefix = subscription.pattern.slice(0, -1);
+        if (action.startsWith(prefix)) {
+            subscription.handler(args, action, sender);
+        }
+    }
+};
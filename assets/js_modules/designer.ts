@@ -169,3 +169,97 @@ export class CheckBox extends Widget {
     (globalThis as any).ui_main(ui);
 }
 
+/////////////////////////////////////////
+// Message bus, a script calling registerPlugin takes part in DesignerCore messaging as a plugin.
+
+export interface PluginDependency {
+    uuid: string
+    versions?: {min: [number, number, number], max?: [number, number, number]}
+}
+
+export interface PluginInfo {
+    uuid: string
+    name: string
+    description?: string
+    version?: [number, number, number]
+    author?: string
+    dependencies?: PluginDependency[]
+}
+
+export type MessageHandler = (args: any, action: string, sender: string) => void
+
+const __subscriptions: {pattern: string, handler: MessageHandler}[] = []
+let __plugin_info: PluginInfo | undefined = undefined
+
+/** Declares the script as a plugin, call it once at the top level of the script. */
+export function registerPlugin(info: PluginInfo): void {
+    __plugin_info = info
+}
+
+/** Publishes a message to the other plugins, `args` must be JSON serializable. */
+export function publish(action: string, args: any = null): void {
+    //implemented in rust
+    (globalThis as any).__designer_publish(action, JSON.stringify(args ?? null))
+}
+
+/** Calls `handler` for every action starting with `prefix` ("*" observes everything), returns a function that removes the subscription. */
+export function subscribe(prefix: string, handler: MessageHandler): () => void {
+    const subscription = {pattern: prefix.endsWith("*") ? prefix : prefix + "*", handler}
+    __subscriptions.push(subscription)
+    return () => {
+        const index = __subscriptions.indexOf(subscription)
+        if (index >= 0) __subscriptions.splice(index, 1)
+    }
+}
+
+/////////////////////////////////////////
+// Node types of the node editor, ports connect when their types are the same or one of them is "any".
+
+export interface PortDefinition {
+    id: string
+    name: string
+    port_type?: string
+}
+
+export interface NodeType {
+    /** Unique across plugins, such as "math.add". */
+    id: string
+    title: string
+    category?: string
+    inputs?: PortDefinition[]
+    outputs?: PortDefinition[]
+}
+
+/** Defines node types for the graphs of the node editor, call it after registerPlugin. Extensions can list them under `contributes.node_types` instead. */
+export function registerNodeTypes(types: NodeType[]): void {
+    publish("designer.node_graph.management", {RegisterNodeTypes: {owner: __plugin_info?.uuid ?? "", types}})
+}
+
+const __windows: Map<string, (ui: Ui) => void> = new Map()
+
+/** Renders a window contributed in extension.json under `contributes.windows` with the same id. */
+export function registerWindow(id: string, render: (ui: Ui) => void): void {
+    __windows.set(id, render)
+}
+
+(globalThis as any).__designer_render_window = function(id: string, ui_object_pointer: number) {
+    const render = __windows.get(id)
+    if (render === undefined) throw Error(`Window "${id}" is not registered, call registerWindow("${id}", ...) in the entry module.`)
+    render(new Ui(ui_object_pointer))
+}
+
+(globalThis as any).__designer_plugin_info = function(): string | null {
+    if (__plugin_info === undefined) return null
+    const subscriptions = [...new Set(__subscriptions.map((subscription) => subscription.pattern))]
+    return JSON.stringify({...__plugin_info, subscriptions})
+}
+
+(globalThis as any).__designer_handle_message = function(sender: string, action: string, args_json: string) {
+    const args = JSON.parse(args_json)
+    for (const subscription of [...__subscriptions]) {
+        const prefix = subscription.pattern.slice(0, -1)
+        if (action.startsWith(prefix)) {
+            subscription.handler(args, action, sender)
+        }
+    }
+}
@@ -0,0 +1,24 @@
+import {Button, Label, publish, registerPlugin, registerWindow, subscribe} from "designer";
+
+registerPlugin({
+    uuid: "{{package_name}}",
+    name: "{{name}}",
+    author: "{{author}}",
+    version: [0, 0, 1],
+})
+
+let greetings = 0
+
+subscribe("{{package_name}}.hello", () => {
+    greetings++
+    publish("designer.logger.log", {Generate: {category: "Info", content: "Hello from {{name}}!"}})
+})
+
+registerWindow("main", (ui) => {
+    ui.add(new Label(`{{name}} said hello ${greetings} time(s).`))
+    ui.add(new Button("Say hello").withResponse((response) => {
+        if (response.clicked) {
+            publish("{{package_name}}.hello")
+        }
+    }))
+})
@@ -0,0 +1,753 @@
+use std::{any::TypeId, cell::RefCell, collections::{HashMap, VecDeque}, fmt::Display, ops::Deref, path::PathBuf, rc::Rc, sync::Arc};
+
+use log::warn;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+
+use super::{commands::ICommand, history::{History, UndoEntry}, plugin::{parse_args, IPlugin, PluginError, PluginInfo, PluginStatus, Version}, plugin_graph::{dependents, initialization_order, PluginGraphError}, plugin_settings::{PluginSettings, SettingScope}, plugin_state::FileStateStorage, request::{RequestError, RequestId}, plugins::{document_manager::DocumentManager, event_interceptor::SystemEventInterceptor, file_manager::FileManager, logger::{LogCommand, Logger}, node_graph_manager::NodeGraphManager, notification_center::NotificationCenter, preferences::Preferences, project_manager::ProjectManager, search_manager::SearchManager, window_manager::WindowManager}};
+
+struct QueuedMessage {
+    sender: String,
+    /// uuid of the only plugin that should handle the message, `None` means every subscriber.
+    target: Option<String>,
+    action: String,
+    args: Value,
+}
+
+/// Plugins that receive an action, resolved once per action from the declared subscriptions.
+#[derive(Debug, Clone, Default)]
+struct Route {
+    handlers: Vec<usize>,
+    /// subscribed with `*`, they see everything but do not count as handling an action.
+    observers: Vec<usize>,
+}
+
+pub struct DesignerCore {
+    plugins: Vec<Rc<RefCell<dyn IPlugin>>>,
+    /// index into `plugins` for every native plugin type.
+    plugin_types: HashMap<TypeId, usize>,
+    notify_ui: Box<dyn Fn()>,
+    /// Same as `notify_ui` but callable from background threads such as the file watcher.
+    background_notifier: Option<Arc<dyn Fn() + Send + Sync>>,
+    message_loop_busy: bool,
+    message_queue: VecDeque<QueuedMessage>,
+    /// Messages for plugins that were busy when the messages were dispatched, by plugin index. They are delivered once the plugin is free.
+    deferred_messages: Vec<(usize, QueuedMessage)>,
+    routes: HashMap<String, Route>,
+    /// `info()` of every plugin captured at registration, plugins may be borrowed while routing.
+    plugin_infos: Vec<PluginInfo>,
+    plugin_indices: HashMap<String, usize>,
+    initialized: Vec<bool>,
+    /// `initialize_plugins` has run, plugins registered afterwards are started right away.
+    started: bool,
+    next_request_id: u64,
+    replies: HashMap<RequestId, (String, Result<Value, RequestError>)>,
+    /// `None` keeps plugin choices in memory only.
+    user_config_dir: Option<PathBuf>,
+    user_settings: PluginSettings,
+    /// directory of the attached project and its plugin choices.
+    project_settings: Option<(PathBuf, PluginSettings)>,
+    history: History,
+}
+
+impl DesignerCore {
+    /// Version of the plugin facing API, checked against `PluginInfo::api_version`.
+    pub const API_VERSION: Version = (0, 1, 0);
+
+    /// `notifier` is invoked whenever a plugin wants the front-end to refresh, the GUI passes a repaint request there.
+    pub fn new(notifier: impl Fn() + 'static) -> Self {
+        Self::with_config_dir(notifier, dirs::config_dir().map(|directory| directory.join("bluebird-ide")))
+    }
+
+    /// Core without any front-end attached, used by integration tests and scripting. It does not touch the user config directory.
+    pub fn headless() -> Self {
+        Self::with_config_dir(|| {}, None)
+    }
+
+    fn with_config_dir(notifier: impl Fn() + 'static, user_config_dir: Option<PathBuf>) -> Self {
+        let user_settings = user_config_dir.as_deref().map(|directory| PluginSettings::load(&PluginSettings::user_file(directory))).unwrap_or_default();
+        let mut core = Self {
+            notify_ui: Box::new(notifier),
+            background_notifier: None,
+            plugins: vec![],
+            plugin_types: HashMap::new(),
+            message_loop_busy: false,
+            message_queue:VecDeque::new(),
+            deferred_messages: vec![],
+            routes: HashMap::new(),
+            plugin_infos: vec![],
+            plugin_indices: HashMap::new(),
+            initialized: vec![],
+            started: false,
+            next_request_id: 0,
+            replies: HashMap::new(),
+            user_config_dir,
+            user_settings,
+            project_settings: None,
+            history: History::default(),
+        };
+        core.register_plugin(SystemEventInterceptor::new()).unwrap();
+        core.register_plugin(Preferences::new()).unwrap();
+        core.register_plugin(Logger::new()).unwrap();
+        core.register_plugin(ProjectManager::new()).unwrap();
+        core.register_plugin(WindowManager::new()).unwrap();
+        core.register_plugin(FileManager::new()).unwrap();
+        core.register_plugin(DocumentManager::new()).unwrap();
+        core.register_plugin(SearchManager::new()).unwrap();
+        core.register_plugin(NodeGraphManager::new()).unwrap();
+        core.register_plugin(NotificationCenter::new()).unwrap();
+        core
+    }
+
+    /// Adds a native plugin, it becomes reachable through `with_plugin` by its type. A type or uuid can only be registered once.
+    pub fn register_plugin<T: IPlugin>(&mut self, plugin: T) -> Result<(), PluginGraphError> {
+        if self.plugin_types.contains_key(&TypeId::of::<T>()) {
+            return Err(PluginGraphError::DuplicatePlugin(std::any::type_name::<T>().to_string()));
+        }
+        let index = self.plugins.len();
+        self.register_dynamic_plugin(plugin)?;
+        self.plugin_types.insert(TypeId::of::<T>(), index);
+        Ok(())
+    }
+
+    /// Adds a plugin that is only known by its uuid, such as a script loaded at runtime. Several instances of the same type may be registered.
+    pub fn register_dynamic_plugin(&mut self, plugin: impl IPlugin) -> Result<(), PluginGraphError> {
+        let info = plugin.info();
+        if self.plugin_indices.contains_key(&info.uuid) {
+            return Err(PluginGraphError::DuplicatePlugin(info.uuid));
+        }
+        let index = self.plugins.len();
+        self.plugin_indices.insert(info.uuid.clone(), index);
+        self.plugin_infos.push(info);
+        self.plugins.push(Rc::new(RefCell::new(plugin)));
+        self.initialized.push(false);
+        self.routes.clear();
+        if self.started {
+            self.load_plugin_state(index, SettingScope::User);
+            if self.project_settings.is_some() {
+                self.load_plugin_state(index, SettingScope::Project);
+            }
+            self.apply_plugin_settings();
+        }
+        Ok(())
+    }
+
+    /// Replaces the cached info of a plugin whose subscriptions changed at runtime, callable from the plugin's own handler.
+    pub fn update_plugin_info(&mut self, info: PluginInfo) -> Result<(), PluginGraphError> {
+        let index = *self.plugin_indices.get(&info.uuid).ok_or_else(|| PluginGraphError::UnknownPlugin(info.uuid.clone()))?;
+        self.plugin_infos[index] = info;
+        self.routes.clear();
+        Ok(())
+    }
+
+    /// Initializes and enables plugins after their dependencies, plugins with missing, incompatible or cyclic dependencies stay disabled.
+    pub fn initialize_plugins(&mut self) -> Result<(), Vec<PluginGraphError>> {
+        self.load_plugin_states(SettingScope::User);
+        let (order, errors) = initialization_order(&self.plugin_infos, Self::API_VERSION);
+        for index in order {
+            let plugin = self.plugins[index].clone();
+            let mut borrows = plugin.borrow_mut();
+            borrows.initialize(self);
+            borrows.enable(self);
+            self.initialized[index] = true;
+        }
+        self.started = true;
+        self.publish_message_from_queue();
+        // reported after the loop, the logger may come later in the order.
+        for error in &errors {
+            log::error!("{}", error);
+            self.publish_message("designer.core", &LogCommand::Generate { category: String::from("Error"), content: error.to_string() });
+        }
+        self.apply_plugin_settings();
+        if errors.is_empty() {Ok(())} else {Err(errors)}
+    }
+
+    /// Enables a plugin, its disabled dependencies are enabled first.
+    pub fn enable_plugin(&mut self, uuid: &str) -> Result<(), PluginGraphError> {
+        let index = *self.plugin_indices.get(uuid).ok_or_else(|| PluginGraphError::UnknownPlugin(uuid.to_string()))?;
+        let (order, errors) = initialization_order(&self.plugin_infos, Self::API_VERSION);
+        if !order.contains(&index) {
+            let name = self.plugin_infos[index].name.clone();
+            let cycle = errors.iter().find(|error| matches!(error, PluginGraphError::Cycle(plugins) if plugins.contains(&name))).cloned();
+            return Err(errors.into_iter().find(|error| error.plugin() == Some(name.as_str())).or(cycle).unwrap_or(PluginGraphError::UnknownPlugin(uuid.to_string())));
+        }
+        for dependency in self.plugin_infos[index].dependencies.clone() {
+            self.enable_plugin(&dependency.uuid)?;
+        }
+        let plugin = self.plugins[index].clone();
+        let mut borrows = plugin.try_borrow_mut().map_err(|_| PluginGraphError::Busy(self.plugin_infos[index].name.clone()))?;
+        if !self.initialized[index] {
+            borrows.initialize(self);
+            self.initialized[index] = true;
+        }
+        if borrows.status() != PluginStatus::Enabled {
+            borrows.enable(self);
+        }
+        drop(borrows);
+        self.publish_message_from_queue();
+        Ok(())
+    }
+
+    /// Disables a plugin together with every plugin depending on it, returns the uuids that were disabled.
+    pub fn disable_plugin(&mut self, uuid: &str) -> Result<Vec<String>, PluginGraphError> {
+        let index = *self.plugin_indices.get(uuid).ok_or_else(|| PluginGraphError::UnknownPlugin(uuid.to_string()))?;
+        let mut affected = dependents(&self.plugin_infos, index);
+        affected.push(index);
+        let mut disabled = vec![];
+        for index in affected {
+            let plugin = self.plugins[index].clone();
+            let mut borrows = plugin.try_borrow_mut().map_err(|_| PluginGraphError::Busy(self.plugin_infos[index].name.clone()))?;
+            if borrows.status() == PluginStatus::Enabled {
+                borrows.disable(self);
+                disabled.push(self.plugin_infos[index].uuid.to_string());
+            }
+        }
+        self.publish_message_from_queue();
+        Ok(disabled)
+    }
+
+    pub fn user_config_dir(&self) -> Option<&PathBuf> {
+        self.user_config_dir.as_ref()
+    }
+
+    /// Switches the directory user wide plugin choices are read from and saved to, `None` keeps them in memory only.
+    pub fn set_user_config_dir(&mut self, directory: Option<PathBuf>) {
+        self.user_settings = directory.as_deref().map(|directory| PluginSettings::load(&PluginSettings::user_file(directory))).unwrap_or_default();
+        self.user_config_dir = directory;
+        self.apply_plugin_settings();
+    }
+
+    /// The choice stored for a plugin in one scope, `None` when that scope does not override it.
+    pub fn plugin_setting(&self, uuid: &str, scope: SettingScope) -> Option<bool> {
+        match scope {
+            SettingScope::User => self.user_settings.enabled.get(uuid).copied(),
+            SettingScope::Project => self.project_settings.as_ref().and_then(|(_, settings)| settings.enabled.get(uuid).copied()),
+        }
+    }
+
+    /// Project choice wins over the user choice, plugins are enabled when neither has one.
+    fn effective_setting(&self, uuid: &str) -> bool {
+        self.plugin_setting(uuid, SettingScope::Project).or(self.plugin_setting(uuid, SettingScope::User)).unwrap_or(true)
+    }
+
+    pub fn project_attached(&self) -> bool {
+        self.project_settings.is_some()
+    }
+
+    /// Brings every initialized plugin to its effective enabled state and publishes the status changes.
+    fn apply_plugin_settings(&mut self) {
+        if !self.started {
+            return;
+        }
+        let before = self.statuses();
+        let uuids = self.plugin_infos.iter().map(|info| info.uuid.to_string()).collect::<Vec<_>>();
+        // plugins with unmet dependencies are left alone, `initialize_plugins` already reported them.
+        let (usable, _) = initialization_order(&self.plugin_infos, Self::API_VERSION);
+        // enabling first, so a disabled dependency wins over an enabled dependent.
+        for (index, uuid) in uuids.iter().enumerate() {
+            if usable.contains(&index) && self.effective_setting(uuid) {
+                if let Err(error) = self.enable_plugin(uuid) {
+                    warn!("{}", error);
+                }
+            }
+        }
+        for uuid in uuids.iter() {
+            if self.effective_setting(uuid) {
+                continue;
+            }
+            if let Err(error) = self.disable_plugin(uuid) {
+                warn!("{}", error);
+            }
+        }
+        let after = self.statuses();
+        for (index, (before, after)) in before.into_iter().zip(after).enumerate() {
+            if let (Some(before), Some(after)) = (before, after) {
+                if before != after {
+                    let event = CoreEvent::PluginStatusChanged { uuid: self.plugin_infos[index].uuid.to_string(), status: after };
+                    self.message_queue.push_back(QueuedMessage { sender: String::from("designer.core"), target: None, action: event.action().to_string(), args: event.args() });
+                }
+            }
+        }
+        self.publish_message_from_queue();
+    }
+
+    fn statuses(&self) -> Vec<Option<PluginStatus>> {
+        self.plugins.iter().map(|plugin| plugin.try_borrow().ok().map(|plugin| plugin.status())).collect()
+    }
+
+    /// Stores a choice in the given scope and applies it, `None` removes the override of that scope.
+    fn set_plugin_setting(&mut self, uuid: &str, enabled: Option<bool>, scope: SettingScope) -> Result<(), PluginError> {
+        if !self.plugin_indices.contains_key(uuid) {
+            return Err(PluginError::Other(PluginGraphError::UnknownPlugin(uuid.to_string()).to_string()));
+        }
+        let (settings, path) = match scope {
+            SettingScope::User => (&mut self.user_settings, self.user_config_dir.as_deref().map(PluginSettings::user_file)),
+            SettingScope::Project => {
+                let (directory, settings) = self.project_settings.as_mut().ok_or(PluginError::ProjectNotOpened)?;
+                let path = PluginSettings::project_file(directory);
+                (settings, Some(path))
+            },
+        };
+        match enabled {
+            Some(enabled) => settings.enabled.insert(uuid.to_string(), enabled),
+            None => settings.enabled.remove(uuid),
+        };
+        let saved = match path {
+            Some(path) => settings.save(&path).map_err(|error| PluginError::io(&path, error)),
+            None => Ok(()),
+        };
+        self.apply_plugin_settings();
+        saved
+    }
+
+    fn handle_core_command(&mut self, args: Value) -> Result<(), PluginError> {
+        match parse_args::<CoreCommand>("designer.core.management", args)? {
+            CoreCommand::SetPluginEnabled { uuid, enabled, scope } => {
+                self.set_plugin_setting(&uuid, Some(enabled), scope)?;
+            },
+            CoreCommand::ResetPluginEnabled { uuid, scope } => {
+                self.set_plugin_setting(&uuid, None, scope)?;
+            },
+            CoreCommand::AttachProject { path } => {
+                if self.project_settings.is_some() {
+                    self.store_plugin_states(SettingScope::Project);
+                }
+                self.project_settings = path.map(|path| {
+                    let settings = PluginSettings::load(&PluginSettings::project_file(&path));
+                    (path, settings)
+                });
+                if self.project_settings.is_some() {
+                    self.load_plugin_states(SettingScope::Project);
+                }
+                // the steps refer to files and settings of the previous project.
+                self.history.clear();
+                self.apply_plugin_settings();
+            },
+            CoreCommand::Undo => self.walk_history(false)?,
+            CoreCommand::Redo => self.walk_history(true)?,
+            CoreCommand::ClearHistory => self.history.clear(),
+        }
+        self.notify_ui();
+        Ok(())
+    }
+
+    fn state_storage(&self, scope: SettingScope, uuid: &str) -> FileStateStorage {
+        match scope {
+            SettingScope::User => FileStateStorage::user(self.user_config_dir.as_deref(), uuid),
+            SettingScope::Project => FileStateStorage::project(self.project_settings.as_ref().map(|(path, _)| path.as_path()), uuid),
+        }
+    }
+
+    /// Hands every plugin its stored state of the scope, plugins busy at that moment keep their current state.
+    fn load_plugin_states(&mut self, scope: SettingScope) {
+        for index in 0..self.plugins.len() {
+            self.load_plugin_state(index, scope);
+        }
+    }
+
+    fn load_plugin_state(&mut self, index: usize, scope: SettingScope) {
+        let mut storage = self.state_storage(scope, &self.plugin_infos[index].uuid);
+        match self.plugins[index].try_borrow_mut() {
+            Ok(mut borrows) => borrows.load_state(&mut storage),
+            Err(_) => warn!("{} is busy, its state is not loaded.", self.plugin_infos[index].name),
+        }
+    }
+
+    /// Stores the state of every initialized plugin in the scope, failures are logged and do not stop the others.
+    fn store_plugin_states(&mut self, scope: SettingScope) {
+        for (index, plugin) in self.plugins.clone().iter().enumerate() {
+            if !self.initialized[index] {
+                continue;
+            }
+            let mut storage = self.state_storage(scope, &self.plugin_infos[index].uuid);
+            let result = match plugin.try_borrow_mut() {
+                Ok(mut borrows) => borrows.store_state(&mut storage),
+                Err(_) => Err(PluginError::Other(String::from("Plugin is busy."))),
+            };
+            if let Err(error) = result {
+                let content = format!("Cannot store the state of {}: {}", self.plugin_infos[index].name, error);
+                log::error!("{}", content);
+                let log = LogCommand::Generate { category: String::from("Error"), content };
+                self.message_queue.push_back(QueuedMessage { sender: String::from("designer.core"), target: None, action: log.action().to_string(), args: log.args() });
+            }
+        }
+    }
+
+    /// Stores the project and user state of every plugin, the front-end calls it right before exiting.
+    pub fn shutdown(&mut self) {
+        if self.project_settings.is_some() {
+            self.store_plugin_states(SettingScope::Project);
+        }
+        self.store_plugin_states(SettingScope::User);
+        self.publish_message_from_queue();
+    }
+
+    pub fn plugin_infos(&self) -> &[PluginInfo] {
+        &self.plugin_infos
+    }
+
+    /// `None` when the plugin is unknown or currently busy.
+    pub fn plugin_status(&self, uuid: &str) -> Option<PluginStatus> {
+        let index = self.plugin_indices.get(uuid)?;
+        self.plugins[*index].try_borrow().ok().map(|plugin| plugin.status())
+    }
+
+    pub fn publish_message(&mut self, sender: &str, message: &dyn ICommand) {
+        self.enqueue_message(sender, None, message);
+    }
+
+    /// Delivers a message only to the plugin with the given uuid (and the `*` observers).
+    pub fn publish_message_to(&mut self, sender: &str, target: &str, message: &dyn ICommand) {
+        self.enqueue_message(sender, Some(target.to_string()), message);
+    }
+
+    fn enqueue_message(&mut self, sender: &str, target: Option<String>, message: &dyn ICommand) {
+        self.message_queue.push_back(QueuedMessage { sender: sender.to_string(), target, action: message.action().to_string(), args: message.args() });
+        self.publish_message_from_queue();
+    }
+
+    fn publish_message_from_queue(&mut self) {
+        if self.message_loop_busy {
+            return;
+        }
+        self.message_loop_busy = true;
+        while let Some(message) = self.next_message() {
+            let route = self.route(&message.action);
+            let handlers = match &message.target {
+                Some(target) => self.plugin_indices.get(target).map(|index| vec![*index]).unwrap_or_default(),
+                None => route.handlers,
+            };
+            // the core serves its own commands, observers still see them.
+            if message.action == CoreCommand::ACTION {
+                if let Err(error) = self.handle_core_command(message.args.clone()) {
+                    self.report_core_error(&message, error);
+                }
+            } else if handlers.is_empty() {
+                self.report_undelivered(&message);
+            }
+            for (plugin, error) in self.dispatch(&message, &route.observers, &handlers) {
+                self.report_plugin_error(&plugin, &message, error);
+            }
+        }
+        self.message_loop_busy = false;
+    }
+
+    /// Once the queue is empty the deferred messages are delivered, their handlers may publish more.
+    fn next_message(&mut self) -> Option<QueuedMessage> {
+        if self.message_queue.is_empty() {
+            self.deliver_deferred_messages();
+        }
+        self.message_queue.pop_front()
+    }
+
+    /// Hands the deferred messages to their plugins that are free again, the others keep waiting.
+    fn deliver_deferred_messages(&mut self) {
+        for (index, message) in std::mem::take(&mut self.deferred_messages) {
+            if self.plugins[index].try_borrow_mut().is_err() {
+                self.deferred_messages.push((index, message));
+                continue;
+            }
+            for (plugin, error) in self.dispatch(&message, &[], &[index]) {
+                self.report_plugin_error(&plugin, &message, error);
+            }
+        }
+    }
+
+    /// Hands a message to the observers and handlers that are enabled, returns the errors of the ones that failed.
+    fn dispatch(&mut self, message: &QueuedMessage, observers: &[usize], handlers: &[usize]) -> Vec<(PluginInfo, PluginError)> {
+        let plugins = self.plugins.clone();
+        let mut errors = vec![];
+        for index in observers.iter().chain(handlers.iter()) {
+            // the plugin is up the call stack, such as in its own `enable`, it gets the message once that returns.
+            let Ok(mut borrows) = plugins[*index].try_borrow_mut() else {
+                warn!("{} is busy, message \"{}\" is delivered to it later.", self.plugin_infos[*index].name, message.action);
+                self.deferred_messages.push((*index, QueuedMessage { sender: message.sender.clone(), target: Some(self.plugin_infos[*index].uuid.clone()), action: message.action.clone(), args: message.args.clone() }));
+                continue;
+            };
+            if borrows.status() == PluginStatus::Enabled {
+                if let Err(error) = borrows.handle_message(self, message.sender.as_str(), &message.action, message.args.clone()) {
+                    errors.push((borrows.info(), error));
+                }
+            }
+        }
+        errors
+    }
+
+    /// Adds an action a plugin just carried out to the undo history, the Edit menu can then revert it.
+    /// Recording while the action is itself being undone or redone updates that step instead.
+    pub fn record_undo(&mut self, entry: UndoEntry) {
+        let label = entry.label.clone();
+        if self.history.record(entry) {
+            self.queue_log("History", label);
+        }
+    }
+
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    /// Publishes the commands of the last step, or of the last undone step when `redo` is true, right away instead of queueing them.
+    fn walk_history(&mut self, redo: bool) -> Result<(), PluginError> {
+        let Some(entry) = self.history.begin_replay(redo) else {
+            return Ok(());
+        };
+        let verb = if redo {"Redo"} else {"Undo"};
+        let commands = if redo {entry.redo.clone()} else {entry.undo.clone()};
+        let mut failure = None;
+        for command in commands {
+            let message = QueuedMessage { sender: String::from("designer.core"), target: None, action: command.action, args: command.args };
+            let route = self.route(&message.action);
+            if route.handlers.is_empty() {
+                failure = Some(format!("no plugin handles \"{}\"", message.action));
+                break;
+            }
+            if let Some((plugin, error)) = self.dispatch(&message, &route.observers, &route.handlers).into_iter().next() {
+                failure = Some(format!("{} failed: {}", plugin.name, error));
+                break;
+            }
+        }
+        let label = entry.label.clone();
+        self.history.finish_replay(entry, redo, failure.is_none());
+        match failure {
+            None => {
+                self.queue_log("History", format!("{}: {}", verb, label));
+                Ok(())
+            },
+            Some(reason) => Err(PluginError::Other(format!("Cannot {} \"{}\", it is removed from the history: {}", verb.to_lowercase(), label, reason))),
+        }
+    }
+
+    /// Logs from the core itself, they are delivered once the current message is handled.
+    fn queue_log(&mut self, category: &str, content: String) {
+        let log = LogCommand::Generate { category: category.to_string(), content };
+        self.message_queue.push_back(QueuedMessage { sender: String::from("designer.core"), target: None, action: log.action().to_string(), args: log.args() });
+    }
+
+    /// Resolves (and caches) the plugins subscribed to an action.
+    fn route(&mut self, action: &str) -> Route {
+        if let Some(route) = self.routes.get(action) {
+            return route.clone();
+        }
+        let mut route = Route::default();
+        for (index, info) in self.plugin_infos.iter().enumerate() {
+            if info.subscriptions.iter().any(|pattern| pattern == "*") {
+                route.observers.push(index);
+            } else if info.subscribes_to(action) {
+                route.handlers.push(index);
+            }
+        }
+        self.routes.insert(action.to_string(), route.clone());
+        route
+    }
+
+    /// Nobody subscribed to the action (or the target does not exist), most likely a typo in the action string.
+    fn report_undelivered(&mut self, message: &QueuedMessage) {
+        let content = match &message.target {
+            Some(target) => format!("Message \"{}\" from \"{}\" is addressed to unknown plugin {}.", message.action, message.sender, target),
+            None => format!("No plugin handles action \"{}\" sent by \"{}\".", message.action, message.sender),
+        };
+        warn!("{}", content);
+        let log = LogCommand::Generate { category: String::from("Warning"), content };
+        // an undeliverable log message would report itself forever.
+        if message.action != log.action() {
+            self.message_queue.push_back(QueuedMessage { sender: String::from("designer.core"), target: None, action: log.action().to_string(), args: log.args() });
+        }
+    }
+
+    /// Logs a failed `handle_message` and publishes it as `designer.core.error` so the front-end can show it.
+    fn report_plugin_error(&mut self, plugin: &PluginInfo, message: &QueuedMessage, error: PluginError) {
+        let content = format!("{} failed to handle \"{}\" sent by \"{}\": {}", plugin.name, message.action, message.sender, error);
+        log::error!("{}", content);
+        let log = LogCommand::Generate { category: String::from("Error"), content };
+        if message.action != log.action() {
+            self.message_queue.push_back(QueuedMessage { sender: String::from("designer.core"), target: None, action: log.action().to_string(), args: log.args() });
+        }
+        let event = CoreErrorEvent { plugin: plugin.uuid.to_string(), plugin_name: plugin.name.clone(), sender: message.sender.clone(), action: message.action.clone(), error };
+        self.message_queue.push_back(QueuedMessage { sender: String::from("designer.core"), target: None, action: event.action().to_string(), args: event.args() });
+    }
+
+    fn report_core_error(&mut self, message: &QueuedMessage, error: PluginError) {
+        let content = format!("Core failed to handle \"{}\" sent by \"{}\": {}", message.action, message.sender, error);
+        log::error!("{}", content);
+        let log = LogCommand::Generate { category: String::from("Error"), content };
+        self.message_queue.push_back(QueuedMessage { sender: String::from("designer.core"), target: None, action: log.action().to_string(), args: log.args() });
+        let event = CoreErrorEvent { plugin: String::from("designer.core"), plugin_name: String::from("Designer core"), sender: message.sender.clone(), action: message.action.clone(), error };
+        self.message_queue.push_back(QueuedMessage { sender: String::from("designer.core"), target: None, action: event.action().to_string(), args: event.args() });
+    }
+
+    /// Sends a request to whichever enabled plugin serves its action and waits for the typed reply.
+    pub fn request<R: DeserializeOwned>(&mut self, sender: &str, request: &dyn ICommand) -> Result<R, RequestError> {
+        let id = self.post_request(sender, request);
+        self.take_reply(id).unwrap_or_else(|| Err(RequestError::NoHandler { action: request.action().to_string() }))
+    }
+
+    /// Dispatches a request and keeps its reply until `take_reply` is called with the returned id.
+    pub fn post_request(&mut self, sender: &str, request: &dyn ICommand) -> RequestId {
+        self.next_request_id += 1;
+        let id = RequestId(self.next_request_id);
+        let action = request.action().to_string();
+        let args = request.args();
+        let mut reply = Err(RequestError::NoHandler { action: action.clone() });
+        let plugins = self.plugins.clone();
+        for plugin in self.route(&action).handlers.iter().map(|index| &plugins[*index]) {
+            // a plugin that is already handling a message (usually the requester itself) cannot answer.
+            let Ok(mut borrows) = plugin.try_borrow_mut() else {
+                continue;
+            };
+            if borrows.status() != PluginStatus::Enabled {
+                continue;
+            }
+            if let Some(result) = borrows.handle_request(self, sender, &action, args.clone()) {
+                reply = result.map_err(|error| RequestError::Failed { action: action.clone(), message: error.to_string() });
+                break;
+            }
+        }
+        self.replies.insert(id, (action, reply));
+        id
+    }
+
+    /// Takes the reply of a posted request, `None` if the id is unknown or was already taken.
+    pub fn take_reply<R: DeserializeOwned>(&mut self, id: RequestId) -> Option<Result<R, RequestError>> {
+        let (action, reply) = self.replies.remove(&id)?;
+        Some(reply.and_then(|value| {
+            serde_json::from_value::<R>(value).map_err(|error| RequestError::InvalidReply { action, message: error.to_string() })
+        }))
+    }
+
+    pub fn notify_ui(&self) {
+        self.notify_ui.deref()();
+    }
+
+    /// Lets background threads of plugins wake the front-end, the GUI passes a repaint request there.
+    pub fn set_background_notifier(&mut self, notifier: impl Fn() + Send + Sync + 'static) {
+        self.background_notifier = Some(Arc::new(notifier));
+    }
+
+    pub fn background_notifier(&self) -> Option<Arc<dyn Fn() + Send + Sync>> {
+        self.background_notifier.clone()
+    }
+
+    /// Borrows a native plugin by its type, fails instead of aliasing when the plugin is mutably borrowed (for example while it handles a message).
+    pub fn with_plugin<T: IPlugin, R>(&self, accessor: impl FnOnce(&T) -> R) -> Result<R, PluginAccessError> {
+        let plugin = self.typed_plugin::<T>()?;
+        let borrows = plugin.try_borrow().map_err(|_| PluginAccessError::Busy(std::any::type_name::<T>()))?;
+        let plugin = borrows.as_any().downcast_ref::<T>().ok_or(PluginAccessError::NotRegistered(std::any::type_name::<T>()))?;
+        Ok(accessor(plugin))
+    }
+
+    /// Mutable counterpart of `with_plugin`, fails when the plugin is borrowed anywhere else.
+    pub fn with_plugin_mut<T: IPlugin, R>(&self, accessor: impl FnOnce(&mut T) -> R) -> Result<R, PluginAccessError> {
+        let plugin = self.typed_plugin::<T>()?;
+        let mut borrows = plugin.try_borrow_mut().map_err(|_| PluginAccessError::Busy(std::any::type_name::<T>()))?;
+        let plugin = borrows.as_any_mut().downcast_mut::<T>().ok_or(PluginAccessError::NotRegistered(std::any::type_name::<T>()))?;
+        Ok(accessor(plugin))
+    }
+
+    /// Borrows a plugin by uuid, for plugins registered through `register_dynamic_plugin`. `None` when the plugin is unknown or busy.
+    pub fn with_dynamic_plugin_mut<R>(&self, uuid: &str, accessor: impl FnOnce(&mut dyn IPlugin) -> R) -> Option<R> {
+        let index = self.plugin_indices.get(uuid)?;
+        let mut borrows = self.plugins[*index].try_borrow_mut().ok()?;
+        Some(accessor(&mut *borrows))
+    }
+
+    fn typed_plugin<T: IPlugin>(&self) -> Result<Rc<RefCell<dyn IPlugin>>, PluginAccessError> {
+        let index = self.plugin_types.get(&TypeId::of::<T>()).ok_or(PluginAccessError::NotRegistered(std::any::type_name::<T>()))?;
+        Ok(self.plugins[*index].clone())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PluginAccessError {
+    /// No native plugin of that type is registered.
+    NotRegistered(&'static str),
+    /// The plugin is already mutably borrowed, usually because the caller is reached from that plugin's own handler.
+    Busy(&'static str),
+}
+
+impl Display for PluginAccessError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PluginAccessError::NotRegistered(name) => write!(f, "Plugin {} is not registered.", name),
+            PluginAccessError::Busy(name) => write!(f, "Plugin {} is busy (re-entrant access).", name),
+        }
+    }
+}
+
+impl std::error::Error for PluginAccessError {}
+
+/// Published as `designer.core.error` whenever a plugin fails to handle a message.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CoreErrorEvent {
+    pub plugin: String,
+    pub plugin_name: String,
+    pub sender: String,
+    pub action: String,
+    pub error: PluginError,
+}
+
+impl ICommand for CoreErrorEvent {
+    fn action(&self) -> &str {
+        "designer.core.error"
+    }
+
+    fn args(&self) -> Value {
+        serde_json::to_value(self).unwrap()
+    }
+}
+
+/// Commands served by the core itself.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum CoreCommand {
+    /// Enables or disables a plugin at runtime and remembers the choice in the given scope.
+    SetPluginEnabled {
+        uuid: String,
+        enabled: bool,
+        scope: SettingScope,
+    },
+    /// Forgets the choice of a scope, the plugin falls back to the other scope or to enabled.
+    ResetPluginEnabled {
+        uuid: String,
+        scope: SettingScope,
+    },
+    /// Loads the project scoped plugin choices, `None` detaches the project.
+    AttachProject {
+        path: Option<PathBuf>,
+    },
+    /// Reverts the last step of the undo history, see `DesignerCore::record_undo`.
+    Undo,
+    /// Carries out the last undone step again.
+    Redo,
+    ClearHistory,
+}
+
+impl CoreCommand {
+    pub const ACTION: &'static str = "designer.core.management";
+}
+
+impl ICommand for CoreCommand {
+    fn action(&self) -> &str {
+        Self::ACTION
+    }
+
+    fn args(&self) -> Value {
+        serde_json::to_value(self).unwrap()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum CoreEvent {
+    PluginStatusChanged {
+        uuid: String,
+        status: PluginStatus,
+    },
+}
+
+impl ICommand for CoreEvent {
+    fn action(&self) -> &str {
+        "designer.core.event"
+    }
+
+    fn args(&self) -> Value {
+        serde_json::to_value(self).unwrap()
+    }
+}
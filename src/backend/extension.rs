@@ -0,0 +1,225 @@
+use std::{fmt::Display, fs::File, io::Read, path::{Component, Path, PathBuf}};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::{node_graph::NodeType, plugin::{PluginDependency, PluginInfo, Version, VersionRange}, plugin_settings::SettingScope};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExtensionManifest {
+    pub uuid: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub version: Version,
+    #[serde(default)]
+    pub author: String,
+    /// JavaScript module evaluated as the plugin, relative to the package root.
+    pub entry: String,
+    #[serde(default)]
+    pub dependencies: Vec<PluginDependency>,
+    /// Core API versions the extension works with, any version when missing.
+    #[serde(default)]
+    pub api_version: Option<VersionRange>,
+    #[serde(default)]
+    pub contributes: ExtensionContributions,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExtensionContributions {
+    #[serde(default)]
+    pub windows: Vec<WindowContribution>,
+    #[serde(default)]
+    pub menu_items: Vec<MenuItemContribution>,
+    #[serde(default)]
+    pub commands: Vec<CommandContribution>,
+    /// Node types the graphs of the node editor can use while the extension is enabled.
+    #[serde(default)]
+    pub node_types: Vec<NodeType>,
+}
+
+/// A dock tab rendered by the function the entry module passes to `registerWindow(id, ...)`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WindowContribution {
+    pub id: String,
+    pub title: String,
+}
+
+/// A menu entry publishing `action` with `args` when clicked.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MenuItemContribution {
+    /// Top level menu the item is added to, such as "Extensions" or "Project".
+    #[serde(default = "MenuItemContribution::default_menu")]
+    pub menu: String,
+    pub label: String,
+    pub action: String,
+    #[serde(default)]
+    pub args: Value,
+}
+
+impl MenuItemContribution {
+    fn default_menu() -> String {
+        String::from("Extensions")
+    }
+}
+
+/// An action handled by the extension, it becomes one of the plugin's subscriptions.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommandContribution {
+    pub action: String,
+    #[serde(default)]
+    pub title: String,
+}
+
+impl ExtensionManifest {
+    pub const FILE_NAME: &'static str = "extension.json";
+
+    pub fn parse(path: &Path, content: &str) -> Result<Self, ExtensionError> {
+        let manifest = serde_json::from_str::<Self>(content).map_err(|error| ExtensionError::InvalidManifest { path: path.to_path_buf(), reason: error.to_string() })?;
+        if manifest.uuid.trim().is_empty() || manifest.entry.trim().is_empty() {
+            return Err(ExtensionError::InvalidManifest { path: path.to_path_buf(), reason: String::from("uuid and entry cannot be empty.") });
+        }
+        // the uuid names the directory a zip package is unpacked into, and is removed before unpacking again.
+        let uuid_characters = manifest.uuid.chars().all(|character| character.is_ascii_alphanumeric() || matches!(character, '.' | '_' | '-'));
+        if !uuid_characters || manifest.uuid == "." || manifest.uuid == ".." {
+            return Err(ExtensionError::InvalidManifest { path: path.to_path_buf(), reason: format!("uuid \"{}\" can only have letters, digits, '.', '_' and '-'.", manifest.uuid) });
+        }
+        if !is_relative_inside(Path::new(&manifest.entry)) {
+            return Err(ExtensionError::InvalidManifest { path: path.to_path_buf(), reason: format!("entry \"{}\" must be a path inside the package.", manifest.entry) });
+        }
+        Ok(manifest)
+    }
+
+    pub fn plugin_info(&self) -> PluginInfo {
+        PluginInfo {
+            uuid: self.uuid.clone(),
+            name: self.name.clone(),
+            description: self.description.clone(),
+            version: self.version,
+            author: self.author.clone(),
+            subscriptions: self.contributes.commands.iter().map(|command| command.action.clone()).collect(),
+            dependencies: self.dependencies.clone(),
+            api_version: self.api_version.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ExtensionPackage {
+    pub manifest: ExtensionManifest,
+    /// Directory holding the manifest and the scripts, zip packages are unpacked first.
+    pub root: PathBuf,
+    /// The directory or zip the package was discovered as.
+    pub source: PathBuf,
+    pub scope: SettingScope,
+}
+
+impl ExtensionPackage {
+    /// `<config dir>/bluebird-ide/extensions`
+    pub fn user_directory(config_dir: &Path) -> PathBuf {
+        config_dir.join("extensions")
+    }
+
+    /// `<project>/.designer/extensions`
+    pub fn project_directory(project_dir: &Path) -> PathBuf {
+        project_dir.join(".designer").join("extensions")
+    }
+
+    pub fn from_directory(path: &Path, scope: SettingScope) -> Result<Self, ExtensionError> {
+        let manifest_path = path.join(ExtensionManifest::FILE_NAME);
+        let content = std::fs::read_to_string(&manifest_path).map_err(|error| ExtensionError::io(&manifest_path, error))?;
+        let manifest = ExtensionManifest::parse(&manifest_path, &content)?;
+        Ok(Self { manifest, root: path.to_path_buf(), source: path.to_path_buf(), scope })
+    }
+
+    /// Unpacks a zip package into `unpack_root/<uuid>`, replacing what an older version left there.
+    pub fn from_zip(path: &Path, scope: SettingScope, unpack_root: &Path) -> Result<Self, ExtensionError> {
+        let archive_error = |error: zip::result::ZipError| ExtensionError::Archive { path: path.to_path_buf(), reason: error.to_string() };
+        let file = File::open(path).map_err(|error| ExtensionError::io(path, error))?;
+        let mut archive = zip::ZipArchive::new(file).map_err(archive_error)?;
+        let mut content = String::new();
+        archive.by_name(ExtensionManifest::FILE_NAME).map_err(archive_error)?
+            .read_to_string(&mut content).map_err(|error| ExtensionError::io(path, error))?;
+        let manifest = ExtensionManifest::parse(&path.join(ExtensionManifest::FILE_NAME), &content)?;
+
+        let root = unpack_root.join(&manifest.uuid);
+        if !is_inside(unpack_root, &root) {
+            return Err(ExtensionError::InvalidManifest { path: path.to_path_buf(), reason: format!("uuid \"{}\" does not name a directory of {:?}.", manifest.uuid, unpack_root) });
+        }
+        if root.exists() {
+            std::fs::remove_dir_all(&root).map_err(|error| ExtensionError::io(&root, error))?;
+        }
+        std::fs::create_dir_all(&root).map_err(|error| ExtensionError::io(&root, error))?;
+        archive.extract(&root).map_err(archive_error)?;
+        Ok(Self { manifest, root, source: path.to_path_buf(), scope })
+    }
+
+    /// Absolute path of the entry module, it is also the module name so relative imports resolve inside the package.
+    pub fn entry_path(&self) -> Result<PathBuf, ExtensionError> {
+        let entry = self.root.join(&self.manifest.entry);
+        if !is_inside(&self.root, &entry) {
+            return Err(ExtensionError::InvalidManifest { path: self.root.join(ExtensionManifest::FILE_NAME), reason: format!("entry \"{}\" must be a path inside the package.", self.manifest.entry) });
+        }
+        Ok(entry)
+    }
+}
+
+/// Only names of files and directories, no root, `.` or `..` that could leave the directory it is joined to.
+pub(crate) fn is_relative_inside(path: &Path) -> bool {
+    path.components().next().is_some() && path.components().all(|component| matches!(component, Component::Normal(_)))
+}
+
+/// `path` is `directory` joined with a relative path that stays inside it.
+fn is_inside(directory: &Path, path: &Path) -> bool {
+    path.strip_prefix(directory).is_ok_and(is_relative_inside)
+}
+
+/// Finds the packages (sub directories with a manifest and `*.zip` files) of an extensions directory, a missing directory has no packages.
+pub fn discover_extensions(directory: &Path, scope: SettingScope, unpack_root: &Path) -> (Vec<ExtensionPackage>, Vec<ExtensionError>) {
+    let mut packages = vec![];
+    let mut errors = vec![];
+    let Ok(read_dir) = directory.read_dir() else {
+        return (packages, errors);
+    };
+    let mut paths = read_dir.flatten().map(|entry| entry.path()).collect::<Vec<_>>();
+    paths.sort();
+    for path in paths {
+        let package = if path.is_dir() && path.join(ExtensionManifest::FILE_NAME).exists() {
+            ExtensionPackage::from_directory(&path, scope)
+        } else if path.extension().is_some_and(|extension| extension == "zip") {
+            ExtensionPackage::from_zip(&path, scope, unpack_root)
+        } else {
+            continue;
+        };
+        match package {
+            Ok(package) => packages.push(package),
+            Err(error) => errors.push(error),
+        }
+    }
+    (packages, errors)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExtensionError {
+    Io { path: PathBuf, reason: String },
+    InvalidManifest { path: PathBuf, reason: String },
+    Archive { path: PathBuf, reason: String },
+}
+
+impl ExtensionError {
+    pub fn io(path: &Path, error: std::io::Error) -> Self {
+        Self::Io { path: path.to_path_buf(), reason: error.to_string() }
+    }
+}
+
+impl Display for ExtensionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExtensionError::Io { path, reason } => write!(f, "Cannot read extension {:?}: {}", path, reason),
+            ExtensionError::InvalidManifest { path, reason } => write!(f, "Invalid extension manifest {:?}: {}", path, reason),
+            ExtensionError::Archive { path, reason } => write!(f, "Invalid extension package {:?}: {}", path, reason),
+        }
+    }
+}
+
+impl std::error::Error for ExtensionError {}
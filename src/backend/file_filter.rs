@@ -0,0 +1,76 @@
+use std::{collections::HashMap, path::{Component, Path, PathBuf}};
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+
+/// Never shown: version control data and the editor's own `.designer` directory.
+pub const ALWAYS_EXCLUDED: [&str; 2] = [".git", ".designer"];
+
+/// Hidden unless the project exclusions bring them back with a `!` pattern such as `!target/`.
+pub const DEFAULT_EXCLUDED: [&str; 2] = ["target/", "node_modules/"];
+
+#[derive(Debug, Clone)]
+pub struct FileFilter {
+    root: PathBuf,
+    /// `DEFAULT_EXCLUDED` followed by the patterns of `Project::exclude`, it wins over `.gitignore` files.
+    project: Gitignore,
+    /// `.gitignore` of every directory asked about so far, `None` when the directory has none.
+    gitignores: HashMap<PathBuf, Option<Gitignore>>,
+}
+
+impl FileFilter {
+    /// Invalid patterns are skipped and returned with the reason.
+    pub fn new(root: &Path, exclude: &[String]) -> (Self, Vec<String>) {
+        let mut builder = GitignoreBuilder::new(root);
+        let mut problems = vec![];
+        for pattern in DEFAULT_EXCLUDED.iter().copied().chain(exclude.iter().map(String::as_str)) {
+            if pattern.trim().is_empty() {
+                continue;
+            }
+            if let Err(error) = builder.add_line(None, pattern) {
+                problems.push(format!("Exclusion \"{}\" is ignored: {}", pattern, error));
+            }
+        }
+        let project = builder.build().unwrap_or_else(|error| {
+            problems.push(error.to_string());
+            Gitignore::empty()
+        });
+        (Self { root: root.to_path_buf(), project, gitignores: HashMap::new() }, problems)
+    }
+
+    /// `path` is absolute and inside the project, paths outside of it are never ignored.
+    pub fn is_ignored(&mut self, path: &Path, is_dir: bool) -> bool {
+        let Ok(relative) = path.strip_prefix(&self.root) else {
+            return false;
+        };
+        let Some(Component::Normal(first)) = relative.components().next() else {
+            return false;
+        };
+        if ALWAYS_EXCLUDED.iter().any(|excluded| first == *excluded) {
+            return true;
+        }
+        let decided = self.project.matched_path_or_any_parents(path, is_dir);
+        if !decided.is_none() {
+            return decided.is_ignore();
+        }
+        // the nearest `.gitignore` with a matching pattern decides, like git does.
+        let directories = path.ancestors().skip(1).take_while(|directory| directory.starts_with(&self.root)).map(Path::to_path_buf).collect::<Vec<_>>();
+        for directory in directories {
+            let gitignore = self.gitignores.entry(directory.clone()).or_insert_with(|| {
+                let file = directory.join(".gitignore");
+                file.is_file().then(|| Gitignore::new(&file).0)
+            });
+            if let Some(gitignore) = gitignore {
+                let decided = gitignore.matched_path_or_any_parents(path, is_dir);
+                if !decided.is_none() {
+                    return decided.is_ignore();
+                }
+            }
+        }
+        false
+    }
+
+    /// Forgets the cached `.gitignore` of a directory after it was edited.
+    pub fn invalidate(&mut self, directory: &Path) {
+        self.gitignores.remove(directory);
+    }
+}
@@ -0,0 +1,97 @@
+use std::{path::{Path, PathBuf}, sync::{Arc, Mutex}, time::Duration};
+
+use notify::{event::ModifyKind, Config, Event, EventKind, PollWatcher, RecommendedWatcher, RecursiveMode, Watcher};
+
+/// Used by the polling fallback, also how often a polled project notices a change.
+pub const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchMode {
+    /// OS notifications: inotify on Linux, FSEvents on macOS, ReadDirectoryChangesW on Windows.
+    Native,
+    /// The tree is scanned every `POLL_INTERVAL`, used when OS notifications cannot be set up (inotify watch limit, network drives).
+    Polling,
+}
+
+/// A path reported by the OS, whether it was added, removed or renamed is decided by looking at the disk when it is applied.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawChange {
+    pub path: PathBuf,
+    /// The content of the file was written, not only created, removed or renamed.
+    pub content_changed: bool,
+}
+
+/// Collects changes on a background thread until the owner takes them, `wake` is called whenever a change arrives.
+/// Folders are watched one by one without their sub folders, so only what the file manager has loaded costs OS watches.
+pub struct ProjectWatcher {
+    /// Dropping it stops the background thread.
+    watcher: Box<dyn Watcher>,
+    mode: WatchMode,
+    changes: Arc<Mutex<Vec<RawChange>>>,
+}
+
+impl std::fmt::Debug for ProjectWatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProjectWatcher").field("mode", &self.mode).finish()
+    }
+}
+
+impl ProjectWatcher {
+    pub fn watch(root: &Path, mode: WatchMode, wake: Option<Arc<dyn Fn() + Send + Sync>>) -> Result<Self, notify::Error> {
+        let changes = Arc::new(Mutex::new(vec![]));
+        let sink = changes.clone();
+        let handler = move |event: notify::Result<Event>| {
+            let Ok(event) = event else {
+                return;
+            };
+            let content_changed = match event.kind {
+                EventKind::Create(_) | EventKind::Remove(_) | EventKind::Modify(ModifyKind::Name(_)) | EventKind::Any | EventKind::Other => false,
+                EventKind::Modify(_) => true,
+                EventKind::Access(_) => return,
+            };
+            if let Ok(mut changes) = sink.lock() {
+                changes.extend(event.paths.into_iter().map(|path| RawChange { path, content_changed }));
+            }
+            if let Some(wake) = &wake {
+                wake();
+            }
+        };
+        let mut watcher: Box<dyn Watcher> = match mode {
+            WatchMode::Native => Box::new(RecommendedWatcher::new(handler, Config::default())?),
+            WatchMode::Polling => Box::new(PollWatcher::new(handler, Config::default().with_poll_interval(POLL_INTERVAL))?),
+        };
+        watcher.watch(root, RecursiveMode::NonRecursive)?;
+        Ok(Self { watcher, mode, changes })
+    }
+
+    /// Also reports changes of the direct children of `folder`.
+    pub fn watch_folder(&mut self, folder: &Path) -> Result<(), notify::Error> {
+        self.watcher.watch(folder, RecursiveMode::NonRecursive)
+    }
+
+    /// A folder that was removed from disk may already be unwatched by the OS, that is not an error.
+    pub fn unwatch_folder(&mut self, folder: &Path) {
+        let _ = self.watcher.unwatch(folder);
+    }
+
+    /// Native notifications first, polling when they cannot be set up.
+    pub fn watch_with_fallback(root: &Path, wake: Option<Arc<dyn Fn() + Send + Sync>>) -> Result<Self, notify::Error> {
+        Self::watch(root, WatchMode::Native, wake.clone()).or_else(|error| {
+            log::warn!("Cannot watch {:?} with OS notifications ({}), polling it instead.", root, error);
+            Self::watch(root, WatchMode::Polling, wake)
+        })
+    }
+
+    pub fn mode(&self) -> WatchMode {
+        self.mode
+    }
+
+    pub fn has_changes(&self) -> bool {
+        self.changes.lock().map(|changes| !changes.is_empty()).unwrap_or(false)
+    }
+
+    /// Changes in the order they were reported, a path may appear more than once.
+    pub fn take_changes(&self) -> Vec<RawChange> {
+        self.changes.lock().map(|mut changes| std::mem::take(&mut *changes)).unwrap_or_default()
+    }
+}
@@ -0,0 +1,121 @@
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::commands::ICommand;
+
+/// A message kept in the history, it is published again as it was recorded.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedCommand {
+    pub action: String,
+    pub args: Value,
+}
+
+impl RecordedCommand {
+    pub fn new(command: &dyn ICommand) -> Self {
+        Self { action: command.action().to_string(), args: command.args() }
+    }
+}
+
+impl ICommand for RecordedCommand {
+    fn action(&self) -> &str {
+        &self.action
+    }
+
+    fn args(&self) -> Value {
+        self.args.clone()
+    }
+}
+
+/// One step of the history. Plugins record it right after carrying out a command, with the command reverting it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UndoEntry {
+    /// Shown in the Edit menu and the log, such as `Rename "a.js" to "b.js"`.
+    pub label: String,
+    /// Published in order to carry the action out again.
+    pub redo: Vec<RecordedCommand>,
+    /// Published in order to revert the action.
+    pub undo: Vec<RecordedCommand>,
+}
+
+impl UndoEntry {
+    pub fn new(label: impl Into<String>, redo: &dyn ICommand, undo: &dyn ICommand) -> Self {
+        Self { label: label.into(), redo: vec![RecordedCommand::new(redo)], undo: vec![RecordedCommand::new(undo)] }
+    }
+}
+
+/// Bounded undo and redo stacks kept by the core, text edits have their own history in each document.
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    undo: VecDeque<UndoEntry>,
+    redo: Vec<UndoEntry>,
+    /// Entries recorded while a step is undone or redone, they describe the replay rather than a new action.
+    replayed: Option<Vec<UndoEntry>>,
+}
+
+impl History {
+    /// Steps kept for undo, the oldest ones are dropped beyond it.
+    pub const LIMIT: usize = 100;
+
+    pub fn undo_label(&self) -> Option<&str> {
+        self.undo.back().map(|entry| entry.label.as_str())
+    }
+
+    pub fn redo_label(&self) -> Option<&str> {
+        self.redo.last().map(|entry| entry.label.as_str())
+    }
+
+    /// Oldest first.
+    pub fn undo_entries(&self) -> impl Iterator<Item = &UndoEntry> {
+        self.undo.iter()
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+
+    /// Adds a new action and forgets what could be redone, returns false when the entry only describes a step being replayed.
+    pub(crate) fn record(&mut self, entry: UndoEntry) -> bool {
+        if let Some(replayed) = self.replayed.as_mut() {
+            replayed.push(entry);
+            return false;
+        }
+        self.redo.clear();
+        self.undo.push_back(entry);
+        if self.undo.len() > Self::LIMIT {
+            self.undo.pop_front();
+        }
+        true
+    }
+
+    /// Takes the step to undo, or to redo, and starts collecting what its replay records.
+    pub(crate) fn begin_replay(&mut self, redo: bool) -> Option<UndoEntry> {
+        let entry = if redo {self.redo.pop()} else {self.undo.pop_back()}?;
+        self.replayed = Some(vec![]);
+        Some(entry)
+    }
+
+    /// Moves a replayed step to the other stack, a failed one is dropped.
+    /// Commands whose inverse changes with every run, such as a delete restored from a new trash item, recorded their replay,
+    /// the step then keeps the commands of that replay.
+    pub(crate) fn finish_replay(&mut self, entry: UndoEntry, redo: bool, succeeded: bool) {
+        let replayed = self.replayed.take().unwrap_or_default();
+        if !succeeded {
+            return;
+        }
+        let entry = if replayed.is_empty() {
+            entry
+        } else if redo {
+            UndoEntry { label: entry.label, redo: replayed.iter().flat_map(|step| step.redo.clone()).collect(), undo: replayed.iter().rev().flat_map(|step| step.undo.clone()).collect() }
+        } else {
+            UndoEntry { label: entry.label, redo: replayed.iter().rev().flat_map(|step| step.undo.clone()).collect(), undo: replayed.iter().flat_map(|step| step.redo.clone()).collect() }
+        };
+        if redo {
+            self.undo.push_back(entry);
+        } else {
+            self.redo.push(entry);
+        }
+    }
+}
@@ -9,5 +9,20 @@
 // pub mod message_bus;
 pub mod commands;
 pub mod plugin;
+pub mod plugin_graph;
+pub mod plugin_settings;
+pub mod plugin_state;
+pub mod project_file;
+pub mod project_template;
+pub mod extension;
+pub mod file_filter;
+pub mod file_watcher;
+pub mod history;
+pub mod node_graph;
+pub mod search;
+pub mod syntax;
+pub mod text_buffer;
 pub mod plugins;
-pub mod counter;
\ No newline at end of file
+pub mod counter;
+pub mod designer_core;
+pub mod request;
\ No newline at end of file
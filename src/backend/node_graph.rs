@@ -0,0 +1,253 @@
+use std::{collections::HashMap, fmt::Display};
+
+use serde::{Deserialize, Serialize};
+
+use super::plugin::PluginError;
+
+/// Extension of the graph files in the project, they open in a node editor instead of a code editor.
+pub const FILE_EXTENSION: &str = "bluegraph";
+
+/// Version written to graph files, files of a newer version are not opened.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Port type that connects to ports of every type.
+pub const ANY_TYPE: &str = "any";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PortDefinition {
+    /// Unique among the inputs, or the outputs, of its node type.
+    pub id: String,
+    pub name: String,
+    /// Such as `number` or `string`, ports connect when their types are the same or one of them is `ANY_TYPE`.
+    #[serde(default = "PortDefinition::default_type")]
+    pub port_type: String,
+}
+
+impl PortDefinition {
+    pub fn new(id: &str, name: &str, port_type: &str) -> Self {
+        Self { id: id.to_string(), name: name.to_string(), port_type: port_type.to_string() }
+    }
+
+    fn default_type() -> String {
+        String::from(ANY_TYPE)
+    }
+}
+
+/// A kind of node, defined by a plugin or in the `contributes.node_types` of an extension manifest.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NodeType {
+    /// Unique across plugins, such as `math.add`.
+    pub id: String,
+    pub title: String,
+    /// Groups the node types in the menu of the editor.
+    #[serde(default)]
+    pub category: String,
+    #[serde(default)]
+    pub inputs: Vec<PortDefinition>,
+    #[serde(default)]
+    pub outputs: Vec<PortDefinition>,
+    /// Uuid of the plugin that defined it, filled in by the node graph manager.
+    #[serde(default)]
+    pub owner: String,
+}
+
+impl NodeType {
+    pub fn input(&self, id: &str) -> Option<&PortDefinition> {
+        self.inputs.iter().find(|port| port.id == id)
+    }
+
+    pub fn output(&self, id: &str) -> Option<&PortDefinition> {
+        self.outputs.iter().find(|port| port.id == id)
+    }
+}
+
+pub fn ports_compatible(output: &str, input: &str) -> bool {
+    output == input || output == ANY_TYPE || input == ANY_TYPE
+}
+
+pub type NodeId = u64;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GraphNode {
+    pub id: NodeId,
+    /// Id of its `NodeType`, the node is kept when no plugin defines the type any more.
+    pub type_id: String,
+    /// Top left corner on the canvas.
+    pub position: [f32; 2],
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct PortRef {
+    pub node: NodeId,
+    pub port: String,
+}
+
+impl PortRef {
+    pub fn new(node: NodeId, port: &str) -> Self {
+        Self { node, port: port.to_string() }
+    }
+}
+
+/// Runs from an output port to an input port.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Connection {
+    pub from: PortRef,
+    pub to: PortRef,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GraphError {
+    UnknownNodeType(String),
+    NodeNotFound(NodeId),
+    PortNotFound(PortRef),
+    TypeMismatch { output: String, input: String },
+    /// An output of a node cannot feed one of its own inputs.
+    SameNode,
+    UnsupportedVersion(u32),
+    InvalidFile(String),
+}
+
+impl Display for GraphError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GraphError::UnknownNodeType(id) => write!(f, "No enabled plugin defines the node type \"{}\".", id),
+            GraphError::NodeNotFound(id) => write!(f, "Node {} is not in the graph.", id),
+            GraphError::PortNotFound(port) => write!(f, "Node {} has no port \"{}\".", port.node, port.port),
+            GraphError::TypeMismatch { output, input } => write!(f, "An output of type \"{}\" cannot be connected to an input of type \"{}\".", output, input),
+            GraphError::SameNode => write!(f, "A node cannot be connected to itself."),
+            GraphError::UnsupportedVersion(version) => write!(f, "The graph was written by a newer version of the editor (format {}).", version),
+            GraphError::InvalidFile(reason) => write!(f, "The graph file is not valid: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for GraphError {}
+
+impl From<GraphError> for PluginError {
+    fn from(error: GraphError) -> Self {
+        PluginError::Other(error.to_string())
+    }
+}
+
+/// Nodes copied from a graph with the connections between them, positions are kept so the pasted nodes keep their arrangement.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GraphClipboard {
+    pub nodes: Vec<GraphNode>,
+    pub connections: Vec<Connection>,
+}
+
+/// Content of a `.bluegraph` file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Graph {
+    pub version: u32,
+    #[serde(default)]
+    pub nodes: Vec<GraphNode>,
+    #[serde(default)]
+    pub connections: Vec<Connection>,
+}
+
+impl Default for Graph {
+    fn default() -> Self {
+        Self { version: FORMAT_VERSION, nodes: vec![], connections: vec![] }
+    }
+}
+
+impl Graph {
+    /// An empty file is an empty graph, so a graph can be created as a new file in the file manager.
+    pub fn from_json(text: &str) -> Result<Self, GraphError> {
+        if text.trim().is_empty() {
+            return Ok(Self::default());
+        }
+        let graph = serde_json::from_str::<Graph>(text).map_err(|error| GraphError::InvalidFile(error.to_string()))?;
+        if graph.version > FORMAT_VERSION {
+            return Err(GraphError::UnsupportedVersion(graph.version));
+        }
+        Ok(Self { version: FORMAT_VERSION, ..graph })
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    pub fn node(&self, id: NodeId) -> Option<&GraphNode> {
+        self.nodes.iter().find(|node| node.id == id)
+    }
+
+    /// Id the next added node gets, the ids of nodes added together follow it.
+    pub fn next_id(&self) -> NodeId {
+        self.nodes.iter().map(|node| node.id + 1).max().unwrap_or(1)
+    }
+
+    pub fn add_node(&mut self, type_id: &str, position: [f32; 2]) -> NodeId {
+        let id = self.next_id();
+        self.nodes.push(GraphNode { id, type_id: type_id.to_string(), position });
+        id
+    }
+
+    /// Connections of the removed nodes are removed with them.
+    pub fn remove_nodes(&mut self, ids: &[NodeId]) {
+        self.nodes.retain(|node| !ids.contains(&node.id));
+        self.connections.retain(|connection| !ids.contains(&connection.from.node) && !ids.contains(&connection.to.node));
+    }
+
+    pub fn move_nodes(&mut self, ids: &[NodeId], delta: [f32; 2]) {
+        for node in self.nodes.iter_mut().filter(|node| ids.contains(&node.id)) {
+            node.position = [node.position[0] + delta[0], node.position[1] + delta[1]];
+        }
+    }
+
+    /// Checks that `from` is an output and `to` an input of nodes in the graph, and that their types are compatible.
+    pub fn check_connection(&self, types: &[NodeType], from: &PortRef, to: &PortRef) -> Result<(), GraphError> {
+        if from.node == to.node {
+            return Err(GraphError::SameNode);
+        }
+        let port = |port: &PortRef, output: bool| -> Result<String, GraphError> {
+            let node = self.node(port.node).ok_or(GraphError::NodeNotFound(port.node))?;
+            let node_type = types.iter().find(|node_type| node_type.id == node.type_id).ok_or_else(|| GraphError::UnknownNodeType(node.type_id.clone()))?;
+            let definition = if output {node_type.output(&port.port)} else {node_type.input(&port.port)};
+            definition.map(|definition| definition.port_type.clone()).ok_or_else(|| GraphError::PortNotFound(port.clone()))
+        };
+        let (output, input) = (port(from, true)?, port(to, false)?);
+        if !ports_compatible(&output, &input) {
+            return Err(GraphError::TypeMismatch { output, input });
+        }
+        Ok(())
+    }
+
+    /// An input takes a single connection, the one it had is replaced. Outputs can feed any number of inputs.
+    pub fn connect(&mut self, types: &[NodeType], from: PortRef, to: PortRef) -> Result<(), GraphError> {
+        self.check_connection(types, &from, &to)?;
+        self.connections.retain(|connection| connection.to != to);
+        self.connections.push(Connection { from, to });
+        Ok(())
+    }
+
+    /// Removes the connection going into the input.
+    pub fn disconnect(&mut self, to: &PortRef) {
+        self.connections.retain(|connection| connection.to != *to);
+    }
+
+    pub fn copy(&self, ids: &[NodeId]) -> GraphClipboard {
+        GraphClipboard {
+            nodes: self.nodes.iter().filter(|node| ids.contains(&node.id)).cloned().collect(),
+            connections: self.connections.iter().filter(|connection| ids.contains(&connection.from.node) && ids.contains(&connection.to.node)).cloned().collect(),
+        }
+    }
+
+    /// Adds the copied nodes with new ids, moved by `offset`. Returns the ids of the new nodes.
+    pub fn paste(&mut self, clipboard: &GraphClipboard, offset: [f32; 2]) -> Vec<NodeId> {
+        let mut ids = HashMap::new();
+        for node in &clipboard.nodes {
+            let id = self.add_node(&node.type_id, [node.position[0] + offset[0], node.position[1] + offset[1]]);
+            ids.insert(node.id, id);
+        }
+        for connection in &clipboard.connections {
+            if let (Some(from), Some(to)) = (ids.get(&connection.from.node), ids.get(&connection.to.node)) {
+                let to = PortRef { node: *to, port: connection.to.port.clone() };
+                self.connections.retain(|existing| existing.to != to);
+                self.connections.push(Connection { from: PortRef { node: *from, port: connection.from.port.clone() }, to });
+            }
+        }
+        clipboard.nodes.iter().filter_map(|node| ids.get(&node.id).copied()).collect()
+    }
+}
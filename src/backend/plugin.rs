@@ -1,11 +1,11 @@
-use std::{any::Any, cell::RefCell, rc::Rc};
+use std::{any::Any, fmt::Display};
 
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_json::Value;
 
 use crate::{DesignerCore};
 
-use super::commands::ICommand;
+use super::plugin_settings::SettingScope;
 
 /**
  * @author مۇختەرجان مەخمۇت
@@ -21,18 +21,130 @@ pub enum PluginStatus {
     Disabled
 }
 
+/// Failure of a plugin while handling a message, the core logs it and publishes a `designer.core.error` event instead of crashing.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum PluginError {
+    /// Arguments of the message cannot be deserialized into the expected command.
+    InvalidArguments { action: String, reason: String },
+    /// Reading or writing a file failed.
+    Io { path: String, reason: String },
+    /// The action requires an opened project.
+    ProjectNotOpened,
+    /// Another plugin did not answer a request this plugin relies on.
+    Request(String),
+    Other(String),
+}
+
+impl PluginError {
+    pub fn io(path: impl std::fmt::Debug, error: impl Display) -> Self {
+        Self::Io { path: format!("{:?}", path), reason: error.to_string() }
+    }
+}
+
+impl Display for PluginError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PluginError::InvalidArguments { action, reason } => write!(f, "Invalid arguments for \"{}\": {}", action, reason),
+            PluginError::Io { path, reason } => write!(f, "File operation on {} failed: {}", path, reason),
+            PluginError::ProjectNotOpened => write!(f, "Please open or create a project first."),
+            PluginError::Request(reason) => write!(f, "Request failed: {}", reason),
+            PluginError::Other(reason) => write!(f, "{}", reason),
+        }
+    }
+}
+
+impl std::error::Error for PluginError {}
+
+impl From<super::request::RequestError> for PluginError {
+    fn from(value: super::request::RequestError) -> Self {
+        Self::Request(value.to_string())
+    }
+}
+
+/// Deserializes message arguments, reporting malformed messages as `PluginError::InvalidArguments`.
+pub fn parse_args<T: DeserializeOwned>(action: &str, args: Value) -> Result<T, PluginError> {
+    serde_json::from_value::<T>(args).map_err(|error| PluginError::InvalidArguments { action: action.to_string(), reason: error.to_string() })
+}
+
+/// Persistent storage of one plugin in one scope, handed to `IPlugin::load_state` and `IPlugin::store_state`.
 pub trait IPluginStateStorage {
-    fn store_state(&mut self, value: Value);
-    fn load_state(&mut self) -> Value;
+    /// User wide state is loaded at startup and stored at shutdown, project state when a project is opened and closed.
+    fn scope(&self) -> SettingScope;
+    fn store_state(&mut self, value: Value) -> Result<(), PluginError>;
+    /// `None` when nothing was stored yet in this scope or the stored state is unreadable.
+    fn load_state(&mut self) -> Option<Value>;
+}
+
+pub type Version = (usize, usize, usize);
+
+/// Accepted versions, `max` is exclusive and open ended when `None`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct VersionRange {
+    pub min: Version,
+    pub max: Option<Version>,
+}
+
+impl VersionRange {
+    pub fn at_least(min: Version) -> Self {
+        Self { min, max: None }
+    }
+
+    pub fn between(min: Version, max: Version) -> Self {
+        Self { min, max: Some(max) }
+    }
+
+    pub fn contains(&self, version: Version) -> bool {
+        version >= self.min && self.max.map(|max| version < max).unwrap_or(true)
+    }
+}
+
+impl Display for VersionRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, ">={}.{}.{}", self.min.0, self.min.1, self.min.2)?;
+        if let Some(max) = self.max {
+            write!(f, ", <{}.{}.{}", max.0, max.1, max.2)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginDependency {
+    pub uuid: String,
+    /// Accepted versions of the dependency, any version when `None`.
+    pub versions: Option<VersionRange>,
+}
+
+impl PluginDependency {
+    pub fn new(uuid: impl Into<String>) -> Self {
+        Self { uuid: uuid.into(), versions: None }
+    }
 }
 
 #[derive(Debug, Clone, Serialize)]
 pub struct PluginInfo {
-    pub uuid: &'static str,
+    pub uuid: String,
     pub name: String,
     pub description: String,
-    pub version: (usize, usize, usize),
-    pub author: &'static str,
+    pub version: Version,
+    pub author: String,
+    /// Action patterns this plugin handles: an exact action, a namespace such as `designer.file_manager.*`, or `*` to observe every message.
+    pub subscriptions: Vec<String>,
+    /// Plugins that must be initialized and enabled before this one.
+    pub dependencies: Vec<PluginDependency>,
+    /// Core API versions (`DesignerCore::API_VERSION`) this plugin works with.
+    pub api_version: Option<VersionRange>,
+}
+
+impl PluginInfo {
+    pub fn subscribes_to(&self, action: &str) -> bool {
+        self.subscriptions.iter().any(|pattern| {
+            match pattern.strip_suffix('*') {
+                Some(prefix) => action.starts_with(prefix),
+                None => pattern == action,
+            }
+        })
+    }
 }
 
 pub trait IPlugin: Any {
@@ -41,9 +153,15 @@ pub trait IPlugin: Any {
     fn initialize(&mut self, root: &mut DesignerCore);
     fn enable(&mut self, root: &mut DesignerCore);
     fn disable(&mut self, root: &mut DesignerCore);
+    /// Must tolerate a missing or older state, see `plugin_state::parse_state`.
     fn load_state(&mut self, storage: &mut dyn IPluginStateStorage);
-    fn store_state(&mut self, storage: &mut dyn IPluginStateStorage);
+    fn store_state(&mut self, storage: &mut dyn IPluginStateStorage) -> Result<(), PluginError>;
     fn get_state(&self) -> Value;
-    fn handle_message(&mut self, root: &mut DesignerCore, sender: &str, action: &str, args: Value);
+    fn handle_message(&mut self, root: &mut DesignerCore, sender: &str, action: &str, args: Value) -> Result<(), PluginError>;
+    /// Answers a request sent through `DesignerCore::request`, returns `None` when this plugin does not serve the action.
+    fn handle_request(&mut self, _root: &mut DesignerCore, _sender: &str, _action: &str, _args: Value) -> Option<Result<Value, PluginError>> {
+        None
+    }
     fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
 }
@@ -0,0 +1,145 @@
+use std::{collections::HashMap, fmt::Display};
+
+use serde::{Deserialize, Serialize};
+
+use super::plugin::{PluginInfo, Version};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum PluginGraphError {
+    MissingDependency { plugin: String, dependency: String },
+    IncompatibleDependency { plugin: String, dependency: String, required: String, found: Version },
+    IncompatibleApi { plugin: String, required: String, found: Version },
+    /// Names of the plugins that depend on each other.
+    Cycle(Vec<String>),
+    /// A dependency could not be initialized, so neither can this plugin.
+    DependencyFailed { plugin: String, dependency: String },
+    UnknownPlugin(String),
+    /// Another plugin is already registered with this uuid.
+    DuplicatePlugin(String),
+    /// The plugin is handling a message and cannot change its status right now.
+    Busy(String),
+}
+
+impl Display for PluginGraphError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PluginGraphError::MissingDependency { plugin, dependency } => write!(f, "{} depends on plugin {} which is not registered.", plugin, dependency),
+            PluginGraphError::IncompatibleDependency { plugin, dependency, required, found } => write!(f, "{} requires {} {} but {}.{}.{} is registered.", plugin, dependency, required, found.0, found.1, found.2),
+            PluginGraphError::IncompatibleApi { plugin, required, found } => write!(f, "{} requires core API {} but the core provides {}.{}.{}.", plugin, required, found.0, found.1, found.2),
+            PluginGraphError::Cycle(plugins) => write!(f, "Plugins depend on each other in a cycle: {}.", plugins.join(" -> ")),
+            PluginGraphError::DependencyFailed { plugin, dependency } => write!(f, "{} is skipped because its dependency {} failed.", plugin, dependency),
+            PluginGraphError::UnknownPlugin(uuid) => write!(f, "Plugin {} is not registered.", uuid),
+            PluginGraphError::DuplicatePlugin(uuid) => write!(f, "Plugin {} is already registered.", uuid),
+            PluginGraphError::Busy(plugin) => write!(f, "{} is busy, try again later.", plugin),
+        }
+    }
+}
+
+impl std::error::Error for PluginGraphError {}
+
+impl PluginGraphError {
+    /// Names the plugin this error is about, `None` for errors involving several plugins.
+    pub fn plugin(&self) -> Option<&str> {
+        match self {
+            PluginGraphError::MissingDependency { plugin, .. } => Some(plugin),
+            PluginGraphError::IncompatibleDependency { plugin, .. } => Some(plugin),
+            PluginGraphError::IncompatibleApi { plugin, .. } => Some(plugin),
+            PluginGraphError::DependencyFailed { plugin, .. } => Some(plugin),
+            PluginGraphError::Busy(plugin) => Some(plugin),
+            PluginGraphError::Cycle(_) | PluginGraphError::UnknownPlugin(_) | PluginGraphError::DuplicatePlugin(_) => None,
+        }
+    }
+}
+
+/// Orders plugins so that every plugin comes after its dependencies, registration order is kept otherwise.
+/// Plugins with unresolved dependencies are left out of the order and reported.
+pub fn initialization_order(infos: &[PluginInfo], api_version: Version) -> (Vec<usize>, Vec<PluginGraphError>) {
+    let indices: HashMap<&str, usize> = infos.iter().enumerate().map(|(index, info)| (&*info.uuid, index)).collect();
+    let mut errors = vec![];
+    let mut usable = vec![true; infos.len()];
+    for (index, info) in infos.iter().enumerate() {
+        if let Some(range) = &info.api_version {
+            if !range.contains(api_version) {
+                errors.push(PluginGraphError::IncompatibleApi { plugin: info.name.clone(), required: range.to_string(), found: api_version });
+                usable[index] = false;
+            }
+        }
+        for dependency in &info.dependencies {
+            match indices.get(&*dependency.uuid) {
+                None => {
+                    errors.push(PluginGraphError::MissingDependency { plugin: info.name.clone(), dependency: dependency.uuid.to_string() });
+                    usable[index] = false;
+                },
+                Some(found) => {
+                    let found = &infos[*found];
+                    if let Some(range) = &dependency.versions {
+                        if !range.contains(found.version) {
+                            errors.push(PluginGraphError::IncompatibleDependency { plugin: info.name.clone(), dependency: found.name.clone(), required: range.to_string(), found: found.version });
+                            usable[index] = false;
+                        }
+                    }
+                },
+            }
+        }
+    }
+
+    // Kahn's algorithm, always picking the earliest registered plugin that is ready.
+    let mut order = vec![];
+    let mut placed = vec![false; infos.len()];
+    loop {
+        let next = (0..infos.len()).find(|index| {
+            !placed[*index] && infos[*index].dependencies.iter().all(|dependency| {
+                indices.get(&*dependency.uuid).map(|dependency| placed[*dependency]).unwrap_or(true)
+            })
+        });
+        let Some(index) = next else {
+            break;
+        };
+        placed[index] = true;
+        order.push(index);
+    }
+    let cyclic: Vec<usize> = (0..infos.len()).filter(|index| !placed[*index]).collect();
+    if !cyclic.is_empty() {
+        errors.push(PluginGraphError::Cycle(cyclic.iter().map(|index| infos[*index].name.clone()).collect()));
+    }
+
+    // a plugin is only usable if everything it depends on is.
+    let mut initialized = vec![];
+    for index in order {
+        let failed = infos[index].dependencies.iter().filter_map(|dependency| indices.get(&*dependency.uuid)).find(|dependency| !usable[**dependency]);
+        if let Some(dependency) = failed {
+            if usable[index] {
+                errors.push(PluginGraphError::DependencyFailed { plugin: infos[index].name.clone(), dependency: infos[*dependency].name.clone() });
+            }
+            usable[index] = false;
+        }
+        if usable[index] {
+            initialized.push(index);
+        }
+    }
+    (initialized, errors)
+}
+
+/// Every plugin that directly or transitively depends on `index`, ordered so a plugin always comes before the plugins it depends on.
+pub fn dependents(infos: &[PluginInfo], index: usize) -> Vec<usize> {
+    let depends_on = |plugin: usize, dependency: usize| infos[plugin].dependencies.iter().any(|item| item.uuid == infos[dependency].uuid);
+    let mut found: Vec<usize> = vec![];
+    let mut pending = vec![index];
+    while let Some(current) = pending.pop() {
+        for candidate in 0..infos.len() {
+            if candidate != index && !found.contains(&candidate) && depends_on(candidate, current) {
+                found.push(candidate);
+                pending.push(candidate);
+            }
+        }
+    }
+    let mut ordered = vec![];
+    while !found.is_empty() {
+        // nothing left in the set depends on the picked plugin, fall back to the first one when there is a cycle.
+        let position = (0..found.len()).find(|position| {
+            !found.iter().any(|other| *other != found[*position] && depends_on(*other, found[*position]))
+        }).unwrap_or(0);
+        ordered.push(found.remove(position));
+    }
+    ordered
+}
@@ -0,0 +1,47 @@
+use std::{collections::BTreeMap, path::{Path, PathBuf}};
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum SettingScope {
+    /// `<config dir>/bluebird-ide/plugins.json`, applies to every project.
+    User,
+    /// `<project>/.designer/plugins.json`, overrides the user choice while the project is opened.
+    Project,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PluginSettings {
+    /// plugin uuid -> enabled, plugins without an entry keep their default.
+    #[serde(default)]
+    pub enabled: BTreeMap<String, bool>,
+}
+
+impl PluginSettings {
+    pub fn user_file(config_dir: &Path) -> PathBuf {
+        config_dir.join("plugins.json")
+    }
+
+    pub fn project_file(project_dir: &Path) -> PathBuf {
+        project_dir.join(".designer").join("plugins.json")
+    }
+
+    /// Missing or unreadable files give empty settings, a broken file must not keep the editor from starting.
+    pub fn load(path: &Path) -> Self {
+        let Ok(content) = std::fs::read_to_string(path) else {
+            return Self::default();
+        };
+        serde_json::from_str(&content).unwrap_or_else(|error| {
+            warn!("Ignoring malformed plugin settings {:?}: {}", path, error);
+            Self::default()
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+}
@@ -0,0 +1,62 @@
+use std::path::{Path, PathBuf};
+
+use log::warn;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use super::{plugin::{IPluginStateStorage, PluginError}, plugin_settings::SettingScope};
+
+pub struct FileStateStorage {
+    scope: SettingScope,
+    /// `None` when the scope has no directory (headless core or no opened project), the state is then dropped.
+    path: Option<PathBuf>,
+}
+
+impl FileStateStorage {
+    /// `<config dir>/bluebird-ide/plugins/<uuid>.json`
+    pub fn user(config_dir: Option<&Path>, uuid: &str) -> Self {
+        Self { scope: SettingScope::User, path: config_dir.map(|directory| directory.join("plugins").join(format!("{}.json", uuid))) }
+    }
+
+    /// `<project>/.designer/plugins/<uuid>.json`
+    pub fn project(project_dir: Option<&Path>, uuid: &str) -> Self {
+        Self { scope: SettingScope::Project, path: project_dir.map(|directory| directory.join(".designer").join("plugins").join(format!("{}.json", uuid))) }
+    }
+
+    pub fn path(&self) -> Option<&PathBuf> {
+        self.path.as_ref()
+    }
+}
+
+impl IPluginStateStorage for FileStateStorage {
+    fn scope(&self) -> SettingScope {
+        self.scope
+    }
+
+    fn store_state(&mut self, value: Value) -> Result<(), PluginError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|error| PluginError::io(parent, error))?;
+        }
+        let json_text = serde_json::to_string_pretty(&value).map_err(|error| PluginError::Other(format!("Cannot serialize plugin state: {}", error)))?;
+        std::fs::write(path, json_text).map_err(|error| PluginError::io(path, error))
+    }
+
+    fn load_state(&mut self) -> Option<Value> {
+        let content = std::fs::read_to_string(self.path.as_ref()?).ok()?;
+        serde_json::from_str(&content).inspect_err(|error| warn!("Ignoring malformed plugin state {:?}: {}", self.path, error)).ok()
+    }
+}
+
+/// Reads a stored state leniently: missing fields take their default (mark the state struct `#[serde(default)]`) and an incompatible state is discarded instead of panicking.
+pub fn parse_state<T: DeserializeOwned + Default>(value: Option<Value>) -> T {
+    let Some(value) = value else {
+        return T::default();
+    };
+    serde_json::from_value(value).unwrap_or_else(|error| {
+        warn!("Discarding incompatible plugin state of {}: {}", std::any::type_name::<T>(), error);
+        T::default()
+    })
+}
@@ -0,0 +1,497 @@
+use std::{ops::Range, path::{Path, PathBuf}};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{backend::{commands::ICommand, plugin::{parse_args, IPlugin, PluginError, PluginDependency, PluginInfo, PluginStatus, VersionRange}, request::reply, syntax::{tokenize_line, Language, LineState, Token}, text_buffer::{content_hash, Buffer, EditKind, Selection, TextChange}}, DesignerCore};
+
+use super::{file_manager::{relative_path, sandboxed_path, write_replacing, DiskChange, FileManager, FileManagerEvent}, project_manager::{ProjectManager, ProjectManagerEvent, ProjectManagerRequest}, window_manager::FileTabChange};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DiskState {
+    /// The file on disk is the one the buffer was read from or last saved to.
+    InSync,
+    /// Another program changed the file while the buffer had unsaved changes.
+    Modified,
+    /// The file was removed outside of the editor, saving creates it again.
+    Removed,
+}
+
+#[derive(Debug, Clone)]
+pub struct Document {
+    /// Relative to the project directory with `/` separators.
+    pub path: String,
+    pub language: Language,
+    pub buffer: Buffer,
+    /// Hash of the file content when it was last read or saved, tells our own writes apart from other programs.
+    saved_hash: u64,
+    pub disk_state: DiskState,
+    /// State at the start of each line, valid for the lines highlighted since the last change above them.
+    line_states: Vec<LineState>,
+}
+
+impl Document {
+    fn new(path: String, text: &str) -> Self {
+        Self { language: Language::from_path(&path), path, buffer: Buffer::new(text), saved_hash: content_hash([text]), disk_state: DiskState::InSync, line_states: vec![] }
+    }
+
+    pub fn dirty(&self) -> bool {
+        self.buffer.is_dirty()
+    }
+
+    /// Increased by every change of the text, editors compare it to notice changes they did not make.
+    pub fn version(&self) -> u64 {
+        self.buffer.version()
+    }
+
+    pub fn snapshot(&self) -> DocumentSnapshot {
+        DocumentSnapshot { path: self.path.clone(), language: self.language, text: self.buffer.text(), dirty: self.dirty(), version: self.version(), disk_state: self.disk_state }
+    }
+
+    /// Makes the buffer hold what was read from or written to disk, replacing the text is undoable.
+    fn mark_saved(&mut self, saved_text: &str) -> Option<TextChange> {
+        let hash = content_hash([saved_text]);
+        let change = (content_hash(self.buffer.rope().chunks()) != hash).then(|| self.buffer.replace_all(saved_text));
+        self.buffer.mark_saved();
+        self.saved_hash = hash;
+        self.disk_state = DiskState::InSync;
+        change
+    }
+
+    /// Text and tokens of the lines in `lines`, the highlighting state is carried from the first line down and cached.
+    fn highlight_lines(&mut self, lines: Range<usize>) -> Vec<(String, Vec<Token>)> {
+        if let Some(changed) = self.buffer.take_changed_line() {
+            self.line_states.truncate(changed + 1);
+        }
+        let end = lines.end.min(self.buffer.len_lines());
+        if self.line_states.is_empty() {
+            self.line_states.push(LineState::Normal);
+        }
+        while self.line_states.len() <= lines.start.min(end) {
+            let line = self.line_states.len() - 1;
+            let (_, state) = tokenize_line(self.language, &self.buffer.line(line), self.line_states[line]);
+            self.line_states.push(state);
+        }
+        (lines.start..end).map(|line| {
+            let text = self.buffer.line(line);
+            let (tokens, state) = tokenize_line(self.language, &text, self.line_states[line]);
+            if self.line_states.get(line + 1) != Some(&state) {
+                self.line_states.truncate(line + 1);
+                self.line_states.push(state);
+            }
+            (text, tokens)
+        }).collect()
+    }
+}
+
+/// Copy of a document without its history, answered by `DocumentManagerRequest::Document`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentSnapshot {
+    pub path: String,
+    pub language: Language,
+    pub text: String,
+    pub dirty: bool,
+    pub version: u64,
+    pub disk_state: DiskState,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentManager {
+    #[serde(skip_serializing)]
+    status: PluginStatus,
+    /// In the order they were opened.
+    #[serde(skip)]
+    documents: Vec<Document>,
+}
+
+impl DocumentManager {
+    pub const UUID: &'static str = "6b0c3f9e-2d41-4a8e-9d57-0f5a2c7e81b4";
+
+    pub fn new() -> Self {
+        Self { status: PluginStatus::Disabled, documents: vec![] }
+    }
+
+    pub fn document(&self, path: &str) -> Option<&Document> {
+        self.documents.iter().find(|document| document.path == path)
+    }
+
+    pub fn documents(&self) -> &[Document] {
+        &self.documents
+    }
+
+    pub fn has_unsaved_documents(&self) -> bool {
+        self.documents.iter().any(|document| document.dirty())
+    }
+
+    /// Text and highlighting tokens of the lines in `lines` of an open document, lines past its end are left out.
+    pub fn highlight_lines(&mut self, path: &str, lines: Range<usize>) -> Vec<(String, Vec<Token>)> {
+        self.documents.iter_mut().find(|document| document.path == path).map(|document| document.highlight_lines(lines)).unwrap_or_default()
+    }
+
+    fn document_mut(&mut self, path: &str) -> Result<&mut Document, PluginError> {
+        self.documents.iter_mut().find(|document| document.path == path).ok_or_else(|| PluginError::Other(format!("\"{}\" is not open.", path)))
+    }
+}
+
+impl Default for DocumentManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IPlugin for DocumentManager {
+    fn info(&self) -> PluginInfo {
+        PluginInfo {
+            name: String::from("Document manager (Native Plugin)"),
+            author: String::from("dream-lab"),
+            uuid: String::from(Self::UUID),
+            version: (0, 0, 1),
+            description: String::from("Keeps the buffers of the files open in code editors and saves them."),
+            subscriptions: vec![String::from("designer.document_manager.*"), String::from("designer.project_manager.event"), String::from("designer.file_manager.event")],
+            dependencies: vec![PluginDependency::new(ProjectManager::UUID), PluginDependency::new(FileManager::UUID)],
+            api_version: Some(VersionRange::at_least((0, 1, 0))),
+        }
+    }
+
+    fn status(&self) -> PluginStatus {
+        self.status.clone()
+    }
+
+    fn initialize(&mut self, _root: &mut DesignerCore) {
+
+    }
+
+    fn enable(&mut self, _root: &mut DesignerCore) {
+        self.status = PluginStatus::Enabled
+    }
+
+    fn disable(&mut self, _root: &mut DesignerCore) {
+        self.status = PluginStatus::Disabled
+    }
+
+    fn load_state(&mut self, _storage: &mut dyn crate::backend::plugin::IPluginStateStorage) {
+        // buffers are read from disk when their editors open again.
+    }
+
+    fn store_state(&mut self, _storage: &mut dyn crate::backend::plugin::IPluginStateStorage) -> Result<(), PluginError> {
+        Ok(())
+    }
+
+    fn get_state(&self) -> Value {
+        serde_json::to_value(self).unwrap()
+    }
+
+    fn handle_message(&mut self, root: &mut DesignerCore, _sender: &str, action: &str, args: Value) -> Result<(), PluginError> {
+        if action == "designer.project_manager.event" {
+            match parse_args::<ProjectManagerEvent>(action, args)? {
+                // the project path is already cleared, the event carries it.
+                // a buffer that cannot be saved keeps the project open, see `ProjectManagerCommand::FinishClosing`.
+                ProjectManagerEvent::Closing { path, save_documents: true } => {
+                    let dirty = self.documents.iter().filter(|document| document.dirty()).map(|document| document.path.clone()).collect::<Vec<_>>();
+                    self.save_documents(root, Path::new(&path), dirty)?;
+                },
+                ProjectManagerEvent::Closed { .. } => {
+                    self.documents.clear();
+                },
+                _ => {},
+            }
+            return Ok(());
+        }
+        if action == "designer.file_manager.event" {
+            let event = parse_args::<FileManagerEvent>(action, args)?;
+            self.follow_file_event(root, event)?;
+            return Ok(());
+        }
+        if action != "designer.document_manager.management" {
+            return Ok(());
+        }
+        let project = root.request::<Option<PathBuf>>("designer.document_manager", &ProjectManagerRequest::ProjectPath)?.ok_or(PluginError::ProjectNotOpened)?;
+        match parse_args::<DocumentManagerCommand>(action, args)? {
+            DocumentManagerCommand::Open { path } => {
+                self.open(root, &project, &path)?;
+            },
+            DocumentManagerCommand::Reveal { path, line, column, length } => {
+                let path = self.open(root, &project, &path)?;
+                let buffer = &mut self.document_mut(&path)?.buffer;
+                let (anchor, head) = (buffer.position(line, column), buffer.position(line, column + length));
+                buffer.set_selections(vec![Selection { anchor, head }]);
+                root.publish_message("designer.document_manager", &DocumentManagerEvent::Revealed { path, line });
+            },
+            DocumentManagerCommand::Edit { path, changes, kind } => {
+                let document = self.document_mut(&path)?;
+                document.buffer.apply(changes.clone(), kind)?;
+                let version = document.version();
+                root.publish_message("designer.document_manager", &DocumentManagerEvent::Changed { path, version, changes });
+            },
+            DocumentManagerCommand::SetSelections { path, selections } => {
+                self.document_mut(&path)?.buffer.set_selections(selections);
+            },
+            DocumentManagerCommand::Undo { path } => {
+                let document = self.document_mut(&path)?;
+                if let Some(changes) = document.buffer.undo() {
+                    let version = document.version();
+                    root.publish_message("designer.document_manager", &DocumentManagerEvent::Changed { path, version, changes });
+                }
+            },
+            DocumentManagerCommand::Redo { path } => {
+                let document = self.document_mut(&path)?;
+                if let Some(changes) = document.buffer.redo() {
+                    let version = document.version();
+                    root.publish_message("designer.document_manager", &DocumentManagerEvent::Changed { path, version, changes });
+                }
+            },
+            DocumentManagerCommand::Save { path } => {
+                self.save(root, &project, &path)?;
+            },
+            DocumentManagerCommand::SaveAll => {
+                let dirty = self.documents.iter().filter(|document| document.dirty()).map(|document| document.path.clone()).collect::<Vec<_>>();
+                self.save_documents(root, &project, dirty)?;
+            },
+            DocumentManagerCommand::Autosave => {
+                let dirty = self.documents.iter().filter(|document| document.dirty() && document.disk_state == DiskState::InSync).map(|document| document.path.clone()).collect::<Vec<_>>();
+                self.save_documents(root, &project, dirty)?;
+            },
+            DocumentManagerCommand::Reload { path } => {
+                let text = read_text(&sandboxed_path(&project, &path)?, &path)?;
+                self.reload(root, &path, &text)?;
+            },
+            DocumentManagerCommand::Close { path } => {
+                let count = self.documents.len();
+                self.documents.retain(|document| document.path != path);
+                if self.documents.len() != count {
+                    root.publish_message("designer.document_manager", &DocumentManagerEvent::Closed { path });
+                }
+            },
+        }
+        root.notify_ui();
+        Ok(())
+    }
+
+    fn handle_request(&mut self, _root: &mut DesignerCore, _sender: &str, action: &str, args: Value) -> Option<Result<Value, PluginError>> {
+        if action != "designer.document_manager.request" {
+            return None;
+        }
+        let request = match parse_args::<DocumentManagerRequest>(action, args) {
+            Ok(request) => request,
+            Err(error) => return Some(Err(error)),
+        };
+        match request {
+            DocumentManagerRequest::Document { path } => reply(self.document(&path).map(|document| document.snapshot())),
+            DocumentManagerRequest::OpenDocuments => reply(self.documents.iter().map(|document| document.path.clone()).collect::<Vec<_>>()),
+            DocumentManagerRequest::UnsavedDocuments => reply(self.documents.iter().filter(|document| document.dirty()).map(|document| document.path.clone()).collect::<Vec<_>>()),
+        }
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+impl DocumentManager {
+    /// Reads the file into a buffer unless it is already open, returns the normalized relative path.
+    fn open(&mut self, root: &mut DesignerCore, project: &Path, path: &str) -> Result<String, PluginError> {
+        let absolute = sandboxed_path(project, path)?;
+        let path = relative_path(project, &absolute);
+        if self.document(&path).is_none() {
+            let text = read_text(&absolute, &path)?;
+            self.documents.push(Document::new(path.clone(), &text));
+        }
+        root.publish_message("designer.document_manager", &DocumentManagerEvent::Opened { path: path.clone() });
+        Ok(path)
+    }
+
+    /// Writes a buffer to its file through a temporary file, folders removed in the meantime are created again.
+    fn save(&mut self, root: &mut DesignerCore, project: &Path, path: &str) -> Result<(), PluginError> {
+        let absolute = sandboxed_path(project, path)?;
+        let document = self.document_mut(path)?;
+        if let Some(parent) = absolute.parent() {
+            std::fs::create_dir_all(parent).map_err(|error| PluginError::io(parent, error))?;
+        }
+        write_replacing(&absolute, |writer| document.buffer.rope().write_to(writer)).map_err(|error| PluginError::io(&absolute, error))?;
+        document.buffer.mark_saved();
+        document.saved_hash = content_hash(document.buffer.rope().chunks());
+        document.disk_state = DiskState::InSync;
+        root.publish_message("designer.document_manager", &DocumentManagerEvent::Saved { path: path.to_string() });
+        Ok(())
+    }
+
+    /// Saves every buffer in `paths`, one that cannot be written does not stop the others and all failures are reported together.
+    fn save_documents(&mut self, root: &mut DesignerCore, project: &Path, paths: Vec<String>) -> Result<(), PluginError> {
+        let failures = paths.iter().filter_map(|path| self.save(root, project, path).err()).map(|error| error.to_string()).collect::<Vec<_>>();
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(PluginError::Other(failures.join("\n")))
+        }
+    }
+
+    /// Replaces the buffer with the content of its file as one undo step.
+    fn reload(&mut self, root: &mut DesignerCore, path: &str, text: &str) -> Result<(), PluginError> {
+        let document = self.document_mut(path)?;
+        if let Some(change) = document.mark_saved(text) {
+            let version = document.version();
+            root.publish_message("designer.document_manager", &DocumentManagerEvent::Changed { path: path.to_string(), version, changes: vec![change] });
+        }
+        root.publish_message("designer.document_manager", &DocumentManagerEvent::Reloaded { path: path.to_string() });
+        Ok(())
+    }
+
+    /// Buffers follow renamed and moved files, and notice files changed or removed by other programs.
+    fn follow_file_event(&mut self, root: &mut DesignerCore, event: FileManagerEvent) -> Result<(), PluginError> {
+        match event {
+            FileManagerEvent::Renamed { from, to } | FileManagerEvent::Moved { from, to } => {
+                let change = FileTabChange::Moved { from, to };
+                for document in self.documents.iter_mut() {
+                    if let Some(path) = change.apply(&document.path).filter(|path| *path != document.path) {
+                        document.language = Language::from_path(&path);
+                        document.path = path;
+                    }
+                }
+            },
+            // the editor tabs of deleted files are closed, their buffers go with them.
+            // like a file removed by another program, a buffer with unsaved changes stays open so they can be saved again.
+            FileManagerEvent::Deleted { path, .. } => {
+                let change = FileTabChange::Removed { path };
+                let (closed, kept) = std::mem::take(&mut self.documents).into_iter().partition::<Vec<_>, _>(|document| change.apply(&document.path).is_none() && !document.dirty());
+                self.documents = kept;
+                for document in closed {
+                    root.publish_message("designer.document_manager", &DocumentManagerEvent::Closed { path: document.path });
+                }
+                let removed = self.documents.iter_mut().filter(|document| change.apply(&document.path).is_none()).map(|document| {
+                    document.disk_state = DiskState::Removed;
+                    document.path.clone()
+                }).collect::<Vec<_>>();
+                for path in removed {
+                    root.publish_message("designer.document_manager", &DocumentManagerEvent::DiskStateChanged { path, state: DiskState::Removed });
+                }
+            },
+            FileManagerEvent::Disk(DiskChange::Removed { path }) => {
+                let change = FileTabChange::Removed { path };
+                let removed = self.documents.iter_mut().filter(|document| change.apply(&document.path).is_none()).map(|document| {
+                    document.disk_state = DiskState::Removed;
+                    document.path.clone()
+                }).collect::<Vec<_>>();
+                for path in removed {
+                    root.publish_message("designer.document_manager", &DocumentManagerEvent::DiskStateChanged { path, state: DiskState::Removed });
+                }
+            },
+            FileManagerEvent::Disk(DiskChange::Modified { path }) | FileManagerEvent::Disk(DiskChange::Created { path, folder: false }) | FileManagerEvent::Restored { path, .. } => {
+                let Some(project) = root.request::<Option<PathBuf>>("designer.document_manager", &ProjectManagerRequest::ProjectPath)? else {
+                    return Ok(());
+                };
+                let Ok(document) = self.document_mut(&path) else {
+                    return Ok(());
+                };
+                let Ok(text) = read_text(&project.join(&path), &path) else {
+                    return Ok(());
+                };
+                let hash = content_hash([text.as_str()]);
+                if hash == document.saved_hash && document.disk_state == DiskState::InSync {
+                    // written by `save`.
+                    return Ok(());
+                }
+                if !document.dirty() {
+                    self.reload(root, &path, &text)?;
+                } else if hash == content_hash(document.buffer.rope().chunks()) {
+                    // the other program wrote what the buffer holds.
+                    document.mark_saved(&text);
+                    root.publish_message("designer.document_manager", &DocumentManagerEvent::DiskStateChanged { path, state: DiskState::InSync });
+                } else {
+                    document.disk_state = DiskState::Modified;
+                    root.publish_message("designer.document_manager", &DocumentManagerEvent::DiskStateChanged { path, state: DiskState::Modified });
+                }
+            },
+            _ => return Ok(()),
+        }
+        root.notify_ui();
+        Ok(())
+    }
+}
+
+/// Files that are not valid UTF-8 cannot be edited as text.
+fn read_text(absolute: &Path, path: &str) -> Result<String, PluginError> {
+    let bytes = std::fs::read(absolute).map_err(|error| PluginError::io(absolute, error))?;
+    String::from_utf8(bytes).map_err(|_| PluginError::Other(format!("\"{}\" is not a UTF-8 text file and cannot be opened in the code editor.", path)))
+}
+
+/// Paths are relative to the project directory, offsets in changes and selections count characters of the buffer.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum DocumentManagerCommand {
+    /// Reads the file into a buffer unless it is already open, `DocumentManagerEvent::Opened` asks the window to show it.
+    Open { path: String },
+    /// Opens the document and selects `length` characters from the line and column, its editor scrolls there.
+    Reveal { path: String, line: usize, column: usize, #[serde(default)] length: usize },
+    /// Applies the changes in order as one edit, formatters and other plugins can send it as well as editors.
+    Edit { path: String, changes: Vec<TextChange>, #[serde(default)] kind: EditKind },
+    SetSelections { path: String, selections: Vec<Selection> },
+    Undo { path: String },
+    Redo { path: String },
+    Save { path: String },
+    SaveAll,
+    /// Saves the modified buffers whose file was not changed by another program in the meantime.
+    Autosave,
+    /// Discards the buffer and reads the file again.
+    Reload { path: String },
+    /// Drops the buffer, unsaved changes are lost.
+    Close { path: String },
+}
+
+impl ICommand for DocumentManagerCommand {
+    fn action(&self) -> &str {
+        "designer.document_manager.management"
+    }
+
+    fn args(&self) -> Value {
+        serde_json::to_value(self).unwrap()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum DocumentManagerRequest {
+    /// Replies `Option<DocumentSnapshot>`.
+    Document { path: String },
+    /// Replies `Vec<String>`, paths of the open buffers.
+    OpenDocuments,
+    /// Replies `Vec<String>`, paths of the buffers with unsaved changes.
+    UnsavedDocuments,
+}
+
+impl ICommand for DocumentManagerRequest {
+    fn action(&self) -> &str {
+        "designer.document_manager.request"
+    }
+
+    fn args(&self) -> Value {
+        serde_json::to_value(self).unwrap()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum DocumentManagerEvent {
+    /// Also published for a buffer that was already open, the window focuses its editor.
+    Opened { path: String },
+    /// Follows `Opened` when a line was asked for, the editor brings it into view.
+    Revealed { path: String, line: usize },
+    /// The text changed by an edit, undo, redo or reload, `changes` turn the previous text into the one of `version`.
+    Changed { path: String, version: u64, changes: Vec<TextChange> },
+    Saved { path: String },
+    /// The buffer was replaced by the content of the file.
+    Reloaded { path: String },
+    /// The file was changed or removed while the buffer had unsaved changes.
+    DiskStateChanged { path: String, state: DiskState },
+    Closed { path: String },
+}
+
+impl ICommand for DocumentManagerEvent {
+    fn action(&self) -> &str {
+        "designer.document_manager.event"
+    }
+
+    fn args(&self) -> Value {
+        serde_json::to_value(self).unwrap()
+    }
+}
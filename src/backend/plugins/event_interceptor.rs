@@ -3,7 +3,7 @@ use std::collections::VecDeque;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-use crate::backend::plugin::{IPlugin, PluginInfo, PluginStatus};
+use crate::backend::{plugin::{IPlugin, IPluginStateStorage, PluginError, PluginInfo, PluginStatus, VersionRange}, plugin_settings::SettingScope, plugin_state::parse_state};
 
 /**
  * @author مۇختەرجان مەخمۇت
@@ -27,7 +27,21 @@ pub struct SystemEventInterceptor {
     maximum_log_count: u32,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+struct InterceptorState {
+    maximum_log_count: u32,
+}
+
+impl Default for InterceptorState {
+    fn default() -> Self {
+        Self { maximum_log_count: 512 }
+    }
+}
+
 impl SystemEventInterceptor {
+    pub const UUID: &'static str = "f9006025-8a2c-424f-b9a7-e9fb5aeddedf";
+
     pub fn new() -> Self {
         Self { status: PluginStatus::Disabled, collected_events: VecDeque::with_capacity(512), maximum_log_count: 512 }
     }
@@ -36,11 +50,14 @@ impl SystemEventInterceptor {
 impl IPlugin for SystemEventInterceptor {
     fn info(&self) -> crate::backend::plugin::PluginInfo {
         PluginInfo {
-            uuid: "f9006025-8a2c-424f-b9a7-e9fb5aeddedf",
+            uuid: String::from(Self::UUID),
             name: String::from("System event logger (Core)"),
             description: String::from("Collects all the system events, commands to provide debuggrable feature."),
-            author: "dream-lab",
+            author: String::from("dream-lab"),
             version: (0, 0, 1),
+            subscriptions: vec![String::from("*")],
+            dependencies: vec![],
+            api_version: Some(VersionRange::at_least((0, 1, 0))),
         }
     }
 
@@ -64,23 +81,35 @@ impl IPlugin for SystemEventInterceptor {
         serde_json::to_value(&self).unwrap()
     }
 
-    fn load_state(&mut self, storage: &mut dyn crate::backend::plugin::IPluginStateStorage) {
-        *self = serde_json::from_value(storage.load_state()).unwrap();
+    fn load_state(&mut self, storage: &mut dyn IPluginStateStorage) {
+        if storage.scope() != SettingScope::User {
+            return;
+        }
+        let state = parse_state::<InterceptorState>(storage.load_state());
+        self.maximum_log_count = state.maximum_log_count;
     }
 
-    fn store_state(&mut self, storage: &mut dyn crate::backend::plugin::IPluginStateStorage) {
-        storage.store_state(serde_json::to_value(self).unwrap());
+    fn store_state(&mut self, storage: &mut dyn IPluginStateStorage) -> Result<(), PluginError> {
+        if storage.scope() != SettingScope::User {
+            return Ok(());
+        }
+        storage.store_state(serde_json::to_value(InterceptorState { maximum_log_count: self.maximum_log_count }).unwrap())
     }
 
-    fn handle_message(&mut self, root: &mut crate::DesignerCore, sender: &str, action: &str, args: Value) {
+    fn handle_message(&mut self, root: &mut crate::DesignerCore, sender: &str, action: &str, args: Value) -> Result<(), PluginError> {
         self.collected_events.push_back(SystemEvent { sender: sender.to_string(), action: action.to_string(), args: args });
         if self.collected_events.len() > self.maximum_log_count as usize {
             self.collected_events.pop_front();
         }
         root.notify_ui();
+        Ok(())
     }
 
     fn as_any(&self) -> &dyn std::any::Any {
         self
     }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
 }
\ No newline at end of file
@@ -1,10 +1,11 @@
-use std::fs::FileType;
+use std::{cell::RefCell, fmt::Display, io::Write, path::{Component, Path, PathBuf}, rc::Rc, time::{SystemTime, UNIX_EPOCH}};
 
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
-use crate::{backend::{commands::ICommand, plugin::{IPlugin, PluginInfo, PluginStatus}}, DesignerCore};
+use crate::{backend::{commands::ICommand, file_filter::FileFilter, file_watcher::{ProjectWatcher, RawChange, WatchMode}, history::UndoEntry, node_graph, plugin::{parse_args, IPlugin, PluginError, PluginDependency, PluginInfo, PluginStatus, VersionRange}, request::reply}, DesignerCore};
 
-use super::project_manager::{ProjectManager, ProjectManagerEvent};
+use super::{document_manager::{DocumentManagerCommand, DocumentManagerEvent, DocumentManagerRequest}, logger::LogCommand, node_graph_manager::NodeGraphCommand, project_manager::{Project, ProjectManager, ProjectManagerEvent, ProjectManagerRequest}};
 
 /**
  * @author مۇختەرجان مەخمۇت
@@ -17,32 +18,120 @@ use super::project_manager::{ProjectManager, ProjectManagerEvent};
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum FileEntryType {
     File,
-    Folder(Vec<FileEntry>)
+    Folder(Vec<FileEntry>),
+    /// A folder whose content is read when it is expanded, see `FileManagerCommand::LoadFolder`.
+    UnloadedFolder,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileEntry {
     pub name: String,
-    pub file_type: FileEntryType
+    pub file_type: FileEntryType,
+    /// Why the entry cannot be opened, expanded or changed: an unreadable folder, a symbolic link loop or a name that is not valid UTF-8.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub problem: Option<String>,
 }
 
 impl FileEntry {
+    /// Describes a single path without reading the content of folders.
     pub fn from_path(path: &std::path::Path) -> Self {
-        let mut me = Self {name: path.file_name().unwrap().to_str().unwrap().into(), file_type: if path.is_dir() {FileEntryType::Folder(vec![])} else {FileEntryType::File}};
-        if path.exists() {
-            if path.is_dir() {
-                let mut entries = vec![];
-                for i in path.read_dir().unwrap() {
-                    if let Ok(entry) = i {
-                        let p = entry.path();
-                        entries.push(FileEntry::from_path(&p));
-                    }
-                }
-                me.file_type = FileEntryType::Folder(entries);
+        let file_name = path.file_name().unwrap_or(path.as_os_str());
+        let name = file_name.to_string_lossy().to_string();
+        let mut me = Self {name, file_type: if path.is_dir() {FileEntryType::UnloadedFolder} else {FileEntryType::File}, problem: None};
+        if file_name.to_str().is_none() {
+            me.problem = Some(String::from("The name is not valid UTF-8, rename it outside of the editor to use it here."));
+        } else if path.symlink_metadata().map(|metadata| metadata.file_type().is_symlink()).unwrap_or(false) {
+            match (path.canonicalize(), path.parent().map(Path::canonicalize)) {
+                (Err(error), _) => me.problem = Some(format!("Broken symbolic link: {}", error)),
+                (Ok(target), Some(Ok(parent))) if me.is_folder() && parent.starts_with(&target) => me.problem = Some(String::from("Symbolic link loop, it points to one of its parent folders.")),
+                _ => {},
             }
         }
         me
     }
+
+    /// Reads a folder with its direct children, ignored ones are left out and sub folders stay unloaded.
+    pub fn load_folder(path: &Path, filter: &mut FileFilter) -> Self {
+        let mut me = Self::from_path(path);
+        if me.problem.is_some() {
+            return me;
+        }
+        match path.read_dir() {
+            Ok(read_dir) => {
+                let mut entries = read_dir.filter_map(|entry| entry.ok()).map(|entry| entry.path())
+                    .filter(|path| !filter.is_ignored(path, path.is_dir()))
+                    .map(|path| FileEntry::from_path(&path))
+                    .collect::<Vec<_>>();
+                entries.sort_by_cached_key(|entry| (!entry.is_folder(), entry.name.to_lowercase()));
+                me.file_type = FileEntryType::Folder(entries);
+            },
+            Err(error) => {
+                me.file_type = FileEntryType::Folder(vec![]);
+                me.problem = Some(format!("Cannot read this folder: {}", error));
+            },
+        }
+        me
+    }
+
+    /// Relative paths of the loaded folders of this tree, parents before their sub folders.
+    pub fn loaded_folders(&self) -> Vec<String> {
+        let mut folders = vec![];
+        let mut pending = vec![(String::new(), self)];
+        while let Some((path, entry)) = pending.pop() {
+            if let FileEntryType::Folder(children) = &entry.file_type {
+                for child in children {
+                    pending.push((if path.is_empty() {child.name.clone()} else {format!("{}/{}", path, child.name)}, child));
+                }
+                folders.push(path);
+            }
+        }
+        folders.sort_by_key(|path| path.matches('/').count() + (!path.is_empty()) as usize);
+        folders
+    }
+
+    pub fn is_folder(&self) -> bool {
+        matches!(self.file_type, FileEntryType::Folder(_) | FileEntryType::UnloadedFolder)
+    }
+
+    /// Finds an entry by its path relative to this folder, `""` is the folder itself.
+    pub fn get(&self, relative: &str) -> Option<&FileEntry> {
+        relative.split('/').filter(|name| !name.is_empty()).try_fold(self, |entry, name| match &entry.file_type {
+            FileEntryType::Folder(children) => children.iter().find(|child| child.name == name),
+            FileEntryType::File | FileEntryType::UnloadedFolder => None,
+        })
+    }
+
+    /// The folder at `relative` is in the tree with its content read.
+    pub fn is_loaded_folder(&self, relative: &str) -> bool {
+        matches!(self.get(relative).map(|entry| &entry.file_type), Some(FileEntryType::Folder(_)))
+    }
+
+    fn get_mut(&mut self, relative: &str) -> Option<&mut FileEntry> {
+        relative.split('/').filter(|name| !name.is_empty()).try_fold(self, |entry, name| match &mut entry.file_type {
+            FileEntryType::Folder(children) => children.iter_mut().find(|child| child.name == name),
+            FileEntryType::File | FileEntryType::UnloadedFolder => None,
+        })
+    }
+
+    /// Adds or replaces the entry at `relative`, its parent folder must already be in the tree.
+    pub fn insert(&mut self, relative: &str, entry: FileEntry) -> bool {
+        let Some(FileEntry { file_type: FileEntryType::Folder(children), .. }) = self.get_mut(parent_path(relative)) else {
+            return false;
+        };
+        children.retain(|child| child.name != entry.name);
+        let index = children.partition_point(|child| (!child.is_folder(), child.name.to_lowercase()) < (!entry.is_folder(), entry.name.to_lowercase()));
+        children.insert(index, entry);
+        true
+    }
+
+    pub fn remove(&mut self, relative: &str) -> Option<FileEntry> {
+        let (parent, name) = relative.rsplit_once('/').unwrap_or(("", relative));
+        let Some(FileEntry { file_type: FileEntryType::Folder(children), .. }) = self.get_mut(parent) else {
+            return None;
+        };
+        let index = children.iter().position(|child| child.name == name)?;
+        Some(children.remove(index))
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -50,11 +139,28 @@ pub struct FileManager {
     #[serde(skip_serializing)]
     status: PluginStatus,
     pub root: Option<FileEntry>,
+    /// Keeps the loaded folders of `root` in sync with the disk while a project is open.
+    #[serde(skip)]
+    watcher: Option<Rc<RefCell<ProjectWatcher>>>,
+    #[serde(skip)]
+    filter: Option<FileFilter>,
 }
 
 impl FileManager {
+    pub const UUID: &'static str = "cfa0bc17-f2f7-4ca4-bd6d-1957ab5bfeff";
+
     pub fn new() -> Self {
-        Self { status: PluginStatus::Disabled, root: None }
+        Self { status: PluginStatus::Disabled, root: None, watcher: None, filter: None }
+    }
+
+    /// `None` while no project is watched.
+    pub fn watch_mode(&self) -> Option<WatchMode> {
+        self.watcher.as_ref().map(|watcher| watcher.borrow().mode())
+    }
+
+    /// Changes reported by the OS that `FileManagerCommand::ApplyFileChanges` has not applied yet.
+    pub fn has_pending_changes(&self) -> bool {
+        self.watcher.as_ref().map(|watcher| watcher.borrow().has_changes()).unwrap_or(false)
     }
 }
 
@@ -62,10 +168,13 @@ impl IPlugin for FileManager {
     fn info(&self) -> crate::backend::plugin::PluginInfo {
         PluginInfo {
             name: String::from("File manager (Native Plugin)"),
-            author: "dream-lab",
-            uuid: "cfa0bc17-f2f7-4ca4-bd6d-1957ab5bfeff",
+            author: String::from("dream-lab"),
+            uuid: String::from(Self::UUID),
             version: (0, 0, 1),
             description: String::from("Responsible to load or store files, as well as serve for file manager window."),
+            subscriptions: vec![String::from("designer.file_manager.*"), String::from("designer.project_manager.event"), String::from("designer.document_manager.event")],
+            dependencies: vec![PluginDependency::new(ProjectManager::UUID)],
+            api_version: Some(VersionRange::at_least((0, 1, 0))),
         }
     }
 
@@ -82,66 +191,653 @@ impl IPlugin for FileManager {
     }
 
     fn disable(&mut self, root: &mut crate::DesignerCore) {
+        self.watcher = None;
         self.status = PluginStatus::Disabled;
     }
 
     fn load_state(&mut self, storage: &mut dyn crate::backend::plugin::IPluginStateStorage) {
-        *self = serde_json::from_value(storage.load_state()).unwrap();
+        // the file tree is read from disk whenever a project is opened.
     }
 
-    fn store_state(&mut self, storage: &mut dyn crate::backend::plugin::IPluginStateStorage) {
-        storage.store_state(serde_json::to_value(self).unwrap());
+    fn store_state(&mut self, _storage: &mut dyn crate::backend::plugin::IPluginStateStorage) -> Result<(), PluginError> {
+        Ok(())
     }
 
     fn get_state(&self) -> serde_json::Value {
         serde_json::to_value(self).unwrap()
     }
 
-    fn handle_message(&mut self, root: &mut crate::DesignerCore, sender: &str, action: &str, args: serde_json::Value) {
+    fn handle_message(&mut self, root: &mut crate::DesignerCore, sender: &str, action: &str, args: serde_json::Value) -> Result<(), PluginError> {
         if sender == "designer.project_manager" {
             if action == "designer.project_manager.event" {
-                let args = serde_json::from_value::<ProjectManagerEvent>(args.clone()).unwrap();
+                let args = parse_args::<ProjectManagerEvent>(action, args.clone())?;
                 match args {
                     ProjectManagerEvent::Created { path } => {},
                     ProjectManagerEvent::Opened { path } => {
                         root.publish_message("designer.file_manager", &FileManagerCommand::ReloadProjectFiles);
                     },
+                    // the exclusions of the project may have changed.
+                    ProjectManagerEvent::Updated { .. } => {
+                        self.filter = None;
+                        self.read_all_file_list(root)?;
+                        root.notify_ui();
+                    },
+                    ProjectManagerEvent::Closing { .. } => {},
+                    ProjectManagerEvent::Closed { .. } => {
+                        self.root = None;
+                        self.watcher = None;
+                        self.filter = None;
+                        root.publish_message("designer.file_manager", &FileManagerEvent::ProjectFilesAreClosed);
+                        root.notify_ui();
+                    },
                 }
             }
         }
 
+        // the folders of open documents are watched even when the tree has not loaded them.
+        if action == "designer.document_manager.event" {
+            match parse_args::<DocumentManagerEvent>(action, args)? {
+                DocumentManagerEvent::Opened { .. } => {
+                    if let Some(project) = self.project_path(root)? {
+                        self.watch_document_folders(root, &project);
+                    }
+                },
+                DocumentManagerEvent::Closed { path } => {
+                    if let Some(project) = self.project_path(root)? {
+                        self.unwatch_document_folder(root, &project, &path);
+                    }
+                },
+                _ => {},
+            }
+            return Ok(());
+        }
+
         if action == "designer.file_manager.management" {
-            let args = serde_json::from_value::<FileManagerCommand>(args.clone()).unwrap();
+            let args = parse_args::<FileManagerCommand>(action, args.clone())?;
             match args {
-                FileManagerCommand::OpenFile(_) => {},
-                FileManagerCommand::CloseFile(_) => {},
+                FileManagerCommand::OpenFile(path) => {
+                    // graphs open in a node editor, every other file in a code editor.
+                    if Path::new(&path).extension().is_some_and(|extension| extension == node_graph::FILE_EXTENSION) {
+                        root.publish_message("designer.file_manager", &NodeGraphCommand::Open { path });
+                    } else {
+                        root.publish_message("designer.file_manager", &DocumentManagerCommand::Open { path });
+                    }
+                },
+                FileManagerCommand::CloseFile(path) => {
+                    if Path::new(&path).extension().is_some_and(|extension| extension == node_graph::FILE_EXTENSION) {
+                        root.publish_message("designer.file_manager", &NodeGraphCommand::Close { path });
+                    } else {
+                        root.publish_message("designer.file_manager", &DocumentManagerCommand::Close { path });
+                    }
+                },
                 FileManagerCommand::RequestContextMenu(_) => {},
                 FileManagerCommand::ReloadProjectFiles => {
-                    self.read_all_file_list(root);
+                    self.filter = None;
+                    self.read_all_file_list(root)?;
                     root.publish_message("designer.file_manager", &FileManagerEvent::ProjectFilesAreReopened);
                 },
+                FileManagerCommand::LoadFolder { path } => {
+                    let project = self.project_path(root)?.ok_or(PluginError::ProjectNotOpened)?;
+                    self.load_folder(root, &project, &path)?;
+                    root.notify_ui();
+                },
+                FileManagerCommand::ApplyFileChanges => {
+                    let changes = self.watcher.as_ref().map(|watcher| watcher.borrow().take_changes()).unwrap_or_default();
+                    let project = self.project_path(root)?.ok_or(PluginError::ProjectNotOpened)?;
+                    let documents = root.request::<Vec<String>>("designer.file_manager", &DocumentManagerRequest::OpenDocuments).unwrap_or_default();
+                    let mut disk_changes: Vec<DiskChange> = vec![];
+                    let mut gitignore_changed = false;
+                    for change in changes {
+                        if change.path.file_name().map(|name| name == ".gitignore").unwrap_or(false) {
+                            gitignore_changed = true;
+                            if let (Some(filter), Some(directory)) = (self.filter.as_mut(), change.path.parent()) {
+                                filter.invalidate(directory);
+                            }
+                        }
+                        if let Some(disk_change) = self.apply_change(root, &project, &change, &documents) {
+                            if !disk_changes.contains(&disk_change) {
+                                disk_changes.push(disk_change);
+                            }
+                        }
+                    }
+                    if gitignore_changed {
+                        self.read_all_file_list(root)?;
+                    }
+                    for disk_change in &disk_changes {
+                        root.publish_message("designer.file_manager", &FileManagerEvent::Disk(disk_change.clone()));
+                    }
+                    if !disk_changes.is_empty() {
+                        root.notify_ui();
+                    }
+                },
+                command => {
+                    let event = self.run_file_operation(root, command)?;
+                    if let Some(entry) = event.undo_entry() {
+                        root.record_undo(entry);
+                    }
+                    // the tree follows the operation right away, the notifications of it find nothing to change later.
+                    if let Some(project) = self.project_path(root)? {
+                        for path in event.paths() {
+                            self.apply_change(root, &project, &RawChange { path: project.join(path), content_changed: false }, &[]);
+                        }
+                    }
+                    root.publish_message("designer.file_manager", &event);
+                    root.notify_ui();
+                },
             }
         }
+        Ok(())
+    }
+
+    fn handle_request(&mut self, root: &mut DesignerCore, _sender: &str, action: &str, args: Value) -> Option<Result<Value, PluginError>> {
+        if action != "designer.file_manager.request" {
+            return None;
+        }
+        let request = match parse_args::<FileManagerRequest>(action, args) {
+            Ok(request) => request,
+            Err(error) => return Some(Err(error)),
+        };
+        match request {
+            FileManagerRequest::ProjectFiles => reply(self.root.clone()),
+            FileManagerRequest::Trash => match self.project_path(root).and_then(|project| project.ok_or(PluginError::ProjectNotOpened)) {
+                Ok(project) => reply(Trash::open(&project).items),
+                Err(error) => Some(Err(error)),
+            },
+            FileManagerRequest::ListDirectory { path } => {
+                let path = match self.resolve_project_path(root, &path) {
+                    Ok(path) => path,
+                    Err(error) => return Some(Err(error)),
+                };
+                let Some(filter) = self.filter.as_mut() else {
+                    return Some(Err(PluginError::ProjectNotOpened));
+                };
+                match FileEntry::load_folder(&path, filter) {
+                    FileEntry { file_type: FileEntryType::Folder(entries), problem: None, .. } => reply(entries),
+                    FileEntry { problem, .. } => Some(Err(PluginError::io(&path, problem.unwrap_or_else(|| String::from("not a folder."))))),
+                }
+            },
+            FileManagerRequest::ReadFile { path } => {
+                let path = match self.resolve_project_path(root, &path) {
+                    Ok(path) => path,
+                    Err(error) => return Some(Err(error)),
+                };
+                match std::fs::read_to_string(&path) {
+                    Ok(content) => reply(content),
+                    Err(error) => Some(Err(PluginError::io(&path, error))),
+                }
+            },
+        }
     }
 
     fn as_any(&self) -> &dyn std::any::Any {
         self
     }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
 }
 
 impl FileManager {
-    fn read_all_file_list(&mut self, root: &mut DesignerCore) {
-        let project_manager = root.get_plugin::<ProjectManager>().unwrap();
-        self.root = Some(FileEntry::from_path(project_manager.path.clone().unwrap().as_path()))
+    fn project_path(&self, root: &mut DesignerCore) -> Result<Option<PathBuf>, PluginError> {
+        Ok(root.request::<Option<PathBuf>>("designer.file_manager", &ProjectManagerRequest::ProjectPath)?)
+    }
+
+    /// Resolves a path relative to the opened project directory, paths leaving the project are rejected.
+    fn resolve_project_path(&self, root: &mut DesignerCore, relative: &str) -> Result<PathBuf, PluginError> {
+        let project_path = self.project_path(root)?.ok_or(PluginError::ProjectNotOpened)?;
+        Ok(sandboxed_path(&project_path, relative)?)
+    }
+
+    /// Applies a create, rename, move, delete or restore command and returns the event describing it.
+    fn run_file_operation(&mut self, root: &mut DesignerCore, command: FileManagerCommand) -> Result<FileManagerEvent, FileOperationError> {
+        let project = self.project_path(root).ok().flatten().ok_or(FileOperationError::ProjectNotOpened)?;
+        let event = match command {
+            FileManagerCommand::CreateFile { path } => {
+                let target = writable_target(&project, &path)?;
+                std::fs::OpenOptions::new().write(true).create_new(true).open(&target).map_err(|error| FileOperationError::io(&path, error))?;
+                FileManagerEvent::Created { path: relative_path(&project, &target), folder: false }
+            },
+            FileManagerCommand::CreateFolder { path } => {
+                let target = writable_target(&project, &path)?;
+                std::fs::create_dir(&target).map_err(|error| FileOperationError::io(&path, error))?;
+                FileManagerEvent::Created { path: relative_path(&project, &target), folder: true }
+            },
+            FileManagerCommand::Rename { path, new_name } => {
+                if new_name.is_empty() || new_name == "." || new_name == ".." || new_name.contains(['/', '\\']) {
+                    return Err(FileOperationError::InvalidName { name: new_name });
+                }
+                let source = existing_source(&project, &path)?;
+                let target = source.with_file_name(&new_name);
+                let to = relative_path(&project, &target);
+                if is_protected(&to) {
+                    return Err(FileOperationError::Protected { path: to });
+                }
+                move_entry(&source, &target, &to)?;
+                FileManagerEvent::Renamed { from: relative_path(&project, &source), to }
+            },
+            FileManagerCommand::Move { path, destination } => {
+                let source = existing_source(&project, &path)?;
+                let folder = sandboxed_path(&project, &destination)?;
+                if !folder.is_dir() {
+                    return Err(FileOperationError::NotFound { path: destination });
+                }
+                let target = folder.join(source.file_name().unwrap_or_default());
+                let to = relative_path(&project, &target);
+                if target.starts_with(&source) {
+                    return Err(FileOperationError::IntoItself { path, destination });
+                }
+                if is_protected(&to) {
+                    return Err(FileOperationError::Protected { path: to });
+                }
+                move_entry(&source, &target, &to)?;
+                FileManagerEvent::Moved { from: relative_path(&project, &source), to }
+            },
+            FileManagerCommand::Delete { path } => {
+                let source = existing_source(&project, &path)?;
+                let path = relative_path(&project, &source);
+                let item = Trash::open(&project).put(&project, &source, &path)?;
+                FileManagerEvent::Deleted { path, trash_id: item.id }
+            },
+            FileManagerCommand::RestoreDeleted { id } => {
+                let mut trash = Trash::open(&project);
+                let item = trash.restore(&project, id.as_deref())?;
+                FileManagerEvent::Restored { path: item.original_path, trash_id: item.id }
+            },
+            FileManagerCommand::EmptyTrash => {
+                let directory = Trash::directory(&project);
+                if directory.exists() {
+                    std::fs::remove_dir_all(&directory).map_err(|error| FileOperationError::io(TRASH_DIRECTORY, error))?;
+                }
+                FileManagerEvent::TrashEmptied
+            },
+            command => return Err(FileOperationError::Unsupported(format!("{:?}", command))),
+        };
+        Ok(event)
+    }
+
+    /// Reads the project root again together with the folders that were loaded before, and watches them.
+    fn read_all_file_list(&mut self, root: &mut DesignerCore) -> Result<(), PluginError> {
+        let loaded = self.root.as_ref().map(FileEntry::loaded_folders).unwrap_or_default();
+        self.root = None;
+        self.watcher = None;
+        let Some(project) = self.project_path(root)? else {
+            return Ok(());
+        };
+        match ProjectWatcher::watch_with_fallback(&project, root.background_notifier()) {
+            Ok(watcher) => self.watcher = Some(Rc::new(RefCell::new(watcher))),
+            Err(error) => root.publish_message("designer.file_manager", &LogCommand::Generate { category: String::from("Warning"), content: format!("Project files are not watched, use Refresh to see changes made outside of the editor: {}", error) }),
+        }
+        let filter = self.filter(root, &project);
+        self.root = Some(FileEntry::load_folder(&project, filter));
+        for folder in loaded.iter().filter(|folder| !folder.is_empty()) {
+            // folders that disappeared or are excluded now are skipped.
+            let _ = self.load_folder(root, &project, folder);
+        }
+        self.watch_document_folders(root, &project);
+        Ok(())
+    }
+
+    /// Watches the folders of the open documents that the tree has not loaded, so their files are followed as well.
+    fn watch_document_folders(&mut self, root: &mut DesignerCore, project: &Path) {
+        let (Some(watcher), Some(tree)) = (&self.watcher, &self.root) else {
+            return;
+        };
+        let documents = root.request::<Vec<String>>("designer.file_manager", &DocumentManagerRequest::OpenDocuments).unwrap_or_default();
+        for document in &documents {
+            let parent = parent_path(document);
+            if !tree.is_loaded_folder(parent) {
+                if let Err(error) = watcher.borrow_mut().watch_folder(&project.join(parent)) {
+                    log::warn!("Cannot watch the folder of {:?}: {}", document, error);
+                }
+            }
+        }
+    }
+
+    /// Stops watching the folder of a closed document, unless the tree loaded it or another open document is in it.
+    fn unwatch_document_folder(&mut self, root: &mut DesignerCore, project: &Path, path: &str) {
+        let (Some(watcher), Some(tree)) = (&self.watcher, &self.root) else {
+            return;
+        };
+        let parent = parent_path(path);
+        let documents = root.request::<Vec<String>>("designer.file_manager", &DocumentManagerRequest::OpenDocuments).unwrap_or_default();
+        if !tree.is_loaded_folder(parent) && !documents.iter().any(|document| parent_path(document) == parent) {
+            watcher.borrow_mut().unwatch_folder(&project.join(parent));
+        }
+    }
+
+    /// The filter of the opened project, built from its exclusions the first time it is needed.
+    fn filter(&mut self, root: &mut DesignerCore, project: &Path) -> &mut FileFilter {
+        if self.filter.is_none() {
+            let exclude = root.request::<Option<Project>>("designer.file_manager", &ProjectManagerRequest::Project).ok().flatten().map(|project| project.exclude).unwrap_or_default();
+            let (filter, problems) = FileFilter::new(project, &exclude);
+            for problem in problems {
+                root.publish_message("designer.file_manager", &LogCommand::Generate { category: String::from("Warning"), content: problem });
+            }
+            self.filter = Some(filter);
+        }
+        self.filter.as_mut().unwrap()
+    }
+
+    /// Reads the content of a folder of the tree and starts watching it, loaded folders are read again.
+    fn load_folder(&mut self, root: &mut DesignerCore, project: &Path, relative: &str) -> Result<(), PluginError> {
+        let path = sandboxed_path(project, relative)?;
+        let relative = relative_path(project, &path);
+        match self.root.as_ref().and_then(|tree| tree.get(&relative)) {
+            Some(entry) if entry.is_folder() && entry.problem.is_none() => {},
+            Some(entry) => return Err(PluginError::Other(entry.problem.clone().unwrap_or_else(|| format!("\"{}\" is not a folder.", relative)))),
+            None => return Err(FileOperationError::NotFound { path: relative }.into()),
+        }
+        let entry = FileEntry::load_folder(&path, self.filter(root, project));
+        if let Some(tree) = self.root.as_mut() {
+            if relative.is_empty() {
+                *tree = entry;
+            } else {
+                tree.insert(&relative, entry);
+            }
+        }
+        if let Some(watcher) = &self.watcher {
+            if let Err(error) = watcher.borrow_mut().watch_folder(&path) {
+                log::warn!("Cannot watch {:?}: {}", path, error);
+            }
+        }
+        Ok(())
+    }
+
+    /// Brings the entry of one changed path in line with the disk and describes what happened to it.
+    /// Only direct children of loaded folders are tracked, the content of unloaded folders is read when they are expanded.
+    /// The files of open `documents` are reported wherever they are, so their buffers follow the disk.
+    fn apply_change(&mut self, root: &mut DesignerCore, project: &Path, change: &RawChange, documents: &[String]) -> Option<DiskChange> {
+        let relative = match change.path.strip_prefix(project) {
+            Ok(relative) => relative_path(Path::new(""), relative),
+            // some backends such as FSEvents report canonical paths.
+            Err(_) => relative_path(Path::new(""), change.path.strip_prefix(project.canonicalize().ok()?).ok()?),
+        };
+        if relative.is_empty() {
+            return None;
+        }
+        let path = project.join(&relative);
+        let exists = path.symlink_metadata().is_ok();
+        let ignored = exists && self.filter(root, project).is_ignored(&path, path.is_dir());
+        let tree = self.root.as_mut()?;
+        let document_change = |relative: String| documents.contains(&relative).then_some(if exists { DiskChange::Modified { path: relative } } else { DiskChange::Removed { path: relative } });
+        if !tree.is_loaded_folder(parent_path(&relative)) {
+            return document_change(relative);
+        }
+        match (exists && !ignored, tree.get(&relative).map(FileEntry::is_folder)) {
+            (true, None) => {
+                let entry = FileEntry::from_path(&path);
+                let folder = entry.is_folder();
+                tree.insert(&relative, entry).then_some(DiskChange::Created { path: relative, folder })
+            },
+            (true, Some(folder)) if folder != path.is_dir() => {
+                let entry = FileEntry::from_path(&path);
+                let folder = entry.is_folder();
+                tree.insert(&relative, entry);
+                Some(DiskChange::Created { path: relative, folder })
+            },
+            (true, Some(false)) if change.content_changed => Some(DiskChange::Modified { path: relative }),
+            (true, Some(_)) => None,
+            (false, Some(_)) => {
+                let removed = tree.remove(&relative)?;
+                if let Some(watcher) = &self.watcher {
+                    for folder in removed.loaded_folders() {
+                        watcher.borrow_mut().unwatch_folder(&path.join(folder));
+                    }
+                }
+                Some(DiskChange::Removed { path: relative })
+            },
+            // an ignored file is not in the tree, but may be open.
+            (false, None) => document_change(relative),
+        }
+    }
+}
+
+/// Lexically resolves `relative` inside `project`, `..` may not climb above the project and symbolic links may not point outside of it.
+pub fn sandboxed_path(project: &Path, relative: &str) -> Result<PathBuf, FileOperationError> {
+    let outside = || FileOperationError::OutsideProject { path: relative.to_string() };
+    let mut resolved = PathBuf::new();
+    for component in Path::new(relative).components() {
+        match component {
+            Component::Normal(name) => resolved.push(name),
+            Component::ParentDir => if !resolved.pop() { return Err(outside()) },
+            // a leading `/` means the project root, like `project://` in the file manager window.
+            Component::RootDir | Component::CurDir => {},
+            Component::Prefix(_) => return Err(outside()),
+        }
+    }
+    let path = project.join(resolved);
+    // the deepest existing ancestor decides where the path really points to.
+    let existing = path.ancestors().find(|ancestor| ancestor.exists()).unwrap_or(project);
+    if let (Ok(project), Ok(existing)) = (project.canonicalize(), existing.canonicalize()) {
+        if !existing.starts_with(&project) {
+            return Err(outside());
+        }
+    }
+    Ok(path)
+}
+
+/// Project relative path with `/` separators, as used by the commands and events of the file manager.
+pub fn relative_path(project: &Path, path: &Path) -> String {
+    path.strip_prefix(project).unwrap_or(path).components().map(|component| component.as_os_str().to_string_lossy().to_string()).collect::<Vec<_>>().join("/")
+}
+
+/// Writes a file through a temporary file next to it which then replaces it, so a crash never leaves a half written file behind.
+/// A symbolic link keeps pointing to the written file, and the file keeps its permissions.
+pub fn write_replacing(path: &Path, write: impl FnOnce(&mut std::io::BufWriter<std::fs::File>) -> std::io::Result<()>) -> std::io::Result<()> {
+    let path = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".tmp");
+    let temporary = path.with_file_name(name);
+    let written = std::fs::File::create(&temporary).and_then(|file| {
+        let mut writer = std::io::BufWriter::new(file);
+        write(&mut writer).and_then(|_| writer.flush())
+    });
+    if let Err(error) = written {
+        let _ = std::fs::remove_file(&temporary);
+        return Err(error);
+    }
+    if let Ok(metadata) = std::fs::metadata(&path) {
+        let _ = std::fs::set_permissions(&temporary, metadata.permissions());
+    }
+    std::fs::rename(&temporary, &path).inspect_err(|_| {
+        let _ = std::fs::remove_file(&temporary);
+    })
+}
+
+/// The folder of a project relative path, `""` for the project root.
+fn parent_path(relative: &str) -> &str {
+    relative.rsplit_once('/').map(|(parent, _)| parent).unwrap_or_default()
+}
+
+/// The project file and the editor data in `.designer` are managed by the editor itself.
+fn is_protected(relative: &str) -> bool {
+    let relative = relative.trim_start_matches('/');
+    relative.is_empty() || relative == crate::backend::project_file::FILE_NAME || relative == ".designer" || relative.starts_with(".designer/")
+}
+
+fn existing_source(project: &Path, relative: &str) -> Result<PathBuf, FileOperationError> {
+    let source = sandboxed_path(project, relative)?;
+    let normalized = relative_path(project, &source);
+    if is_protected(&normalized) {
+        return Err(FileOperationError::Protected { path: normalized });
+    }
+    if !source.exists() {
+        return Err(FileOperationError::NotFound { path: normalized });
+    }
+    Ok(source)
+}
+
+fn writable_target(project: &Path, relative: &str) -> Result<PathBuf, FileOperationError> {
+    let target = sandboxed_path(project, relative)?;
+    let normalized = relative_path(project, &target);
+    if is_protected(&normalized) {
+        return Err(FileOperationError::Protected { path: normalized });
+    }
+    if target.exists() {
+        return Err(FileOperationError::AlreadyExists { path: normalized });
+    }
+    if !target.parent().map(Path::is_dir).unwrap_or(false) {
+        return Err(FileOperationError::NotFound { path: relative_path(project, target.parent().unwrap_or(project)) });
+    }
+    Ok(target)
+}
+
+fn move_entry(source: &Path, target: &Path, relative_target: &str) -> Result<(), FileOperationError> {
+    if target.exists() {
+        return Err(FileOperationError::AlreadyExists { path: relative_target.to_string() });
+    }
+    std::fs::rename(source, target).map_err(|error| FileOperationError::io(relative_target, error))
+}
+
+/// Where deleted files are kept until the trash is emptied, relative to the project directory.
+pub const TRASH_DIRECTORY: &str = ".designer/trash";
+
+/// A deleted file or folder, stored as `<trash>/<id>/<file name>`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrashedItem {
+    pub id: String,
+    pub original_path: String,
+    /// Seconds since the unix epoch.
+    pub deleted_at: u64,
+}
+
+/// Project local trash, `index.json` lists the items from the oldest to the most recently deleted.
+struct Trash {
+    items: Vec<TrashedItem>,
+}
+
+impl Trash {
+    fn directory(project: &Path) -> PathBuf {
+        project.join(TRASH_DIRECTORY)
+    }
+
+    fn open(project: &Path) -> Self {
+        let index = Self::directory(project).join("index.json");
+        let items = std::fs::read_to_string(index).ok().and_then(|content| serde_json::from_str(&content).ok()).unwrap_or_default();
+        Self { items }
+    }
+
+    fn save(&self, project: &Path) -> Result<(), FileOperationError> {
+        let index = Self::directory(project).join("index.json");
+        let json_text = serde_json::to_string_pretty(&self.items).map_err(|error| FileOperationError::io(TRASH_DIRECTORY, error))?;
+        write_replacing(&index, |writer| writer.write_all(json_text.as_bytes())).map_err(|error| FileOperationError::io(TRASH_DIRECTORY, error))
+    }
+
+    fn put(&mut self, project: &Path, source: &Path, relative: &str) -> Result<TrashedItem, FileOperationError> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        let mut id = now.as_millis().to_string();
+        while self.items.iter().any(|item| item.id == id) {
+            id.push('_');
+        }
+        let slot = Self::directory(project).join(&id);
+        std::fs::create_dir_all(&slot).map_err(|error| FileOperationError::io(TRASH_DIRECTORY, error))?;
+        std::fs::rename(source, slot.join(source.file_name().unwrap_or_default())).map_err(|error| FileOperationError::io(relative, error))?;
+        let item = TrashedItem { id, original_path: relative.to_string(), deleted_at: now.as_secs() };
+        self.items.push(item.clone());
+        self.save(project)?;
+        Ok(item)
+    }
+
+    /// Moves an item back to where it was deleted from, `None` restores the most recently deleted one.
+    fn restore(&mut self, project: &Path, id: Option<&str>) -> Result<TrashedItem, FileOperationError> {
+        let position = match id {
+            Some(id) => self.items.iter().position(|item| item.id == id),
+            None => self.items.len().checked_sub(1),
+        }.ok_or_else(|| FileOperationError::NotFound { path: format!("{}/{}", TRASH_DIRECTORY, id.unwrap_or_default()) })?;
+        let item = self.items[position].clone();
+        let target = sandboxed_path(project, &item.original_path)?;
+        if target.exists() {
+            return Err(FileOperationError::AlreadyExists { path: item.original_path });
+        }
+        let slot = Self::directory(project).join(&item.id);
+        let source = slot.join(target.file_name().unwrap_or_default());
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent).map_err(|error| FileOperationError::io(&item.original_path, error))?;
+        }
+        std::fs::rename(&source, &target).map_err(|error| FileOperationError::io(&item.original_path, error))?;
+        let _ = std::fs::remove_dir(&slot);
+        self.items.remove(position);
+        self.save(project)?;
+        Ok(item)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FileOperationError {
+    ProjectNotOpened,
+    /// The path resolves to a location outside of the project directory.
+    OutsideProject { path: String },
+    /// The project file and the `.designer` directory cannot be changed from the file manager.
+    Protected { path: String },
+    NotFound { path: String },
+    AlreadyExists { path: String },
+    InvalidName { name: String },
+    /// A folder cannot be moved into itself or one of its sub folders.
+    IntoItself { path: String, destination: String },
+    Io { path: String, reason: String },
+    Unsupported(String),
+}
+
+impl FileOperationError {
+    fn io(path: &str, error: impl Display) -> Self {
+        Self::Io { path: path.to_string(), reason: error.to_string() }
+    }
+}
+
+impl Display for FileOperationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FileOperationError::ProjectNotOpened => write!(f, "Please open or create a project first."),
+            FileOperationError::OutsideProject { path } => write!(f, "\"{}\" is outside of the project directory.", path),
+            FileOperationError::Protected { path } => write!(f, "\"{}\" is managed by the editor and cannot be changed from the file manager.", path),
+            FileOperationError::NotFound { path } => write!(f, "\"{}\" does not exist.", path),
+            FileOperationError::AlreadyExists { path } => write!(f, "\"{}\" already exists.", path),
+            FileOperationError::InvalidName { name } => write!(f, "\"{}\" is not a valid file name.", name),
+            FileOperationError::IntoItself { path, destination } => write!(f, "Cannot move \"{}\" into \"{}\".", path, destination),
+            FileOperationError::Io { path, reason } => write!(f, "File operation on \"{}\" failed: {}", path, reason),
+            FileOperationError::Unsupported(command) => write!(f, "{} is not a file operation.", command),
+        }
     }
 }
 
+impl std::error::Error for FileOperationError {}
+
+impl From<FileOperationError> for PluginError {
+    fn from(error: FileOperationError) -> Self {
+        match error {
+            FileOperationError::ProjectNotOpened => PluginError::ProjectNotOpened,
+            FileOperationError::Io { path, reason } => PluginError::Io { path, reason },
+            error => PluginError::Other(error.to_string()),
+        }
+    }
+}
+
+/// Paths are relative to the project directory and use `/` as separator.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum FileManagerCommand {
     ReloadProjectFiles,
+    /// Opens the file in a code editor, see `DocumentManagerCommand::Open`.
     OpenFile(String),
+    /// Closes the buffer of the file, unsaved changes are lost.
     CloseFile(String),
-    RequestContextMenu(String)
+    RequestContextMenu(String),
+    /// Creates an empty file, its parent folder must exist.
+    CreateFile { path: String },
+    CreateFolder { path: String },
+    /// Renames a file or folder in place, `new_name` is a plain file name.
+    Rename { path: String, new_name: String },
+    /// Moves a file or folder into the `destination` folder, `""` is the project root.
+    Move { path: String, destination: String },
+    /// Moves a file or folder to the project trash, it can be restored by `RestoreDeleted`.
+    Delete { path: String },
+    /// Restores an item of the trash, `None` restores the most recently deleted one.
+    RestoreDeleted { id: Option<String> },
+    EmptyTrash,
+    /// Applies the changes the file watcher collected, the front-end sends it when `FileManager::has_pending_changes` is true.
+    ApplyFileChanges,
+    /// Reads the content of a folder when it is expanded in the tree, `""` is the project root.
+    LoadFolder { path: String },
 }
 
 impl ICommand for FileManagerCommand {
@@ -154,9 +850,88 @@ impl ICommand for FileManagerCommand {
     }
 }
 
+/// Queries answered through `DesignerCore::request`, paths are relative to the project directory.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum FileManagerRequest {
+    /// Replies `Option<FileEntry>`, the scanned project tree.
+    ProjectFiles,
+    /// Replies `Vec<FileEntry>`, direct children of a directory without descending into sub folders.
+    ListDirectory { path: String },
+    /// Replies `String`, the content of a text file.
+    ReadFile { path: String },
+    /// Replies `Vec<TrashedItem>`, the most recently deleted item is the last one.
+    Trash,
+}
+
+impl ICommand for FileManagerRequest {
+    fn action(&self) -> &str {
+        "designer.file_manager.request"
+    }
+
+    fn args(&self) -> serde_json::Value {
+        serde_json::to_value(self).unwrap()
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum FileManagerEvent {
     ProjectFilesAreReopened,
+    ProjectFilesAreClosed,
+    Created { path: String, folder: bool },
+    /// Editors showing `from` or a file inside of it should follow it to `to`.
+    Renamed { from: String, to: String },
+    Moved { from: String, to: String },
+    /// The item was moved to the trash, editors of it should be closed.
+    Deleted { path: String, trash_id: String },
+    Restored { path: String, trash_id: String },
+    TrashEmptied,
+    /// A change made outside of the file manager, reported by the file watcher.
+    Disk(DiskChange),
+}
+
+impl FileManagerEvent {
+    /// Project relative paths touched by a file operation.
+    fn paths(&self) -> Vec<&str> {
+        match self {
+            FileManagerEvent::Created { path, .. } | FileManagerEvent::Deleted { path, .. } | FileManagerEvent::Restored { path, .. } => vec![path],
+            FileManagerEvent::Renamed { from, to } | FileManagerEvent::Moved { from, to } => vec![from, to],
+            _ => vec![],
+        }
+    }
+
+    /// The history step of a file operation, emptying the trash cannot be undone.
+    fn undo_entry(&self) -> Option<UndoEntry> {
+        let name = |path: &str| path.rsplit('/').next().unwrap_or(path).to_string();
+        let parent = |path: &str| path.rsplit_once('/').map(|(parent, _)| parent.to_string()).unwrap_or_default();
+        Some(match self {
+            FileManagerEvent::Created { path, folder } => {
+                let create = if *folder {FileManagerCommand::CreateFolder { path: path.clone() }} else {FileManagerCommand::CreateFile { path: path.clone() }};
+                UndoEntry::new(format!("Create \"{}\"", path), &create, &FileManagerCommand::Delete { path: path.clone() })
+            },
+            FileManagerEvent::Renamed { from, to } => UndoEntry::new(
+                format!("Rename \"{}\" to \"{}\"", from, name(to)),
+                &FileManagerCommand::Rename { path: from.clone(), new_name: name(to) },
+                &FileManagerCommand::Rename { path: to.clone(), new_name: name(from) },
+            ),
+            FileManagerEvent::Moved { from, to } => UndoEntry::new(
+                format!("Move \"{}\" to \"{}\"", from, parent(to)),
+                &FileManagerCommand::Move { path: from.clone(), destination: parent(to) },
+                &FileManagerCommand::Move { path: to.clone(), destination: parent(from) },
+            ),
+            FileManagerEvent::Deleted { path, trash_id } => UndoEntry::new(format!("Delete \"{}\"", path), &FileManagerCommand::Delete { path: path.clone() }, &FileManagerCommand::RestoreDeleted { id: Some(trash_id.clone()) }),
+            FileManagerEvent::Restored { path, trash_id } => UndoEntry::new(format!("Restore \"{}\"", path), &FileManagerCommand::RestoreDeleted { id: Some(trash_id.clone()) }, &FileManagerCommand::Delete { path: path.clone() }),
+            _ => return None,
+        })
+    }
+}
+
+/// Paths are relative to the project directory, a created folder is reported once with its content.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum DiskChange {
+    Created { path: String, folder: bool },
+    /// The content of a file was written, editors of it should reload or warn about unsaved changes.
+    Modified { path: String },
+    Removed { path: String },
 }
 
 impl ICommand for FileManagerEvent {
@@ -1,7 +1,9 @@
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 
-use crate::backend::{commands::{Command, ICommand}, plugin::{IPlugin, PluginInfo, PluginStatus}};
+use crate::{backend::{commands::ICommand, plugin::{parse_args, IPlugin, IPluginStateStorage, PluginError, PluginInfo, PluginStatus, VersionRange}, plugin_settings::SettingScope, plugin_state::parse_state}, DesignerCore};
+
+use super::preferences::{Preferences, PreferencesEvent, PreferencesRequest};
 
 /**
  * @author مۇختەرجان مەخمۇت
@@ -16,29 +18,66 @@ pub struct Logger {
     ///sender, category and content of logs.
     pub logs: Vec<(String, String, String)>,
     status: PluginStatus,
+    /// Logs kept in memory, `Preferences::LOG_BUFFER_SIZE`.
+    #[serde(skip)]
+    capacity: usize,
+}
+
+/// Logs kept between sessions, only the latest `Logger::STORED_LOG_COUNT` ones.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct LoggerState {
+    logs: Vec<(String, String, String)>,
 }
 
 impl Logger {
+    pub const UUID: &'static str = "aacd2e16-52b1-40e8-b504-0aceffd5b466";
+    const STORED_LOG_COUNT: usize = 512;
+
+    const DEFAULT_CAPACITY: usize = 1024;
+
     pub fn new() -> Self {
-        Self { logs: vec![], status: PluginStatus::Disabled }
+        Self { logs: vec![], status: PluginStatus::Disabled, capacity: Self::DEFAULT_CAPACITY }
+    }
+
+    /// Reads the buffer size from the preferences, the current one is kept when they are not available.
+    fn read_capacity(&mut self, root: &mut DesignerCore) {
+        if let Ok(Some(capacity)) = root.request::<Option<usize>>("designer.logger", &PreferencesRequest::Value { key: String::from(Preferences::LOG_BUFFER_SIZE) }) {
+            self.capacity = capacity;
+        }
+        let overflow = self.logs.len().saturating_sub(self.capacity);
+        self.logs.drain(..overflow);
     }
 }
 
 impl IPlugin for Logger {
     fn info(&self) -> crate::backend::plugin::PluginInfo {
         PluginInfo {
-            uuid: "aacd2e16-52b1-40e8-b504-0aceffd5b466",
+            uuid: String::from(Self::UUID),
             name: String::from("Logger (Native plugin)"),
             version: (0, 0, 1),
-            author: "dream-lab",
-            description: String::from("Collect logs from other plugin or extension.")
+            author: String::from("dream-lab"),
+            description: String::from("Collect logs from other plugin or extension."),
+            subscriptions: vec![String::from("designer.logger.*"), String::from("designer.preferences.event")],
+            dependencies: vec![],
+            api_version: Some(VersionRange::at_least((0, 1, 0))),
         }
     }
-    fn load_state(&mut self, storage: &mut dyn crate::backend::plugin::IPluginStateStorage) {
-        *self = serde_json::from_value(storage.load_state()).unwrap();
+    fn load_state(&mut self, storage: &mut dyn IPluginStateStorage) {
+        if storage.scope() != SettingScope::User {
+            return;
+        }
+        // logs of the previous session come before the ones generated so far.
+        let mut state = parse_state::<LoggerState>(storage.load_state());
+        state.logs.append(&mut self.logs);
+        self.logs = state.logs;
     }
-    fn store_state(&mut self, storage: &mut dyn crate::backend::plugin::IPluginStateStorage) {
-        
+    fn store_state(&mut self, storage: &mut dyn IPluginStateStorage) -> Result<(), PluginError> {
+        if storage.scope() != SettingScope::User {
+            return Ok(());
+        }
+        let logs = self.logs[self.logs.len().saturating_sub(Self::STORED_LOG_COUNT)..].to_vec();
+        storage.store_state(serde_json::to_value(LoggerState { logs }).unwrap())
     }
     fn status(&self) -> crate::backend::plugin::PluginStatus {
         self.status.clone()
@@ -49,6 +88,7 @@ impl IPlugin for Logger {
     }
 
     fn enable(&mut self, root: &mut crate::DesignerCore) {
+        self.read_capacity(root);
         self.status = PluginStatus::Enabled
     }
 
@@ -62,25 +102,42 @@ impl IPlugin for Logger {
         })
     }
 
-    fn handle_message(&mut self, root: &mut crate::DesignerCore, sender: &str, action: &str, args: Value) {
+    fn handle_message(&mut self, root: &mut crate::DesignerCore, sender: &str, action: &str, args: Value) -> Result<(), PluginError> {
+        if action == "designer.preferences.event" {
+            if let PreferencesEvent::Changed { keys } = parse_args(action, args)? {
+                if keys.iter().any(|key| key == Preferences::LOG_BUFFER_SIZE) {
+                    self.read_capacity(root);
+                    root.notify_ui();
+                }
+            }
+            return Ok(());
+        }
         if action != "designer.logger.log" {
-            return;
+            return Ok(());
         }
-        let command: LogCommand  = serde_json::from_value(args).unwrap();
+        let command: LogCommand = parse_args(action, args)?;
         match command {
             LogCommand::Generate { category, content } => {
                 self.logs.push((sender.to_string(), category, content));
+                if self.logs.len() > self.capacity {
+                    self.logs.remove(0);
+                }
             },
             LogCommand::Clear => {
                 self.logs.clear();
             }
         }
         root.notify_ui();
+        Ok(())
     }
 
     fn as_any(&self) -> &dyn std::any::Any {
         self
     }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -11,4 +11,9 @@ pub mod project_manager;
 pub mod logger;
 pub mod event_interceptor;
 pub mod window_manager;
-pub mod file_manager;
\ No newline at end of file
+pub mod file_manager;
+pub mod document_manager;
+pub mod notification_center;
+pub mod preferences;
+pub mod search_manager;
+pub mod node_graph_manager;
\ No newline at end of file
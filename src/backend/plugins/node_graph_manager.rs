@@ -0,0 +1,418 @@
+use std::{io::Write, path::{Path, PathBuf}};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{backend::{commands::ICommand, history::UndoEntry, node_graph::{Graph, GraphClipboard, GraphError, NodeId, NodeType, PortDefinition, PortRef}, plugin::{parse_args, IPlugin, PluginError, PluginDependency, PluginInfo, PluginStatus, VersionRange}, request::reply}, DesignerCore};
+
+use super::{file_manager::{relative_path, sandboxed_path, write_replacing, FileManager, FileManagerEvent}, project_manager::{ProjectManager, ProjectManagerEvent, ProjectManagerRequest}, window_manager::FileTabChange};
+
+/// A graph file open in a node editor.
+#[derive(Debug, Clone)]
+pub struct OpenGraph {
+    /// Relative to the project directory with `/` separators.
+    pub path: String,
+    pub graph: Graph,
+    /// What the file holds, the graph has unsaved changes while it differs.
+    saved: Graph,
+}
+
+impl OpenGraph {
+    pub fn dirty(&self) -> bool {
+        self.graph != self.saved
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeGraphManager {
+    #[serde(skip_serializing)]
+    status: PluginStatus,
+    /// Of every plugin, including disabled ones, see `node_types`.
+    #[serde(skip)]
+    node_types: Vec<NodeType>,
+    #[serde(skip)]
+    graphs: Vec<OpenGraph>,
+}
+
+impl NodeGraphManager {
+    pub const UUID: &'static str = "b7e2a9d4-5c13-4f6e-8a0b-93d1e6f24c58";
+
+    pub fn new() -> Self {
+        Self { status: PluginStatus::Disabled, node_types: builtin_node_types(), graphs: vec![] }
+    }
+
+    pub fn graph(&self, path: &str) -> Option<&OpenGraph> {
+        self.graphs.iter().find(|graph| graph.path == path)
+    }
+
+    pub fn has_unsaved_graphs(&self) -> bool {
+        self.graphs.iter().any(|graph| graph.dirty())
+    }
+
+    /// Node types of the enabled plugins, the ones of plugins that are disabled or unloaded are left out.
+    pub fn node_types(&self, root: &DesignerCore) -> Vec<NodeType> {
+        self.node_types.iter().filter(|node_type| node_type.owner == Self::UUID || root.plugin_status(&node_type.owner).map(|status| status == PluginStatus::Enabled).unwrap_or(true)).cloned().collect()
+    }
+
+    fn graph_mut(&mut self, path: &str) -> Result<&mut OpenGraph, PluginError> {
+        self.graphs.iter_mut().find(|graph| graph.path == path).ok_or_else(|| PluginError::Other(format!("\"{}\" is not open.", path)))
+    }
+
+    /// Types defined again by the same plugin replace the previous ones, ids of other plugins cannot be taken over.
+    fn register_node_types(&mut self, root: &mut DesignerCore, owner: &str, types: Vec<NodeType>) -> Result<(), PluginError> {
+        if let Some(taken) = types.iter().find_map(|new| self.node_types.iter().find(|existing| existing.id == new.id && existing.owner != owner)) {
+            return Err(PluginError::Other(format!("The node type \"{}\" is already defined by plugin {}.", taken.id, taken.owner)));
+        }
+        self.node_types.retain(|existing| !types.iter().any(|new| new.id == existing.id));
+        self.node_types.extend(types.into_iter().map(|node_type| NodeType { owner: owner.to_string(), ..node_type }));
+        root.publish_message("designer.node_graph", &NodeGraphEvent::NodeTypesChanged);
+        Ok(())
+    }
+
+    /// Reads the graph file unless it is already open, returns the normalized relative path.
+    fn open(&mut self, root: &mut DesignerCore, project: &Path, path: &str) -> Result<String, PluginError> {
+        let absolute = sandboxed_path(project, path)?;
+        let path = relative_path(project, &absolute);
+        if self.graph(&path).is_none() {
+            let text = std::fs::read_to_string(&absolute).map_err(|error| PluginError::io(&absolute, error))?;
+            let graph = Graph::from_json(&text)?;
+            self.graphs.push(OpenGraph { path: path.clone(), saved: graph.clone(), graph });
+        }
+        root.publish_message("designer.node_graph", &NodeGraphEvent::Opened { path: path.clone() });
+        Ok(path)
+    }
+
+    fn edit(&mut self, root: &mut DesignerCore, path: &str, edit: GraphEdit) -> Result<(), PluginError> {
+        let types = self.node_types(root);
+        let open = self.graph_mut(path)?;
+        let before = open.graph.clone();
+        let mut graph = before.clone();
+        match &edit {
+            GraphEdit::AddNode { type_id, position } => {
+                if !types.iter().any(|node_type| node_type.id == *type_id) {
+                    return Err(GraphError::UnknownNodeType(type_id.clone()).into());
+                }
+                graph.add_node(type_id, *position);
+            },
+            GraphEdit::RemoveNodes { nodes } => graph.remove_nodes(nodes),
+            GraphEdit::MoveNodes { nodes, delta } => graph.move_nodes(nodes, *delta),
+            GraphEdit::Connect { from, to } => graph.connect(&types, from.clone(), to.clone())?,
+            GraphEdit::Disconnect { to } => graph.disconnect(to),
+            GraphEdit::Paste { clipboard, offset } => {
+                graph.paste(clipboard, *offset);
+            },
+        }
+        if graph == before {
+            return Ok(());
+        }
+        open.graph = graph.clone();
+        let path = path.to_string();
+        root.record_undo(UndoEntry::new(edit.label(), &NodeGraphCommand::Restore { path: path.clone(), graph }, &NodeGraphCommand::Restore { path: path.clone(), graph: before }));
+        root.publish_message("designer.node_graph", &NodeGraphEvent::Changed { path });
+        Ok(())
+    }
+
+    /// Writes a graph to its file through a temporary file, folders removed in the meantime are created again.
+    fn save(&mut self, root: &mut DesignerCore, project: &Path, path: &str) -> Result<(), PluginError> {
+        let absolute = sandboxed_path(project, path)?;
+        let open = self.graph_mut(path)?;
+        if let Some(parent) = absolute.parent() {
+            std::fs::create_dir_all(parent).map_err(|error| PluginError::io(parent, error))?;
+        }
+        write_replacing(&absolute, |writer| writer.write_all(open.graph.to_json().as_bytes())).map_err(|error| PluginError::io(&absolute, error))?;
+        open.saved = open.graph.clone();
+        root.publish_message("designer.node_graph", &NodeGraphEvent::Saved { path: path.to_string() });
+        Ok(())
+    }
+
+    /// Saves every graph in `paths`, one that cannot be written does not stop the others and all failures are reported together.
+    fn save_graphs(&mut self, root: &mut DesignerCore, project: &Path, paths: Vec<String>) -> Result<(), PluginError> {
+        let failures = paths.iter().filter_map(|path| self.save(root, project, path).err()).map(|error| error.to_string()).collect::<Vec<_>>();
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(PluginError::Other(failures.join("\n")))
+        }
+    }
+
+    /// Graphs follow renamed and moved files, the ones of deleted files are closed.
+    fn follow_file_event(&mut self, root: &mut DesignerCore, event: FileManagerEvent) {
+        match event {
+            FileManagerEvent::Renamed { from, to } | FileManagerEvent::Moved { from, to } => {
+                let change = FileTabChange::Moved { from, to };
+                for open in self.graphs.iter_mut() {
+                    if let Some(path) = change.apply(&open.path) {
+                        open.path = path;
+                    }
+                }
+            },
+            FileManagerEvent::Deleted { path, .. } => {
+                let change = FileTabChange::Removed { path };
+                let (closed, kept) = std::mem::take(&mut self.graphs).into_iter().partition::<Vec<_>, _>(|open| change.apply(&open.path).is_none());
+                self.graphs = kept;
+                for open in closed {
+                    root.publish_message("designer.node_graph", &NodeGraphEvent::Closed { path: open.path });
+                }
+            },
+            _ => return,
+        }
+        root.notify_ui();
+    }
+}
+
+/// Node types every graph can use, other plugins add their own with `NodeGraphCommand::RegisterNodeTypes`.
+fn builtin_node_types() -> Vec<NodeType> {
+    let node_type = |id: &str, title: &str, category: &str, inputs: Vec<PortDefinition>, outputs: Vec<PortDefinition>| NodeType {
+        id: id.to_string(), title: title.to_string(), category: category.to_string(), inputs, outputs, owner: String::from(NodeGraphManager::UUID),
+    };
+    let numbers = || vec![PortDefinition::new("a", "A", "number"), PortDefinition::new("b", "B", "number")];
+    vec![
+        node_type("core.add", "Add", "Math", numbers(), vec![PortDefinition::new("result", "Result", "number")]),
+        node_type("core.multiply", "Multiply", "Math", numbers(), vec![PortDefinition::new("result", "Result", "number")]),
+        node_type("core.compare", "Compare", "Math", numbers(), vec![PortDefinition::new("less", "A < B", "boolean"), PortDefinition::new("equal", "A = B", "boolean")]),
+        node_type("core.branch", "Branch", "Logic", vec![PortDefinition::new("condition", "Condition", "boolean"), PortDefinition::new("value", "Value", "any")], vec![PortDefinition::new("true", "True", "any"), PortDefinition::new("false", "False", "any")]),
+        node_type("core.join", "Join text", "Text", vec![PortDefinition::new("a", "A", "string"), PortDefinition::new("b", "B", "string")], vec![PortDefinition::new("result", "Result", "string")]),
+        node_type("core.to_text", "To text", "Text", vec![PortDefinition::new("value", "Value", "any")], vec![PortDefinition::new("text", "Text", "string")]),
+        node_type("core.log", "Log", "Debug", vec![PortDefinition::new("value", "Value", "any")], vec![]),
+    ]
+}
+
+impl Default for NodeGraphManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IPlugin for NodeGraphManager {
+    fn info(&self) -> PluginInfo {
+        PluginInfo {
+            name: String::from("Node graph manager (Native Plugin)"),
+            author: String::from("dream-lab"),
+            uuid: String::from(Self::UUID),
+            version: (0, 0, 1),
+            description: String::from("Keeps the node types of the plugins and the graphs open in node editors."),
+            subscriptions: vec![String::from("designer.node_graph.*"), String::from("designer.project_manager.event"), String::from("designer.file_manager.event")],
+            dependencies: vec![PluginDependency::new(ProjectManager::UUID), PluginDependency::new(FileManager::UUID)],
+            api_version: Some(VersionRange::at_least((0, 1, 0))),
+        }
+    }
+
+    fn status(&self) -> PluginStatus {
+        self.status.clone()
+    }
+
+    fn initialize(&mut self, _root: &mut DesignerCore) {
+
+    }
+
+    fn enable(&mut self, _root: &mut DesignerCore) {
+        self.status = PluginStatus::Enabled
+    }
+
+    fn disable(&mut self, _root: &mut DesignerCore) {
+        self.status = PluginStatus::Disabled
+    }
+
+    fn load_state(&mut self, _storage: &mut dyn crate::backend::plugin::IPluginStateStorage) {
+        // graphs are read from their files when their editors open again.
+    }
+
+    fn store_state(&mut self, _storage: &mut dyn crate::backend::plugin::IPluginStateStorage) -> Result<(), PluginError> {
+        Ok(())
+    }
+
+    fn get_state(&self) -> Value {
+        serde_json::to_value(self).unwrap()
+    }
+
+    fn handle_message(&mut self, root: &mut DesignerCore, sender: &str, action: &str, args: Value) -> Result<(), PluginError> {
+        if action == "designer.project_manager.event" {
+            match parse_args::<ProjectManagerEvent>(action, args)? {
+                ProjectManagerEvent::Closing { path, save_documents: true } => {
+                    let dirty = self.graphs.iter().filter(|open| open.dirty()).map(|open| open.path.clone()).collect::<Vec<_>>();
+                    self.save_graphs(root, Path::new(&path), dirty)?;
+                },
+                ProjectManagerEvent::Closed { .. } => {
+                    self.graphs.clear();
+                },
+                _ => {},
+            }
+            return Ok(());
+        }
+        if action == "designer.file_manager.event" {
+            let event = parse_args::<FileManagerEvent>(action, args)?;
+            self.follow_file_event(root, event);
+            return Ok(());
+        }
+        if action != "designer.node_graph.management" {
+            return Ok(());
+        }
+        let command = parse_args::<NodeGraphCommand>(action, args)?;
+        match command {
+            NodeGraphCommand::RegisterNodeTypes { owner, types } => {
+                let owner = if owner.is_empty() {sender.to_string()} else {owner};
+                self.register_node_types(root, &owner, types)?;
+                root.notify_ui();
+                return Ok(());
+            },
+            NodeGraphCommand::UnregisterNodeTypes { owner } => {
+                self.node_types.retain(|node_type| node_type.owner != owner);
+                root.publish_message("designer.node_graph", &NodeGraphEvent::NodeTypesChanged);
+                root.notify_ui();
+                return Ok(());
+            },
+            _ => {},
+        }
+        let project = root.request::<Option<PathBuf>>("designer.node_graph", &ProjectManagerRequest::ProjectPath)?.ok_or(PluginError::ProjectNotOpened)?;
+        match command {
+            NodeGraphCommand::Open { path } => {
+                self.open(root, &project, &path)?;
+            },
+            NodeGraphCommand::Edit { path, edit } => {
+                self.edit(root, &path, edit)?;
+            },
+            NodeGraphCommand::Restore { path, graph } => {
+                let path = self.open(root, &project, &path)?;
+                self.graph_mut(&path)?.graph = graph;
+                root.publish_message("designer.node_graph", &NodeGraphEvent::Changed { path });
+            },
+            NodeGraphCommand::Save { path } => {
+                self.save(root, &project, &path)?;
+            },
+            NodeGraphCommand::SaveAll => {
+                let dirty = self.graphs.iter().filter(|open| open.dirty()).map(|open| open.path.clone()).collect::<Vec<_>>();
+                self.save_graphs(root, &project, dirty)?;
+            },
+            NodeGraphCommand::Close { path } => {
+                let count = self.graphs.len();
+                self.graphs.retain(|open| open.path != path);
+                if self.graphs.len() != count {
+                    root.publish_message("designer.node_graph", &NodeGraphEvent::Closed { path });
+                }
+            },
+            NodeGraphCommand::RegisterNodeTypes { .. } | NodeGraphCommand::UnregisterNodeTypes { .. } => {},
+        }
+        root.notify_ui();
+        Ok(())
+    }
+
+    fn handle_request(&mut self, root: &mut DesignerCore, _sender: &str, action: &str, args: Value) -> Option<Result<Value, PluginError>> {
+        if action != "designer.node_graph.request" {
+            return None;
+        }
+        let request = match parse_args::<NodeGraphRequest>(action, args) {
+            Ok(request) => request,
+            Err(error) => return Some(Err(error)),
+        };
+        match request {
+            NodeGraphRequest::NodeTypes => reply(self.node_types(root)),
+            NodeGraphRequest::Graph { path } => reply(self.graph(&path).map(|open| open.graph.clone())),
+            NodeGraphRequest::OpenGraphs => reply(self.graphs.iter().map(|open| open.path.clone()).collect::<Vec<_>>()),
+            NodeGraphRequest::UnsavedGraphs => reply(self.graphs.iter().filter(|open| open.dirty()).map(|open| open.path.clone()).collect::<Vec<_>>()),
+        }
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+/// One change of a graph, sent by the node editor or by other plugins.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum GraphEdit {
+    AddNode { type_id: String, position: [f32; 2] },
+    RemoveNodes { nodes: Vec<NodeId> },
+    MoveNodes { nodes: Vec<NodeId>, delta: [f32; 2] },
+    /// From an output to an input, the types of the ports are checked.
+    Connect { from: PortRef, to: PortRef },
+    Disconnect { to: PortRef },
+    /// The new nodes get the ids from `Graph::next_id` on.
+    Paste { clipboard: GraphClipboard, offset: [f32; 2] },
+}
+
+impl GraphEdit {
+    fn label(&self) -> String {
+        match self {
+            GraphEdit::AddNode { .. } => String::from("Add a node"),
+            GraphEdit::RemoveNodes { nodes } => format!("Remove {} node(s)", nodes.len()),
+            GraphEdit::MoveNodes { nodes, .. } => format!("Move {} node(s)", nodes.len()),
+            GraphEdit::Connect { .. } => String::from("Connect nodes"),
+            GraphEdit::Disconnect { .. } => String::from("Disconnect nodes"),
+            GraphEdit::Paste { clipboard, .. } => format!("Paste {} node(s)", clipboard.nodes.len()),
+        }
+    }
+}
+
+/// Paths are relative to the project directory.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum NodeGraphCommand {
+    /// `owner` is the uuid of the plugin defining the types, the sender is taken when it is empty.
+    RegisterNodeTypes { #[serde(default)] owner: String, types: Vec<NodeType> },
+    UnregisterNodeTypes { owner: String },
+    /// Reads the graph unless it is already open, `NodeGraphEvent::Opened` asks the window to show it.
+    Open { path: String },
+    Edit { path: String, edit: GraphEdit },
+    /// Sets the whole graph, sent by the undo and redo of an edit.
+    Restore { path: String, graph: Graph },
+    Save { path: String },
+    SaveAll,
+    /// Drops the graph, unsaved changes are lost.
+    Close { path: String },
+}
+
+impl ICommand for NodeGraphCommand {
+    fn action(&self) -> &str {
+        "designer.node_graph.management"
+    }
+
+    fn args(&self) -> Value {
+        serde_json::to_value(self).unwrap()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum NodeGraphRequest {
+    /// Replies `Vec<NodeType>`, the types of the enabled plugins.
+    NodeTypes,
+    /// Replies `Option<Graph>`.
+    Graph { path: String },
+    /// Replies `Vec<String>`, paths of the open graphs.
+    OpenGraphs,
+    /// Replies `Vec<String>`, paths of the graphs with unsaved changes.
+    UnsavedGraphs,
+}
+
+impl ICommand for NodeGraphRequest {
+    fn action(&self) -> &str {
+        "designer.node_graph.request"
+    }
+
+    fn args(&self) -> Value {
+        serde_json::to_value(self).unwrap()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum NodeGraphEvent {
+    /// Also published for a graph that was already open, the window focuses its editor.
+    Opened { path: String },
+    /// The graph changed by an edit, undo or redo.
+    Changed { path: String },
+    Saved { path: String },
+    Closed { path: String },
+    NodeTypesChanged,
+}
+
+impl ICommand for NodeGraphEvent {
+    fn action(&self) -> &str {
+        "designer.node_graph.event"
+    }
+
+    fn args(&self) -> Value {
+        serde_json::to_value(self).unwrap()
+    }
+}
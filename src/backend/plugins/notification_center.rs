@@ -0,0 +1,133 @@
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{backend::{commands::ICommand, counter::Counter, designer_core::CoreErrorEvent, plugin::{parse_args, IPlugin, PluginError, PluginInfo, PluginStatus, VersionRange}}, DesignerCore};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Notification {
+    pub id: usize,
+    pub title: String,
+    pub message: String,
+    pub created: SystemTime,
+}
+
+/// Collects user facing notifications (core errors for now) which the front-end shows as toasts.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationCenter {
+    status: PluginStatus,
+    pub notifications: Vec<Notification>,
+    ids: Counter,
+}
+
+impl NotificationCenter {
+    pub const UUID: &'static str = "0c7e0b9e-5f5e-4d55-a1a4-6a3f5b8b9d21";
+
+    pub fn new() -> Self {
+        Self { status: PluginStatus::Disabled, notifications: vec![], ids: Counter::new() }
+    }
+
+    fn push(&mut self, title: String, message: String) {
+        let id = self.ids.count();
+        self.notifications.push(Notification { id, title, message, created: SystemTime::now() });
+    }
+}
+
+impl Default for NotificationCenter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IPlugin for NotificationCenter {
+    fn info(&self) -> PluginInfo {
+        PluginInfo {
+            uuid: String::from(Self::UUID),
+            name: String::from("Notification center (Native plugin)"),
+            description: String::from("Turns core errors and plugin notices into notifications for the UI."),
+            version: (0, 0, 1),
+            author: String::from("dream-lab"),
+            subscriptions: vec![String::from("designer.core.error"), String::from("designer.notification_center.*")],
+            dependencies: vec![],
+            api_version: Some(VersionRange::at_least((0, 1, 0))),
+        }
+    }
+
+    fn status(&self) -> PluginStatus {
+        self.status.clone()
+    }
+
+    fn initialize(&mut self, _root: &mut DesignerCore) {
+
+    }
+
+    fn enable(&mut self, _root: &mut DesignerCore) {
+        self.status = PluginStatus::Enabled;
+    }
+
+    fn disable(&mut self, _root: &mut DesignerCore) {
+        self.status = PluginStatus::Disabled;
+    }
+
+    fn load_state(&mut self, _storage: &mut dyn crate::backend::plugin::IPluginStateStorage) {
+
+    }
+
+    fn store_state(&mut self, _storage: &mut dyn crate::backend::plugin::IPluginStateStorage) -> Result<(), PluginError> {
+        Ok(())
+    }
+
+    fn get_state(&self) -> Value {
+        serde_json::to_value(self).unwrap()
+    }
+
+    fn handle_message(&mut self, root: &mut DesignerCore, _sender: &str, action: &str, args: Value) -> Result<(), PluginError> {
+        if action == "designer.core.error" {
+            let event = parse_args::<CoreErrorEvent>(action, args)?;
+            self.push(format!("{} failed", event.plugin_name), event.error.to_string());
+        } else if action == "designer.notification_center.management" {
+            match parse_args::<NotificationCommand>(action, args)? {
+                NotificationCommand::Notify { title, message } => {
+                    self.push(title, message);
+                },
+                NotificationCommand::Dismiss(id) => {
+                    self.notifications.retain(|item| item.id != id);
+                },
+                NotificationCommand::Clear => {
+                    self.notifications.clear();
+                },
+            }
+        }
+        root.notify_ui();
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum NotificationCommand {
+    Notify {
+        title: String,
+        message: String,
+    },
+    Dismiss(usize),
+    Clear,
+}
+
+impl ICommand for NotificationCommand {
+    fn action(&self) -> &str {
+        "designer.notification_center.management"
+    }
+
+    fn args(&self) -> Value {
+        serde_json::to_value(self).unwrap()
+    }
+}
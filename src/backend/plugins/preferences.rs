@@ -0,0 +1,416 @@
+use std::collections::BTreeMap;
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::{backend::{commands::ICommand, plugin::{parse_args, IPlugin, IPluginStateStorage, PluginError, PluginInfo, PluginStatus, VersionRange}, plugin_settings::SettingScope, plugin_state::parse_state, request::reply}, DesignerCore};
+
+use super::project_manager::ProjectManagerEvent;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum PreferenceKind {
+    Bool,
+    Integer { min: i64, max: i64 },
+    Float { min: f64, max: f64 },
+    Text,
+    Choice(Vec<String>),
+    /// A shortcut such as `Ctrl+Shift+S`, an empty text removes the binding.
+    Keybinding,
+}
+
+impl PreferenceKind {
+    pub fn validate(&self, value: &Value) -> Result<(), String> {
+        let valid = match self {
+            PreferenceKind::Bool => value.is_boolean(),
+            PreferenceKind::Integer { min, max } => value.as_i64().is_some_and(|value| (*min..=*max).contains(&value)),
+            PreferenceKind::Float { min, max } => value.as_f64().is_some_and(|value| (*min..=*max).contains(&value)),
+            PreferenceKind::Text => value.is_string(),
+            PreferenceKind::Choice(choices) => value.as_str().is_some_and(|value| choices.iter().any(|choice| choice == value)),
+            PreferenceKind::Keybinding => value.as_str().is_some_and(is_valid_keybinding),
+        };
+        if valid {
+            return Ok(());
+        }
+        Err(match self {
+            PreferenceKind::Integer { min, max } => format!("expected a whole number between {} and {}", min, max),
+            PreferenceKind::Float { min, max } => format!("expected a number between {} and {}", min, max),
+            PreferenceKind::Choice(choices) => format!("expected one of {}", choices.join(", ")),
+            PreferenceKind::Keybinding => String::from("expected modifiers and a key such as Ctrl+S"),
+            kind => format!("expected a {:?} value", kind),
+        })
+    }
+}
+
+/// Modifiers joined by `+` and followed by a single key, for example `Ctrl+Shift+Z` or `F5`.
+fn is_valid_keybinding(binding: &str) -> bool {
+    if binding.is_empty() {
+        return true;
+    }
+    let parts = binding.split('+').collect::<Vec<_>>();
+    let (key, modifiers) = parts.split_last().unwrap();
+    !key.is_empty() && !matches!(*key, "Ctrl" | "Shift" | "Alt" | "Cmd")
+        && modifiers.iter().all(|modifier| matches!(*modifier, "Ctrl" | "Shift" | "Alt" | "Cmd"))
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PreferenceEntry {
+    /// Unique among all sections, prefixed by the section id such as `appearance.theme`.
+    pub key: String,
+    pub title: String,
+    #[serde(default)]
+    pub description: String,
+    pub kind: PreferenceKind,
+    pub default: Value,
+}
+
+impl PreferenceEntry {
+    pub fn new(key: &str, title: &str, kind: PreferenceKind, default: Value) -> Self {
+        Self { key: key.to_string(), title: title.to_string(), description: String::new(), kind, default }
+    }
+
+    pub fn with_description(mut self, description: &str) -> Self {
+        self.description = description.to_string();
+        self
+    }
+}
+
+/// A group of preferences shown under one heading, plugins contribute theirs with `PreferencesCommand::RegisterSection`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PreferenceSection {
+    pub id: String,
+    pub title: String,
+    /// Uuid of the plugin contributing the section.
+    pub owner: String,
+    pub entries: Vec<PreferenceEntry>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Theme {
+    Dark,
+    Light,
+    /// Follows the theme of the operating system.
+    System,
+}
+
+/// Typed view of the built in preferences with the user and project overrides applied.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EditorPreferences {
+    pub reopen_last_project: bool,
+    pub theme: Theme,
+    pub font_size: f32,
+    /// Seconds between automatic saves of modified documents, 0 turns autosave off.
+    pub autosave_interval: u64,
+    /// Logs kept in the log window.
+    pub log_buffer_size: usize,
+    /// Shortcut of each command, commands without a binding are left out.
+    pub keybindings: BTreeMap<String, String>,
+}
+
+/// Values of one scope, keys of sections that are not registered (yet) are kept as they are.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct PreferencesState {
+    values: BTreeMap<String, Value>,
+}
+
+pub struct Preferences {
+    status: PluginStatus,
+    sections: Vec<PreferenceSection>,
+    user_values: BTreeMap<String, Value>,
+    project_values: BTreeMap<String, Value>,
+}
+
+impl Preferences {
+    pub const UUID: &'static str = "0d8f0f2a-43c4-4a57-9a53-52f6c5b39a3e";
+    pub const REOPEN_LAST_PROJECT: &'static str = "general.reopen_last_project";
+    pub const THEME: &'static str = "appearance.theme";
+    pub const FONT_SIZE: &'static str = "appearance.font_size";
+    pub const AUTOSAVE_INTERVAL: &'static str = "editor.autosave_interval";
+    pub const LOG_BUFFER_SIZE: &'static str = "logger.buffer_size";
+    pub const KEYBINDING_PREFIX: &'static str = "keybindings.";
+
+    pub fn new() -> Self {
+        Self { status: PluginStatus::Disabled, sections: Self::builtin_sections(), user_values: BTreeMap::new(), project_values: BTreeMap::new() }
+    }
+
+    fn builtin_sections() -> Vec<PreferenceSection> {
+        let keybinding = |command: &str, title: &str, default: &str| PreferenceEntry::new(&format!("{}{}", Self::KEYBINDING_PREFIX, command), title, PreferenceKind::Keybinding, json!(default));
+        vec![
+            PreferenceSection { id: String::from("general"), title: String::from("General"), owner: String::from(Self::UUID), entries: vec![
+                PreferenceEntry::new(Self::REOPEN_LAST_PROJECT, "Reopen last project on startup", PreferenceKind::Bool, json!(false)),
+            ]},
+            PreferenceSection { id: String::from("appearance"), title: String::from("Appearance"), owner: String::from(Self::UUID), entries: vec![
+                PreferenceEntry::new(Self::THEME, "Theme", PreferenceKind::Choice(vec![String::from("Dark"), String::from("Light"), String::from("System")]), json!("System")),
+                PreferenceEntry::new(Self::FONT_SIZE, "Font size", PreferenceKind::Float { min: 8.0, max: 32.0 }, json!(14.0)),
+            ]},
+            PreferenceSection { id: String::from("editor"), title: String::from("Editor"), owner: String::from(Self::UUID), entries: vec![
+                PreferenceEntry::new(Self::AUTOSAVE_INTERVAL, "Autosave interval", PreferenceKind::Integer { min: 0, max: 3600 }, json!(60))
+                    .with_description("Seconds between automatic saves of modified documents, 0 turns autosave off."),
+            ]},
+            PreferenceSection { id: String::from("logger"), title: String::from("Logger"), owner: String::from(Self::UUID), entries: vec![
+                PreferenceEntry::new(Self::LOG_BUFFER_SIZE, "Log buffer size", PreferenceKind::Integer { min: 16, max: 100_000 }, json!(1024))
+                    .with_description("Older logs are dropped from the log window beyond this count."),
+            ]},
+            PreferenceSection { id: String::from("keybindings"), title: String::from("Keybindings"), owner: String::from(Self::UUID), entries: vec![
+                keybinding("save", "Save", "Ctrl+S"),
+                keybinding("save_all", "Save all", "Ctrl+Shift+S"),
+                keybinding("undo", "Undo", "Ctrl+Z"),
+                keybinding("redo", "Redo", "Ctrl+Shift+Z"),
+                keybinding("find_in_files", "Find in files", "Ctrl+Shift+F"),
+                keybinding("open_project", "Open project", "Ctrl+O"),
+                keybinding("preferences", "Preferences", "Ctrl+Comma"),
+            ]},
+        ]
+    }
+
+    pub fn sections(&self) -> &[PreferenceSection] {
+        &self.sections
+    }
+
+    pub fn entry(&self, key: &str) -> Option<&PreferenceEntry> {
+        self.sections.iter().flat_map(|section| section.entries.iter()).find(|entry| entry.key == key)
+    }
+
+    fn layer(&self, scope: SettingScope) -> &BTreeMap<String, Value> {
+        match scope {
+            SettingScope::User => &self.user_values,
+            SettingScope::Project => &self.project_values,
+        }
+    }
+
+    /// The value stored in one scope, `None` when the scope does not override it.
+    pub fn value_in(&self, key: &str, scope: SettingScope) -> Option<&Value> {
+        self.layer(scope).get(key)
+    }
+
+    /// Project value, then user value, then the default; stored values that no longer match the schema are skipped.
+    pub fn value(&self, key: &str) -> Option<Value> {
+        let entry = self.entry(key)?;
+        [SettingScope::Project, SettingScope::User].into_iter()
+            .filter_map(|scope| self.layer(scope).get(key))
+            .find(|value| entry.kind.validate(value).is_ok())
+            .cloned()
+            .or_else(|| Some(entry.default.clone()))
+    }
+
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        self.value(key).and_then(|value| serde_json::from_value(value).ok())
+    }
+
+    pub fn editor_preferences(&self) -> EditorPreferences {
+        let keybindings = self.sections.iter().flat_map(|section| section.entries.iter())
+            .filter_map(|entry| entry.key.strip_prefix(Self::KEYBINDING_PREFIX).map(|command| (command.to_string(), entry.key.as_str())))
+            .filter_map(|(command, key)| self.get::<String>(key).filter(|binding| !binding.is_empty()).map(|binding| (command, binding)))
+            .collect();
+        EditorPreferences {
+            reopen_last_project: self.get(Self::REOPEN_LAST_PROJECT).unwrap_or_default(),
+            theme: self.get(Self::THEME).unwrap_or(Theme::System),
+            font_size: self.get(Self::FONT_SIZE).unwrap_or(14.0),
+            autosave_interval: self.get(Self::AUTOSAVE_INTERVAL).unwrap_or(60),
+            log_buffer_size: self.get(Self::LOG_BUFFER_SIZE).unwrap_or(1024),
+            keybindings,
+        }
+    }
+
+    fn set_value(&mut self, key: &str, value: Option<Value>, scope: SettingScope) -> Result<(), PluginError> {
+        let entry = self.entry(key).ok_or_else(|| PluginError::Other(format!("Unknown preference \"{}\".", key)))?;
+        if let Some(value) = &value {
+            entry.kind.validate(value).map_err(|reason| PluginError::Other(format!("Invalid value {} for \"{}\": {}.", value, entry.title, reason)))?;
+        }
+        let layer = match scope {
+            SettingScope::User => &mut self.user_values,
+            SettingScope::Project => &mut self.project_values,
+        };
+        match value {
+            Some(value) => layer.insert(key.to_string(), value),
+            None => layer.remove(key),
+        };
+        Ok(())
+    }
+
+    fn all_keys(&self) -> Vec<String> {
+        self.sections.iter().flat_map(|section| section.entries.iter().map(|entry| entry.key.clone())).collect()
+    }
+}
+
+impl Default for Preferences {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IPlugin for Preferences {
+    fn info(&self) -> PluginInfo {
+        PluginInfo {
+            uuid: String::from(Self::UUID),
+            name: String::from("Preferences (Native plugin)"),
+            version: (0, 0, 1),
+            author: String::from("dream-lab"),
+            description: String::from("Editor preferences with user and project overrides."),
+            subscriptions: vec![String::from("designer.preferences.*"), String::from("designer.project_manager.event")],
+            dependencies: vec![],
+            api_version: Some(VersionRange::at_least((0, 1, 0))),
+        }
+    }
+
+    fn status(&self) -> PluginStatus {
+        self.status.clone()
+    }
+
+    fn initialize(&mut self, _root: &mut DesignerCore) {
+
+    }
+
+    fn enable(&mut self, _root: &mut DesignerCore) {
+        self.status = PluginStatus::Enabled
+    }
+
+    fn disable(&mut self, _root: &mut DesignerCore) {
+        self.status = PluginStatus::Disabled
+    }
+
+    fn load_state(&mut self, storage: &mut dyn IPluginStateStorage) {
+        let values = parse_state::<PreferencesState>(storage.load_state()).values;
+        match storage.scope() {
+            SettingScope::User => self.user_values = values,
+            SettingScope::Project => self.project_values = values,
+        }
+    }
+
+    fn store_state(&mut self, storage: &mut dyn IPluginStateStorage) -> Result<(), PluginError> {
+        let values = self.layer(storage.scope()).clone();
+        storage.store_state(serde_json::to_value(PreferencesState { values }).unwrap())
+    }
+
+    fn get_state(&self) -> Value {
+        json!({
+            "user": self.user_values,
+            "project": self.project_values,
+        })
+    }
+
+    fn handle_message(&mut self, root: &mut DesignerCore, sender: &str, action: &str, args: Value) -> Result<(), PluginError> {
+        if action == "designer.project_manager.event" {
+            // the project layer is loaded by the core before `Opened` and stored before `Closed`.
+            match parse_args::<ProjectManagerEvent>(action, args)? {
+                ProjectManagerEvent::Opened { .. } => {
+                    root.publish_message("designer.preferences", &PreferencesEvent::Changed { keys: self.all_keys() });
+                },
+                ProjectManagerEvent::Closed { .. } => {
+                    self.project_values.clear();
+                    root.publish_message("designer.preferences", &PreferencesEvent::Changed { keys: self.all_keys() });
+                },
+                _ => {},
+            }
+            return Ok(());
+        }
+        if action != "designer.preferences.management" {
+            return Ok(());
+        }
+        match parse_args::<PreferencesCommand>(action, args)? {
+            PreferencesCommand::Set { key, value, scope } => {
+                self.set_value(&key, Some(value), scope)?;
+                root.publish_message("designer.preferences", &PreferencesEvent::Changed { keys: vec![key] });
+            },
+            PreferencesCommand::Reset { key, scope } => {
+                self.set_value(&key, None, scope)?;
+                root.publish_message("designer.preferences", &PreferencesEvent::Changed { keys: vec![key] });
+            },
+            PreferencesCommand::RegisterSection(mut section) => {
+                section.owner = sender.to_string();
+                let keys = section.entries.iter().map(|entry| entry.key.clone()).collect::<Vec<_>>();
+                if let Some(duplicate) = keys.iter().find(|key| self.sections.iter().any(|other| other.id != section.id && other.entries.iter().any(|entry| &&entry.key == key))) {
+                    return Err(PluginError::Other(format!("Preference \"{}\" is already registered by another section.", duplicate)));
+                }
+                self.sections.retain(|other| other.id != section.id);
+                let id = section.id.clone();
+                self.sections.push(section);
+                root.publish_message("designer.preferences", &PreferencesEvent::SectionRegistered { id });
+                root.publish_message("designer.preferences", &PreferencesEvent::Changed { keys });
+            },
+        }
+        root.notify_ui();
+        Ok(())
+    }
+
+    fn handle_request(&mut self, _root: &mut DesignerCore, _sender: &str, action: &str, args: Value) -> Option<Result<Value, PluginError>> {
+        if action != "designer.preferences.request" {
+            return None;
+        }
+        let request = match parse_args::<PreferencesRequest>(action, args) {
+            Ok(request) => request,
+            Err(error) => return Some(Err(error)),
+        };
+        match request {
+            PreferencesRequest::Value { key } => reply(self.value(&key)),
+            PreferencesRequest::Editor => reply(self.editor_preferences()),
+            PreferencesRequest::Sections => reply(self.sections.clone()),
+        }
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum PreferencesCommand {
+    /// Overrides a preference in one scope, the value must match the schema of the entry.
+    Set { key: String, value: Value, scope: SettingScope },
+    /// Removes the override of one scope.
+    Reset { key: String, scope: SettingScope },
+    /// Adds or replaces a section, its owner becomes the sender.
+    RegisterSection(PreferenceSection),
+}
+
+impl ICommand for PreferencesCommand {
+    fn action(&self) -> &str {
+        "designer.preferences.management"
+    }
+
+    fn args(&self) -> Value {
+        serde_json::to_value(self).unwrap()
+    }
+}
+
+/// Queries answered through `DesignerCore::request`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum PreferencesRequest {
+    /// Replies `Option<Value>`, the effective value of a preference.
+    Value { key: String },
+    /// Replies `EditorPreferences`.
+    Editor,
+    /// Replies `Vec<PreferenceSection>`.
+    Sections,
+}
+
+impl ICommand for PreferencesRequest {
+    fn action(&self) -> &str {
+        "designer.preferences.request"
+    }
+
+    fn args(&self) -> Value {
+        serde_json::to_value(self).unwrap()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum PreferencesEvent {
+    /// The effective value of these preferences may have changed.
+    Changed { keys: Vec<String> },
+    SectionRegistered { id: String },
+}
+
+impl ICommand for PreferencesEvent {
+    fn action(&self) -> &str {
+        "designer.preferences.event"
+    }
+
+    fn args(&self) -> Value {
+        serde_json::to_value(self).unwrap()
+    }
+}
@@ -1,10 +1,10 @@
-use std::{cell::RefCell, fs::File, io::Write, ops::Deref, path::PathBuf, rc::Rc, str::FromStr, vec};
+use std::{path::{Path, PathBuf}, time::{SystemTime, UNIX_EPOCH}, vec};
 
-use log::info;
+use log::{info, warn};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 
-use crate::{backend::{commands::ICommand, plugin::{IPlugin, PluginInfo, PluginStatus}, plugins::logger::LogCommand}, DesignerCore, DesignerUI};
+use crate::{backend::{commands::ICommand, designer_core::CoreCommand, history::UndoEntry, plugin::{parse_args, IPlugin, IPluginStateStorage, PluginError, PluginInfo, PluginStatus, VersionRange}, plugin_settings::SettingScope, plugin_state::parse_state, plugins::{document_manager::DocumentManagerRequest, logger::LogCommand, node_graph_manager::NodeGraphRequest}, project_file::{self, LoadedProject, ProjectFileError}, project_template::{available_templates, TemplateError}, request::reply}, DesignerCore};
 
 
 
@@ -16,23 +16,84 @@ use crate::{backend::{commands::ICommand, plugin::{IPlugin, PluginInfo, PluginSt
  * @desc [description]
 */
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Project {
+    /// Layout version of `project.blueproj`, see `project_file::CURRENT_FORMAT_VERSION`.
+    #[serde(default)]
+    pub format_version: u32,
     pub name: String,
     pub description: String,
     pub version: (u32, u32, u32),
     pub package_name: String,
     pub author: String,
     pub email: String,
+    /// Gitignore style patterns hidden from the file manager, applied on top of the `.gitignore` files of the project.
+    #[serde(default)]
+    pub exclude: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProjectManager {
     pub path: Option<PathBuf>,
-    pub recent_projects: Vec<String>,
+    /// Pinned projects first, then the most recently opened ones.
+    pub recent_projects: Vec<RecentProject>,
     pub status: PluginStatus,
     #[serde(skip_serializing)]
-    pub project: Option<Project>
+    pub project: Option<Project>,
+    /// Project opened once the open one is closed by `FinishClosing`.
+    #[serde(skip)]
+    opening: Option<(String, LoadedProject)>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct ProjectManagerState {
+    recent_projects: Vec<StoredRecentProject>,
+}
+
+/// Older builds stored bare paths.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+enum StoredRecentProject {
+    Path(PathBuf),
+    Project(RecentProject),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecentProject {
+    pub path: PathBuf,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Seconds since the unix epoch, 0 when unknown.
+    #[serde(default)]
+    pub last_opened: u64,
+    #[serde(default)]
+    pub pinned: bool,
+    /// The project file no longer exists, refreshed whenever the list is loaded or requested.
+    #[serde(default)]
+    pub missing: bool,
+}
+
+impl RecentProject {
+    /// Reads name and description from the project file, the directory name is used when it cannot be read.
+    fn from_path(path: PathBuf) -> Self {
+        let project = std::fs::read_to_string(path.join(project_file::FILE_NAME)).ok().and_then(|content| serde_json::from_str::<Value>(&content).ok());
+        let text = |field: &str| project.as_ref().and_then(|project| project.get(field)).and_then(|value| value.as_str()).map(|value| value.to_string());
+        let name = text("name").unwrap_or_else(|| path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default());
+        Self { name, description: text("description").unwrap_or_default(), last_opened: 0, pinned: false, missing: project.is_none(), path }
+    }
+
+    fn is(&self, path: &Path) -> bool {
+        let canonical = |path: &Path| path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        self.path == path || canonical(&self.path) == canonical(path)
+    }
+}
+
+impl Project {
+    pub fn new_from_empty() -> Self {
+        Self { format_version: project_file::CURRENT_FORMAT_VERSION, name: "".into(), description: "".into(), version: (0, 0, 1), package_name: "".into(), author: "".into(), email: "".into(), exclude: vec![] }
+    }
 }
 
 impl ProjectManager {
@@ -45,10 +106,13 @@ impl<'b> IPlugin for ProjectManager {
     fn info(&self) -> crate::backend::plugin::PluginInfo {
         PluginInfo {
             name: String::from("Project manager (Native plugin)"),
-            author: "dream-lab",
-            uuid: "3979dec2-8e5c-4860-8b1c-07a8fd2d560f",
+            author: String::from("dream-lab"),
+            uuid: String::from(Self::UUID),
             description: String::from("Provides Opening, Creating And Analyzing features for IDE"),
-            version: (0, 0, 1)
+            version: (0, 0, 1),
+            subscriptions: vec![String::from("designer.project_manager.*")],
+            dependencies: vec![],
+            api_version: Some(VersionRange::at_least((0, 1, 0))),
         }
     }
 
@@ -71,75 +135,282 @@ impl<'b> IPlugin for ProjectManager {
     fn as_any(&self) -> &dyn std::any::Any {
         self
     }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
     
     fn status(&self) -> PluginStatus {self.status.clone()}
     
-    fn load_state(&mut self, storage: &mut dyn crate::backend::plugin::IPluginStateStorage) {
-        *self = serde_json::from_value(storage.load_state()).unwrap();
+    fn load_state(&mut self, storage: &mut dyn IPluginStateStorage) {
+        if storage.scope() != SettingScope::User {
+            return;
+        }
+        self.recent_projects = parse_state::<ProjectManagerState>(storage.load_state()).recent_projects.into_iter().map(|project| match project {
+            StoredRecentProject::Path(path) => RecentProject::from_path(path),
+            StoredRecentProject::Project(project) => project,
+        }).collect();
+        self.refresh_recent_projects();
     }
     
-    fn store_state(&mut self, storage: &mut dyn crate::backend::plugin::IPluginStateStorage) {
-        storage.store_state(serde_json::to_value(self).unwrap());
+    fn store_state(&mut self, storage: &mut dyn IPluginStateStorage) -> Result<(), PluginError> {
+        if storage.scope() != SettingScope::User {
+            return Ok(());
+        }
+        let recent_projects = self.recent_projects.iter().cloned().map(StoredRecentProject::Project).collect();
+        storage.store_state(serde_json::to_value(ProjectManagerState { recent_projects }).unwrap())
     }
 
-    fn handle_message(&mut self, root: &mut DesignerCore, sender: &str, action: &str, args: Value) {
-        if action != "designer.project_manager.management" {return;}
-        match serde_json::from_value::<ProjectManagerCommand>(args).unwrap() {
-            ProjectManagerCommand::OpenProject { path } => {
-                self.open_project(root, &path);
+    fn handle_message(&mut self, root: &mut DesignerCore, _sender: &str, action: &str, args: Value) -> Result<(), PluginError> {
+        if action != "designer.project_manager.management" {return Ok(());}
+        match parse_args::<ProjectManagerCommand>(action, args)? {
+            ProjectManagerCommand::OpenProject { path, save_documents } => {
+                self.open_project(root, &path, save_documents)?;
             },
-            ProjectManagerCommand::CloseProject => {
-
+            ProjectManagerCommand::UpdateProject { project } => {
+                self.update_project(root, project)?;
+            },
+            ProjectManagerCommand::CloseProject { save_documents } => {
+                self.close_project(root, save_documents);
+            },
+            ProjectManagerCommand::FinishClosing { save_documents } => {
+                self.finish_closing(root, save_documents)?;
+            },
+            ProjectManagerCommand::PinRecentProject { path, pinned } => {
+                if let Some(project) = self.recent_projects.iter_mut().find(|project| project.is(&path)) {
+                    project.pinned = pinned;
+                }
+                self.refresh_recent_projects();
+            },
+            ProjectManagerCommand::ForgetRecentProject { path } => {
+                self.recent_projects.retain(|project| !project.is(&path));
             },
-            ProjectManagerCommand::CreateProject { path, project } => {
-                self.create_project_directory(root, &path, &project);
+            ProjectManagerCommand::PruneRecentProjects => {
+                self.refresh_recent_projects();
+                self.recent_projects.retain(|project| !project.missing);
+            },
+            ProjectManagerCommand::ClearRecentProjects => {
+                self.recent_projects.retain(|project| project.pinned);
+            },
+            ProjectManagerCommand::ImportRecentProjects { paths } => {
+                for path in paths {
+                    if !self.recent_projects.iter().any(|project| project.is(&path)) {
+                        self.recent_projects.push(RecentProject::from_path(path));
+                    }
+                }
+                self.refresh_recent_projects();
+            },
+            ProjectManagerCommand::CreateProject { path, project, template } => {
+                self.create_project_directory(root, &path, &project, template.as_deref())?;
             }
         }
         root.notify_ui();
+        Ok(())
+    }
+
+    fn handle_request(&mut self, root: &mut DesignerCore, _sender: &str, action: &str, args: Value) -> Option<Result<Value, PluginError>> {
+        if action != "designer.project_manager.request" {return None;}
+        let request = match parse_args::<ProjectManagerRequest>(action, args) {
+            Ok(request) => request,
+            Err(error) => return Some(Err(error)),
+        };
+        match request {
+            ProjectManagerRequest::ProjectPath => reply(self.path.clone()),
+            ProjectManagerRequest::Project => reply(self.project.clone()),
+            ProjectManagerRequest::RecentProjects => {
+                self.refresh_recent_projects();
+                reply(self.recent_projects.clone())
+            },
+            ProjectManagerRequest::Templates => reply(available_templates(root.user_config_dir().map(|directory| directory.as_path()))),
+        }
     }
 }
 
 impl ProjectManager {
-    fn open_project(&mut self, root: &mut DesignerCore, path: &String) {
-        let original = path;
-        let path = PathBuf::from_str(&path).unwrap();
-        let mut buffer = std::fs::read_to_string(path.join("project.blueproj")).expect("Unable to join path");
-        self.project = serde_json::from_str(&buffer).expect("Cannot open project file");
+    fn open_project(&mut self, root: &mut DesignerCore, path: &String, save_documents: bool) -> Result<(), PluginError> {
+        let loaded = project_file::load(&PathBuf::from(path))?;
+        // the open project is closed like with `CloseProject`, so its documents, file tree and watcher are released first.
+        if self.path.is_some() {
+            self.opening = Some((path.clone(), loaded));
+            self.close_project(root, save_documents);
+            return Ok(());
+        }
+        self.attach_project(root, path, loaded);
+        Ok(())
+    }
+
+    fn attach_project(&mut self, root: &mut DesignerCore, original: &String, loaded: LoadedProject) {
+        let path = PathBuf::from(original);
+        if let Some(version) = loaded.migrated_from {
+            info!("Project file is upgraded from format version {}.", version);
+            root.publish_message("designer.project_manager", &LogCommand::Generate { category: String::from("Info"), content: format!("Project file is upgraded from format version {} to {}, the original is kept as {:?}.", version, project_file::CURRENT_FORMAT_VERSION, loaded.backup.unwrap_or_default()) });
+        }
+        for problem in &loaded.problems {
+            warn!("Project {:?}: {}", path, problem);
+            root.publish_message("designer.project_manager", &LogCommand::Generate { category: String::from("Warning"), content: format!("Project settings need attention, {}", problem) });
+        }
+        self.remember_project(&path, &loaded.project);
+        self.project = Some(loaded.project);
         self.path = Some(path.clone());
 
         info!("Project file is opened.");
+        root.publish_message("designer.project_manager", &CoreCommand::AttachProject { path: Some(path) });
         root.publish_message("designer.project_manager", &ProjectManagerEvent::Opened { path: original.clone() });
     }
 
-    fn create_project_directory(&mut self, root: &mut DesignerCore, path: &String, project: &Project) {
+    /// Moves the project to the top of the recent projects, unpinned ones beyond `MAX_RECENT_PROJECTS` are dropped.
+    fn remember_project(&mut self, path: &Path, project: &Project) {
+        let last_opened = SystemTime::now().duration_since(UNIX_EPOCH).map(|duration| duration.as_secs()).unwrap_or_default();
+        let pinned = self.recent_projects.iter().any(|recent| recent.is(path) && recent.pinned);
+        self.recent_projects.retain(|recent| !recent.is(path));
+        self.recent_projects.push(RecentProject { path: path.to_path_buf(), name: project.name.clone(), description: project.description.clone(), last_opened, pinned, missing: false });
+        self.refresh_recent_projects();
+        let mut unpinned = 0;
+        self.recent_projects.retain(|recent| {
+            unpinned += usize::from(!recent.pinned);
+            recent.pinned || unpinned <= Self::MAX_RECENT_PROJECTS
+        });
+    }
+
+    /// Flags projects whose file is gone and sorts the list, pinned first and then by last opened time.
+    fn refresh_recent_projects(&mut self) {
+        for project in self.recent_projects.iter_mut() {
+            project.missing = !project.path.join(project_file::FILE_NAME).is_file();
+        }
+        self.recent_projects.sort_by(|a, b| b.pinned.cmp(&a.pinned).then(b.last_opened.cmp(&a.last_opened)));
+    }
+
+    fn update_project(&mut self, root: &mut DesignerCore, project: Project) -> Result<(), PluginError> {
+        let path = self.path.clone().ok_or(PluginError::ProjectNotOpened)?;
+        let problems = project.validate();
+        if !problems.is_empty() {
+            return Err(ProjectFileError::Invalid(problems).into());
+        }
+        project_file::save(&path, &project)?;
+        let previous = self.project.replace(project.clone());
+        if let Some(previous) = previous.filter(|previous| *previous != project) {
+            root.record_undo(UndoEntry::new(
+                "Edit the project settings",
+                &ProjectManagerCommand::UpdateProject { project },
+                &ProjectManagerCommand::UpdateProject { project: previous },
+            ));
+        }
+
+        info!("Project file is updated.");
+        root.publish_message("designer.project_manager", &ProjectManagerEvent::Updated { path: path.to_string_lossy().to_string() });
+        Ok(())
+    }
+
+    /// `Closing` and `Closed` carry the project path because it is already cleared when subscribers receive them.
+    /// Editors handle `Closing` first, the project is released by `FinishClosing` after them.
+    fn close_project(&mut self, root: &mut DesignerCore, save_documents: bool) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        root.publish_message("designer.project_manager", &ProjectManagerEvent::Closing { path: path.to_string_lossy().to_string(), save_documents });
+        root.publish_message("designer.project_manager", &ProjectManagerCommand::FinishClosing { save_documents });
+    }
+
+    /// The project stays open when buffers that should have been saved still have unsaved changes.
+    fn finish_closing(&mut self, root: &mut DesignerCore, save_documents: bool) -> Result<(), PluginError> {
+        if save_documents {
+            let mut unsaved = root.request::<Vec<String>>("designer.project_manager", &DocumentManagerRequest::UnsavedDocuments).unwrap_or_default();
+            unsaved.extend(root.request::<Vec<String>>("designer.project_manager", &NodeGraphRequest::UnsavedGraphs).unwrap_or_default());
+            if !unsaved.is_empty() {
+                self.opening = None;
+                return Err(PluginError::Other(format!("The project is not closed, {} could not be saved.", unsaved.join(", "))));
+            }
+        }
+        let Some(path) = self.path.take() else {
+            return Ok(());
+        };
+        self.project = None;
+        let original = path.to_string_lossy().to_string();
+
+        info!("Project is closed.");
+        root.publish_message("designer.project_manager", &CoreCommand::AttachProject { path: None });
+        root.publish_message("designer.project_manager", &ProjectManagerEvent::Closed { path: original });
+        if let Some((path, loaded)) = self.opening.take() {
+            self.attach_project(root, &path, loaded);
+        }
+        Ok(())
+    }
+
+    fn create_project_directory(&mut self, root: &mut DesignerCore, path: &String, project: &Project, template: Option<&str>) -> Result<(), PluginError> {
         let original = path;
-        let path = PathBuf::from_str(&path).unwrap();
-        let mut file = File::create(path.join("project.blueproj")).unwrap();
-        let json_content = serde_json::to_string_pretty(project).expect("Cannot serialize project into file");
-        file.write_all(json_content.as_bytes()).expect("Cannot write project file");
+        let problems = project.validate();
+        if !problems.is_empty() {
+            return Err(ProjectFileError::Invalid(problems).into());
+        }
+        match template {
+            Some(id) => {
+                let template = available_templates(root.user_config_dir().map(|directory| directory.as_path())).into_iter()
+                    .find(|template| template.id == id)
+                    .ok_or_else(|| TemplateError::NotFound(id.to_string()))?;
+                template.instantiate(&PathBuf::from(path), project)?;
+            },
+            None => project_file::save(&PathBuf::from(path), project)?,
+        }
+        self.remember_project(&PathBuf::from(path), project);
 
         info!("Project file is created.");
         root.publish_message("designer.project_manager", &ProjectManagerEvent::Created { path: original.clone() });
+        Ok(())
     }
 }
 
 
 impl ProjectManager {
+    pub const UUID: &'static str = "3979dec2-8e5c-4860-8b1c-07a8fd2d560f";
+    const MAX_RECENT_PROJECTS: usize = 16;
+
     pub fn new() -> Self {
-        Self { path: None, status: PluginStatus::Disabled, recent_projects: vec![], project: None, }
+        Self { path: None, status: PluginStatus::Disabled, recent_projects: vec![], project: None, opening: None }
     }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ProjectManagerCommand {
+    /// A project that is already open is closed first, its unsaved buffers are saved when `save_documents` is true.
     OpenProject {
-        path: String
+        path: String,
+        #[serde(default)]
+        save_documents: bool,
     },
     CreateProject {
         path: String,
-        project: Project
+        project: Project,
+        /// Id of a template from `ProjectManagerRequest::Templates`, only the project file is written when missing.
+        #[serde(default)]
+        template: Option<String>,
+    },
+    /// Validates and writes the metadata of the opened project.
+    UpdateProject {
+        project: Project,
+    },
+    /// Editors save their unsaved buffers when `save_documents` is true and discard them otherwise.
+    CloseProject {
+        save_documents: bool,
+    },
+    /// Sent by the project manager to itself after `ProjectManagerEvent::Closing`, releases the project once the editors saved it.
+    FinishClosing {
+        save_documents: bool,
+    },
+    PinRecentProject {
+        path: PathBuf,
+        pinned: bool,
+    },
+    ForgetRecentProject {
+        path: PathBuf,
+    },
+    /// Forgets the recent projects whose file no longer exists.
+    PruneRecentProjects,
+    /// Forgets the recent projects that are not pinned.
+    ClearRecentProjects,
+    /// Adds projects known from elsewhere, such as the list older builds kept in the window storage.
+    ImportRecentProjects {
+        paths: Vec<PathBuf>,
     },
-    CloseProject,
 }
 
 impl ICommand for ProjectManagerCommand {
@@ -152,6 +423,29 @@ impl ICommand for ProjectManagerCommand {
     }
 }
 
+/// Queries answered through `DesignerCore::request`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ProjectManagerRequest {
+    /// Replies `Option<PathBuf>`, the directory of the opened project.
+    ProjectPath,
+    /// Replies `Option<Project>`, metadata of the opened project.
+    Project,
+    /// Replies `Vec<ProjectTemplate>`, built in and user templates for new projects.
+    Templates,
+    /// Replies `Vec<RecentProject>`, pinned ones first and then the most recently opened ones.
+    RecentProjects,
+}
+
+impl ICommand for ProjectManagerRequest {
+    fn action(&self) -> &str {
+        "designer.project_manager.request"
+    }
+
+    fn args(&self) -> Value {
+        serde_json::to_value(self).unwrap()
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ProjectManagerEvent {
     Opened {
@@ -159,7 +453,20 @@ pub enum ProjectManagerEvent {
     },
     Created {
         path: String,
-    }
+    },
+    /// The metadata of the opened project is saved, request `ProjectManagerRequest::Project` for the new values.
+    Updated {
+        path: String,
+    },
+    /// Published before the project is detached, editors save or discard their buffers of the project.
+    Closing {
+        path: String,
+        save_documents: bool,
+    },
+    /// Plugins release everything they hold for the project.
+    Closed {
+        path: String,
+    },
 }
 
 impl ICommand for ProjectManagerEvent {
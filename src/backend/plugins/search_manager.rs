@@ -0,0 +1,439 @@
+use std::{collections::HashMap, io::Write, ops::Range, path::{Path, PathBuf}, rc::Rc};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{backend::{commands::ICommand, file_filter::FileFilter, history::UndoEntry, plugin::{parse_args, IPlugin, PluginError, PluginDependency, PluginInfo, PluginStatus, VersionRange}, request::reply, search::{FileMatches, Matcher, SearchError, SearchJob, SearchOptions}, text_buffer::{apply_text_changes, content_hash, EditKind, TextChange}}, DesignerCore};
+
+use super::{document_manager::{DocumentManager, DocumentManagerCommand, DocumentManagerRequest, DocumentSnapshot}, file_manager::{sandboxed_path, write_replacing, FileManager}, project_manager::{Project, ProjectManager, ProjectManagerEvent, ProjectManagerRequest}};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchManager {
+    #[serde(skip_serializing)]
+    status: PluginStatus,
+    /// Options of the last search, the search window starts from them.
+    pub options: SearchOptions,
+    /// Files with matches in the order they were searched, filled while the search runs.
+    #[serde(skip)]
+    pub results: Vec<FileMatches>,
+    #[serde(skip)]
+    pub searching: bool,
+    #[serde(skip)]
+    pub searched_files: usize,
+    /// The last search stopped at `search::MAX_MATCHES`.
+    #[serde(skip)]
+    pub truncated: bool,
+    #[serde(skip)]
+    matcher: Option<Matcher>,
+    #[serde(skip)]
+    job: Option<Rc<SearchJob>>,
+}
+
+impl SearchManager {
+    pub const UUID: &'static str = "40dfc3fa-6fd4-4e4e-aca7-7578a4f5de31";
+
+    pub fn new() -> Self {
+        Self { status: PluginStatus::Disabled, options: SearchOptions::default(), results: vec![], searching: false, searched_files: 0, truncated: false, matcher: None, job: None }
+    }
+
+    /// Found files or the end of the search that `SearchManagerCommand::ApplyResults` has not applied yet.
+    pub fn has_pending_results(&self) -> bool {
+        self.job.as_ref().map(|job| job.has_progress()).unwrap_or(false)
+    }
+
+    pub fn match_count(&self) -> usize {
+        self.results.iter().map(|file| file.matches.len()).sum()
+    }
+
+    fn clear(&mut self) {
+        self.job = None;
+        self.matcher = None;
+        self.results.clear();
+        self.searching = false;
+        self.searched_files = 0;
+        self.truncated = false;
+    }
+
+    fn search(&mut self, root: &mut DesignerCore, project: &Path, options: SearchOptions) -> Result<(), PluginError> {
+        self.clear();
+        self.options = options.clone();
+        let matcher = Matcher::new(project, &options)?;
+        let exclude = root.request::<Option<Project>>("designer.search_manager", &ProjectManagerRequest::Project)?.map(|project| project.exclude).unwrap_or_default();
+        // problems of the exclusions are already reported by the file manager.
+        let (filter, _) = FileFilter::new(project, &exclude);
+        let mut documents = HashMap::new();
+        for path in root.request::<Vec<String>>("designer.search_manager", &DocumentManagerRequest::UnsavedDocuments)? {
+            if let Some(document) = root.request::<Option<DocumentSnapshot>>("designer.search_manager", &DocumentManagerRequest::Document { path: path.clone() })? {
+                documents.insert(path, document.text);
+            }
+        }
+        self.job = Some(Rc::new(SearchJob::start(project, filter, matcher.clone(), documents, root.background_notifier())));
+        self.matcher = Some(matcher);
+        self.searching = true;
+        root.publish_message("designer.search_manager", &SearchManagerEvent::Started { options });
+        Ok(())
+    }
+
+    fn apply_results(&mut self, root: &mut DesignerCore) {
+        let Some(job) = self.job.clone() else {
+            return;
+        };
+        let progress = job.take_progress();
+        self.searched_files = progress.searched_files;
+        if !progress.found.is_empty() {
+            self.results.extend(progress.found.iter().cloned());
+            root.publish_message("designer.search_manager", &SearchManagerEvent::Found { files: progress.found });
+        }
+        if progress.finished && self.searching {
+            self.searching = false;
+            self.truncated = progress.truncated;
+            root.publish_message("designer.search_manager", &SearchManagerEvent::Finished { files: self.results.len(), matches: self.match_count(), truncated: progress.truncated });
+        }
+    }
+
+    /// The lines with matches as they are now and after the replacement, lines joined by a match spanning them are one preview.
+    /// The matches are found in the whole text like `replace` does, so a match running over a line break is previewed as it is written.
+    fn preview(&self, root: &mut DesignerCore, project: &Path, replacement: &str) -> Result<Vec<ReplacementPreview>, PluginError> {
+        let matcher = self.matcher.as_ref().ok_or(SearchError::NoSearch)?;
+        let mut previews = vec![];
+        for file in &self.results {
+            // a file removed since the search has nothing left to replace.
+            let Ok((text, _)) = current_text(root, project, &file.path) else {
+                continue;
+            };
+            let mut spans: Vec<(Range<usize>, Replacements)> = vec![];
+            for (range, replaced) in matcher.replacements(&text, replacement) {
+                let line_start = text[..range.start].rfind('\n').map(|index| index + 1).unwrap_or(0);
+                // a match ending with a line break ends on the line of that break.
+                let last = if range.end > range.start && text[..range.end].ends_with('\n') { range.end - 1 } else { range.end };
+                let line_end = text[last..].find('\n').map(|index| last + index).unwrap_or(text.len());
+                match spans.last_mut() {
+                    Some((span, matches)) if line_start <= span.end => {
+                        span.end = span.end.max(line_end);
+                        matches.push((range, replaced));
+                    },
+                    _ => spans.push((line_start..line_end, vec![(range, replaced)])),
+                }
+            }
+            previews.extend(spans.into_iter().map(|(span, matches)| {
+                let mut after = String::new();
+                let mut copied = span.start;
+                for (range, replaced) in matches {
+                    after.push_str(&text[copied..range.start]);
+                    after.push_str(&replaced);
+                    copied = range.end.max(copied);
+                }
+                after.push_str(&text[copied.min(span.end)..span.end]);
+                ReplacementPreview {
+                    path: file.path.clone(),
+                    line: text[..span.start].matches('\n').count(),
+                    before: text[span].trim_end_matches('\r').to_string(),
+                    after: after.trim_end_matches('\r').to_string(),
+                }
+            }));
+        }
+        Ok(previews)
+    }
+
+    fn replace(&mut self, root: &mut DesignerCore, project: &Path, replacement: &str, skipped: &[String]) -> Result<(), PluginError> {
+        let matcher = self.matcher.clone().ok_or(SearchError::NoSearch)?;
+        let (mut forward, mut backward, mut replacements) = (vec![], vec![], vec![]);
+        for path in self.results.iter().map(|file| file.path.clone()).filter(|path| !skipped.contains(path)) {
+            let (text, _) = current_text(root, project, &path)?;
+            let found = matcher.replacements(&text, replacement);
+            if found.is_empty() {
+                continue;
+            }
+            replacements.push(found.len());
+            let (changes, reverting) = replacement_changes(&text, &found);
+            let replaced = apply_text_changes(&text, &changes)?;
+            backward.push(FileEdit { path: path.clone(), expected: content_hash([replaced.as_str()]), changes: reverting });
+            forward.push(FileEdit { path, expected: content_hash([text.as_str()]), changes });
+        }
+        if forward.is_empty() {
+            return Ok(());
+        }
+        // when a file cannot be written, the undo step still covers the files changed before it.
+        let (written, result) = match self.edit_files(root, project, &forward) {
+            Ok(()) => (forward.len(), Ok(())),
+            Err((written, error)) => (written, Err(error)),
+        };
+        if written > 0 {
+            forward.truncate(written);
+            backward.truncate(written);
+            let label = format!("Replace \"{}\" with \"{}\" in {} file(s)", self.options.query, replacement, forward.len());
+            root.record_undo(UndoEntry::new(label, &SearchManagerCommand::EditFiles(forward.clone()), &SearchManagerCommand::EditFiles(backward)));
+            root.publish_message("designer.search_manager", &SearchManagerEvent::Replaced { files: forward.into_iter().map(|file| file.path).collect(), replacements: replacements[..written].iter().sum() });
+        }
+        result
+    }
+
+    /// Nothing is written unless every file still holds the text its changes were computed from.
+    /// Open documents are edited in their buffers, the ones without other unsaved changes are saved as well.
+    /// A failure comes with the number of files changed before it, they are the first ones of `files`.
+    fn edit_files(&mut self, root: &mut DesignerCore, project: &Path, files: &[FileEdit]) -> Result<(), (usize, PluginError)> {
+        let mut current = vec![];
+        for file in files {
+            let (text, dirty) = current_text(root, project, &file.path).map_err(|error| (0, error))?;
+            if content_hash([text.as_str()]) != file.expected {
+                return Err((0, SearchError::FileChanged { path: file.path.clone() }.into()));
+            }
+            current.push((text, dirty));
+        }
+        for (written, (file, (text, dirty))) in files.iter().zip(current).enumerate() {
+            match dirty {
+                Some(dirty) => {
+                    root.publish_message("designer.search_manager", &DocumentManagerCommand::Edit { path: file.path.clone(), changes: file.changes.clone(), kind: EditKind::Other });
+                    if !dirty {
+                        root.publish_message("designer.search_manager", &DocumentManagerCommand::Save { path: file.path.clone() });
+                    }
+                },
+                None => {
+                    let replaced = apply_text_changes(&text, &file.changes).map_err(|error| (written, error.into()))?;
+                    let absolute = sandboxed_path(project, &file.path).map_err(|error| (written, error.into()))?;
+                    write_replacing(&absolute, |writer| writer.write_all(replaced.as_bytes())).map_err(|error| (written, PluginError::io(&absolute, error)))?;
+                },
+            }
+        }
+        // the results no longer match the files, searching again after the edits lists what is left.
+        if self.matcher.is_some() {
+            root.publish_message("designer.search_manager", &SearchManagerCommand::Search(self.options.clone()));
+        }
+        Ok(())
+    }
+}
+
+/// Byte ranges of matches with the text replacing each of them, as `Matcher::replacements` finds them.
+type Replacements = Vec<(Range<usize>, String)>;
+
+/// The changes replacing the matches of `text`, and the ones turning the replaced text back.
+/// Both run from the end of the text to its start, so the offsets of every change are the ones of the text it applies to.
+fn replacement_changes(text: &str, replacements: &[(Range<usize>, String)]) -> (Vec<TextChange>, Vec<TextChange>) {
+    let (mut forward, mut backward) = (vec![], vec![]);
+    let (mut counted_bytes, mut counted_chars, mut shift) = (0, 0, 0isize);
+    for (range, replaced) in replacements {
+        counted_chars += text[counted_bytes..range.start].chars().count();
+        counted_bytes = range.start;
+        let original = &text[range.clone()];
+        let (start, end) = (counted_chars, counted_chars + original.chars().count());
+        let replaced_length = replaced.chars().count();
+        let replaced_start = (start as isize + shift) as usize;
+        forward.push(TextChange { start, end, text: replaced.clone() });
+        backward.push(TextChange { start: replaced_start, end: replaced_start + replaced_length, text: original.to_string() });
+        shift += replaced_length as isize - (end - start) as isize;
+    }
+    forward.reverse();
+    backward.reverse();
+    (forward, backward)
+}
+
+/// Text of an open document, with whether it has unsaved changes, or of the file on disk.
+fn current_text(root: &mut DesignerCore, project: &Path, path: &str) -> Result<(String, Option<bool>), PluginError> {
+    if let Some(document) = root.request::<Option<DocumentSnapshot>>("designer.search_manager", &DocumentManagerRequest::Document { path: path.to_string() })? {
+        return Ok((document.text, Some(document.dirty)));
+    }
+    let absolute = sandboxed_path(project, path)?;
+    let text = std::fs::read_to_string(&absolute).map_err(|error| PluginError::io(&absolute, error))?;
+    Ok((text, None))
+}
+
+impl Default for SearchManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IPlugin for SearchManager {
+    fn info(&self) -> PluginInfo {
+        PluginInfo {
+            name: String::from("Search manager (Native Plugin)"),
+            author: String::from("dream-lab"),
+            uuid: String::from(Self::UUID),
+            version: (0, 0, 1),
+            description: String::from("Finds and replaces text across the project files."),
+            subscriptions: vec![String::from("designer.search_manager.*"), String::from("designer.project_manager.event")],
+            dependencies: vec![PluginDependency::new(ProjectManager::UUID), PluginDependency::new(FileManager::UUID), PluginDependency::new(DocumentManager::UUID)],
+            api_version: Some(VersionRange::at_least((0, 1, 0))),
+        }
+    }
+
+    fn status(&self) -> PluginStatus {
+        self.status.clone()
+    }
+
+    fn initialize(&mut self, _root: &mut DesignerCore) {
+
+    }
+
+    fn enable(&mut self, _root: &mut DesignerCore) {
+        self.status = PluginStatus::Enabled
+    }
+
+    fn disable(&mut self, _root: &mut DesignerCore) {
+        self.clear();
+        self.status = PluginStatus::Disabled
+    }
+
+    fn load_state(&mut self, _storage: &mut dyn crate::backend::plugin::IPluginStateStorage) {
+
+    }
+
+    fn store_state(&mut self, _storage: &mut dyn crate::backend::plugin::IPluginStateStorage) -> Result<(), PluginError> {
+        Ok(())
+    }
+
+    fn get_state(&self) -> Value {
+        serde_json::to_value(self).unwrap()
+    }
+
+    fn handle_message(&mut self, root: &mut DesignerCore, _sender: &str, action: &str, args: Value) -> Result<(), PluginError> {
+        if action == "designer.project_manager.event" {
+            if let ProjectManagerEvent::Opened { .. } | ProjectManagerEvent::Closed { .. } = parse_args::<ProjectManagerEvent>(action, args)? {
+                self.clear();
+                root.notify_ui();
+            }
+            return Ok(());
+        }
+        if action != "designer.search_manager.management" {
+            return Ok(());
+        }
+        let command = parse_args::<SearchManagerCommand>(action, args)?;
+        if let SearchManagerCommand::ApplyResults = command {
+            self.apply_results(root);
+            root.notify_ui();
+            return Ok(());
+        }
+        let project = root.request::<Option<PathBuf>>("designer.search_manager", &ProjectManagerRequest::ProjectPath)?.ok_or(PluginError::ProjectNotOpened)?;
+        match command {
+            SearchManagerCommand::Search(options) => {
+                self.search(root, &project, options)?;
+            },
+            SearchManagerCommand::Cancel => {
+                if let Some(job) = &self.job {
+                    job.cancel();
+                }
+                if self.searching {
+                    self.searching = false;
+                    root.publish_message("designer.search_manager", &SearchManagerEvent::Finished { files: self.results.len(), matches: self.match_count(), truncated: true });
+                }
+            },
+            SearchManagerCommand::Replace { replacement, skipped } => {
+                self.replace(root, &project, &replacement, &skipped)?;
+            },
+            SearchManagerCommand::EditFiles(files) => {
+                self.edit_files(root, &project, &files).map_err(|(_, error)| error)?;
+            },
+            SearchManagerCommand::ApplyResults => {},
+        }
+        root.notify_ui();
+        Ok(())
+    }
+
+    fn handle_request(&mut self, root: &mut DesignerCore, _sender: &str, action: &str, args: Value) -> Option<Result<Value, PluginError>> {
+        if action != "designer.search_manager.request" {
+            return None;
+        }
+        let request = match parse_args::<SearchManagerRequest>(action, args) {
+            Ok(request) => request,
+            Err(error) => return Some(Err(error)),
+        };
+        match request {
+            SearchManagerRequest::Results => reply(self.results.clone()),
+            SearchManagerRequest::ReplacePreview { replacement } => {
+                let previews = root.request::<Option<PathBuf>>("designer.search_manager", &ProjectManagerRequest::ProjectPath).map_err(PluginError::from)
+                    .and_then(|project| project.ok_or(PluginError::ProjectNotOpened))
+                    .and_then(|project| self.preview(root, &project, &replacement));
+                match previews {
+                    Ok(previews) => reply(previews),
+                    Err(error) => Some(Err(error)),
+                }
+            },
+        }
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+/// Changes of a project file, `expected` is the hash of the text they apply to.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileEdit {
+    pub path: String,
+    pub expected: u64,
+    pub changes: Vec<TextChange>,
+}
+
+/// Lines with matches, starting at `line`, before and after a replacement, answered by `SearchManagerRequest::ReplacePreview`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReplacementPreview {
+    pub path: String,
+    pub line: usize,
+    pub before: String,
+    pub after: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum SearchManagerCommand {
+    /// Stops the running search and starts a new one, the results are cleared.
+    Search(SearchOptions),
+    Cancel,
+    /// Takes what the background search found since the last time, the window sends it when `has_pending_results` is true.
+    ApplyResults,
+    /// Replaces the matches in the files of the results, except the `skipped` ones, as one undoable step.
+    Replace { replacement: String, #[serde(default)] skipped: Vec<String> },
+    /// Applies the changes to project files, sent by the undo and redo of a replacement.
+    EditFiles(Vec<FileEdit>),
+}
+
+impl ICommand for SearchManagerCommand {
+    fn action(&self) -> &str {
+        "designer.search_manager.management"
+    }
+
+    fn args(&self) -> Value {
+        serde_json::to_value(self).unwrap()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum SearchManagerRequest {
+    /// Replies `Vec<FileMatches>`, what the last search found so far.
+    Results,
+    /// Replies `Vec<ReplacementPreview>`.
+    ReplacePreview { replacement: String },
+}
+
+impl ICommand for SearchManagerRequest {
+    fn action(&self) -> &str {
+        "designer.search_manager.request"
+    }
+
+    fn args(&self) -> Value {
+        serde_json::to_value(self).unwrap()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum SearchManagerEvent {
+    Started { options: SearchOptions },
+    /// Files found since the last event, in the order they were searched.
+    Found { files: Vec<FileMatches> },
+    /// `truncated` is also set when the search was cancelled.
+    Finished { files: usize, matches: usize, truncated: bool },
+    Replaced { files: Vec<String>, replacements: usize },
+}
+
+impl ICommand for SearchManagerEvent {
+    fn action(&self) -> &str {
+        "designer.search_manager.event"
+    }
+
+    fn args(&self) -> Value {
+        serde_json::to_value(self).unwrap()
+    }
+}
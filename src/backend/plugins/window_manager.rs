@@ -1,12 +1,12 @@
-use std::{fs::File, io::Write};
+use std::path::{Path, PathBuf};
 
 use log::error;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{json, Map, Value};
 
-use crate::{backend::{commands::ICommand, counter::Counter, plugin::{IPlugin, PluginInfo, PluginStatus}, plugins::logger::LogCommand}, DesignerCore};
+use crate::{backend::{commands::ICommand, counter::Counter, history::UndoEntry, plugin::{parse_args, IPlugin, PluginError, PluginDependency, PluginInfo, PluginStatus, VersionRange}, plugins::logger::LogCommand}, DesignerCore};
 
-use super::project_manager::{ProjectManager, ProjectManagerEvent};
+use super::{document_manager::DocumentManagerEvent, file_manager::FileManagerEvent, node_graph_manager::NodeGraphEvent, project_manager::{ProjectManager, ProjectManagerEvent, ProjectManagerRequest}};
 
 /**
  * @author مۇختەرجان مەخمۇت
@@ -21,53 +21,138 @@ use super::project_manager::{ProjectManager, ProjectManagerEvent};
 pub struct WindowManager {
     pub status: PluginStatus,
     pub dock_state: Option<Value>,
+    /// The layout the window last reported, it is stored in the project when the project closes.
+    #[serde(skip)]
+    pub shown_layout: Option<Value>,
     #[serde(skip_serializing)]
     pub dock_changes: Counter,
+    /// Renamed or deleted project files, the window takes them to update the tabs showing those files.
+    #[serde(skip)]
+    pub file_changes: Vec<FileTabChange>,
+    /// Documents to show in a code editor tab, the window takes them and focuses or opens their tabs.
+    #[serde(skip)]
+    pub opened_documents: Vec<String>,
+    /// Graphs to show in a node editor tab, taken like `opened_documents`.
+    #[serde(skip)]
+    pub opened_graphs: Vec<String>,
+    /// Lines by document path that its code editor should scroll to, the editor takes its own.
+    #[serde(skip)]
+    pub revealed_lines: Vec<(String, usize)>,
+}
+
+/// How an editor tab of a project file has to follow a file manager operation.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FileTabChange {
+    /// Tabs of `from` and of files inside of it now show `to`.
+    Moved { from: String, to: String },
+    /// Tabs of `path` and of files inside of it should be closed.
+    Removed { path: String },
+    /// The project closed, the tabs of all its files should be closed.
+    ProjectClosed,
+}
+
+impl FileTabChange {
+    /// The new file name of a tab after this change, `None` when the tab should be closed, `Some(file_name)` when it is not affected.
+    pub fn apply(&self, file_name: &str) -> Option<String> {
+        let inside = |folder: &str| file_name.strip_prefix(folder).filter(|rest| rest.is_empty() || rest.starts_with('/')).map(str::to_string);
+        match self {
+            FileTabChange::Moved { from, to } => Some(inside(from).map(|rest| format!("{}{}", to, rest)).unwrap_or_else(|| file_name.to_string())),
+            FileTabChange::Removed { path } => inside(path).is_none().then(|| file_name.to_string()),
+            FileTabChange::ProjectClosed => None,
+        }
+    }
 }
 
 impl WindowManager {
+    pub const UUID: &'static str = "35f27394-492c-4af5-804d-803a18a606e8";
+
     pub fn new() -> Self {
-        Self { status: PluginStatus::Disabled, dock_state: None, dock_changes: Counter::new() }
+        Self { status: PluginStatus::Disabled, dock_state: None, shown_layout: None, dock_changes: Counter::new(), file_changes: vec![], opened_documents: vec![], opened_graphs: vec![], revealed_lines: vec![] }
     }
 
-    pub fn load_dock_state(&mut self, root: &mut DesignerCore) -> Option<Value> {
-        let project_manager = root.get_plugin::<ProjectManager>().unwrap();
-        let path = project_manager.path.clone().as_ref().unwrap().join(".designer").join("settings.json");
+    pub fn load_dock_state(&mut self, root: &mut DesignerCore) -> Result<Option<Value>, PluginError> {
+        let Some(project_path) = root.request::<Option<PathBuf>>("WindowManager", &ProjectManagerRequest::ProjectPath)? else {
+            return Ok(None);
+        };
+        let path = project_path.join(".designer").join("settings.json");
         if !path.exists() {
-            return None;
+            return Ok(None);
         }
         let state = {
-            serde_json::from_str::<Value>(&std::fs::read_to_string(path).expect("Cannot read project settings.json file"))
+            serde_json::from_str::<Value>(&std::fs::read_to_string(&path).map_err(|error| PluginError::io(&path, error))?)
         };
         if let Ok(state) = state {
-            root.publish_message("WindowManager", &LogCommand::Generate { category: String::from("Succeed"), content: String::from("Window dock state is stored.") });
+            root.publish_message("WindowManager", &LogCommand::Generate { category: String::from("Succeed"), content: String::from("Window dock state is restored.") });
             root.publish_message("WindowManager", &WindowManagerEvent::DockStateRestored);
             self.dock_changes.count();
-            Some(state)
+            Ok(Some(state))
         } else {
             error!("Cannot deserialize window dock state so discarded.");
             root.publish_message("WindowManager", &LogCommand::Generate { category: String::from("Error"), content: String::from("Cannot deserialize window dock state so discarded.") });
-            None
+            Ok(None)
         }
     }
 
-    pub fn store_dock_state(&mut self, root: &mut DesignerCore, dock_state: Value) {
-        let json_text = {
-            serde_json::to_string_pretty(&dock_state).unwrap_or("".into())
-        };
-        if json_text.is_empty() {
-            error!("Cannot store window dock state.");
-            root.publish_message("WindowManager", &LogCommand::Generate { category: String::from("Error"), content: String::from("Cannot store window dock state.") });
-        } else {
-            let project_manager = root.get_plugin::<ProjectManager>().unwrap();
-            let path = project_manager.path.clone().unwrap().join(".designer").join("settings.json");
-            if !path.parent().unwrap().exists() {
-                std::fs::create_dir_all(&path.parent().unwrap()).expect("Cannot create project settings files directory.");
-            }
-            let mut file = File::create(&path).expect(format!("Cannot open/create editor settings file in your project {:?}.", &path).as_str());
-            file.write_all(json_text.as_bytes()).expect("Cannot write editor settings file in your project.");
-            root.publish_message("WindowManager", &LogCommand::Generate { category: String::from("Succeed"), content: String::from("Window dock state is stored.") });
-            root.publish_message("WindowManager", &WindowManagerEvent::DockStateSaved);
+    /// Takes the project path because the project manager has already forgotten it while the project is closing.
+    pub fn store_dock_state(&mut self, root: &mut DesignerCore, project_path: &Path, dock_state: Value) -> Result<(), PluginError> {
+        let json_text = serde_json::to_string_pretty(&dock_state).map_err(|error| PluginError::Other(format!("Cannot store window dock state: {}", error)))?;
+        let directory = project_path.join(".designer");
+        let path = directory.join("settings.json");
+        std::fs::create_dir_all(&directory).map_err(|error| PluginError::io(&directory, error))?;
+        // written next to the file first so a crash never leaves a half written layout behind.
+        let temporary = path.with_extension("json.tmp");
+        std::fs::write(&temporary, json_text).map_err(|error| PluginError::io(&temporary, error))?;
+        std::fs::rename(&temporary, &path).map_err(|error| PluginError::io(&path, error))?;
+        root.publish_message("WindowManager", &LogCommand::Generate { category: String::from("Succeed"), content: String::from("Window dock state is stored.") });
+        root.publish_message("WindowManager", &WindowManagerEvent::DockStateSaved);
+        Ok(())
+    }
+
+    /// The arrangement of a serialized dock state without what changes while it is only looked at,
+    /// such as sizes in pixels, scroll offsets, the active tab or the focus. Two states with the same structure are the same layout.
+    pub fn layout_structure(state: &Value) -> Value {
+        const VOLATILE_KEYS: [&str; 11] = ["rect", "viewport", "scroll", "active", "focused_node", "focused_surface", "screen_rect", "next_position", "next_size", "dragged", "new"];
+        match state {
+            Value::Object(map) => Value::Object(map.iter()
+                .filter(|(key, _)| !VOLATILE_KEYS.contains(&key.as_str()))
+                .map(|(key, value)| (key.clone(), Self::layout_structure(value)))
+                .collect()),
+            Value::Array(items) => Value::Array(items.iter().map(Self::layout_structure).collect()),
+            value => value.clone(),
+        }
+    }
+
+    /// A dock state that can be restored from a `layout_structure`. What the structure leaves out starts over:
+    /// sizes are measured again by the next frame, the first tab of every leaf is active and nothing has the focus.
+    pub fn layout_from_structure(structure: &Value) -> Value {
+        match structure {
+            Value::Object(map) => {
+                let mut map = map.iter().map(|(key, value)| (key.clone(), Self::layout_from_structure(value))).collect::<Map<String, Value>>();
+                let rect = json!({ "min": { "x": 0.0, "y": 0.0 }, "max": { "x": 0.0, "y": 0.0 } });
+                let missing = if map.contains_key("tabs") {
+                    vec![("rect", rect.clone()), ("viewport", rect), ("active", json!(0)), ("scroll", json!(0.0))]
+                } else if map.contains_key("fraction") {
+                    vec![("rect", rect)]
+                } else if map.contains_key("nodes") {
+                    vec![("focused_node", Value::Null)]
+                } else if map.contains_key("surfaces") {
+                    vec![("focused_surface", Value::Null)]
+                } else {
+                    vec![]
+                };
+                for (key, value) in missing {
+                    map.entry(key).or_insert(value);
+                }
+                // a floating window is placed again like a new one.
+                if let Some(Value::Array(window)) = map.get_mut("Window") {
+                    if let Some(state) = window.get_mut(1) {
+                        *state = json!({ "screen_rect": null, "dragged": false, "next_position": null, "next_size": null, "new": true });
+                    }
+                }
+                Value::Object(map)
+            },
+            Value::Array(items) => Value::Array(items.iter().map(Self::layout_from_structure).collect()),
+            value => value.clone(),
         }
     }
 }
@@ -75,11 +160,14 @@ impl WindowManager {
 impl IPlugin for WindowManager {
     fn info(&self) -> crate::backend::plugin::PluginInfo {
         PluginInfo {
-            uuid: "35f27394-492c-4af5-804d-803a18a606e8",
+            uuid: String::from(Self::UUID),
             name: String::from("Window manager"),
             version: (0, 0, 1),
-            author: "dream-lab",
+            author: String::from("dream-lab"),
             description: String::from("Manages all the windowing functionality."),
+            subscriptions: vec![String::from("designer.window_manager.*"), String::from("designer.project_manager.event"), String::from("designer.file_manager.event"), String::from("designer.document_manager.event"), String::from("designer.node_graph.event")],
+            dependencies: vec![PluginDependency::new(ProjectManager::UUID)],
+            api_version: Some(VersionRange::at_least((0, 1, 0))),
         }
     }
 
@@ -100,33 +188,70 @@ impl IPlugin for WindowManager {
     }
 
     fn load_state(&mut self, storage: &mut dyn crate::backend::plugin::IPluginStateStorage) {
-        *self = serde_json::from_value(storage.load_state()).unwrap()
+        // the dock layout lives in `.designer/settings.json`, see `load_dock_state`.
     }
 
-    fn store_state(&mut self, storage: &mut dyn crate::backend::plugin::IPluginStateStorage) {
-        storage.store_state(serde_json::to_value(self).unwrap());
+    fn store_state(&mut self, _storage: &mut dyn crate::backend::plugin::IPluginStateStorage) -> Result<(), PluginError> {
+        Ok(())
     }
 
     fn get_state(&self) -> serde_json::Value {
         serde_json::to_value(self).unwrap()
     }
 
-    fn handle_message(&mut self, root: &mut crate::DesignerCore, sender: &str, action: &str, args: Value) {
+    fn handle_message(&mut self, root: &mut crate::DesignerCore, _sender: &str, action: &str, args: Value) -> Result<(), PluginError> {
         if action != "designer.window_manager.management" {
             if action == "designer.project_manager.event" {
-                let command = serde_json::from_value::<ProjectManagerEvent>(args).unwrap();
+                let command = parse_args::<ProjectManagerEvent>(action, args)?;
                 match command {
                     ProjectManagerEvent::Opened { path } => {
+                        self.shown_layout = None;
                         root.publish_message("WindowManager", &WindowManagerCommand::LoadDockState);
                     },
                     ProjectManagerEvent::Created { path } => {
                         
                     },
+                    ProjectManagerEvent::Updated { .. } => {},
+                    ProjectManagerEvent::Closing { path, .. } => {
+                        // the window reports its layout before closing the project, the restored one is kept when nothing was reported.
+                        if let Some(state) = self.shown_layout.take().or_else(|| self.dock_state.clone()) {
+                            self.store_dock_state(root, Path::new(&path), state)?;
+                        }
+                    },
+                    ProjectManagerEvent::Closed { .. } => {
+                        self.dock_state = None;
+                        self.file_changes.clear();
+                        self.file_changes.push(FileTabChange::ProjectClosed);
+                        self.opened_documents.clear();
+                        self.opened_graphs.clear();
+                        self.revealed_lines.clear();
+                        root.notify_ui();
+                    },
+                }
+            } else if action == "designer.file_manager.event" {
+                let change = match parse_args::<FileManagerEvent>(action, args)? {
+                    FileManagerEvent::Renamed { from, to } | FileManagerEvent::Moved { from, to } => FileTabChange::Moved { from, to },
+                    FileManagerEvent::Deleted { path, .. } => FileTabChange::Removed { path },
+                    _ => return Ok(()),
+                };
+                self.file_changes.push(change);
+                root.notify_ui();
+            } else if action == "designer.document_manager.event" {
+                match parse_args::<DocumentManagerEvent>(action, args)? {
+                    DocumentManagerEvent::Opened { path } => self.opened_documents.push(path),
+                    DocumentManagerEvent::Revealed { path, line } => self.revealed_lines.push((path, line)),
+                    _ => return Ok(()),
+                }
+                root.notify_ui();
+            } else if action == "designer.node_graph.event" {
+                if let NodeGraphEvent::Opened { path } = parse_args::<NodeGraphEvent>(action, args)? {
+                    self.opened_graphs.push(path);
+                    root.notify_ui();
                 }
             }
-            return;
+            return Ok(());
         }
-        let command = serde_json::from_value::<WindowManagerCommand>(args).unwrap();
+        let command = parse_args::<WindowManagerCommand>(action, args)?;
         match command {
             WindowManagerCommand::OpenWindow(id) => {
 
@@ -135,19 +260,38 @@ impl IPlugin for WindowManager {
 
             },
             WindowManagerCommand::SaveDockState(state) => {
-                self.store_dock_state(root, state);
+                let project_path = root.request::<Option<PathBuf>>("WindowManager", &ProjectManagerRequest::ProjectPath)?.ok_or(PluginError::ProjectNotOpened)?;
+                self.store_dock_state(root, &project_path, state)?;
+            },
+            WindowManagerCommand::UpdateLayout(state) => {
+                self.shown_layout = Some(state);
+                return Ok(());
             },
             WindowManagerCommand::LoadDockState => {
-                let state = self.load_dock_state(root);
+                let state = self.load_dock_state(root)?;
                 self.dock_state = state;
-            }
+            },
+            WindowManagerCommand::RestoreLayout(structure) => {
+                self.dock_state = Some(Self::layout_from_structure(&structure));
+                self.dock_changes.count();
+            },
+            WindowManagerCommand::LayoutChanged { before, after } => {
+                // only the structures are kept in the history, they are a fraction of the dock state.
+                let (before, after) = (Self::layout_structure(&before), Self::layout_structure(&after));
+                root.record_undo(UndoEntry::new("Change the window layout", &WindowManagerCommand::RestoreLayout(after), &WindowManagerCommand::RestoreLayout(before)));
+            },
         }
         root.notify_ui();
+        Ok(())
     }
 
     fn as_any(&self) -> &dyn std::any::Any {
         self
     }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -155,7 +299,14 @@ pub enum WindowManagerCommand {
     OpenWindow(EditorWindowId),
     CloseWindow(EditorWindowId),
     SaveDockState(Value),
+    /// The layout the window shows, stored in the project when it closes.
+    UpdateLayout(Value),
     LoadDockState,
+    /// Replaces the dock layout of the window with a `layout_structure`, it is not stored in the project.
+    RestoreLayout(Value),
+    /// Reported by the window when the user rearranged the tabs, it becomes a step of the undo history.
+    /// Both are `layout_structure`s, full dock states are reduced to them.
+    LayoutChanged { before: Value, after: Value },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
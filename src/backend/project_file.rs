@@ -0,0 +1,178 @@
+use std::{fmt::Display, path::{Path, PathBuf}};
+
+use serde_json::{Map, Value};
+
+use super::{plugin::PluginError, plugins::project_manager::Project};
+
+pub const FILE_NAME: &str = "project.blueproj";
+
+/// Bumped whenever the layout of `project.blueproj` changes, a migration from the previous version must be added too.
+pub const CURRENT_FORMAT_VERSION: u32 = 2;
+
+/// `MIGRATIONS[n]` upgrades a file of format version `n` to `n + 1`.
+const MIGRATIONS: [fn(&mut Map<String, Value>); CURRENT_FORMAT_VERSION as usize] = [
+    migrate_unversioned,
+    migrate_v1,
+];
+
+/// Files written before `format_version` existed: only `name` was guaranteed to be present.
+fn migrate_unversioned(project: &mut Map<String, Value>) {
+    for field in ["description", "package_name", "author", "email"] {
+        project.entry(field).or_insert_with(|| Value::String(String::new()));
+    }
+    project.entry("version").or_insert_with(|| serde_json::json!([0, 0, 1]));
+}
+
+/// Version 2 added the file manager exclusions.
+fn migrate_v1(project: &mut Map<String, Value>) {
+    project.entry("exclude").or_insert_with(|| Value::Array(vec![]));
+}
+
+/// A project file after migration, `migrated_from` is the version found on disk when it was upgraded.
+#[derive(Debug, Clone)]
+pub struct LoadedProject {
+    pub project: Project,
+    pub migrated_from: Option<u32>,
+    pub backup: Option<PathBuf>,
+    /// Problems that do not prevent opening the project, such as an invalid package name.
+    pub problems: Vec<ProjectValidationError>,
+}
+
+/// Reads `<directory>/project.blueproj`, older files are upgraded in place after a backup is written next to them.
+pub fn load(directory: &Path) -> Result<LoadedProject, ProjectFileError> {
+    let path = directory.join(FILE_NAME);
+    let content = std::fs::read_to_string(&path).map_err(|error| ProjectFileError::io(&path, error))?;
+    let mut value = serde_json::from_str::<Value>(&content).map_err(|error| ProjectFileError::Malformed { path: path.clone(), reason: error.to_string() })?;
+    let version = migrate(&path, &mut value)?;
+
+    let project = serde_json::from_value::<Project>(value).map_err(|error| ProjectFileError::Malformed { path: path.clone(), reason: error.to_string() })?;
+    let mut loaded = LoadedProject { problems: project.validate(), project, migrated_from: None, backup: None };
+    if version < CURRENT_FORMAT_VERSION {
+        let backup = path.with_extension(format!("blueproj.v{}.bak", version));
+        std::fs::copy(&path, &backup).map_err(|error| ProjectFileError::io(&backup, error))?;
+        save(directory, &loaded.project)?;
+        loaded.migrated_from = Some(version);
+        loaded.backup = Some(backup);
+    }
+    Ok(loaded)
+}
+
+/// Writes `<directory>/project.blueproj` with the current format version, the old file is replaced atomically.
+pub fn save(directory: &Path, project: &Project) -> Result<(), ProjectFileError> {
+    let path = directory.join(FILE_NAME);
+    let mut project = project.clone();
+    project.format_version = CURRENT_FORMAT_VERSION;
+    let json_text = serde_json::to_string_pretty(&project).map_err(|error| ProjectFileError::Malformed { path: path.clone(), reason: error.to_string() })?;
+    // written next to the file first so a crash never leaves a half written project file behind.
+    let temporary = path.with_extension("blueproj.tmp");
+    std::fs::write(&temporary, json_text).map_err(|error| ProjectFileError::io(&temporary, error))?;
+    std::fs::rename(&temporary, &path).map_err(|error| ProjectFileError::io(&path, error))
+}
+
+/// Runs the migrations the file needs and returns the format version it had.
+fn migrate(path: &Path, value: &mut Value) -> Result<u32, ProjectFileError> {
+    let Value::Object(project) = value else {
+        return Err(ProjectFileError::Malformed { path: path.to_path_buf(), reason: String::from("expected a json object.") });
+    };
+    let version = match project.get("format_version") {
+        None => 0,
+        Some(version) => version.as_u64().and_then(|version| u32::try_from(version).ok())
+            .ok_or_else(|| ProjectFileError::Malformed { path: path.to_path_buf(), reason: format!("format_version {} is not a number.", version) })?,
+    };
+    if version > CURRENT_FORMAT_VERSION {
+        return Err(ProjectFileError::UnsupportedVersion { path: path.to_path_buf(), version });
+    }
+    for migration in &MIGRATIONS[version as usize..] {
+        migration(project);
+    }
+    project.insert(String::from("format_version"), Value::from(CURRENT_FORMAT_VERSION));
+    Ok(version)
+}
+
+impl Project {
+    /// Checks the fields a user types in, an empty list means the project is valid.
+    pub fn validate(&self) -> Vec<ProjectValidationError> {
+        let mut errors = vec![];
+        if self.name.trim().is_empty() {
+            errors.push(ProjectValidationError::new("name", "Project name cannot be empty."));
+        }
+        if !is_valid_package_name(&self.package_name) {
+            errors.push(ProjectValidationError::new("package_name", format!("\"{}\" is not a valid package name, use a reversed domain such as com.example.app.", self.package_name)));
+        }
+        if !self.email.is_empty() && !is_valid_email(&self.email) {
+            errors.push(ProjectValidationError::new("email", format!("\"{}\" is not a valid email address.", self.email)));
+        }
+        errors
+    }
+}
+
+/// At least two dot separated segments, each starting with a letter and made of letters, digits or underscores.
+fn is_valid_package_name(name: &str) -> bool {
+    let segments = name.split('.').collect::<Vec<_>>();
+    segments.len() >= 2 && segments.iter().all(|segment| {
+        segment.starts_with(|c: char| c.is_ascii_alphabetic()) && segment.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    })
+}
+
+fn is_valid_email(email: &str) -> bool {
+    let Some((local, domain)) = email.split_once('@') else {
+        return false;
+    };
+    !local.is_empty() && !domain.contains('@') && !email.chars().any(char::is_whitespace)
+        && domain.split('.').count() >= 2 && domain.split('.').all(|part| !part.is_empty())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProjectValidationError {
+    pub field: &'static str,
+    pub message: String,
+}
+
+impl ProjectValidationError {
+    fn new(field: &'static str, message: impl Into<String>) -> Self {
+        Self { field, message: message.into() }
+    }
+}
+
+impl Display for ProjectValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProjectFileError {
+    Io { path: PathBuf, reason: String },
+    Malformed { path: PathBuf, reason: String },
+    /// The file was written by a newer BlueBird build.
+    UnsupportedVersion { path: PathBuf, version: u32 },
+    Invalid(Vec<ProjectValidationError>),
+}
+
+impl ProjectFileError {
+    pub fn io(path: &Path, error: std::io::Error) -> Self {
+        Self::Io { path: path.to_path_buf(), reason: error.to_string() }
+    }
+}
+
+impl Display for ProjectFileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProjectFileError::Io { path, reason } => write!(f, "Cannot access project file {:?}: {}", path, reason),
+            ProjectFileError::Malformed { path, reason } => write!(f, "Project file {:?} is malformed: {}", path, reason),
+            ProjectFileError::UnsupportedVersion { path, version } => write!(f, "Project file {:?} has format version {}, this build supports up to {}. Please update BlueBird.", path, version, CURRENT_FORMAT_VERSION),
+            ProjectFileError::Invalid(errors) => write!(f, "Invalid project: {}", errors.iter().map(|error| error.to_string()).collect::<Vec<_>>().join(" ")),
+        }
+    }
+}
+
+impl std::error::Error for ProjectFileError {}
+
+impl From<ProjectFileError> for PluginError {
+    fn from(error: ProjectFileError) -> Self {
+        match error {
+            ProjectFileError::Io { path, reason } => PluginError::Io { path: format!("{:?}", path), reason },
+            error => PluginError::Other(error.to_string()),
+        }
+    }
+}
@@ -0,0 +1,212 @@
+use std::{fmt::Display, fs::File, io::Read, path::{Path, PathBuf}};
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use super::{extension::is_relative_inside, plugin::PluginError, plugins::project_manager::Project, project_file};
+
+/// Optional file at the root of a template describing it, it is not copied into the project.
+pub const MANIFEST_FILE_NAME: &str = "template.json";
+
+/// Id of the template used when none is selected.
+pub const EMPTY_TEMPLATE: &str = "empty";
+
+const BUILTIN_TEMPLATES: &[(&str, &[(&str, &str)])] = &[
+    (EMPTY_TEMPLATE, &[
+        ("template.json", include_str!("../../assets/templates/empty/template.json")),
+        ("README.md", include_str!("../../assets/templates/empty/README.md")),
+    ]),
+    ("js_ui_extension", &[
+        ("template.json", include_str!("../../assets/templates/js_ui_extension/template.json")),
+        ("extension.json", include_str!("../../assets/templates/js_ui_extension/extension.json")),
+        ("src/main.js", include_str!("../../assets/templates/js_ui_extension/src/main.js")),
+    ]),
+    ("node_graph", &[
+        ("template.json", include_str!("../../assets/templates/node_graph/template.json")),
+        ("README.md", include_str!("../../assets/templates/node_graph/README.md")),
+        ("graphs/main.bluegraph", include_str!("../../assets/templates/node_graph/graphs/main.bluegraph")),
+    ]),
+];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct TemplateManifest {
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    description: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum TemplateSource {
+    Builtin,
+    Directory(PathBuf),
+    Archive(PathBuf),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectTemplate {
+    /// Id of a built in template, or the folder / archive name of a user template.
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub source: TemplateSource,
+    /// Relative paths of the files the template creates, used as a preview.
+    pub files: Vec<String>,
+}
+
+impl ProjectTemplate {
+    /// `<config dir>/bluebird-ide/templates`
+    pub fn user_directory(config_dir: &Path) -> PathBuf {
+        config_dir.join("templates")
+    }
+
+    /// Reads every file of the template, paths are relative and use `/`.
+    fn read_files(&self) -> Result<Vec<(String, String)>, TemplateError> {
+        match &self.source {
+            TemplateSource::Builtin => {
+                let (_, files) = BUILTIN_TEMPLATES.iter().find(|(id, _)| *id == self.id).ok_or_else(|| TemplateError::NotFound(self.id.clone()))?;
+                Ok(files.iter().map(|(path, content)| (path.to_string(), content.to_string())).collect())
+            },
+            TemplateSource::Directory(root) => {
+                let mut files = vec![];
+                read_directory(root, root, &mut files)?;
+                Ok(files)
+            },
+            TemplateSource::Archive(path) => read_archive(path),
+        }
+    }
+
+    fn from_files(id: String, source: TemplateSource, files: &[(String, String)]) -> Self {
+        let manifest = files.iter().find(|(path, _)| path == MANIFEST_FILE_NAME)
+            .and_then(|(_, content)| serde_json::from_str::<TemplateManifest>(content).ok())
+            .unwrap_or_default();
+        let mut preview = files.iter().map(|(path, _)| path.clone()).filter(|path| path != MANIFEST_FILE_NAME && path != project_file::FILE_NAME).collect::<Vec<_>>();
+        preview.push(String::from(project_file::FILE_NAME));
+        preview.sort();
+        Self { name: if manifest.name.is_empty() { id.clone() } else { manifest.name }, id, description: manifest.description, source, files: preview }
+    }
+
+    /// Writes the template into `directory` with the placeholders replaced, then the project file itself.
+    pub fn instantiate(&self, directory: &Path, project: &Project) -> Result<(), TemplateError> {
+        for (relative, content) in self.read_files()? {
+            if relative == MANIFEST_FILE_NAME || relative == project_file::FILE_NAME {
+                continue;
+            }
+            let relative = substitute(&relative, project);
+            if !is_relative_inside(Path::new(&relative)) {
+                return Err(TemplateError::Invalid { template: self.id.clone(), reason: format!("{} points outside of the project.", relative) });
+            }
+            let path = directory.join(&relative);
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent).map_err(|error| TemplateError::io(parent, error))?;
+            }
+            std::fs::write(&path, substitute(&content, project)).map_err(|error| TemplateError::io(&path, error))?;
+        }
+        project_file::save(directory, project).map_err(|error| TemplateError::Invalid { template: self.id.clone(), reason: error.to_string() })
+    }
+}
+
+/// Replaces `{{name}}`, `{{package_name}}` and `{{author}}` with the fields of the project.
+pub fn substitute(text: &str, project: &Project) -> String {
+    text.replace("{{name}}", &project.name)
+        .replace("{{package_name}}", &project.package_name)
+        .replace("{{author}}", &project.author)
+}
+
+/// Built in templates followed by the user templates, user templates with a broken archive are skipped.
+pub fn available_templates(config_dir: Option<&Path>) -> Vec<ProjectTemplate> {
+    let mut templates = BUILTIN_TEMPLATES.iter().map(|(id, files)| {
+        let files = files.iter().map(|(path, content)| (path.to_string(), content.to_string())).collect::<Vec<_>>();
+        ProjectTemplate::from_files(id.to_string(), TemplateSource::Builtin, &files)
+    }).collect::<Vec<_>>();
+
+    let Some(read_dir) = config_dir.and_then(|directory| ProjectTemplate::user_directory(directory).read_dir().ok()) else {
+        return templates;
+    };
+    let mut paths = read_dir.flatten().map(|entry| entry.path()).collect::<Vec<_>>();
+    paths.sort();
+    for path in paths {
+        let Some(id) = path.file_stem().map(|stem| stem.to_string_lossy().to_string()) else {
+            continue;
+        };
+        let source = if path.is_dir() {
+            TemplateSource::Directory(path)
+        } else if path.extension().is_some_and(|extension| extension == "zip") {
+            TemplateSource::Archive(path)
+        } else {
+            continue;
+        };
+        let template = ProjectTemplate { id, name: String::new(), description: String::new(), source, files: vec![] };
+        match template.read_files() {
+            Ok(files) => templates.push(ProjectTemplate::from_files(template.id, template.source, &files)),
+            Err(error) => warn!("Skipping project template {:?}: {}", template.source, error),
+        }
+    }
+    templates
+}
+
+fn read_directory(root: &Path, directory: &Path, files: &mut Vec<(String, String)>) -> Result<(), TemplateError> {
+    for entry in directory.read_dir().map_err(|error| TemplateError::io(directory, error))?.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            read_directory(root, &path, files)?;
+        } else {
+            let relative = path.strip_prefix(root).unwrap_or(&path).components().map(|part| part.as_os_str().to_string_lossy().to_string()).collect::<Vec<_>>().join("/");
+            let content = std::fs::read_to_string(&path).map_err(|error| TemplateError::io(&path, error))?;
+            files.push((relative, content));
+        }
+    }
+    Ok(())
+}
+
+fn read_archive(path: &Path) -> Result<Vec<(String, String)>, TemplateError> {
+    let archive_error = |error: zip::result::ZipError| TemplateError::Invalid { template: path.to_string_lossy().to_string(), reason: error.to_string() };
+    let file = File::open(path).map_err(|error| TemplateError::io(path, error))?;
+    let mut archive = zip::ZipArchive::new(file).map_err(archive_error)?;
+    let mut files = vec![];
+    for index in 0..archive.len() {
+        let mut entry = archive.by_index(index).map_err(archive_error)?;
+        if entry.is_dir() {
+            continue;
+        }
+        let name = entry.name().to_string();
+        let mut content = String::new();
+        entry.read_to_string(&mut content).map_err(|error| TemplateError::io(path, error))?;
+        files.push((name, content));
+    }
+    Ok(files)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TemplateError {
+    NotFound(String),
+    Io { path: PathBuf, reason: String },
+    Invalid { template: String, reason: String },
+}
+
+impl TemplateError {
+    pub fn io(path: &Path, error: std::io::Error) -> Self {
+        Self::Io { path: path.to_path_buf(), reason: error.to_string() }
+    }
+}
+
+impl Display for TemplateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TemplateError::NotFound(id) => write!(f, "Project template \"{}\" does not exist.", id),
+            TemplateError::Io { path, reason } => write!(f, "Cannot access template file {:?}: {}", path, reason),
+            TemplateError::Invalid { template, reason } => write!(f, "Invalid project template \"{}\": {}", template, reason),
+        }
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+impl From<TemplateError> for PluginError {
+    fn from(error: TemplateError) -> Self {
+        match error {
+            TemplateError::Io { path, reason } => PluginError::Io { path: format!("{:?}", path), reason },
+            error => PluginError::Other(error.to_string()),
+        }
+    }
+}
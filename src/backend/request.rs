@@ -0,0 +1,37 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::plugin::PluginError;
+
+/// Correlates a reply with the request that produced it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct RequestId(pub u64);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum RequestError {
+    /// No enabled plugin answered the action.
+    NoHandler { action: String },
+    /// The handling plugin answered with an error.
+    Failed { action: String, message: String },
+    /// The reply could not be converted into the type the caller asked for.
+    InvalidReply { action: String, message: String },
+}
+
+impl Display for RequestError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RequestError::NoHandler { action } => write!(f, "No plugin handles request \"{}\".", action),
+            RequestError::Failed { action, message } => write!(f, "Request \"{}\" failed: {}", action, message),
+            RequestError::InvalidReply { action, message } => write!(f, "Reply of request \"{}\" is malformed: {}", action, message),
+        }
+    }
+}
+
+impl std::error::Error for RequestError {}
+
+/// Serializes a successful answer for `IPlugin::handle_request`.
+pub fn reply<T: Serialize>(value: T) -> Option<Result<Value, PluginError>> {
+    Some(serde_json::to_value(value).map_err(|error| PluginError::Other(error.to_string())))
+}
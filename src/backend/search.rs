@@ -0,0 +1,279 @@
+use std::{collections::HashMap, fmt::Display, ops::Range, path::Path, sync::{atomic::{AtomicBool, Ordering}, Arc, Mutex}};
+
+use ignore::overrides::{Override, OverrideBuilder};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+use super::{file_filter::FileFilter, plugin::PluginError, plugins::file_manager::relative_path};
+
+/// Bigger files are skipped, they are most likely generated or data rather than source.
+pub const MAX_FILE_SIZE: u64 = 8 * 1024 * 1024;
+
+/// A search stops once it found this many matches, the results are then marked as truncated.
+pub const MAX_MATCHES: usize = 10_000;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SearchOptions {
+    pub query: String,
+    /// `query` is a regular expression, `$1` or `${name}` in a replacement insert its groups.
+    #[serde(default)]
+    pub regex: bool,
+    #[serde(default)]
+    pub case_sensitive: bool,
+    /// Only matches that are not part of a longer word.
+    #[serde(default)]
+    pub whole_word: bool,
+    /// Globs of the files to search such as `src/**/*.js`, every file is searched when empty.
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// Globs of the files and folders to skip, on top of the files hidden in the file manager.
+    #[serde(default)]
+    pub exclude: Vec<String>,
+}
+
+/// Lines and columns start at zero, columns and lengths count characters.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchMatch {
+    pub line: usize,
+    pub column: usize,
+    /// A match running into the following lines only counts the characters on its first line.
+    pub length: usize,
+    /// The line of the match without its line break.
+    pub line_text: String,
+}
+
+/// Matches of one file in the order they appear, `path` is relative to the project directory.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileMatches {
+    pub path: String,
+    pub matches: Vec<SearchMatch>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SearchError {
+    EmptyQuery,
+    InvalidPattern(String),
+    InvalidGlob { pattern: String, reason: String },
+    /// A replacement needs the matcher of a search.
+    NoSearch,
+    /// The file no longer holds the text a replacement was computed from.
+    FileChanged { path: String },
+}
+
+impl Display for SearchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SearchError::EmptyQuery => write!(f, "Please enter the text to search for."),
+            SearchError::InvalidPattern(reason) => write!(f, "The regular expression is not valid: {}", reason),
+            SearchError::InvalidGlob { pattern, reason } => write!(f, "\"{}\" is not a valid file pattern: {}", pattern, reason),
+            SearchError::NoSearch => write!(f, "Please search before replacing."),
+            SearchError::FileChanged { path } => write!(f, "\"{}\" was changed since the replacement, nothing is written.", path),
+        }
+    }
+}
+
+impl std::error::Error for SearchError {}
+
+impl From<SearchError> for PluginError {
+    fn from(error: SearchError) -> Self {
+        PluginError::Other(error.to_string())
+    }
+}
+
+/// The compiled query and file patterns of a search.
+#[derive(Debug, Clone)]
+pub struct Matcher {
+    regex: Regex,
+    files: Override,
+    /// `$` in a replacement is taken literally unless the query is a regular expression.
+    literal: bool,
+}
+
+impl Matcher {
+    /// Globs are relative to `root`, an exclusion is written as in `.gitignore` without the leading `!`.
+    pub fn new(root: &Path, options: &SearchOptions) -> Result<Self, SearchError> {
+        if options.query.is_empty() {
+            return Err(SearchError::EmptyQuery);
+        }
+        let pattern = if options.regex {options.query.clone()} else {regex::escape(&options.query)};
+        let pattern = if options.whole_word {format!(r"\b(?:{})\b", pattern)} else {pattern};
+        let regex = RegexBuilder::new(&pattern).case_insensitive(!options.case_sensitive).multi_line(true).build()
+            .map_err(|error| SearchError::InvalidPattern(error.to_string()))?;
+        let mut builder = OverrideBuilder::new(root);
+        let globs = options.include.iter().map(|glob| (glob.trim(), glob.trim().to_string()))
+            .chain(options.exclude.iter().map(|glob| (glob.trim(), format!("!{}", glob.trim()))));
+        for (pattern, glob) in globs.filter(|(pattern, _)| !pattern.is_empty()) {
+            builder.add(&glob).map_err(|error| SearchError::InvalidGlob { pattern: pattern.to_string(), reason: error.to_string() })?;
+        }
+        let files = builder.build().map_err(|error| SearchError::InvalidGlob { pattern: String::new(), reason: error.to_string() })?;
+        Ok(Self { regex, files, literal: !options.regex })
+    }
+
+    /// Whether the file is searched, or the folder is looked into. Folders only stop the search when they are excluded.
+    pub fn accepts(&self, path: &Path, is_dir: bool) -> bool {
+        !self.files.matched(path, is_dir).is_ignore()
+    }
+
+    /// Empty matches, such as the ones of `a*`, are left out.
+    pub fn find(&self, text: &str) -> Vec<SearchMatch> {
+        let mut matches = vec![];
+        let (mut line, mut counted) = (0, 0);
+        for found in self.regex.find_iter(text).filter(|found| !found.is_empty()) {
+            line += text[counted..found.start()].matches('\n').count();
+            counted = found.start();
+            let line_start = text[..found.start()].rfind('\n').map(|index| index + 1).unwrap_or(0);
+            let line_end = text[found.start()..].find('\n').map(|index| found.start() + index).unwrap_or(text.len());
+            matches.push(SearchMatch {
+                line,
+                column: text[line_start..found.start()].chars().count(),
+                length: text[found.start()..found.end().min(line_end)].chars().count(),
+                line_text: text[line_start..line_end].trim_end_matches('\r').to_string(),
+            });
+        }
+        matches
+    }
+
+    /// Byte ranges of the matches in the order they appear, each with the text replacing it.
+    pub fn replacements(&self, text: &str, replacement: &str) -> Vec<(Range<usize>, String)> {
+        self.regex.captures_iter(text).map(|captures| {
+            let mut replaced = String::new();
+            if self.literal {
+                replaced.push_str(replacement);
+            } else {
+                captures.expand(replacement, &mut replaced);
+            }
+            (captures.get(0).unwrap().range(), replaced)
+        }).collect()
+    }
+}
+
+/// What the thread of a search found since the owner last took it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SearchProgress {
+    pub found: Vec<FileMatches>,
+    pub searched_files: usize,
+    pub finished: bool,
+    /// The search stopped at `MAX_MATCHES`.
+    pub truncated: bool,
+}
+
+#[derive(Debug, Default)]
+struct SharedProgress {
+    progress: SearchProgress,
+    /// The owner took the progress since the search finished.
+    finish_taken: bool,
+}
+
+/// Walks the project on a background thread, `wake` is called whenever files with matches were found.
+/// Dropping the job stops the thread at the next file.
+pub struct SearchJob {
+    progress: Arc<Mutex<SharedProgress>>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl std::fmt::Debug for SearchJob {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SearchJob").field("cancelled", &self.cancelled.load(Ordering::Relaxed)).finish()
+    }
+}
+
+impl SearchJob {
+    /// Files hidden by `filter` are skipped like in the file manager. `documents` holds the text of open buffers
+    /// with unsaved changes by their relative path, they are searched instead of what is on disk.
+    pub fn start(root: &Path, mut filter: FileFilter, matcher: Matcher, documents: HashMap<String, String>, wake: Option<Arc<dyn Fn() + Send + Sync>>) -> Self {
+        let progress = Arc::new(Mutex::new(SharedProgress::default()));
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let (shared, stop, root) = (progress.clone(), cancelled.clone(), root.to_path_buf());
+        std::thread::spawn(move || {
+            let mut folders = vec![root.clone()];
+            let mut total = 0;
+            let mut truncated = false;
+            'walk: while let Some(folder) = folders.pop() {
+                let Ok(entries) = std::fs::read_dir(&folder) else {
+                    continue;
+                };
+                let mut entries = entries.flatten().filter_map(|entry| entry.file_type().ok().map(|file_type| (entry.path(), file_type.is_dir()))).collect::<Vec<_>>();
+                entries.sort();
+                // folders are popped from the end, they are pushed in reverse to be searched in name order.
+                for (path, is_dir) in entries.iter().rev().filter(|(_, is_dir)| *is_dir) {
+                    if !filter.is_ignored(path, *is_dir) && matcher.accepts(path, *is_dir) {
+                        folders.push(path.clone());
+                    }
+                }
+                for (path, _) in entries.iter().filter(|(_, is_dir)| !*is_dir) {
+                    if stop.load(Ordering::Relaxed) {
+                        return;
+                    }
+                    if filter.is_ignored(path, false) || !matcher.accepts(path, false) {
+                        continue;
+                    }
+                    let relative = relative_path(&root, path);
+                    let Some(text) = documents.get(&relative).cloned().or_else(|| read_searchable(path)) else {
+                        continue;
+                    };
+                    let mut matches = matcher.find(&text);
+                    matches.truncate(MAX_MATCHES - total);
+                    total += matches.len();
+                    let Ok(mut shared) = shared.lock() else {
+                        return;
+                    };
+                    shared.progress.searched_files += 1;
+                    if !matches.is_empty() {
+                        shared.progress.found.push(FileMatches { path: relative, matches });
+                        drop(shared);
+                        if let Some(wake) = &wake {
+                            wake();
+                        }
+                    }
+                    if total >= MAX_MATCHES {
+                        truncated = true;
+                        break 'walk;
+                    }
+                }
+            }
+            if let Ok(mut shared) = shared.lock() {
+                shared.progress.finished = true;
+                shared.progress.truncated = truncated;
+            }
+            if let Some(wake) = &wake {
+                wake();
+            }
+        });
+        Self { progress, cancelled }
+    }
+
+    /// Files were found, or the search finished, since the last `take_progress`.
+    pub fn has_progress(&self) -> bool {
+        self.progress.lock().map(|shared| !shared.progress.found.is_empty() || (shared.progress.finished && !shared.finish_taken)).unwrap_or(false)
+    }
+
+    pub fn take_progress(&self) -> SearchProgress {
+        let Ok(mut shared) = self.progress.lock() else {
+            return SearchProgress::default();
+        };
+        shared.finish_taken = shared.progress.finished;
+        SearchProgress { found: std::mem::take(&mut shared.progress.found), ..shared.progress.clone() }
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Drop for SearchJob {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+/// Text files up to `MAX_FILE_SIZE`, files with a NUL byte or that are not UTF-8 are taken as binary.
+fn read_searchable(path: &Path) -> Option<String> {
+    if std::fs::metadata(path).ok()?.len() > MAX_FILE_SIZE {
+        return None;
+    }
+    let bytes = std::fs::read(path).ok()?;
+    if bytes.contains(&0) {
+        return None;
+    }
+    String::from_utf8(bytes).ok()
+}
@@ -0,0 +1,263 @@
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Language {
+    JavaScript,
+    TypeScript,
+    Json,
+    Python,
+    PlainText,
+}
+
+impl Language {
+    /// Decided by the file extension, unknown extensions are plain text.
+    pub fn from_path(path: &str) -> Self {
+        let extension = path.rsplit('/').next().unwrap_or(path).rsplit_once('.').map(|(_, extension)| extension.to_lowercase()).unwrap_or_default();
+        match extension.as_str() {
+            "js" | "mjs" | "cjs" | "jsx" => Language::JavaScript,
+            "ts" | "mts" | "cts" | "tsx" => Language::TypeScript,
+            "json" | "blueproj" | "bluegraph" => Language::Json,
+            "py" | "pyw" | "pyi" => Language::Python,
+            _ => Language::PlainText,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Language::JavaScript => "JavaScript",
+            Language::TypeScript => "TypeScript",
+            Language::Json => "JSON",
+            Language::Python => "Python",
+            Language::PlainText => "Plain text",
+        }
+    }
+
+    fn keywords(&self) -> &'static [&'static str] {
+        match self {
+            Language::JavaScript => JAVASCRIPT_KEYWORDS,
+            Language::TypeScript => TYPESCRIPT_KEYWORDS,
+            Language::Python => PYTHON_KEYWORDS,
+            Language::Json | Language::PlainText => &[],
+        }
+    }
+
+    fn literals(&self) -> &'static [&'static str] {
+        match self {
+            Language::JavaScript | Language::TypeScript => &["true", "false", "null", "undefined", "NaN", "Infinity", "this", "super"],
+            Language::Json => &["true", "false", "null"],
+            Language::Python => &["True", "False", "None", "self", "cls"],
+            Language::PlainText => &[],
+        }
+    }
+}
+
+const JAVASCRIPT_KEYWORDS: &[&str] = &[
+    "async", "await", "break", "case", "catch", "class", "const", "continue", "debugger", "default", "delete", "do", "else", "export", "extends",
+    "finally", "for", "from", "function", "get", "if", "import", "in", "instanceof", "let", "new", "of", "return", "set", "static", "switch",
+    "throw", "try", "typeof", "var", "void", "while", "with", "yield",
+];
+
+const TYPESCRIPT_KEYWORDS: &[&str] = &[
+    "abstract", "any", "as", "async", "await", "boolean", "break", "case", "catch", "class", "const", "continue", "debugger", "declare", "default",
+    "delete", "do", "else", "enum", "export", "extends", "finally", "for", "from", "function", "get", "if", "implements", "import", "in", "infer",
+    "instanceof", "interface", "is", "keyof", "let", "module", "namespace", "never", "new", "number", "object", "of", "private", "protected",
+    "public", "readonly", "return", "set", "static", "string", "switch", "symbol", "throw", "try", "type", "typeof", "unknown", "var", "void",
+    "while", "with", "yield",
+];
+
+const PYTHON_KEYWORDS: &[&str] = &[
+    "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del", "elif", "else", "except", "finally", "for", "from",
+    "global", "if", "import", "in", "is", "lambda", "match", "case", "nonlocal", "not", "or", "pass", "raise", "return", "try", "while", "with", "yield",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TokenKind {
+    /// Identifiers, whitespace and anything else without a color of its own.
+    Plain,
+    Keyword,
+    /// `true`, `null`, `None` and similar built in values.
+    Literal,
+    Number,
+    String,
+    Comment,
+    /// A JSON object key.
+    Property,
+    Punctuation,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Token {
+    pub kind: TokenKind,
+    /// Byte range in the tokenized text.
+    pub range: Range<usize>,
+}
+
+/// Where a line starts: inside a construct that began on an earlier line, or not.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum LineState {
+    #[default]
+    Normal,
+    /// A JavaScript or TypeScript `/* */` comment.
+    BlockComment,
+    /// A JavaScript or TypeScript template literal.
+    TemplateString,
+    /// A Python string in triple quotes, `'` or `"`.
+    TripleQuoted(char),
+}
+
+/// Tokens covering the whole text in order, unterminated strings and comments run to the end of the line or the text.
+pub fn tokenize(language: Language, text: &str) -> Vec<Token> {
+    scan(language, text, LineState::Normal).0
+}
+
+/// Tokens of one line without its line break, and the state the next line starts in.
+/// Highlighting line by line only needs the state of the line above, so an editor can start at any line it has the state of.
+pub fn tokenize_line(language: Language, line: &str, state: LineState) -> (Vec<Token>, LineState) {
+    scan(language, line, state)
+}
+
+fn scan(language: Language, text: &str, state: LineState) -> (Vec<Token>, LineState) {
+    if language == Language::PlainText {
+        return (if text.is_empty() {vec![]} else {vec![Token { kind: TokenKind::Plain, range: 0..text.len() }]}, LineState::Normal);
+    }
+    let bytes = text.as_bytes();
+    let mut tokens: Vec<Token> = vec![];
+    let mut position = 0;
+    let mut state = state;
+    // the rest of a construct from the line above.
+    let resumed = match state {
+        LineState::Normal => None,
+        LineState::BlockComment => Some((TokenKind::Comment, text.find("*/").map(|end| end + 2))),
+        LineState::TemplateString => Some((TokenKind::String, closing_quote(text, '`', true))),
+        LineState::TripleQuoted(quote) => {
+            let triple = quote.to_string().repeat(3);
+            Some((TokenKind::String, text.find(&triple).map(|end| end + 3)))
+        },
+    };
+    if let Some((kind, end)) = resumed {
+        position = end.unwrap_or(text.len());
+        if end.is_some() {
+            state = LineState::Normal;
+        }
+        if position > 0 {
+            tokens.push(Token { kind, range: 0..position });
+        }
+    }
+    while position < bytes.len() {
+        let start = position;
+        let rest = &text[position..];
+        let character = rest.chars().next().unwrap_or_default();
+        let kind = if character.is_whitespace() {
+            position += rest.find(|character: char| !character.is_whitespace()).unwrap_or(rest.len());
+            TokenKind::Plain
+        } else if let Some((end, open)) = comment_end(language, rest) {
+            position += end;
+            if let Some(open) = open {
+                state = open;
+            }
+            TokenKind::Comment
+        } else if let Some((end, open)) = string_end(language, rest) {
+            position += end;
+            if let Some(open) = open {
+                state = open;
+            }
+            let after = text[position..].trim_start_matches([' ', '\t']);
+            if language == Language::Json && after.starts_with(':') {TokenKind::Property} else {TokenKind::String}
+        } else if character.is_ascii_digit() || (character == '.' && rest[1..].starts_with(|next: char| next.is_ascii_digit())) {
+            position += rest.find(|character: char| !(character.is_ascii_alphanumeric() || character == '.' || character == '_')).unwrap_or(rest.len());
+            TokenKind::Number
+        } else if character.is_alphabetic() || character == '_' || character == '$' {
+            let length = rest.find(|character: char| !(character.is_alphanumeric() || character == '_' || character == '$')).unwrap_or(rest.len());
+            position += length;
+            let word = &rest[..length];
+            let prefixed_string = (language == Language::Python && is_string_prefix(word)).then(|| string_end(language, &text[position..])).flatten();
+            if let Some((end, open)) = prefixed_string {
+                // `f"..."`, `rb'...'`: the prefix is part of the string.
+                position += end;
+                if let Some(open) = open {
+                    state = open;
+                }
+                TokenKind::String
+            } else if language.keywords().contains(&word) {
+                TokenKind::Keyword
+            } else if language.literals().contains(&word) {
+                TokenKind::Literal
+            } else {
+                TokenKind::Plain
+            }
+        } else {
+            position += character.len_utf8();
+            TokenKind::Punctuation
+        };
+        match tokens.last_mut() {
+            Some(last) if last.kind == kind && kind == TokenKind::Plain => last.range.end = position,
+            _ => tokens.push(Token { kind, range: start..position }),
+        }
+    }
+    (tokens, state)
+}
+
+fn is_string_prefix(word: &str) -> bool {
+    word.len() <= 2 && word.chars().all(|character| matches!(character.to_ascii_lowercase(), 'r' | 'b' | 'f' | 'u'))
+}
+
+/// Length of the comment at the start of `text`, with the state to continue in when it is not closed before the end of `text`.
+fn comment_end(language: Language, text: &str) -> Option<(usize, Option<LineState>)> {
+    let line_end = || text.find('\n').unwrap_or(text.len());
+    match language {
+        Language::JavaScript | Language::TypeScript if text.starts_with("//") => Some((line_end(), None)),
+        Language::JavaScript | Language::TypeScript if text.starts_with("/*") => Some(match text[2..].find("*/") {
+            Some(end) => (end + 4, None),
+            None => (text.len(), Some(LineState::BlockComment)),
+        }),
+        Language::Python if text.starts_with('#') => Some((line_end(), None)),
+        _ => None,
+    }
+}
+
+/// Length of the string literal at the start of `text`, with the state to continue in when a multi-line string is not closed before the end of `text`.
+fn string_end(language: Language, text: &str) -> Option<(usize, Option<LineState>)> {
+    let quote = text.chars().next()?;
+    let quotes: &[char] = match language {
+        Language::JavaScript | Language::TypeScript => &['"', '\'', '`'],
+        Language::Json => &['"'],
+        Language::Python => &['"', '\''],
+        Language::PlainText => &[],
+    };
+    if !quotes.contains(&quote) {
+        return None;
+    }
+    if language == Language::Python {
+        let triple = quote.to_string().repeat(3);
+        if text.starts_with(&triple) {
+            return Some(match text[3..].find(&triple) {
+                Some(end) => (end + 6, None),
+                None => (text.len(), Some(LineState::TripleQuoted(quote))),
+            });
+        }
+    }
+    // template literals span lines, the other strings stop at the end of the line when they are not closed.
+    let multiline = quote == '`';
+    Some(match closing_quote(&text[1..], quote, multiline) {
+        Some(end) => (end + 1, None),
+        None if multiline => (text.len(), Some(LineState::TemplateString)),
+        None => (text[1..].find('\n').map(|end| end + 1).unwrap_or(text.len()), None),
+    })
+}
+
+/// Byte offset after the first unescaped `quote` of `text`, `None` when it is not closed.
+fn closing_quote(text: &str, quote: char, multiline: bool) -> Option<usize> {
+    let mut escaped = false;
+    for (index, character) in text.char_indices() {
+        match character {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '\n' if !multiline => return None,
+            _ if character == quote => return Some(index + 1),
+            _ => {},
+        }
+    }
+    None
+}
@@ -0,0 +1,544 @@
+use std::{fmt::Display, hash::Hasher, ops::Range, time::{Duration, Instant}};
+
+use ropey::Rope;
+use serde::{Deserialize, Serialize};
+
+use super::plugin::PluginError;
+
+/// Transactions kept for undo, the oldest ones are dropped beyond it.
+pub const UNDO_LIMIT: usize = 1000;
+
+/// Typing or deleting within this time after the previous keystroke joins its undo step.
+pub const GROUP_TIMEOUT: Duration = Duration::from_millis(1000);
+
+/// Character offsets, the head is where the cursor is drawn and moves, the anchor stays where the selection started.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Selection {
+    pub anchor: usize,
+    pub head: usize,
+}
+
+impl Selection {
+    pub fn cursor(position: usize) -> Self {
+        Self { anchor: position, head: position }
+    }
+
+    pub fn start(&self) -> usize {
+        self.anchor.min(self.head)
+    }
+
+    pub fn end(&self) -> usize {
+        self.anchor.max(self.head)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.anchor == self.head
+    }
+
+    fn map(&self, change: &TextChange) -> Self {
+        Self { anchor: change.map_position(self.anchor), head: change.map_position(self.head) }
+    }
+}
+
+/// Replaces the characters `start..end` with `text`. Changes of one edit are applied one after the other,
+/// the offsets of a change count characters of the text left by the changes before it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TextChange {
+    pub start: usize,
+    pub end: usize,
+    pub text: String,
+}
+
+impl TextChange {
+    pub fn insert(position: usize, text: &str) -> Self {
+        Self { start: position, end: position, text: text.to_string() }
+    }
+
+    pub fn delete(range: Range<usize>) -> Self {
+        Self { start: range.start, end: range.end, text: String::new() }
+    }
+
+    /// The change turning `before` into `after`, only the part between their common start and end is replaced.
+    pub fn between(before: &str, after: &str) -> Self {
+        let prefix = before.chars().zip(after.chars()).take_while(|(a, b)| a == b).count();
+        let (before_length, after_length) = (before.chars().count(), after.chars().count());
+        let suffix = before.chars().rev().zip(after.chars().rev()).take_while(|(a, b)| a == b).count().min(before_length - prefix).min(after_length - prefix);
+        Self { start: prefix, end: before_length - suffix, text: after.chars().skip(prefix).take(after_length - prefix - suffix).collect() }
+    }
+
+    /// Where a position ends up after this change, positions inside the replaced range move behind the new text.
+    pub fn map_position(&self, position: usize) -> usize {
+        if position < self.start {
+            position
+        } else if position >= self.end {
+            position - (self.end - self.start) + self.text.chars().count()
+        } else {
+            self.start + self.text.chars().count()
+        }
+    }
+}
+
+/// Decides which edits share an undo step.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EditKind {
+    /// Characters typed at the cursors, consecutive ones are undone together.
+    Typing,
+    /// Characters removed by backspace or delete, consecutive ones are undone together.
+    Deleting,
+    /// Pastes, formatting and anything else, always a step of its own.
+    #[default]
+    Other,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Motion {
+    Left,
+    Right,
+    Up,
+    Down,
+    WordLeft,
+    WordRight,
+    /// First non-blank character of the line, or its first column when the cursor is already there.
+    LineStart,
+    LineEnd,
+    DocumentStart,
+    DocumentEnd,
+    PageUp(usize),
+    PageDown(usize),
+}
+
+#[derive(Debug, Clone)]
+struct Transaction {
+    id: u64,
+    kind: EditKind,
+    changes: Vec<TextChange>,
+    /// Undoes `changes`, in the order it is applied.
+    inverse: Vec<TextChange>,
+    selections_before: Vec<Selection>,
+    selections_after: Vec<Selection>,
+    time: Instant,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BufferError {
+    /// The change does not fit in the text it is applied to.
+    OutOfRange { change: TextChange, length: usize },
+}
+
+impl Display for BufferError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BufferError::OutOfRange { change, length } => write!(f, "Change {}..{} is outside of the text of {} characters.", change.start, change.end, length),
+        }
+    }
+}
+
+impl std::error::Error for BufferError {}
+
+impl From<BufferError> for PluginError {
+    fn from(error: BufferError) -> Self {
+        PluginError::Other(error.to_string())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Buffer {
+    rope: Rope,
+    /// Sorted and not overlapping, the first one is the primary selection.
+    selections: Vec<Selection>,
+    undo: Vec<Transaction>,
+    redo: Vec<Transaction>,
+    /// The next edit starts a new undo step even when it could join the last one.
+    sealed: bool,
+    next_id: u64,
+    /// State of the text before the oldest transaction of `undo`.
+    base_id: u64,
+    /// State of the text when it was last read or saved, `None` when no undo or redo can bring it back.
+    saved_id: Option<u64>,
+    version: u64,
+    /// First line touched since `take_changed_line` was last called.
+    changed_line: Option<usize>,
+}
+
+impl Buffer {
+    pub fn new(text: &str) -> Self {
+        Self { rope: Rope::from_str(text), selections: vec![Selection::cursor(0)], undo: vec![], redo: vec![], sealed: true, next_id: 1, base_id: 0, saved_id: Some(0), version: 0, changed_line: None }
+    }
+
+    pub fn rope(&self) -> &Rope {
+        &self.rope
+    }
+
+    pub fn text(&self) -> String {
+        self.rope.to_string()
+    }
+
+    pub fn len_chars(&self) -> usize {
+        self.rope.len_chars()
+    }
+
+    pub fn len_lines(&self) -> usize {
+        self.rope.len_lines()
+    }
+
+    /// A line without its line break.
+    pub fn line(&self, line: usize) -> String {
+        if line >= self.rope.len_lines() {
+            return String::new();
+        }
+        let mut text = self.rope.line(line).to_string();
+        let trimmed = text.trim_end_matches(['\n', '\r']).len();
+        text.truncate(trimmed);
+        text
+    }
+
+    /// Characters of a line without its line break.
+    pub fn line_length(&self, line: usize) -> usize {
+        if line >= self.rope.len_lines() {
+            return 0;
+        }
+        let slice = self.rope.line(line);
+        let mut length = slice.len_chars();
+        while length > 0 && matches!(slice.char(length - 1), '\n' | '\r') {
+            length -= 1;
+        }
+        length
+    }
+
+    /// Line and column of a character offset, both counted from 0.
+    pub fn line_column(&self, position: usize) -> (usize, usize) {
+        let position = position.min(self.rope.len_chars());
+        let line = self.rope.char_to_line(position);
+        (line, position - self.rope.line_to_char(line))
+    }
+
+    /// Character offset of a line and column, both are clamped to the text.
+    pub fn position(&self, line: usize, column: usize) -> usize {
+        let line = line.min(self.rope.len_lines().saturating_sub(1));
+        self.rope.line_to_char(line) + column.min(self.line_length(line))
+    }
+
+    /// Increased by every change of the text.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    pub fn selections(&self) -> &[Selection] {
+        &self.selections
+    }
+
+    /// Moving the cursors also ends the current undo step.
+    pub fn set_selections(&mut self, selections: Vec<Selection>) {
+        self.selections = self.normalized(selections);
+        self.sealed = true;
+    }
+
+    /// Selected text of every selection, one per line.
+    pub fn selected_text(&self) -> String {
+        self.selections.iter().filter(|selection| !selection.is_empty()).map(|selection| self.rope.slice(selection.start()..selection.end()).to_string()).collect::<Vec<_>>().join("\n")
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.saved_id != Some(self.state_id())
+    }
+
+    /// The text now matches the file.
+    pub fn mark_saved(&mut self) {
+        self.saved_id = Some(self.state_id());
+        self.sealed = true;
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// The first line whose content changed since the last call.
+    pub fn take_changed_line(&mut self) -> Option<usize> {
+        self.changed_line.take()
+    }
+
+    /// Applies `changes` as one undo step, or adds them to the last step when both are typing or deleting in quick succession.
+    /// Nothing is changed when one of the changes does not fit.
+    pub fn apply(&mut self, changes: Vec<TextChange>, kind: EditKind) -> Result<(), BufferError> {
+        let mut length = self.rope.len_chars();
+        for change in &changes {
+            if change.start > change.end || change.end > length {
+                return Err(BufferError::OutOfRange { change: change.clone(), length });
+            }
+            length = length - (change.end - change.start) + change.text.chars().count();
+        }
+        if changes.is_empty() {
+            return Ok(());
+        }
+        let selections_before = self.selections.clone();
+        let mut inverse = self.apply_changes(&changes);
+        inverse.reverse();
+        let now = Instant::now();
+        let state_id = self.state_id();
+        match self.undo.last_mut() {
+            Some(last) if !self.sealed && kind != EditKind::Other && last.kind == kind && now.duration_since(last.time) < GROUP_TIMEOUT && self.saved_id != Some(state_id) => {
+                last.changes.extend(changes);
+                inverse.extend(std::mem::take(&mut last.inverse));
+                last.inverse = inverse;
+                last.selections_after = self.selections.clone();
+                last.time = now;
+            },
+            _ => {
+                let id = self.next_id;
+                self.next_id += 1;
+                self.undo.push(Transaction { id, kind, changes, inverse, selections_before, selections_after: self.selections.clone(), time: now });
+                if self.undo.len() > UNDO_LIMIT {
+                    self.base_id = self.undo.remove(0).id;
+                }
+            },
+        }
+        self.sealed = kind == EditKind::Other;
+        self.redo.clear();
+        Ok(())
+    }
+
+    /// Reverts the last undo step and returns the changes that did it.
+    pub fn undo(&mut self) -> Option<Vec<TextChange>> {
+        let transaction = self.undo.pop()?;
+        self.apply_changes(&transaction.inverse);
+        self.selections = transaction.selections_before.clone();
+        self.sealed = true;
+        let changes = transaction.inverse.clone();
+        self.redo.push(transaction);
+        Some(changes)
+    }
+
+    /// Applies the last undone step again and returns its changes.
+    pub fn redo(&mut self) -> Option<Vec<TextChange>> {
+        let transaction = self.redo.pop()?;
+        self.apply_changes(&transaction.changes);
+        self.selections = transaction.selections_after.clone();
+        self.sealed = true;
+        let changes = transaction.changes.clone();
+        self.undo.push(transaction);
+        Some(changes)
+    }
+
+    /// Replaces the whole text as one undo step, such as when the file was changed by another program.
+    /// The change only covers the part that differs, so listeners get a small range instead of the whole text.
+    pub fn replace_all(&mut self, text: &str) -> TextChange {
+        let change = TextChange::between(&self.rope.to_string(), text);
+        self.sealed = true;
+        // the range lies inside the current text.
+        let _ = self.apply(vec![change.clone()], EditKind::Other);
+        change
+    }
+
+    /// Changes replacing every selection with `text`, the last selection first so the offsets of the others stay valid.
+    pub fn insert_changes(&self, text: &str) -> Vec<TextChange> {
+        self.selections.iter().rev().map(|selection| TextChange { start: selection.start(), end: selection.end(), text: text.to_string() }).collect()
+    }
+
+    /// Changes removing the selections, or the character (word with `word`) before or after each cursor.
+    pub fn delete_changes(&self, forward: bool, word: bool) -> Vec<TextChange> {
+        self.selections.iter().rev().filter_map(|selection| {
+            let range = if !selection.is_empty() {
+                selection.start()..selection.end()
+            } else if forward {
+                let end = if word {self.word_boundary(selection.head, true)} else {(selection.head + 1).min(self.rope.len_chars())};
+                selection.head..end
+            } else {
+                let start = if word {self.word_boundary(selection.head, false)} else {selection.head.saturating_sub(1)};
+                start..selection.head
+            };
+            (!range.is_empty()).then(|| TextChange::delete(range))
+        }).collect()
+    }
+
+    /// Inserts a line break at every cursor, keeping the indentation of the line.
+    pub fn newline_changes(&self) -> Vec<TextChange> {
+        self.selections.iter().rev().map(|selection| {
+            let line = self.rope.char_to_line(selection.start());
+            let indentation = self.line(line).chars().take_while(|character| *character == ' ' || *character == '\t').collect::<String>();
+            TextChange { start: selection.start(), end: selection.end(), text: format!("\n{}", indentation) }
+        }).collect()
+    }
+
+    /// The selections after moving every head, `extend` keeps the anchors in place.
+    pub fn moved_selections(&self, motion: Motion, extend: bool) -> Vec<Selection> {
+        let selections = self.selections.iter().map(|selection| {
+            // without extending, left and right first collapse a selection to its side.
+            if !extend && !selection.is_empty() {
+                match motion {
+                    Motion::Left => return Selection::cursor(selection.start()),
+                    Motion::Right => return Selection::cursor(selection.end()),
+                    _ => {},
+                }
+            }
+            let head = self.moved_position(selection.head, motion);
+            if extend {Selection { anchor: selection.anchor, head }} else {Selection::cursor(head)}
+        }).collect();
+        self.normalized(selections)
+    }
+
+    /// The selections with another cursor on the line above or below the primary one.
+    pub fn with_cursor_on_adjacent_line(&self, below: bool) -> Vec<Selection> {
+        let mut selections = self.selections.clone();
+        let edge = if below {selections.iter().map(|selection| selection.head).max()} else {selections.iter().map(|selection| selection.head).min()};
+        if let Some(edge) = edge {
+            let (line, column) = self.line_column(edge);
+            let target = if below {line + 1} else {line.wrapping_sub(1)};
+            if target < self.rope.len_lines() {
+                selections.push(Selection::cursor(self.position(target, column)));
+            }
+        }
+        self.normalized(selections)
+    }
+
+    /// The word around a position, for double clicks.
+    pub fn word_at(&self, position: usize) -> Range<usize> {
+        let position = position.min(self.rope.len_chars());
+        let class = |index: usize| char_class(self.rope.char(index));
+        let mut start = position;
+        let mut end = position;
+        let reference = if position < self.rope.len_chars() {Some(class(position))} else if position > 0 {Some(class(position - 1))} else {None};
+        if let Some(reference) = reference {
+            while start > 0 && class(start - 1) == reference {
+                start -= 1;
+            }
+            while end < self.rope.len_chars() && class(end) == reference {
+                end += 1;
+            }
+        }
+        start..end
+    }
+
+    fn state_id(&self) -> u64 {
+        self.undo.last().map(|transaction| transaction.id).unwrap_or(self.base_id)
+    }
+
+    /// Applies validated changes to the text and the selections, returns the change undoing each of them in the same order.
+    fn apply_changes(&mut self, changes: &[TextChange]) -> Vec<TextChange> {
+        let mut inverse = Vec::with_capacity(changes.len());
+        for change in changes {
+            let line = self.rope.char_to_line(change.start);
+            self.changed_line = Some(self.changed_line.map_or(line, |changed| changed.min(line)));
+            let removed = self.rope.slice(change.start..change.end).to_string();
+            self.rope.remove(change.start..change.end);
+            self.rope.insert(change.start, &change.text);
+            inverse.push(TextChange { start: change.start, end: change.start + change.text.chars().count(), text: removed });
+            self.selections = self.selections.iter().map(|selection| selection.map(change)).collect();
+        }
+        let selections = std::mem::take(&mut self.selections);
+        self.selections = self.normalized(selections);
+        self.version += 1;
+        inverse
+    }
+
+    /// Clamps the selections to the text, sorts them and merges the ones that overlap.
+    fn normalized(&self, mut selections: Vec<Selection>) -> Vec<Selection> {
+        let length = self.rope.len_chars();
+        for selection in selections.iter_mut() {
+            selection.anchor = selection.anchor.min(length);
+            selection.head = selection.head.min(length);
+        }
+        selections.sort_by_key(|selection| (selection.start(), selection.end()));
+        let mut merged: Vec<Selection> = Vec::with_capacity(selections.len());
+        for selection in selections {
+            match merged.last_mut() {
+                Some(last) if selection.start() < last.end() || selection.start() == last.start() => {
+                    let (start, end) = (last.start(), last.end().max(selection.end()));
+                    *last = if last.head < last.anchor {Selection { anchor: end, head: start }} else {Selection { anchor: start, head: end }};
+                },
+                _ => merged.push(selection),
+            }
+        }
+        if merged.is_empty() {
+            merged.push(Selection::cursor(0));
+        }
+        merged
+    }
+
+    fn moved_position(&self, position: usize, motion: Motion) -> usize {
+        let length = self.rope.len_chars();
+        let (line, column) = self.line_column(position);
+        let vertical = |lines: isize| {
+            let target = (line as isize + lines).clamp(0, self.rope.len_lines().saturating_sub(1) as isize) as usize;
+            self.position(target, column)
+        };
+        match motion {
+            Motion::Left => position.saturating_sub(1),
+            Motion::Right => (position + 1).min(length),
+            Motion::Up if line == 0 => 0,
+            Motion::Up => vertical(-1),
+            Motion::Down if line + 1 >= self.rope.len_lines() => length,
+            Motion::Down => vertical(1),
+            Motion::PageUp(lines) => vertical(-(lines as isize)),
+            Motion::PageDown(lines) => vertical(lines as isize),
+            Motion::WordLeft => self.word_boundary(position, false),
+            Motion::WordRight => self.word_boundary(position, true),
+            Motion::LineStart => {
+                let indentation = self.line(line).chars().take_while(|character| character.is_whitespace()).count();
+                self.rope.line_to_char(line) + if column == indentation {0} else {indentation}
+            },
+            Motion::LineEnd => self.rope.line_to_char(line) + self.line_length(line),
+            Motion::DocumentStart => 0,
+            Motion::DocumentEnd => length,
+        }
+    }
+
+    /// Skips blanks, then characters of the same kind as the first one after them.
+    fn word_boundary(&self, position: usize, forward: bool) -> usize {
+        let length = self.rope.len_chars();
+        let mut position = position.min(length);
+        let next = |position: usize| if forward {(position < length).then(|| self.rope.char(position))} else {(position > 0).then(|| self.rope.char(position - 1))};
+        let step = |position: &mut usize| if forward {*position += 1} else {*position -= 1};
+        while next(position).is_some_and(|character| character.is_whitespace() && character != '\n') {
+            step(&mut position);
+        }
+        let Some(first) = next(position) else {
+            return position;
+        };
+        if first == '\n' {
+            step(&mut position);
+            return position;
+        }
+        let class = char_class(first);
+        while next(position).is_some_and(|character| char_class(character) == class && character != '\n') {
+            step(&mut position);
+        }
+        position
+    }
+}
+
+/// 0 for blanks, 1 for word characters, 2 for punctuation.
+fn char_class(character: char) -> u8 {
+    if character.is_whitespace() {
+        0
+    } else if character.is_alphanumeric() || character == '_' {
+        1
+    } else {
+        2
+    }
+}
+
+/// `text` with `changes` applied one after the other, like `Buffer::apply` does. Nothing is changed when one of them does not fit.
+pub fn apply_text_changes(text: &str, changes: &[TextChange]) -> Result<String, BufferError> {
+    let mut rope = Rope::from_str(text);
+    for change in changes {
+        if change.start > change.end || change.end > rope.len_chars() {
+            return Err(BufferError::OutOfRange { change: change.clone(), length: rope.len_chars() });
+        }
+        rope.remove(change.start..change.end);
+        rope.insert(change.start, &change.text);
+    }
+    Ok(rope.to_string())
+}
+
+/// Hash of a text given in pieces, such as the chunks of a rope, the same text hashes the same however it is split.
+pub fn content_hash<'a>(chunks: impl IntoIterator<Item = &'a str>) -> u64 {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    for chunk in chunks {
+        hasher.write(chunk.as_bytes());
+    }
+    hasher.finish()
+}
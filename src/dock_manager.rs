@@ -1,9 +1,9 @@
 use egui::Widget;
-use egui_dock::TabViewer;
+use egui_dock::{DockState, NodeIndex, SurfaceIndex, TabIndex, TabViewer};
 use egui_hooks::UseHookExt;
 use serde::{Deserialize, Serialize};
 
-use crate::{backend::plugins::window_manager::WindowManager, ui::pages::{file_manager::FileManagerPage, library_window::LibraryWindow, logger_window::LoggerWindow, welcome_page::WelcomePage}, DesignerUI};
+use crate::{backend::plugins::{document_manager::{DocumentManager, DocumentManagerCommand}, node_graph_manager::{NodeGraphCommand, NodeGraphManager}}, ui::pages::{code_editor_window::CodeEditorWindow, contributed_window::ContributedWindow, extensions_window::ExtensionsWindow, file_manager::FileManagerPage, library_window::LibraryWindow, logger_window::LoggerWindow, node_editor_window::NodeEditorWindow, preferences_window::PreferencesWindow, project_settings_window::ProjectSettingsWindow, search_window::SearchWindow, welcome_page::WelcomePage}, DesignerUI};
 
 /**
  * @author مۇختەرجان مەخمۇت
@@ -43,9 +43,45 @@ pub enum EditorWindowState {
     },
     CodeEditorWindow {
         file_name: String
+    },
+    /// Find and replace across the project files.
+    SearchWindow {
+
+    },
+    /// A window from the `contributes.windows` of an extension manifest.
+    ExtensionWindow {
+        extension: String,
+        window: String,
+        title: String,
     }
 }
 
+impl EditorWindowState {
+    /// Both tabs edit the same file in the same kind of editor.
+    pub fn same_editor(&self, other: &EditorWindowState) -> bool {
+        match (self, other) {
+            (EditorWindowState::CodeEditorWindow { file_name }, EditorWindowState::CodeEditorWindow { file_name: other })
+            | (EditorWindowState::NodeEditorWindow { file_name }, EditorWindowState::NodeEditorWindow { file_name: other }) => file_name == other,
+            _ => false,
+        }
+    }
+}
+
+/// Location of the first tab matching `predicate`, egui_dock itself only finds a tab equal to a given one.
+pub fn find_tab_where(dock: &DockState<EditorWindowState>, predicate: impl Fn(&EditorWindowState) -> bool) -> Option<(SurfaceIndex, NodeIndex, TabIndex)> {
+    // the tabs of a leaf come one after another, their index restarts at every leaf.
+    let mut previous = None;
+    let mut index = 0;
+    for (location, tab) in dock.iter_all_tabs() {
+        index = if previous == Some(location) {index + 1} else {0};
+        previous = Some(location);
+        if predicate(tab) {
+            return Some((location.0, location.1, TabIndex(index)));
+        }
+    }
+    None
+}
+
 pub struct MyTab<'a> {
     pub app: &'a mut DesignerUI
 }
@@ -63,12 +99,54 @@ impl<'a> TabViewer for MyTab<'a> {
             EditorWindowState::ProjectSettingsWindow {  } => "Project settings".into(),
             EditorWindowState::EditorSettingsWindow {  } => "Preferences".into(),
             EditorWindowState::ExtensionsWindow {  } => "Extensions".into(),
-            EditorWindowState::NodeEditorWindow { file_name } => format!("{}", file_name),
-            EditorWindowState::CodeEditorWindow { file_name } => format!("{}", file_name),
+            EditorWindowState::NodeEditorWindow { file_name } => {
+                let name = file_name.rsplit('/').next().unwrap_or(file_name.as_str());
+                let dirty = self.app.core.with_plugin(|graphs: &NodeGraphManager| graphs.graph(file_name).map(|graph| graph.dirty())).ok().flatten().unwrap_or(false);
+                if dirty {format!("{} ●", name)} else {name.to_string()}
+            },
+            EditorWindowState::CodeEditorWindow { file_name } => {
+                let name = file_name.rsplit('/').next().unwrap_or(file_name.as_str());
+                let dirty = self.app.core.with_plugin(|documents: &DocumentManager| documents.document(file_name).map(|document| document.dirty())).ok().flatten().unwrap_or(false);
+                if dirty {format!("{} ●", name)} else {name.to_string()}
+            },
+            EditorWindowState::SearchWindow {  } => "Search".into(),
+            EditorWindowState::ExtensionWindow { title, .. } => title.clone(),
         };
         egui::WidgetText::from(label)
     }
 
+    /// Editor tabs keep their id while the title shows the unsaved marker.
+    fn id(&mut self, tab: &mut Self::Tab) -> egui::Id {
+        match tab {
+            EditorWindowState::CodeEditorWindow { file_name } | EditorWindowState::NodeEditorWindow { file_name } => egui::Id::new(("editor_tab", file_name.as_str())),
+            _ => egui::Id::new(self.title(tab).text()),
+        }
+    }
+
+    /// A code or node editor with unsaved changes asks first, see `DesignerUI::show_close_document_prompt`.
+    fn on_close(&mut self, tab: &mut Self::Tab) -> bool {
+        match tab {
+            EditorWindowState::CodeEditorWindow { file_name } => {
+                let dirty = self.app.core.with_plugin(|documents: &DocumentManager| documents.document(file_name).map(|document| document.dirty())).ok().flatten().unwrap_or(false);
+                if dirty {
+                    self.app.closing_document = Some(file_name.clone());
+                    return false;
+                }
+                self.app.publish_command("CodeEditor", &DocumentManagerCommand::Close { path: file_name.clone() });
+            },
+            EditorWindowState::NodeEditorWindow { file_name } => {
+                let dirty = self.app.core.with_plugin(|graphs: &NodeGraphManager| graphs.graph(file_name).map(|graph| graph.dirty())).ok().flatten().unwrap_or(false);
+                if dirty {
+                    self.app.closing_document = Some(file_name.clone());
+                    return false;
+                }
+                self.app.publish_command("NodeEditor", &NodeGraphCommand::Close { path: file_name.clone() });
+            },
+            _ => {},
+        }
+        true
+    }
+
     fn ui(&mut self, ui: &mut egui::Ui, tab: &mut Self::Tab) {
 
         match tab {
@@ -88,19 +166,25 @@ impl<'a> TabViewer for MyTab<'a> {
                 ui.add(LibraryWindow {tab: self});
             },
             EditorWindowState::ProjectSettingsWindow {  } => {
-
+                ui.add(ProjectSettingsWindow {tab: self});
             },
             EditorWindowState::EditorSettingsWindow {  } => {
-
+                ui.add(PreferencesWindow {tab: self});
             },
             EditorWindowState::ExtensionsWindow {  } => {
-
+                ui.add(ExtensionsWindow {tab: self});
             },
             EditorWindowState::NodeEditorWindow { file_name } => {
-
+                ui.add(NodeEditorWindow {tab: self, file_name});
             },
             EditorWindowState::CodeEditorWindow { file_name } => {
-
+                ui.add(CodeEditorWindow {tab: self, file_name});
+            },
+            EditorWindowState::SearchWindow {  } => {
+                ui.add(SearchWindow {tab: self});
+            },
+            EditorWindowState::ExtensionWindow { extension, window, .. } => {
+                ui.add(ContributedWindow {tab: self, extension, window});
             },
         }
     }
@@ -0,0 +1,240 @@
+use std::{cell::RefCell, ffi::OsStr, path::{Path, PathBuf}, rc::Rc};
+
+use bluebird_ide::{backend::{commands::{Command, ICommand}, extension::ExtensionPackage, plugin::{IPlugin, IPluginStateStorage, PluginDependency, PluginError, PluginInfo, PluginStatus, Version, VersionRange}, plugins::logger::LogCommand}, DesignerCore};
+use log::{info, warn};
+use egui::Ui;
+use rquickjs::{CatchResultExt, Context, Function, Module, Runtime};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::ui::javascript_widget::SerdeJsonQuickJsValue;
+
+/// What `registerPlugin` of the `designer` module hands over, see `assets/js_modules/designer.ts`.
+#[derive(Debug, Clone, Deserialize)]
+struct JsPluginInfo {
+    uuid: String,
+    name: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default = "JsPluginInfo::default_version")]
+    version: Version,
+    #[serde(default)]
+    author: String,
+    #[serde(default)]
+    dependencies: Vec<PluginDependency>,
+    #[serde(default)]
+    subscriptions: Vec<String>,
+}
+
+impl JsPluginInfo {
+    fn default_version() -> Version {
+        (0, 0, 1)
+    }
+}
+
+impl From<JsPluginInfo> for PluginInfo {
+    fn from(value: JsPluginInfo) -> Self {
+        PluginInfo {
+            uuid: value.uuid,
+            name: value.name,
+            description: value.description,
+            version: value.version,
+            author: value.author,
+            subscriptions: value.subscriptions,
+            dependencies: value.dependencies,
+            api_version: Some(VersionRange::at_least((0, 1, 0))),
+        }
+    }
+}
+
+pub struct JsPlugin {
+    info: PluginInfo,
+    /// subscriptions from the extension manifest, kept when the script changes its own ones.
+    declared_subscriptions: Vec<String>,
+    status: PluginStatus,
+    context: Context,
+    /// messages published by the script, they are handed to the core once the script returns.
+    outbox: Rc<RefCell<Vec<(String, Value)>>>,
+}
+
+impl JsPlugin {
+    /// Evaluates a script as a module, it must call `registerPlugin` while being evaluated unless `manifest` describes it.
+    pub fn load(runtime: &Runtime, name: &str, source: &str, manifest: Option<PluginInfo>) -> Result<Self, String> {
+        let context = Context::full(runtime).map_err(|error| error.to_string())?;
+        let outbox = Rc::new(RefCell::new(Vec::<(String, Value)>::new()));
+        let info = context.with(|ctx| {
+            let globals = ctx.globals();
+            let publish_outbox = outbox.clone();
+            globals.set("__designer_publish", Function::new(ctx.clone(), move |action: String, args_json: String| {
+                let args = serde_json::from_str::<Value>(&args_json).unwrap_or(Value::Null);
+                publish_outbox.borrow_mut().push((action, args));
+            })).map_err(|error| error.to_string())?;
+            let print_outbox = outbox.clone();
+            globals.set("print", Function::new(ctx.clone(), move |args: SerdeJsonQuickJsValue| {
+                let command = LogCommand::Generate { category: "JavaScript".to_string(), content: serde_json::to_string(&args.value).unwrap() };
+                print_outbox.borrow_mut().push((command.action().to_string(), command.args()));
+            })).map_err(|error| error.to_string())?;
+            Module::evaluate(ctx.clone(), name, source).and_then(|promise| promise.finish::<()>()).catch(&ctx).map_err(|error| error.to_string())?;
+            Self::read_info(&ctx)
+        })?;
+        let plugin = match (manifest, info) {
+            (Some(manifest), script) => {
+                let declared_subscriptions = manifest.subscriptions.clone();
+                let mut plugin = Self { info: manifest, declared_subscriptions, status: PluginStatus::Disabled, context, outbox };
+                plugin.merge_subscriptions(script.map(|info| info.subscriptions).unwrap_or_default());
+                plugin
+            },
+            (None, Some(script)) => Self { info: script.into(), declared_subscriptions: vec![], status: PluginStatus::Disabled, context, outbox },
+            (None, None) => return Err(format!("{} does not call registerPlugin of the designer module.", name)),
+        };
+        Ok(plugin)
+    }
+
+    /// Loads the entry module of an extension package, the module is named by its path so relative imports resolve inside the package.
+    pub fn load_extension(runtime: &Runtime, package: &ExtensionPackage) -> Result<Self, String> {
+        let entry = package.entry_path().map_err(|error| error.to_string())?;
+        let source = std::fs::read_to_string(&entry).map_err(|error| format!("Cannot read {:?}: {}", entry, error))?;
+        Self::load(runtime, &module_path(&entry), &source, Some(package.manifest.plugin_info()))
+    }
+
+    /// Returns false when nothing changed.
+    fn merge_subscriptions(&mut self, script_subscriptions: Vec<String>) -> bool {
+        let mut subscriptions = self.declared_subscriptions.clone();
+        subscriptions.extend(script_subscriptions.into_iter().filter(|subscription| !self.declared_subscriptions.contains(subscription)));
+        if subscriptions == self.info.subscriptions {
+            return false;
+        }
+        self.info.subscriptions = subscriptions;
+        true
+    }
+
+    /// Renders a window registered by the script with `registerWindow(id, ...)`.
+    pub fn render_window(&mut self, id: &str, ui: &mut Ui) -> Result<(), String> {
+        self.context.with(|ctx| {
+            ctx.globals().get::<_, Function>("__designer_render_window")
+                .and_then(|render| render.call::<_, ()>((id.to_string(), ui as *const Ui as usize)))
+                .catch(&ctx)
+                .map_err(|error| error.to_string())
+        })
+    }
+
+    /// Messages the script published outside of `handle_message`, for example while rendering a window.
+    pub fn take_published(&mut self) -> Vec<(String, Value)> {
+        self.outbox.borrow_mut().drain(..).collect()
+    }
+
+    /// Loads every `*.js` file of a directory as a plugin, failures are logged and skipped.
+    pub fn register_directory(core: &mut DesignerCore, runtime: &Runtime, directory: &Path) {
+        let Ok(entries) = std::fs::read_dir(directory) else {
+            return;
+        };
+        for path in entries.flatten().map(|entry| entry.path()).filter(|path| path.extension() == Some(OsStr::new("js"))) {
+            let name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+            let result = std::fs::read_to_string(&path).map_err(|error| error.to_string())
+                .and_then(|source| Self::load(runtime, &name, &source, None))
+                .and_then(|plugin| core.register_dynamic_plugin(plugin).map_err(|error| error.to_string()));
+            match result {
+                Ok(()) => info!("JavaScript plugin {} is loaded.", name),
+                Err(error) => {
+                    warn!("Cannot load JavaScript plugin {:?}: {}", path, error);
+                    core.publish_message("JavaScriptEngine", &LogCommand::Generate { category: "JavaScript".to_string(), content: format!("Cannot load JavaScript plugin {}: {}", name, error) });
+                },
+            }
+        }
+    }
+
+    fn read_info(ctx: &rquickjs::Ctx) -> Result<Option<JsPluginInfo>, String> {
+        let info_json = ctx.globals().get::<_, Function>("__designer_plugin_info").and_then(|function| function.call::<_, Option<String>>(())).catch(ctx).map_err(|error| error.to_string())?;
+        match info_json {
+            Some(info_json) => serde_json::from_str::<JsPluginInfo>(&info_json).map(Some).map_err(|error| format!("Invalid plugin info: {}", error)),
+            None => Ok(None),
+        }
+    }
+
+    /// Publishes what the script queued and picks up subscriptions it added at runtime.
+    fn flush(&mut self, root: &mut DesignerCore) {
+        let messages = self.outbox.borrow_mut().drain(..).collect::<Vec<_>>();
+        for (action, args) in messages {
+            root.publish_message(&self.info.name, &Command { action, args });
+        }
+        let subscriptions = self.context.with(|ctx| Self::read_info(&ctx)).ok().flatten().map(|info| info.subscriptions);
+        if let Some(subscriptions) = subscriptions {
+            if self.merge_subscriptions(subscriptions) {
+                let _ = root.update_plugin_info(self.info.clone());
+            }
+        }
+    }
+}
+
+impl IPlugin for JsPlugin {
+    fn info(&self) -> PluginInfo {
+        self.info.clone()
+    }
+
+    fn status(&self) -> PluginStatus {
+        self.status.clone()
+    }
+
+    fn initialize(&mut self, root: &mut DesignerCore) {
+        // messages published while the script was evaluated.
+        self.flush(root);
+    }
+
+    fn enable(&mut self, _root: &mut DesignerCore) {
+        self.status = PluginStatus::Enabled;
+    }
+
+    fn disable(&mut self, _root: &mut DesignerCore) {
+        self.status = PluginStatus::Disabled;
+    }
+
+    fn load_state(&mut self, _storage: &mut dyn IPluginStateStorage) {
+
+    }
+
+    fn store_state(&mut self, _storage: &mut dyn IPluginStateStorage) -> Result<(), PluginError> {
+        Ok(())
+    }
+
+    fn get_state(&self) -> Value {
+        Value::Null
+    }
+
+    fn handle_message(&mut self, root: &mut DesignerCore, sender: &str, action: &str, args: Value) -> Result<(), PluginError> {
+        let args_json = serde_json::to_string(&args).unwrap();
+        let result = self.context.with(|ctx| {
+            ctx.globals().get::<_, Function>("__designer_handle_message")
+                .and_then(|handler| handler.call::<_, ()>((sender.to_string(), action.to_string(), args_json)))
+                .catch(&ctx)
+                .map_err(|error| PluginError::Other(format!("JavaScript error in {}: {}", self.info.name, error)))
+        });
+        self.flush(root);
+        result
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+/// `FileResolver` checks modules relative to the working directory (absolute paths included), so module names and search paths are made relative to it.
+pub fn module_path(path: &Path) -> String {
+    let (Ok(current), Ok(path)) = (std::env::current_dir().and_then(|directory| directory.canonicalize()), path.canonicalize()) else {
+        return path.to_string_lossy().to_string();
+    };
+    let current = current.components().collect::<Vec<_>>();
+    let target = path.components().collect::<Vec<_>>();
+    let common = current.iter().zip(target.iter()).take_while(|(a, b)| a == b).count();
+    // different drives on windows, there is no relative path.
+    if common == 0 {
+        return path.to_string_lossy().to_string();
+    }
+    let mut relative = PathBuf::from(".");
+    current[common..].iter().for_each(|_| relative.push(".."));
+    target[common..].iter().for_each(|component| relative.push(component));
+    relative.to_string_lossy().replace('\\', "/")
+}
@@ -0,0 +1,3 @@
+pub mod backend;
+
+pub use backend::designer_core::DesignerCore;
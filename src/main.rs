@@ -1,22 +1,25 @@
-use std::{any::{Any, TypeId}, cell::RefCell, collections::VecDeque, ffi::OsStr, ops::Deref, path::PathBuf, rc::Rc};
+use std::{ffi::OsStr, path::{Path, PathBuf}, rc::Rc, time::{Duration, Instant}};
 
-use backend::{commands::ICommand, plugin::{IPlugin, PluginStatus}, plugins::{event_interceptor::SystemEventInterceptor, logger::Logger, project_manager::ProjectManager, window_manager::{WindowManager, WindowManagerCommand}}};
-use dock_manager::{EditorWindowState, MyTab};
+use bluebird_ide::{backend, DesignerCore};
+use backend::{commands::{Command, ICommand}, designer_core::CoreCommand, extension::{discover_extensions, ExtensionPackage}, plugin::PluginStatus, plugin_settings::SettingScope, plugins::{document_manager::{DocumentManager, DocumentManagerCommand}, file_manager::{FileManager, FileManagerCommand}, logger::LogCommand, node_graph_manager::{NodeGraphCommand, NodeGraphManager}, search_manager::{SearchManager, SearchManagerCommand}, preferences::{EditorPreferences, Preferences, PreferencesRequest, Theme}, project_manager::{Project, ProjectManagerCommand, ProjectManagerRequest, RecentProject}, window_manager::{FileTabChange, WindowManager, WindowManagerCommand}}};
+use dock_manager::{find_tab_where, EditorWindowState, MyTab};
 use eframe::{App, CreationContext, Frame};
-use egui::{mutex::Mutex, CentralPanel, Color32, Label, Margin, SidePanel, Stroke, TopBottomPanel};
+use egui::{mutex::Mutex, Align2, CentralPanel, TopBottomPanel};
 use egui_dock::{DockArea, DockState, TabViewer};
 use egui_file_dialog::FileDialog;
 use egui_hooks::UseHookExt;
 use js_native_module::EguiJsModule;
+use js_plugin::JsPlugin;
 use log::{info, warn};
 use project_window::{ProjectManagerWindow};
+use ui::{notification_toasts::NotificationToasts, shortcuts};
 use rquickjs::loader::{BuiltinLoader, BuiltinResolver, FileResolver, ModuleLoader, ScriptLoader};
 
 mod dock_manager;
 mod ui;
-mod backend;
 mod project_window;
 mod js_native_module;
+mod js_plugin;
 mod extensions;
 
 /**
@@ -40,117 +43,52 @@ fn main() {
     };
 
     eframe::run_native("bluebird-ide", options, Box::new(|context| {
-        let mut core = DesignerCore::new(context);
-        core.initialize_plugins();
+        let egui_context = context.egui_ctx.clone();
+        let mut core = DesignerCore::new(move || {
+            egui_context.request_repaint();
+        });
+        let egui_context = context.egui_ctx.clone();
+        core.set_background_notifier(move || egui_context.request_repaint());
         Ok(Box::new(DesignerUI::new(core)))
     })).unwrap();
     println!("Bye!");
 }
 
-pub struct DesignerCore {
-    plugins: Vec<Rc<RefCell<dyn IPlugin>>>,
-    notify_ui: Box<dyn Fn() -> ()>,
-    message_loop_busy: bool,
-    message_queue: VecDeque<(String, String, serde_json::Value)>,
-}
-
-impl DesignerCore {
-    pub fn new(context: &CreationContext) -> Self {
-        let context = context.egui_ctx.clone();
-        Self {
-            notify_ui: Box::new(move ||{
-                context.request_repaint();
-            }),
-            plugins: vec![
-                Rc::new(RefCell::new(backend::plugins::event_interceptor::SystemEventInterceptor::new())),
-                Rc::new(RefCell::new(backend::plugins::logger::Logger::new())),
-                Rc::new(RefCell::new(backend::plugins::project_manager::ProjectManager::new())),
-                Rc::new(RefCell::new(backend::plugins::window_manager::WindowManager::new())),
-                Rc::new(RefCell::new(backend::plugins::file_manager::FileManager::new())),
-            ],
-            message_loop_busy: false,
-            message_queue:VecDeque::new(),
-        }
-    }
-
-    pub fn initialize_plugins(&mut self) {
-        self.plugins.clone().iter().for_each(|plugin| {
-            let mut borrows = plugin.borrow_mut();
-            borrows.initialize(self);
-            borrows.enable(self);
-        });
-    }
-
-    pub fn publish_message(&mut self, sender: &str, message: &dyn ICommand) {
-        self.message_queue.push_back((sender.to_string(), message.action().to_string(), message.args()));
-        // println!("+++{}, {}, {}\n", self.message_queue.len(), message.action(), message.args());
-        self.publish_message_from_queue();
-    }
-
-    fn publish_message_from_queue(&mut self) {
-        if self.message_loop_busy {
-            return;
-        }
-        self.message_loop_busy = true;
-        loop {
-            if let Some((sender, action, args)) = self.message_queue.pop_front() {
-                {
-                    // println!("-{}, {}, {}", self.message_queue.len(), action, args);
-                    self.plugins.clone().iter().for_each(|plugin| {
-                        let mut borrows = plugin.borrow_mut();
-                        if borrows.status() == PluginStatus::Enabled {
-                            let name = borrows.info().name;
-                            // println!("{} >> {}", action, name);
-                            borrows.handle_message(self, sender.as_str(), &action, args.clone());
-                        }
-                    });
-                }
-            } else {
-                break;
-            }
-        }
-        self.message_loop_busy = false;
-    }
-
-    pub fn notify_ui(&self) {
-        self.notify_ui.deref()();
-    }
-
-    pub fn get_plugin<T: IPlugin>(&self) -> Option<&T> {
-        /// Very careful there, program may crash of the plugin reference returned by this function is misused, so don't store the reference, request by your needs instead.
-        /// but hopefully plugin instances are stored in editor-state and the editor-state is lives long as until program exits so it's much much safe to use instances,
-        /// but when the program architecture changes may cause the core editor-state is shorter than the program lifetime.
-        let plugins = self.plugins.clone();
-        for i in plugins {
-            let any_type = unsafe {
-                (&*i.as_ptr()).as_any()
-            };
-            if let Some(x) = any_type.downcast_ref::<T>() {
-                return Some(x);
-            }
-        }
-        None
-    }
-}
-
 pub struct DesignerUI {
     dock_state: Rc<Mutex<DockState<EditorWindowState>>>,
     file_dialog: FileDialog,
     project_manager: ProjectManagerWindow,
-    recent_projects: Vec<String>,
     core: DesignerCore,
     js_engine: rquickjs::Runtime,
+    /// extension packages of the user and of the opened project.
+    extensions: Vec<ExtensionPackage>,
+    /// project whose `.designer/extensions` are loaded.
+    extensions_project: Option<PathBuf>,
+    /// preferences last applied to the egui style.
+    preferences: Option<EditorPreferences>,
+    /// code editor whose tab was closed with unsaved changes, the user is asked what to do with them.
+    closing_document: Option<String>,
+    last_autosave: Instant,
+    /// `layout_structure` of the dock the last undo step was compared with, the window manager records a step when the user changes it.
+    last_layout: Option<serde_json::Value>,
 }
 
 impl DesignerUI {
+    const LEGACY_RECENT_PROJECTS_KEY: &'static str = "recently_opened";
+
     fn new(core: DesignerCore) -> Self {
         let mut app = DesignerUI {
             dock_state: Rc::new(Mutex::new(DockState::new(vec![]))),
             file_dialog: FileDialog::new(),
             project_manager: ProjectManagerWindow::new(),
-            recent_projects: vec![],
             core,
             js_engine: rquickjs::Runtime::new().expect("Cannot initialize JavaScript engine."),
+            extensions: vec![],
+            extensions_project: None,
+            preferences: None,
+            closing_document: None,
+            last_autosave: Instant::now(),
+            last_layout: None,
         };
         app.initialize_app();
         app
@@ -159,6 +97,96 @@ impl DesignerUI {
     fn initialize_app(&mut self) {
         self.initialize_tabs();
         self.initialize_js_engine();
+        self.load_js_plugins();
+        if let Some(directory) = self.core.user_config_dir().map(|directory| ExtensionPackage::user_directory(directory)) {
+            self.load_extensions(&directory, SettingScope::User);
+        }
+        if let Err(errors) = self.core.initialize_plugins() {
+            warn!("{} plugin(s) cannot be initialized, see the log window for details.", errors.len());
+        }
+        self.reopen_last_project();
+    }
+
+    /// Scripts in `<config dir>/bluebird-ide/scripts` become plugins, they need the module loader of `initialize_js_engine`.
+    fn load_js_plugins(&mut self) {
+        if let Some(directory) = self.core.user_config_dir().map(|directory| directory.join("scripts")) {
+            JsPlugin::register_directory(&mut self.core, &self.js_engine, &directory);
+        }
+    }
+
+    /// Registers the extension packages of a directory, their roots become module search paths of the JavaScript engine.
+    fn load_extensions(&mut self, directory: &Path, scope: SettingScope) {
+        let unpack_root = dirs::cache_dir().unwrap_or_else(std::env::temp_dir).join("bluebird-ide").join("extensions");
+        let (packages, errors) = discover_extensions(directory, scope, &unpack_root);
+        for error in errors {
+            warn!("{}", error);
+            self.publish_command("ExtensionLoader", &LogCommand::Generate { category: "Error".to_string(), content: error.to_string() });
+        }
+        self.extensions.extend(packages.iter().cloned());
+        self.initialize_js_engine();
+        for package in packages {
+            let uuid = package.manifest.uuid.clone();
+            if !package.manifest.contributes.node_types.is_empty() {
+                self.publish_command("ExtensionLoader", &NodeGraphCommand::RegisterNodeTypes { owner: uuid.clone(), types: package.manifest.contributes.node_types.clone() });
+            }
+            if self.core.plugin_infos().iter().any(|info| info.uuid == uuid) {
+                // the project was opened before in this session, its plugin is still registered.
+                let enabled = self.core.plugin_setting(&uuid, SettingScope::Project).or(self.core.plugin_setting(&uuid, SettingScope::User)).unwrap_or(true);
+                if enabled {
+                    let _ = self.core.enable_plugin(&uuid);
+                }
+                continue;
+            }
+            let result = JsPlugin::load_extension(&self.js_engine, &package)
+                .and_then(|plugin| self.core.register_dynamic_plugin(plugin).map_err(|error| error.to_string()));
+            match result {
+                Ok(()) => info!("Extension {} is loaded from {:?}.", package.manifest.name, package.source),
+                Err(error) => {
+                    warn!("Cannot load extension {:?}: {}", package.source, error);
+                    self.publish_command("ExtensionLoader", &LogCommand::Generate { category: "Error".to_string(), content: format!("Cannot load extension {}: {}", package.manifest.name, error) });
+                },
+            }
+        }
+    }
+
+    /// Loads the extensions of a newly opened project and stops the ones of the previous project.
+    fn sync_project_extensions(&mut self) {
+        let project_path = self.core.request::<Option<PathBuf>>("ExtensionLoader", &ProjectManagerRequest::ProjectPath).ok().flatten();
+        if project_path == self.extensions_project {
+            return;
+        }
+        let project_extensions = self.extensions.iter().filter(|package| package.scope == SettingScope::Project).map(|package| package.manifest.uuid.clone()).collect::<Vec<_>>();
+        for uuid in project_extensions {
+            let _ = self.core.disable_plugin(&uuid);
+            self.publish_command("ExtensionLoader", &NodeGraphCommand::UnregisterNodeTypes { owner: uuid });
+        }
+        self.extensions.retain(|package| package.scope != SettingScope::Project);
+        self.extensions_project = project_path.clone();
+        if let Some(path) = project_path {
+            self.load_extensions(&ExtensionPackage::project_directory(&path), SettingScope::Project);
+        }
+    }
+
+    /// Packages whose plugin is currently enabled, only their contributions are shown.
+    fn enabled_extensions(&self) -> Vec<ExtensionPackage> {
+        self.extensions.iter().filter(|package| self.core.plugin_status(&package.manifest.uuid) == Some(PluginStatus::Enabled)).cloned().collect()
+    }
+
+    fn contributed_menu_items(&mut self, ui: &mut egui::Ui, menu: &str) {
+        let items = self.enabled_extensions().into_iter()
+            .flat_map(|package| package.manifest.contributes.menu_items.into_iter().map(move |item| (package.manifest.uuid.clone(), item)))
+            .filter(|(_, item)| item.menu == menu)
+            .collect::<Vec<_>>();
+        if items.is_empty() {
+            return;
+        }
+        ui.separator();
+        for (uuid, item) in items {
+            if ui.button(&item.label).clicked() {
+                self.publish_command(&uuid, &Command { action: item.action.clone(), args: item.args.clone() });
+                ui.close_menu();
+            }
+        }
     }
 
     fn initialize_tabs(&mut self) {
@@ -169,7 +197,7 @@ impl DesignerUI {
         dock.push_to_first_leaf(EditorWindowState::InspectorWindow { object: "Empty".into() });
         dock.push_to_first_leaf(EditorWindowState::LogViewWindow { label: "Empty".into() });
         dock.push_to_first_leaf(EditorWindowState::LibraryWindow {  });
-        // dock.push_to_first_leaf(EditorWindowState::EditorSettingsWindow {  });
+        dock.push_to_first_leaf(EditorWindowState::EditorSettingsWindow {  });
         dock.push_to_first_leaf(EditorWindowState::ProjectSettingsWindow {  });
         dock.push_to_first_leaf(EditorWindowState::ExtensionsWindow {  });
     }
@@ -180,7 +208,7 @@ impl DesignerUI {
                 .with_module("designer")
                 .with_module("designer/egui"),
             FileResolver::default()
-                // .with_path("")
+                .with_paths(self.extensions.iter().map(|package| js_plugin::module_path(&package.root)))
         );
         let designer_module_source = include_str!("../assets/js_modules/build/designer.js");
         let loader = (
@@ -198,33 +226,278 @@ impl DesignerUI {
         }))
     }
 
-    fn store_recent_files(&mut self, frame: &mut Frame, path: &PathBuf) {
-        if self.recent_projects.iter().any(|item| item.as_str() == path.to_str().unwrap()) {
+    /// Older builds kept the recent projects as plain paths in the window storage, they are handed to ProjectManager once.
+    fn import_legacy_recent_projects(&mut self, frame: &mut Frame) {
+        let Some(storage) = frame.storage_mut() else {
+            return;
+        };
+        let Some(paths) = storage.get_string(Self::LEGACY_RECENT_PROJECTS_KEY).and_then(|content| serde_json::from_str::<Vec<PathBuf>>(&content).ok()) else {
             return;
+        };
+        if paths.is_empty() {
+            return;
+        }
+        storage.set_string(Self::LEGACY_RECENT_PROJECTS_KEY, String::from("[]"));
+        self.publish_command("MainWindow", &ProjectManagerCommand::ImportRecentProjects { paths });
+    }
+
+    /// Focuses the preferences tab, it is opened again when the user closed it.
+    fn show_preferences(&mut self) {
+        let mut dock = self.dock_state.lock();
+        match find_tab_where(&dock, |tab| matches!(tab, EditorWindowState::EditorSettingsWindow {  })) {
+            Some(location) => dock.set_active_tab(location),
+            None => dock.push_to_first_leaf(EditorWindowState::EditorSettingsWindow {  }),
         }
-        self.recent_projects.push(path.to_str().unwrap().to_string());
-        self.write_recent_opened_files_into_storage(frame);
     }
 
-    fn write_recent_opened_files_into_storage(&mut self, frame: &mut Frame) {
-        let projects = serde_json::to_string(&self.recent_projects).unwrap();
-        if let Some(storage) = frame.storage_mut() {
-            storage.set_string("recently_opened", projects);
+    fn show_search(&mut self) {
+        let mut dock = self.dock_state.lock();
+        match find_tab_where(&dock, |tab| matches!(tab, EditorWindowState::SearchWindow {  })) {
+            Some(location) => dock.set_active_tab(location),
+            None => dock.push_to_first_leaf(EditorWindowState::SearchWindow {  }),
+        }
+    }
+
+    /// Applies theme and font size whenever the effective preferences change.
+    fn apply_preferences(&mut self, ctx: &egui::Context, frame: &Frame) {
+        let Ok(preferences) = self.core.request::<EditorPreferences>("MainWindow", &PreferencesRequest::Editor) else {
+            return;
+        };
+        if self.preferences.as_ref() == Some(&preferences) {
+            return;
+        }
+        let visuals = match preferences.theme {
+            Theme::Dark => egui::Visuals::dark(),
+            Theme::Light => egui::Visuals::light(),
+            Theme::System => frame.info().system_theme.unwrap_or(eframe::Theme::Dark).egui_visuals(),
+        };
+        ctx.set_visuals(visuals);
+        ctx.style_mut(|style| {
+            for (text_style, font) in style.text_styles.iter_mut() {
+                font.size = match text_style {
+                    egui::TextStyle::Heading => preferences.font_size * 1.4,
+                    egui::TextStyle::Small => preferences.font_size * 0.75,
+                    _ => preferences.font_size,
+                };
+            }
+        });
+        self.preferences = Some(preferences);
+    }
+
+    fn handle_shortcuts(&mut self, ctx: &egui::Context) {
+        let Some(keybindings) = self.preferences.as_ref().map(|preferences| preferences.keybindings.clone()) else {
+            return;
+        };
+        if shortcuts::pressed(ctx, keybindings.get("preferences")) {
+            self.show_preferences();
+        }
+        if shortcuts::pressed(ctx, keybindings.get("open_project")) {
+            self.show_open_project_dialog();
+        }
+        if shortcuts::pressed(ctx, keybindings.get("save_all")) {
+            self.save_all();
+        }
+        if shortcuts::pressed(ctx, keybindings.get("save")) {
+            self.save_active_document();
+        }
+        if shortcuts::pressed(ctx, keybindings.get("find_in_files")) {
+            self.show_search();
+        }
+        if shortcuts::pressed(ctx, keybindings.get("undo")) {
+            self.undo(false);
+        }
+        if shortcuts::pressed(ctx, keybindings.get("redo")) {
+            self.undo(true);
+        }
+    }
+
+    /// File name of the code editor tab that has the focus.
+    fn active_document(&self) -> Option<String> {
+        match self.dock_state.lock().find_active_focused() {
+            Some((_, EditorWindowState::CodeEditorWindow { file_name })) => Some(file_name.clone()),
+            _ => None,
+        }
+    }
+
+    /// File name of the node editor tab that has the focus.
+    fn active_graph(&self) -> Option<String> {
+        match self.dock_state.lock().find_active_focused() {
+            Some((_, EditorWindowState::NodeEditorWindow { file_name })) => Some(file_name.clone()),
+            _ => None,
+        }
+    }
+
+    fn save_active_document(&mut self) {
+        if let Some(path) = self.active_document() {
+            self.publish_command("MainWindow", &DocumentManagerCommand::Save { path });
+        } else if let Some(path) = self.active_graph() {
+            self.publish_command("MainWindow", &NodeGraphCommand::Save { path });
+        }
+    }
+
+    /// Open documents or graphs have unsaved changes.
+    pub fn has_unsaved_changes(&self) -> bool {
+        self.core.with_plugin(|documents: &DocumentManager| documents.has_unsaved_documents()).unwrap_or(false)
+            || self.core.with_plugin(|graphs: &NodeGraphManager| graphs.has_unsaved_graphs()).unwrap_or(false)
+    }
+
+    fn save_all(&mut self) {
+        self.publish_command("MainWindow", &DocumentManagerCommand::SaveAll);
+        self.publish_command("MainWindow", &NodeGraphCommand::SaveAll);
+    }
+
+    /// Undoes, or with `redo` applies again, the last edit of the focused code editor.
+    fn undo_active_document(&mut self, redo: bool) {
+        if let Some(path) = self.active_document() {
+            let command = if redo {DocumentManagerCommand::Redo { path }} else {DocumentManagerCommand::Undo { path }};
+            self.publish_command("MainWindow", &command);
+        }
+    }
+
+    /// Text edits of the focused code editor are undone in the editor, anything else walks the history of the core.
+    fn undo(&mut self, redo: bool) {
+        if self.active_document().is_some() {
+            self.undo_active_document(redo);
         } else {
-            warn!("App storage is not available so cannot save recent project list.");
+            self.publish_command("MainWindow", &if redo {CoreCommand::Redo} else {CoreCommand::Undo});
         }
     }
 
-    fn clear_recents(&mut self, frame: &mut Frame) {
-        self.recent_projects.clear();
-        self.write_recent_opened_files_into_storage(frame);
+    /// Reports the layout the user arranged. egui_dock moves, closes, detaches and resizes tabs when a pointer button is released,
+    /// so the structure is only compared on those frames. Changes made by the program clear `last_layout` to only move the baseline.
+    fn track_layout_changes(&mut self, ctx: &egui::Context, dock: &DockState<EditorWindowState>) {
+        if self.last_layout.is_some() && !ctx.input(|input| input.pointer.any_released()) {
+            return;
+        }
+        let Ok(layout) = serde_json::to_value(dock) else {
+            return;
+        };
+        let structure = WindowManager::layout_structure(&layout);
+        match self.last_layout.replace(structure.clone()) {
+            Some(before) if before != structure => {
+                self.publish_command("MainWindow", &WindowManagerCommand::LayoutChanged { before, after: structure });
+            },
+            _ => {},
+        }
     }
 
-    fn load_recent_files(&mut self, frame: &mut Frame) {
-        if self.recent_projects.is_empty() {
-            frame.storage_mut().inspect(|storage| {
-                self.recent_projects = serde_json::from_str(&storage.get_string("recently_opened").or(Some("[]".into())).unwrap()).expect("Cannot parse recently opened projects.");
+    /// Focuses the tab of each document or graph opened since the last frame, new tabs join the leaf of the other editors.
+    /// Returns whether any tab was shown.
+    fn show_opened_documents(&mut self, dock: &mut DockState<EditorWindowState>) -> bool {
+        let (documents, graphs) = self.core.with_plugin_mut(|wm: &mut WindowManager| (std::mem::take(&mut wm.opened_documents), std::mem::take(&mut wm.opened_graphs))).unwrap_or_default();
+        let shown = !documents.is_empty() || !graphs.is_empty();
+        let opened = documents.into_iter().map(|file_name| EditorWindowState::CodeEditorWindow { file_name })
+            .chain(graphs.into_iter().map(|file_name| EditorWindowState::NodeEditorWindow { file_name }));
+        for opened_tab in opened {
+            match find_tab_where(dock, |tab| tab.same_editor(&opened_tab)) {
+                Some(location) => dock.set_active_tab(location),
+                None => {
+                    if let Some((surface, node, _)) = find_tab_where(dock, ProjectManagerWindow::is_project_editor) {
+                        dock.set_focused_node_and_surface((surface, node));
+                    }
+                    dock.push_to_focused_leaf(opened_tab);
+                },
+            }
+        }
+        shown
+    }
+
+    /// Asks whether the changes of a code or node editor being closed are saved, its tab only closes once they are saved or discarded.
+    fn show_close_document_prompt(&mut self, ctx: &egui::Context) {
+        let Some(path) = self.closing_document.clone() else {
+            return;
+        };
+        let mut choice = None;
+        egui::Window::new("Unsaved changes").anchor(Align2::CENTER_CENTER, egui::Vec2::ZERO).collapsible(false).resizable(false).show(ctx, |ui| {
+            ui.label(format!("Save the changes of \"{}\" before closing it?", path));
+            ui.horizontal(|ui| {
+                if ui.button("Save").clicked() {
+                    choice = Some(Some(true));
+                }
+                if ui.button("Don't save").clicked() {
+                    choice = Some(Some(false));
+                }
+                if ui.button("Cancel").clicked() {
+                    choice = Some(None);
+                }
             });
+        });
+        let Some(choice) = choice else {
+            return;
+        };
+        self.closing_document = None;
+        let Some(save) = choice else {
+            return;
+        };
+        let is_graph = self.core.with_plugin(|graphs: &NodeGraphManager| graphs.graph(&path).is_some()).unwrap_or(false);
+        if save {
+            if is_graph {
+                self.publish_command("MainWindow", &NodeGraphCommand::Save { path: path.clone() });
+            } else {
+                self.publish_command("MainWindow", &DocumentManagerCommand::Save { path: path.clone() });
+            }
+            let saved = if is_graph {
+                self.core.with_plugin(|graphs: &NodeGraphManager| graphs.graph(&path).map(|graph| !graph.dirty())).ok().flatten().unwrap_or(true)
+            } else {
+                self.core.with_plugin(|documents: &DocumentManager| documents.document(&path).map(|document| !document.dirty())).ok().flatten().unwrap_or(true)
+            };
+            if !saved {
+                // the reason is in the log window, the tab stays open with its changes.
+                return;
+            }
+        }
+        if is_graph {
+            self.publish_command("MainWindow", &NodeGraphCommand::Close { path: path.clone() });
+            self.dock_state.lock().retain_tabs(|tab| !matches!(tab, EditorWindowState::NodeEditorWindow { file_name } if *file_name == path));
+        } else {
+            self.publish_command("MainWindow", &DocumentManagerCommand::Close { path: path.clone() });
+            self.dock_state.lock().retain_tabs(|tab| !matches!(tab, EditorWindowState::CodeEditorWindow { file_name } if *file_name == path));
+        }
+    }
+
+    /// Saves modified documents every `autosave_interval` seconds of the preferences.
+    fn autosave(&mut self, ctx: &egui::Context) {
+        let interval = self.preferences.as_ref().map(|preferences| preferences.autosave_interval).unwrap_or_default();
+        if interval == 0 {
+            return;
+        }
+        let interval = Duration::from_secs(interval);
+        let has_unsaved_changes = self.has_unsaved_changes();
+        let elapsed = self.last_autosave.elapsed();
+        if elapsed >= interval {
+            self.last_autosave = Instant::now();
+            if has_unsaved_changes {
+                self.publish_command("MainWindow", &DocumentManagerCommand::Autosave);
+                self.publish_command("MainWindow", &NodeGraphCommand::SaveAll);
+            }
+        } else if has_unsaved_changes {
+            ctx.request_repaint_after(interval - elapsed);
+        }
+    }
+
+    /// Asks for the directory of a new project, the create dialog opens afterwards with `template` selected.
+    fn show_new_project_dialog(&mut self, template: Option<String>) {
+        if let Some(template) = template {
+            self.project_manager.select_template(template);
+        }
+        self.reset_file_dialog();
+        self.file_dialog.open(egui_file_dialog::DialogMode::SelectDirectory, true, Some("create_project_files")).unwrap();
+    }
+
+    fn show_open_project_dialog(&mut self) {
+        self.reset_file_dialog();
+        self.file_dialog.open(egui_file_dialog::DialogMode::SelectFile, true, Some("open_project_files")).unwrap();
+    }
+
+    /// Opens the most recently opened project when the user asked for it in the preferences.
+    fn reopen_last_project(&mut self) {
+        let reopen = self.core.request::<Option<bool>>("MainWindow", &PreferencesRequest::Value { key: String::from(Preferences::REOPEN_LAST_PROJECT) }).ok().flatten().unwrap_or_default();
+        if !reopen {
+            return;
+        }
+        let recent_projects = self.core.request::<Vec<RecentProject>>("MainWindow", &ProjectManagerRequest::RecentProjects).unwrap_or_default();
+        if let Some(project) = recent_projects.into_iter().filter(|project| !project.missing).max_by_key(|project| project.last_opened) {
+            ProjectManagerWindow::open_project(self, &project.path);
         }
     }
 
@@ -235,63 +508,122 @@ impl DesignerUI {
 
 
 impl App for DesignerUI {
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        self.core.shutdown();
+    }
+
     fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
-        self.load_recent_files(frame);
+        self.import_legacy_recent_projects(frame);
+        self.sync_project_extensions();
+        if self.core.with_plugin(|file_manager: &FileManager| file_manager.has_pending_changes()).unwrap_or(false) {
+            self.publish_command("MainWindow", &FileManagerCommand::ApplyFileChanges);
+        }
+        if self.core.with_plugin(|search: &SearchManager| search.has_pending_results()).unwrap_or(false) {
+            self.publish_command("MainWindow", &SearchManagerCommand::ApplyResults);
+        }
+        self.apply_preferences(ctx, frame);
+        self.handle_shortcuts(ctx);
+        self.autosave(ctx);
         TopBottomPanel::top("top").show_separator_line(false).show(ctx, |ui|{
             ui.use_state(||0u32, ());
 
             egui::menu::bar(ui, |ui|{
                 ui.menu_button("Project", |ui| {
                     if ui.button("New").clicked() {
-                        self.reset_file_dialog();
-                        self.file_dialog.open(egui_file_dialog::DialogMode::SelectDirectory, true, Some("create_project_files")).unwrap();
+                        self.show_new_project_dialog(None);
                         ui.close_menu();
                     }
                     if ui.button("Open").clicked() {
-                        self.reset_file_dialog();
-                        self.file_dialog.open(egui_file_dialog::DialogMode::SelectFile, true, Some("open_project_files")).unwrap();
+                        self.show_open_project_dialog();
                         ui.close_menu();
                     }
-                    if ui.button("Save").clicked() {
-
+                    let can_save = self.active_document().is_some() || self.active_graph().is_some();
+                    let save = ui.add_enabled(can_save, egui::Button::new("Save"))
+                        .on_disabled_hover_text("Focus a code or node editor to save its file.");
+                    if save.clicked() {
+                        self.save_active_document();
+                        ui.close_menu();
+                    }
+                    if ui.add_enabled(self.has_unsaved_changes(), egui::Button::new("Save all")).clicked() {
+                        self.save_all();
+                        ui.close_menu();
+                    }
+                    let project_is_open = self.core.request::<Option<Project>>("MainWindow", &ProjectManagerRequest::Project).ok().flatten().is_some();
+                    if ui.add_enabled(project_is_open, egui::Button::new("Close")).clicked() {
+                        ProjectManagerWindow::close_project(self);
+                        ui.close_menu();
                     }
                     ui.menu_button("Open recent", |ui| {
-                        if self.recent_projects.is_empty() {
+                        let recent_projects = self.core.request::<Vec<RecentProject>>("MainWindow", &ProjectManagerRequest::RecentProjects).unwrap_or_default();
+                        if recent_projects.is_empty() {
                             ui.label("No recent projects opened.");
                         }
-                        self.recent_projects.clone().iter().for_each(|path| {
-                            if ui.button(path).clicked() {
-                                ProjectManagerWindow::open_project(self, &PathBuf::from(path));
+                        for project in recent_projects {
+                            let label = if project.pinned {format!("📌 {}", project.name)} else {project.name.clone()};
+                            let response = ui.add_enabled(!project.missing, egui::Button::new(label))
+                                .on_hover_text(project.path.to_string_lossy())
+                                .on_disabled_hover_text(format!("{} no longer exists.", project.path.to_string_lossy()));
+                            if response.clicked() {
+                                ProjectManagerWindow::open_project(self, &project.path);
                                 ui.close_menu();
                             }
-                            ui.separator();
-                        });
-                        if ui.button("clear").clicked() {
-                            self.clear_recents(frame);
+                        }
+                        ui.separator();
+                        if ui.button("Remove missing").clicked() {
+                            self.publish_command("MainWindow", &ProjectManagerCommand::PruneRecentProjects);
+                        }
+                        if ui.button("Clear unpinned").clicked() {
+                            self.publish_command("MainWindow", &ProjectManagerCommand::ClearRecentProjects);
                         }
                     });
                     // if (ui.button("Close and exit")).clicked() {
                     //     self.quit_app = true;
                     // }
+                    self.contributed_menu_items(ui, "Project");
                 });
                 // ui.separator();
                 ui.menu_button("Edit", |ui|{
-                    if ui.button("Undo").clicked() {
-    
+                    let active_document = self.active_document();
+                    if let Some((can_undo, can_redo)) = active_document.and_then(|path| self.core.with_plugin(|documents: &DocumentManager| {
+                        documents.document(&path).map(|document| (document.buffer.can_undo(), document.buffer.can_redo()))
+                    }).ok().flatten()) {
+                        if ui.add_enabled(can_undo, egui::Button::new("Undo typing")).clicked() {
+                            self.undo_active_document(false);
+                            ui.close_menu();
+                        }
+                        if ui.add_enabled(can_redo, egui::Button::new("Redo typing")).clicked() {
+                            self.undo_active_document(true);
+                            ui.close_menu();
+                        }
+                        ui.separator();
                     }
-                    if ui.button("Redo").clicked() {
-    
+                    let history = self.core.history();
+                    let (undo_label, redo_label) = (history.undo_label().map(|label| format!("Undo {}", label)), history.redo_label().map(|label| format!("Redo {}", label)));
+                    if ui.add_enabled(undo_label.is_some(), egui::Button::new(undo_label.unwrap_or(String::from("Undo")))).clicked() {
+                        self.publish_command("MainWindow", &CoreCommand::Undo);
+                        ui.close_menu();
+                    }
+                    if ui.add_enabled(redo_label.is_some(), egui::Button::new(redo_label.unwrap_or(String::from("Redo")))).clicked() {
+                        self.publish_command("MainWindow", &CoreCommand::Redo);
+                        ui.close_menu();
+                    }
+                    ui.separator();
+                    if ui.button("Find in files").clicked() {
+                        self.show_search();
+                        ui.close_menu();
                     }
                     ui.separator();
                     if ui.button("Preference").clicked() {
-    
+                        self.show_preferences();
+                        ui.close_menu();
                     }
+                    self.contributed_menu_items(ui, "Edit");
                 });
                 // ui.separator();
                 ui.menu_button("Window", |ui| {
                     ui.menu_button("Layout", |ui| {
-                        let project_manager = self.core.get_plugin::<ProjectManager>().unwrap();
-                        if project_manager.project_is_available() {
+                        let project = self.core.request::<Option<Project>>("MainWindow", &ProjectManagerRequest::Project).ok().flatten();
+                        if project.is_some() {
                             if ui.button("Save to project").clicked() {
                                 // ProjectManagerWindow::store_dock_state(self);
                                 let state = {
@@ -307,6 +639,26 @@ impl App for DesignerUI {
                         }
                         ui.button("Reset");
                     });
+                    let windows = self.enabled_extensions().into_iter()
+                        .flat_map(|package| package.manifest.contributes.windows.into_iter().map(move |window| (package.manifest.uuid.clone(), window)))
+                        .collect::<Vec<_>>();
+                    if !windows.is_empty() {
+                        ui.menu_button("Extension windows", |ui| {
+                            for (uuid, window) in windows {
+                                if ui.button(&window.title).clicked() {
+                                    self.dock_state.lock().push_to_first_leaf(EditorWindowState::ExtensionWindow { extension: uuid, window: window.id, title: window.title });
+                                    ui.close_menu();
+                                }
+                            }
+                        });
+                    }
+                    self.contributed_menu_items(ui, "Window");
+                });
+                ui.menu_button("Extensions", |ui| {
+                    if self.extensions.is_empty() {
+                        ui.label("No extension is installed.");
+                    }
+                    self.contributed_menu_items(ui, "Extensions");
                 });
                 // ui.separator();
                 ui.menu_button("Developer", |ui|{
@@ -320,32 +672,62 @@ impl App for DesignerUI {
             {
                 //restore dock layout from opened project.
                 let dock_changes = ui.use_state(|| 0usize, ());
-                let wm = self.core.get_plugin::<WindowManager>().unwrap();
-                if *dock_changes != wm.dock_changes.count && wm.dock_state.is_some() {
-                    *dock_guard = serde_json::from_value(wm.dock_state.clone().unwrap()).unwrap();
-                    dock_changes.set_next(wm.dock_changes.count);
+                let mut changed_by_program = false;
+                let restored = self.core.with_plugin(|wm: &WindowManager| {
+                    wm.dock_state.clone().map(|state| (wm.dock_changes.count, state))
+                }).ok().flatten();
+                if let Some((count, state)) = restored {
+                    if *dock_changes != count {
+                        if let Ok(state) = serde_json::from_value(state) {
+                            *dock_guard = state;
+                        }
+                        dock_changes.set_next(count);
+                        changed_by_program = true;
+                    }
+                }
+                //editor tabs follow renamed, moved or deleted project files.
+                let file_changes = self.core.with_plugin_mut(|wm: &mut WindowManager| std::mem::take(&mut wm.file_changes)).unwrap_or_default();
+                changed_by_program |= !file_changes.is_empty();
+                // buffers with unsaved changes stay open when their files are deleted, so do their tabs.
+                let open_documents = self.core.with_plugin(|documents: &DocumentManager| documents.documents().iter().map(|document| document.path.clone()).collect::<Vec<_>>()).unwrap_or_default();
+                for change in file_changes {
+                    dock_guard.retain_tabs(|tab| match tab {
+                        EditorWindowState::CodeEditorWindow { file_name } | EditorWindowState::NodeEditorWindow { file_name } => match change.apply(file_name) {
+                            Some(new_name) => {
+                                *file_name = new_name;
+                                true
+                            },
+                            None => change != FileTabChange::ProjectClosed && open_documents.contains(file_name),
+                        },
+                        _ => true,
+                    });
+                }
+                changed_by_program |= self.show_opened_documents(&mut dock_guard);
+                if changed_by_program {
+                    self.last_layout = None;
                 }
             }
             DockArea::new(&mut dock_guard).show(ctx, &mut MyTab{app: self});
+            self.track_layout_changes(ctx, &dock_guard);
         });
 
         if let Some(path) = self.file_dialog.update(ctx).selected().and_then(|p| Some(p.to_path_buf())) {
             if let Some(id) = self.file_dialog.operation_id().map(|id| id.to_string()) {
                 if id == "create_project_files" {
-                    self.project_manager.create_project(path.clone());
-                    self.store_recent_files(frame, &path);
+                    ProjectManagerWindow::create_project(self, path.clone());
                 }
     
                 if id == "open_project_files" {
                     let path = path.parent().unwrap().into();
                     ProjectManagerWindow::open_project(self, &path);
-                    self.store_recent_files(frame, &path);
                 }
                 self.reset_file_dialog();
             }
         }
 
         ProjectManagerWindow::update(self, ctx, frame);
+        self.show_close_document_prompt(ctx);
+        NotificationToasts::update(self, ctx);
     }
 }
 
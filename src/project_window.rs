@@ -1,4 +1,4 @@
-use std::{fs::File, io::{Read, Write}, path::PathBuf};
+use std::path::{Path, PathBuf};
 
 use eframe::WindowBuilder;
 use egui::{Align2, CentralPanel, DragValue, Grid, Layout, TopBottomPanel, Ui, Vec2, Widget};
@@ -6,7 +6,7 @@ use egui_dock::DockState;
 use log::{error, info};
 use serde::{de::IntoDeserializer, Deserialize, Serialize};
 
-use crate::{backend::plugins::project_manager::{Project, ProjectManager, ProjectManagerCommand}, dock_manager::EditorWindowState, DesignerUI};
+use crate::{backend::{plugins::{project_manager::{Project, ProjectManagerCommand, ProjectManagerRequest}, window_manager::WindowManagerCommand}, project_template::{ProjectTemplate, EMPTY_TEMPLATE}}, dock_manager::EditorWindowState, DesignerUI};
 
 
 /**
@@ -18,12 +18,6 @@ use crate::{backend::plugins::project_manager::{Project, ProjectManager, Project
 */
 
 
-impl Project {
-    pub fn new_from_empty() -> Self {
-        Self { name: "".into(), description: "".into(), version: (0, 0, 1), package_name: "".into(), author: "".into(), email: "".into() }
-    }
-}
-
 #[derive(Debug, Clone)]
 pub enum WindowStatus {
     Open,
@@ -36,10 +30,22 @@ pub struct ProjectManagerWindow {
     dialog_has_open: bool,
     project: Project,
     path: Option<PathBuf>,
+    /// asks whether buffers of the open editors are saved before the project is closed.
+    confirm_close: bool,
+    /// project opened once the user answered `confirm_close`, the open one is closed on the way.
+    pending_open: Option<PathBuf>,
+    templates: Vec<ProjectTemplate>,
+    /// id of the selected template.
+    template: String,
+}
+
+/// `Project` lives in the headless core library, so its editor form is attached from the UI side.
+pub trait ProjectEditorUi {
+    fn editor_ui(&mut self, ctx: &egui::Context, ui: &mut Ui);
 }
 
-impl Project {
-    pub fn editor_ui(&mut self, ctx: &egui::Context, ui: &mut Ui) {
+impl ProjectEditorUi for Project {
+    fn editor_ui(&mut self, _ctx: &egui::Context, ui: &mut Ui) {
         Grid::new("grid").striped(true).spacing(Vec2::new(0.0, 3.0)).max_col_width(500.0).show(ui, |ui|{
             let project = self;
             ui.label("project name");
@@ -71,45 +77,155 @@ impl Project {
             ui.label("email");
             ui.text_edit_singleline(&mut project.email);
             ui.end_row();
+
+            ui.label("excluded files");
+            let mut exclude = project.exclude.join("\n");
+            if ui.add(egui::TextEdit::multiline(&mut exclude).hint_text("build/\n*.log")).on_hover_text("One gitignore style pattern per line, hidden from the file manager in addition to .gitignore.").changed() {
+                project.exclude = exclude.split('\n').map(str::to_string).collect();
+            }
+            ui.end_row();
         });
     }
 }
 
 impl ProjectManagerWindow {
     pub fn new() -> Self {
-        Self {dialog_has_open: false, status: WindowStatus::Closed, project: Project::new_from_empty(), path: None }
+        Self {dialog_has_open: false, status: WindowStatus::Closed, project: Project::new_from_empty(), path: None, confirm_close: false, pending_open: None, templates: vec![], template: String::from(EMPTY_TEMPLATE) }
     }
     pub fn update(app: &mut DesignerUI, ctx: &egui::Context, frame: &mut eframe::Frame) {
         let mut create_project_button_is_pressed = false;
         if let WindowStatus::New = app.project_manager.status {
             egui::Window::new("Create new project").open(&mut app.project_manager.dialog_has_open).movable(true).resizable(true).collapsible(false).show(ctx, |ui|{
                 ui.vertical_centered_justified(|ui|{
+                    Self::template_selector(ui, &app.project_manager.templates, &mut app.project_manager.template);
+                    ui.separator();
                     app.project_manager.project.editor_ui(ctx, ui);
                     ui.allocate_space(Vec2::new(0.0, 10.0));
                     ui.separator();
                     ui.allocate_space(Vec2::new(0.0, 10.0));
+                    let problems = app.project_manager.project.validate();
+                    for problem in &problems {
+                        ui.colored_label(ui.visuals().error_fg_color, &problem.message);
+                    }
                     ui.with_layout(Layout::top_down(egui::Align::Center), |ui| {
-                        create_project_button_is_pressed = ui.button("Create project").clicked();
+                        create_project_button_is_pressed = ui.add_enabled(problems.is_empty(), egui::Button::new("Create project")).clicked();
                     });
                 });
             });
         }
+        let mut close_choice = None;
+        if app.project_manager.confirm_close {
+            egui::Window::new("Close project").anchor(Align2::CENTER_CENTER, Vec2::ZERO).collapsible(false).resizable(false).show(ctx, |ui| {
+                ui.label("Save the unsaved documents and graphs before closing the project?");
+                ui.horizontal(|ui| {
+                    if ui.button("Save").clicked() {
+                        close_choice = Some(Some(true));
+                    }
+                    if ui.button("Don't save").clicked() {
+                        close_choice = Some(Some(false));
+                    }
+                    if ui.button("Cancel").clicked() {
+                        close_choice = Some(None);
+                    }
+                });
+            });
+        }
+        if let Some(choice) = close_choice {
+            app.project_manager.confirm_close = false;
+            let opening = app.project_manager.pending_open.take();
+            match (choice, opening) {
+                (Some(save_documents), Some(path)) => Self::finish_opening(app, &path, save_documents),
+                (Some(save_documents), None) => Self::finish_closing(app, save_documents),
+                // cancelled, the open project stays open.
+                (None, _) => {},
+            }
+        }
         if create_project_button_is_pressed {
-            app.publish_command("ProjectManagerWindow", &ProjectManagerCommand::CreateProject { path: app.project_manager.path.clone().unwrap().to_str().unwrap().to_string(), project: app.project_manager.project.clone() });
+            app.publish_command("ProjectManagerWindow", &ProjectManagerCommand::CreateProject { path: app.project_manager.path.clone().unwrap().to_str().unwrap().to_string(), project: app.project_manager.project.clone(), template: Some(app.project_manager.template.clone()) });
             app.project_manager.dialog_has_open = false;
             app.project_manager.status = WindowStatus::Closed;
             app.project_manager.path = None;
         }
     }
     
-    pub fn create_project(&mut self, path: PathBuf) {
-        self.path = Some(path);
-        self.dialog_has_open = true;
-        self.status = WindowStatus::New;
+    pub fn create_project(app: &mut DesignerUI, path: PathBuf) {
+        let templates = app.core.request::<Vec<ProjectTemplate>>("ProjectManagerWindow", &ProjectManagerRequest::Templates).unwrap_or_default();
+        let window = &mut app.project_manager;
+        if !templates.iter().any(|template| template.id == window.template) {
+            window.template = String::from(EMPTY_TEMPLATE);
+        }
+        window.templates = templates;
+        window.path = Some(path);
+        window.dialog_has_open = true;
+        window.status = WindowStatus::New;
+    }
+
+    /// Preselects a template for the next create dialog, such as one picked on the welcome page.
+    pub fn select_template(&mut self, template: String) {
+        self.template = template;
+    }
+
+    /// Template list on the left, description and the files it creates on the right.
+    fn template_selector(ui: &mut Ui, templates: &[ProjectTemplate], selected: &mut String) {
+        ui.horizontal_top(|ui| {
+            ui.vertical(|ui| {
+                ui.strong("Template");
+                for template in templates {
+                    ui.selectable_value(selected, template.id.clone(), &template.name);
+                }
+            });
+            ui.separator();
+            ui.vertical(|ui| {
+                if let Some(template) = templates.iter().find(|template| &template.id == selected) {
+                    ui.label(&template.description);
+                    ui.add_space(4.0);
+                    for file in &template.files {
+                        ui.monospace(file);
+                    }
+                }
+            });
+        });
     }
     
+    /// Opens a project in place of the open one, the user is asked first like in `close_project` when it has unsaved changes.
     pub fn open_project(app: &mut DesignerUI, path: &PathBuf) {
-        app.publish_command("ProjectManagerWindow", &ProjectManagerCommand::OpenProject { path: path.to_str().unwrap().to_string() });
+        if app.has_unsaved_changes() {
+            app.project_manager.pending_open = Some(path.clone());
+            app.project_manager.confirm_close = true;
+        } else {
+            Self::finish_opening(app, path, false);
+        }
+    }
+
+    /// An open project is closed first, it stores the layout the window reports here.
+    fn finish_opening(app: &mut DesignerUI, path: &Path, save_documents: bool) {
+        Self::report_layout(app);
+        app.publish_command("ProjectManagerWindow", &ProjectManagerCommand::OpenProject { path: path.to_str().unwrap().to_string(), save_documents });
+    }
+
+    /// Closes the opened project, the user is asked first when open documents or graphs have unsaved changes.
+    pub fn close_project(app: &mut DesignerUI) {
+        app.project_manager.pending_open = None;
+        if app.has_unsaved_changes() {
+            app.project_manager.confirm_close = true;
+        } else {
+            Self::finish_closing(app, false);
+        }
+    }
+
+    /// The window manager stores the reported layout when the project closes, its editor tabs are closed once it is closed.
+    fn finish_closing(app: &mut DesignerUI, save_documents: bool) {
+        Self::report_layout(app);
+        app.publish_command("ProjectManagerWindow", &ProjectManagerCommand::CloseProject { save_documents });
+    }
+
+    fn report_layout(app: &mut DesignerUI) {
+        let state = serde_json::to_value(&*app.dock_state.lock()).unwrap();
+        app.publish_command("ProjectManagerWindow", &WindowManagerCommand::UpdateLayout(state));
+    }
+
+    pub fn is_project_editor(tab: &EditorWindowState) -> bool {
+        matches!(tab, EditorWindowState::CodeEditorWindow { .. } | EditorWindowState::NodeEditorWindow { .. })
     }
 
     
@@ -200,7 +200,7 @@ impl MyJsContextWrapper {
 }
 
 pub struct SerdeJsonQuickJsValue {
-    value: serde_json::Value
+    pub value: serde_json::Value
 }
 
 impl<'js> FromJs<'js> for SerdeJsonQuickJsValue {
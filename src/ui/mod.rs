@@ -8,4 +8,6 @@
 
 
 pub mod pages;
-pub mod javascript_widget;
\ No newline at end of file
+pub mod javascript_widget;
+pub mod notification_toasts;
+pub mod shortcuts;
\ No newline at end of file
@@ -0,0 +1,45 @@
+use std::time::{Duration, SystemTime};
+
+use egui::{Align2, Area, Frame, Id, Vec2};
+
+use crate::{backend::plugins::notification_center::{NotificationCenter, NotificationCommand}, DesignerUI};
+
+const TOAST_LIFETIME: Duration = Duration::from_secs(8);
+
+pub struct NotificationToasts;
+
+impl NotificationToasts {
+    /// Shows recent notifications in the bottom right corner, they fade out after `TOAST_LIFETIME`.
+    pub fn update(app: &mut DesignerUI, ctx: &egui::Context) {
+        let now = SystemTime::now();
+        let notifications: Vec<_> = app.core.with_plugin(|center: &NotificationCenter| {
+            center.notifications.iter()
+                .filter(|item| now.duration_since(item.created).unwrap_or_default() < TOAST_LIFETIME)
+                .cloned()
+                .collect()
+        }).unwrap_or_default();
+        if notifications.is_empty() {
+            return;
+        }
+        let mut dismissed = None;
+        Area::new(Id::new("notification_toasts")).anchor(Align2::RIGHT_BOTTOM, Vec2::new(-10.0, -10.0)).show(ctx, |ui| {
+            ui.set_max_width(360.0);
+            for notification in &notifications {
+                Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.strong(&notification.title);
+                        if ui.small_button("✖").clicked() {
+                            dismissed = Some(notification.id);
+                        }
+                    });
+                    ui.label(&notification.message);
+                });
+                ui.add_space(4.0);
+            }
+        });
+        if let Some(id) = dismissed {
+            app.publish_command("NotificationToasts", &NotificationCommand::Dismiss(id));
+        }
+        ctx.request_repaint_after(Duration::from_secs(1));
+    }
+}
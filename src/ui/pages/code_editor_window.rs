@@ -0,0 +1,380 @@
+use std::sync::Arc;
+
+use egui::{output::IMEOutput, text::{CCursor, LayoutJob}, Align, Align2, Color32, CursorIcon, Event, EventFilter, FontId, Galley, ImeEvent, Key, Pos2, Rect, ScrollArea, Sense, TextFormat, TextStyle, Vec2, Widget};
+use egui_hooks::UseHookExt;
+
+use crate::{backend::{plugins::{document_manager::{DiskState, DocumentManager, DocumentManagerCommand}, window_manager::WindowManager}, syntax::{Token, TokenKind}, text_buffer::{Buffer, EditKind, Motion, Selection, TextChange}}, dock_manager::MyTab};
+
+pub struct CodeEditorWindow<'a, 'b> {
+    pub tab: &'a mut MyTab<'b>,
+    pub file_name: &'a str,
+}
+
+/// Kept between frames in the temporary data of the editor.
+#[derive(Debug, Clone, Default)]
+struct EditorView {
+    /// Longest line seen so far, the scroll area is as wide as it.
+    max_line_chars: usize,
+    /// Where the drag selecting text started.
+    drag_anchor: Option<usize>,
+}
+
+/// Sizes of the monospace font, every line is one row high and most characters one column wide.
+struct Metrics {
+    font_id: FontId,
+    row_height: f32,
+    char_width: f32,
+    gutter_width: f32,
+}
+
+/// A visible line laid out for painting.
+struct VisibleLine {
+    line: usize,
+    /// Character offset of the line in the buffer.
+    start: usize,
+    length: usize,
+    galley: Arc<Galley>,
+}
+
+impl<'a, 'b> Widget for CodeEditorWindow<'a, 'b> {
+    fn ui(mut self, ui: &mut egui::Ui) -> egui::Response {
+        let path = self.file_name.to_string();
+        let open_requested = ui.use_state(|| false, path.clone());
+        let status = self.tab.app.core.with_plugin(|documents: &DocumentManager| {
+            documents.document(&path).map(|document| {
+                let head = document.buffer.selections().first().map(|selection| selection.head).unwrap_or_default();
+                (document.dirty(), document.disk_state, document.language, document.buffer.line_column(head))
+            })
+        }).ok().flatten();
+        let Some((dirty, disk_state, language, (line, column))) = status else {
+            // a tab restored with the dock layout, its buffer is read the first time it is shown.
+            if !*open_requested {
+                open_requested.set_next(true);
+                self.tab.app.publish_command("CodeEditor", &DocumentManagerCommand::Open { path });
+                return ui.spinner();
+            }
+            return ui.label(format!("\"{}\" cannot be opened, see the log window for the reason.", path));
+        };
+
+        ui.vertical(|ui| {
+            ui.horizontal(|ui| {
+                if ui.add_enabled(dirty || disk_state != DiskState::InSync, egui::Button::new("Save")).clicked() {
+                    self.tab.app.publish_command("CodeEditor", &DocumentManagerCommand::Save { path: path.clone() });
+                }
+                if dirty {
+                    ui.label("●").on_hover_text("Unsaved changes");
+                }
+                ui.weak(&path);
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    ui.weak(language.name());
+                    ui.weak(format!("Ln {}, Col {}", line + 1, column + 1));
+                });
+            });
+            match disk_state {
+                DiskState::InSync => {},
+                DiskState::Modified => {
+                    ui.horizontal(|ui| {
+                        ui.colored_label(ui.visuals().warn_fg_color, "The file was changed by another program.");
+                        if ui.button("Reload").on_hover_text("Discard the unsaved changes").clicked() {
+                            self.tab.app.publish_command("CodeEditor", &DocumentManagerCommand::Reload { path: path.clone() });
+                        }
+                        if ui.button("Overwrite").clicked() {
+                            self.tab.app.publish_command("CodeEditor", &DocumentManagerCommand::Save { path: path.clone() });
+                        }
+                    });
+                },
+                DiskState::Removed => {
+                    ui.colored_label(ui.visuals().warn_fg_color, "The file was removed from disk, saving creates it again.");
+                },
+            }
+            ui.separator();
+            self.show_text(ui, &path);
+        }).response
+    }
+}
+
+impl<'a, 'b> CodeEditorWindow<'a, 'b> {
+    /// The scrollable text with its gutter, handles the pointer and the keyboard when focused.
+    fn show_text(&mut self, ui: &mut egui::Ui, path: &str) {
+        let id = egui::Id::new(("code_editor", path));
+        let mut view = ui.data_mut(|data| data.remove_temp::<EditorView>(id)).unwrap_or_default();
+        let font_id = TextStyle::Monospace.resolve(ui.style());
+        let (row_height, char_width) = ui.fonts(|fonts| (fonts.row_height(&font_id), fonts.glyph_width(&font_id, '0')));
+        let dark_mode = ui.visuals().dark_mode;
+        let line_count = self.with_buffer(path, |buffer| buffer.len_lines()).unwrap_or(1);
+        let gutter_width = char_width * (line_count.to_string().len() + 2) as f32;
+        let metrics = Metrics { font_id: font_id.clone(), row_height, char_width, gutter_width };
+        // a line asked for by `DocumentManagerCommand::Reveal`, such as a search result.
+        let revealed = self.tab.app.core.with_plugin_mut(|wm: &mut WindowManager| {
+            let line = wm.revealed_lines.iter().rev().find(|(revealed, _)| revealed == path).map(|(_, line)| *line);
+            wm.revealed_lines.retain(|(revealed, _)| revealed != path);
+            line
+        }).ok().flatten();
+
+        ScrollArea::both().auto_shrink([false, false]).show_viewport(ui, |ui, viewport| {
+            let width = (gutter_width + (view.max_line_chars + 2) as f32 * char_width).max(ui.available_width());
+            let (rect, _) = ui.allocate_exact_size(Vec2::new(width, line_count as f32 * row_height), Sense::hover());
+            let response = ui.interact(rect, id, Sense::click_and_drag()).on_hover_cursor(CursorIcon::Text);
+            if let Some(line) = revealed {
+                ui.scroll_to_rect(Rect::from_min_size(Pos2::new(rect.left(), rect.top() + line as f32 * row_height), Vec2::new(gutter_width, row_height)), Some(Align::Center));
+                response.request_focus();
+            }
+            let text_left = rect.left() + gutter_width;
+            let first = (viewport.min.y / row_height).floor().max(0.) as usize;
+            let last = ((viewport.max.y / row_height).ceil() as usize + 1).min(line_count);
+            let mut lines = self.layout_lines(ui, path, first..last, &font_id, dark_mode);
+
+            // pointer: click places the cursor, Alt adds one, Shift extends the selection, double click selects a word.
+            let pointer_position = |position: Pos2, lines: &[VisibleLine]| {
+                let line = (((position.y - rect.top()) / row_height).floor().max(0.) as usize).min(line_count.saturating_sub(1));
+                let x = position.x - text_left;
+                let column = match lines.iter().find(|visible| visible.line == line) {
+                    Some(visible) => visible.galley.cursor_from_pos(Vec2::new(x, row_height / 2.)).ccursor.index,
+                    None => (x / char_width).round().max(0.) as usize,
+                };
+                (line, column)
+            };
+            if let Some(position) = response.interact_pointer_pos() {
+                let (line, column) = pointer_position(position, &lines);
+                let modifiers = ui.input(|input| input.modifiers);
+                let pressed = ui.input(|input| input.pointer.primary_pressed());
+                let double_clicked = response.double_clicked();
+                let anchor = view.drag_anchor;
+                let selections = self.with_buffer(path, |buffer| {
+                    let position = buffer.position(line, column);
+                    let current = buffer.selections().to_vec();
+                    if double_clicked {
+                        let word = buffer.word_at(position);
+                        return (position, vec![Selection { anchor: word.start, head: word.end }]);
+                    }
+                    let selections = if pressed && modifiers.alt {
+                        current.iter().copied().chain([Selection::cursor(position)]).collect()
+                    } else if pressed && modifiers.shift {
+                        vec![Selection { anchor: current[0].anchor, head: position }]
+                    } else if pressed {
+                        vec![Selection::cursor(position)]
+                    } else {
+                        vec![Selection { anchor: anchor.unwrap_or(position), head: position }]
+                    };
+                    (if pressed && modifiers.shift {current[0].anchor} else {position}, selections)
+                });
+                if let Some((position, selections)) = selections {
+                    if pressed {
+                        view.drag_anchor = Some(position);
+                        response.request_focus();
+                    }
+                    if (pressed || double_clicked || response.dragged()) && self.with_buffer(path, |buffer| buffer.selections() != selections.as_slice()).unwrap_or(false) {
+                        self.tab.app.publish_command("CodeEditor", &DocumentManagerCommand::SetSelections { path: path.to_string(), selections });
+                    }
+                }
+            } else {
+                view.drag_anchor = None;
+            }
+
+            // keyboard: each event is applied to the buffer left by the one before it.
+            if response.has_focus() {
+                let filter = EventFilter { tab: true, horizontal_arrows: true, vertical_arrows: true, escape: true };
+                ui.memory_mut(|memory| memory.set_focus_lock_filter(id, filter));
+                let events = ui.input(|input| input.filtered_events(&filter));
+                let page = (viewport.height() / row_height).max(1.) as usize;
+                let mut moved = false;
+                for event in &events {
+                    match event {
+                        Event::Copy | Event::Cut => {
+                            let text = self.with_buffer(path, |buffer| buffer.selected_text()).unwrap_or_default();
+                            if !text.is_empty() {
+                                ui.ctx().copy_text(text);
+                                if *event == Event::Cut {
+                                    self.edit(path, |buffer| Some((buffer.insert_changes(""), EditKind::Other)));
+                                }
+                            }
+                        },
+                        Event::Key { key: Key::Escape, pressed: true, .. } => {
+                            let single = self.with_buffer(path, |buffer| buffer.selections().len() == 1 && buffer.selections()[0].is_empty()).unwrap_or(true);
+                            if single {
+                                ui.memory_mut(|memory| memory.surrender_focus(id));
+                            } else {
+                                self.select(path, |buffer| vec![Selection::cursor(buffer.selections()[0].head)]);
+                            }
+                        },
+                        _ => moved |= self.handle_event(path, event, page),
+                    }
+                }
+                if moved {
+                    let cursor = self.with_buffer(path, |buffer| buffer.line_column(buffer.selections()[0].head));
+                    if let Some((line, column)) = cursor {
+                        let cursor_rect = Rect::from_min_size(Pos2::new(text_left + column as f32 * char_width, rect.top() + line as f32 * row_height), Vec2::new(char_width, row_height));
+                        ui.scroll_to_rect(cursor_rect.expand2(Vec2::new(gutter_width, 0.)), None);
+                    }
+                }
+                lines = self.layout_lines(ui, path, first..last, &font_id, dark_mode);
+            }
+            view.max_line_chars = lines.iter().map(|visible| visible.length).max().unwrap_or_default().max(view.max_line_chars);
+            let selections = self.with_buffer(path, |buffer| buffer.selections().to_vec()).unwrap_or_default();
+            paint(ui, &response, rect, viewport, &metrics, &lines, &selections);
+        });
+        ui.data_mut(|data| data.insert_temp(id, view));
+    }
+
+    fn with_buffer<R>(&self, path: &str, accessor: impl FnOnce(&Buffer) -> R) -> Option<R> {
+        self.tab.app.core.with_plugin(|documents: &DocumentManager| documents.document(path).map(|document| accessor(&document.buffer))).ok().flatten()
+    }
+
+    /// Publishes the changes computed from the buffer, `None` leaves it as it is.
+    fn edit(&mut self, path: &str, changes: impl FnOnce(&Buffer) -> Option<(Vec<TextChange>, EditKind)>) {
+        if let Some((changes, kind)) = self.with_buffer(path, changes).flatten().filter(|(changes, _)| !changes.is_empty()) {
+            self.tab.app.publish_command("CodeEditor", &DocumentManagerCommand::Edit { path: path.to_string(), changes, kind });
+        }
+    }
+
+    fn select(&mut self, path: &str, selections: impl FnOnce(&Buffer) -> Vec<Selection>) {
+        if let Some(selections) = self.with_buffer(path, selections) {
+            self.tab.app.publish_command("CodeEditor", &DocumentManagerCommand::SetSelections { path: path.to_string(), selections });
+        }
+    }
+
+    /// Applies a typed, pasted or navigation event, returns whether the view follows the cursor.
+    fn handle_event(&mut self, path: &str, event: &Event, page: usize) -> bool {
+        match event {
+            Event::Text(text) | Event::Ime(ImeEvent::Commit(text)) if !text.is_empty() => {
+                self.edit(path, |buffer| Some((buffer.insert_changes(text), EditKind::Typing)));
+            },
+            Event::Paste(text) => {
+                self.edit(path, |buffer| Some((buffer.insert_changes(text), EditKind::Other)));
+            },
+            Event::Key { key, pressed: true, modifiers, .. } => {
+                // word motions use Alt on macOS and Ctrl elsewhere.
+                let word = if cfg!(target_os = "macos") {modifiers.alt} else {modifiers.command};
+                match key {
+                    Key::Enter => self.edit(path, |buffer| Some((buffer.newline_changes(), EditKind::Typing))),
+                    Key::Tab => self.edit(path, |buffer| Some((buffer.insert_changes("    "), EditKind::Typing))),
+                    Key::Backspace => self.edit(path, |buffer| Some((buffer.delete_changes(false, word), EditKind::Deleting))),
+                    Key::Delete => self.edit(path, |buffer| Some((buffer.delete_changes(true, word), EditKind::Deleting))),
+                    Key::A if modifiers.command => {
+                        self.select(path, |buffer| vec![Selection { anchor: 0, head: buffer.len_chars() }]);
+                        return false;
+                    },
+                    Key::ArrowUp | Key::ArrowDown if modifiers.command && modifiers.alt => {
+                        let below = *key == Key::ArrowDown;
+                        self.select(path, |buffer| buffer.with_cursor_on_adjacent_line(below));
+                    },
+                    _ => {
+                        let Some(motion) = key_motion(*key, word, modifiers.command, page) else {
+                            return false;
+                        };
+                        self.select(path, |buffer| buffer.moved_selections(motion, modifiers.shift));
+                    },
+                }
+            },
+            _ => return false,
+        }
+        true
+    }
+
+    /// Highlights and lays out the lines in `lines`, the document keeps the highlighting state between frames.
+    fn layout_lines(&mut self, ui: &egui::Ui, path: &str, lines: std::ops::Range<usize>, font_id: &FontId, dark_mode: bool) -> Vec<VisibleLine> {
+        let first = lines.start;
+        let highlighted = self.tab.app.core.with_plugin_mut(|documents: &mut DocumentManager| {
+            let highlighted = documents.highlight_lines(path, lines);
+            let starts = documents.document(path).map(|document| (first..first + highlighted.len()).map(|line| document.buffer.rope().line_to_char(line)).collect::<Vec<_>>()).unwrap_or_default();
+            highlighted.into_iter().zip(starts).collect::<Vec<_>>()
+        }).unwrap_or_default();
+        highlighted.into_iter().enumerate().map(|(index, ((text, tokens), start))| {
+            let length = text.chars().count();
+            let galley = ui.fonts(|fonts| fonts.layout_job(line_job(&text, &tokens, font_id, dark_mode)));
+            VisibleLine { line: first + index, start, length, galley }
+        }).collect()
+    }
+}
+
+/// Selections, text, cursors and the line numbers of the visible lines, `rect` holds the whole text.
+fn paint(ui: &egui::Ui, response: &egui::Response, rect: Rect, viewport: Rect, metrics: &Metrics, lines: &[VisibleLine], selections: &[Selection]) {
+    let Metrics { font_id, row_height, char_width, gutter_width } = metrics;
+    let (row_height, char_width, gutter_width) = (*row_height, *char_width, *gutter_width);
+    let painter = ui.painter();
+    let visuals = ui.visuals();
+    let text_left = rect.left() + gutter_width;
+    let focused = response.has_focus();
+    let column_x = |visible: &VisibleLine, column: usize| text_left + visible.galley.pos_from_ccursor(CCursor::new(column)).min.x;
+    let mut primary_cursor = None;
+
+    for visible in lines {
+        let top = rect.top() + visible.line as f32 * row_height;
+        let end = visible.start + visible.length;
+        for selection in selections {
+            if selection.is_empty() || selection.end() < visible.start || selection.start() > end {
+                continue;
+            }
+            let from = column_x(visible, selection.start().max(visible.start) - visible.start);
+            // a selected line break shows as one more character.
+            let to = column_x(visible, selection.end().min(end) - visible.start) + if selection.end() > end {char_width} else {0.};
+            painter.rect_filled(Rect::from_min_max(Pos2::new(from, top), Pos2::new(to, top + row_height)), 0., visuals.selection.bg_fill);
+        }
+        painter.galley(Pos2::new(text_left, top), visible.galley.clone(), visuals.text_color());
+        if focused {
+            for selection in selections.iter().filter(|selection| (visible.start..=end).contains(&selection.head)) {
+                let x = column_x(visible, selection.head - visible.start);
+                let cursor_rect = Rect::from_min_max(Pos2::new(x, top), Pos2::new(x, top + row_height));
+                painter.line_segment([cursor_rect.min, cursor_rect.max], visuals.text_cursor.stroke);
+                if Some(selection) == selections.first() {
+                    primary_cursor = Some(cursor_rect);
+                }
+            }
+        }
+    }
+
+    // the gutter stays at the left edge of the view while the text scrolls under it.
+    let gutter = Rect::from_min_size(rect.min + viewport.min.to_vec2(), Vec2::new(gutter_width - char_width / 2., viewport.height()));
+    painter.rect_filled(gutter, 0., visuals.panel_fill);
+    let cursor_lines = selections.iter().map(|selection| selection.head).collect::<Vec<_>>();
+    for visible in lines {
+        let has_cursor = cursor_lines.iter().any(|head| (visible.start..=visible.start + visible.length).contains(head));
+        let color = if has_cursor {visuals.strong_text_color()} else {visuals.weak_text_color()};
+        painter.text(Pos2::new(gutter.right() - char_width, rect.top() + visible.line as f32 * row_height), Align2::RIGHT_TOP, visible.line + 1, font_id.clone(), color);
+    }
+
+    if let Some(cursor_rect) = primary_cursor {
+        ui.ctx().output_mut(|output| output.ime = Some(IMEOutput { rect: ui.clip_rect(), cursor_rect }));
+    }
+}
+
+fn key_motion(key: Key, word: bool, document: bool, page: usize) -> Option<Motion> {
+    Some(match key {
+        Key::ArrowLeft if word => Motion::WordLeft,
+        Key::ArrowRight if word => Motion::WordRight,
+        Key::ArrowLeft => Motion::Left,
+        Key::ArrowRight => Motion::Right,
+        Key::ArrowUp => Motion::Up,
+        Key::ArrowDown => Motion::Down,
+        Key::Home if document => Motion::DocumentStart,
+        Key::End if document => Motion::DocumentEnd,
+        Key::Home => Motion::LineStart,
+        Key::End => Motion::LineEnd,
+        Key::PageUp => Motion::PageUp(page),
+        Key::PageDown => Motion::PageDown(page),
+        _ => return None,
+    })
+}
+
+fn line_job(text: &str, tokens: &[Token], font_id: &FontId, dark_mode: bool) -> LayoutJob {
+    let mut job = LayoutJob::default();
+    for token in tokens {
+        job.append(&text[token.range.clone()], 0., TextFormat::simple(font_id.clone(), token_color(token.kind, dark_mode)));
+    }
+    job.wrap.max_width = f32::INFINITY;
+    job
+}
+
+fn token_color(kind: TokenKind, dark_mode: bool) -> Color32 {
+    let (dark, light) = match kind {
+        TokenKind::Plain => ((0xDC, 0xDF, 0xE4), (0x38, 0x3A, 0x42)),
+        TokenKind::Keyword => ((0xC6, 0x78, 0xDD), (0xA6, 0x26, 0xA4)),
+        TokenKind::Literal | TokenKind::Number => ((0xD1, 0x9A, 0x66), (0x98, 0x68, 0x01)),
+        TokenKind::String => ((0x98, 0xC3, 0x79), (0x50, 0xA1, 0x4F)),
+        TokenKind::Comment => ((0x7F, 0x84, 0x8E), (0xA0, 0xA1, 0xA7)),
+        TokenKind::Property => ((0xE0, 0x6C, 0x75), (0xE4, 0x56, 0x49)),
+        TokenKind::Punctuation => ((0xAB, 0xB2, 0xBF), (0x50, 0x52, 0x5A)),
+    };
+    let (red, green, blue) = if dark_mode {dark} else {light};
+    Color32::from_rgb(red, green, blue)
+}
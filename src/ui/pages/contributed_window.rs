@@ -0,0 +1,35 @@
+use bluebird_ide::backend::commands::Command;
+use egui::Widget;
+
+use crate::{dock_manager::MyTab, js_plugin::JsPlugin};
+
+pub struct ContributedWindow<'a, 'b> {
+    pub tab: &'a mut MyTab<'b>,
+    pub extension: &'a str,
+    pub window: &'a str,
+}
+
+impl<'a, 'b> Widget for ContributedWindow<'a, 'b> {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        let rendered = self.tab.app.core.with_dynamic_plugin_mut(self.extension, |plugin| {
+            let plugin = plugin.as_any_mut().downcast_mut::<JsPlugin>()?;
+            let result = plugin.render_window(self.window, ui);
+            Some((result, plugin.take_published()))
+        }).flatten();
+        ui.vertical(|ui| {
+            match rendered {
+                Some((result, published)) => {
+                    for (action, args) in published {
+                        self.tab.app.publish_command(self.extension, &Command { action, args });
+                    }
+                    if let Err(error) = result {
+                        ui.label(format!("Extension window error:\n {}", error));
+                    }
+                },
+                None => {
+                    ui.label("The extension providing this window is not loaded.");
+                },
+            }
+        }).response
+    }
+}
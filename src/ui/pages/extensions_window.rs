@@ -0,0 +1,54 @@
+use egui::{RichText, ScrollArea, Widget};
+use egui_hooks::UseHookExt;
+
+use crate::{backend::{designer_core::CoreCommand, plugin::PluginStatus, plugin_settings::SettingScope}, dock_manager::MyTab};
+
+pub struct ExtensionsWindow<'a, 'b> {
+    pub tab: &'a mut MyTab<'b>
+}
+
+impl<'a, 'b> Widget for ExtensionsWindow<'a, 'b> {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        let scope = ui.use_state(|| SettingScope::User, ());
+        let project_attached = self.tab.app.core.project_attached();
+
+        ui.vertical(|ui| {
+            ui.horizontal(|ui| {
+                ui.label("Apply changes to:");
+                if ui.selectable_label(*scope == SettingScope::User, "User").clicked() {
+                    scope.set_next(SettingScope::User);
+                }
+                ui.add_enabled_ui(project_attached, |ui| {
+                    if ui.selectable_label(*scope == SettingScope::Project, "Project").on_disabled_hover_text("Please open a project first.").clicked() {
+                        scope.set_next(SettingScope::Project);
+                    }
+                });
+            });
+            ui.separator();
+            let scope = if project_attached {*scope} else {SettingScope::User};
+
+            let infos = self.tab.app.core.plugin_infos().to_vec();
+            ScrollArea::vertical().auto_shrink([false, false]).show(ui, |ui| {
+                for info in infos {
+                    let status = self.tab.app.core.plugin_status(&info.uuid);
+                    let setting = self.tab.app.core.plugin_setting(&info.uuid, scope);
+                    ui.group(|ui| {
+                        ui.set_width(ui.available_width());
+                        ui.horizontal(|ui| {
+                            let mut enabled = status == Some(PluginStatus::Enabled);
+                            if ui.add_enabled(status.is_some(), egui::Checkbox::new(&mut enabled, RichText::new(&info.name).strong())).changed() {
+                                self.tab.app.publish_command("ExtensionsUI", &CoreCommand::SetPluginEnabled { uuid: info.uuid.clone(), enabled, scope });
+                            }
+                            ui.label(format!("v{}.{}.{}", info.version.0, info.version.1, info.version.2));
+                            if setting.is_some() && ui.small_button("Reset").on_hover_text("Forget the choice of this scope.").clicked() {
+                                self.tab.app.publish_command("ExtensionsUI", &CoreCommand::ResetPluginEnabled { uuid: info.uuid.clone(), scope });
+                            }
+                        });
+                        ui.label(&info.description);
+                        ui.label(RichText::new(format!("{} · {}", info.author, info.uuid)).small().weak());
+                    });
+                }
+            });
+        }).response
+    }
+}
@@ -1,9 +1,9 @@
 use std::path::PathBuf;
 
-use egui::{CollapsingHeader, Widget};
+use egui::{CollapsingHeader, Id, RichText, Sense, Widget};
 use egui_hooks::UseHookExt;
 
-use crate::{backend::plugins::{file_manager::{FileEntry, FileManager, FileManagerCommand}, project_manager::ProjectManager}, dock_manager::MyTab};
+use crate::{backend::{file_watcher::WatchMode, plugins::file_manager::{FileEntry, FileEntryType, FileManager, FileManagerCommand, FileManagerRequest, TrashedItem}}, dock_manager::MyTab};
 
 /**
  * @author مۇختەرجان مەخمۇت
@@ -17,58 +17,214 @@ pub struct FileManagerPage<'a, 'b> {
     pub tab: &'a mut MyTab<'b>
 }
 
+/// What the user picked in the tree, paths are relative to the project directory.
+enum FileAction {
+    /// A folder was expanded for the first time.
+    Load(String),
+    Open(String),
+    Delete(String),
+    Prompt(NamePrompt),
+    Cut(String),
+    Move { path: String, destination: String },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PromptKind {
+    NewFile,
+    NewFolder,
+    Rename,
+}
+
+/// Asks a name for a new file or folder inside `path`, or a new name for `path` itself.
+#[derive(Debug, Clone)]
+struct NamePrompt {
+    kind: PromptKind,
+    path: String,
+    text: String,
+}
+
+impl NamePrompt {
+    fn command(&self) -> FileManagerCommand {
+        let child = || if self.path.is_empty() {self.text.clone()} else {format!("{}/{}", self.path, self.text)};
+        match self.kind {
+            PromptKind::NewFile => FileManagerCommand::CreateFile { path: child() },
+            PromptKind::NewFolder => FileManagerCommand::CreateFolder { path: child() },
+            PromptKind::Rename => FileManagerCommand::Rename { path: self.path.clone(), new_name: self.text.clone() },
+        }
+    }
+}
+
 impl<'a, 'b> Widget for FileManagerPage<'a, 'b> {
     fn ui(self, ui: &mut egui::Ui) -> egui::Response {
-        
-        ui.vertical(|ui|{
+        let prompt = ui.use_state(|| None::<NamePrompt>, ());
+        let clipboard = ui.use_state(|| None::<String>, ());
+        let trash = self.tab.app.core.request::<Vec<TrashedItem>>("FileManagerUI", &FileManagerRequest::Trash).unwrap_or_default();
+        let mut actions = vec![];
+
+        let response = ui.vertical(|ui|{
             ui.horizontal(|ui|{
                 if ui.button("Refresh").clicked() {
                     self.tab.app.publish_command("FileManagerUI", &FileManagerCommand::ReloadProjectFiles);
                 }
+                let last_deleted = trash.last().map(|item| format!("Restore \"{}\"", item.original_path)).unwrap_or_else(|| String::from("Trash is empty."));
+                if ui.add_enabled(!trash.is_empty(), egui::Button::new("Undo delete")).on_hover_text(&last_deleted).on_disabled_hover_text(&last_deleted).clicked() {
+                    self.tab.app.publish_command("FileManagerUI", &FileManagerCommand::RestoreDeleted { id: None });
+                }
+                if ui.add_enabled(!trash.is_empty(), egui::Button::new("Empty trash")).on_hover_text(format!("Permanently delete {} item(s).", trash.len())).clicked() {
+                    self.tab.app.publish_command("FileManagerUI", &FileManagerCommand::EmptyTrash);
+                }
+                if self.tab.app.core.with_plugin(|file_manager: &FileManager| file_manager.watch_mode()).ok().flatten() == Some(WatchMode::Polling) {
+                    ui.weak("polling").on_hover_text("OS file notifications are unavailable, changes made outside of the editor show up within a few seconds.");
+                }
                 // ui.button("Collapse folders");
             });
             ui.separator();
 
-            let manager = self.tab.app.core.get_plugin::<FileManager>().unwrap();
-            let (project_is_available, path) = {
-                (manager.root.is_some(), manager.root.clone())
-            };
-            if project_is_available {
-                self.render_file_entry(ui, &path.unwrap(), true);
+            let project_files = self.tab.app.core.request::<Option<FileEntry>>("FileManagerUI", &FileManagerRequest::ProjectFiles).ok().flatten();
+            if let Some(project_files) = project_files {
+                self.render_file_entry(ui, &project_files, "", (*clipboard).as_ref(), &mut actions);
             } else {
                 ui.label("Please open or create a project first.");
             }
-        }).response
+        }).response;
+
+        let mut next_prompt = (*prompt).clone();
+        for action in actions {
+            match action {
+                FileAction::Load(path) => self.tab.app.publish_command("FileManagerUI", &FileManagerCommand::LoadFolder { path }),
+                FileAction::Open(path) => self.tab.app.publish_command("FileManagerUI", &FileManagerCommand::OpenFile(path)),
+                FileAction::Delete(path) => self.tab.app.publish_command("FileManagerUI", &FileManagerCommand::Delete { path }),
+                FileAction::Prompt(name_prompt) => next_prompt = Some(name_prompt),
+                FileAction::Cut(path) => clipboard.set_next(Some(path)),
+                FileAction::Move { path, destination } => {
+                    if (*clipboard).as_deref() == Some(path.as_str()) {
+                        clipboard.set_next(None);
+                    }
+                    self.tab.app.publish_command("FileManagerUI", &FileManagerCommand::Move { path, destination });
+                },
+            }
+        }
+
+        if let Some(mut name_prompt) = next_prompt.take() {
+            let title = match name_prompt.kind {
+                PromptKind::NewFile => "New file",
+                PromptKind::NewFolder => "New folder",
+                PromptKind::Rename => "Rename",
+            };
+            let mut open = true;
+            let mut done = false;
+            egui::Window::new(title).collapsible(false).resizable(false).open(&mut open).show(ui.ctx(), |ui| {
+                let location = if name_prompt.path.is_empty() {"project://"} else {&name_prompt.path};
+                ui.label(location);
+                let edit = ui.text_edit_singleline(&mut name_prompt.text);
+                edit.request_focus();
+                let submitted = edit.lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter));
+                ui.horizontal(|ui| {
+                    if (ui.add_enabled(!name_prompt.text.trim().is_empty(), egui::Button::new("Ok")).clicked() || submitted) && !name_prompt.text.trim().is_empty() {
+                        self.tab.app.publish_command("FileManagerUI", &name_prompt.command());
+                        done = true;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        done = true;
+                    }
+                });
+            });
+            if open && !done {
+                next_prompt = Some(name_prompt);
+            }
+        }
+        prompt.set_next(next_prompt);
+        response
     }
 }
 
 impl<'a, 'b> FileManagerPage<'a, 'b> {
-    fn render_file_entry(&self, ui: &mut egui::Ui, file_entry: &FileEntry, is_root_dir: bool) {
-        match file_entry.file_type.clone() {
-            crate::backend::plugins::file_manager::FileEntryType::File => {
-                ui.label(&file_entry.name).context_menu(|ui|{
-                    ui.button("Open");
-                    ui.button("Delete");
-                    ui.button("Rename");
-                    ui.separator();
-                    ui.button("New Folder");
-                    ui.button("New File");
+    fn render_file_entry(&self, ui: &mut egui::Ui, file_entry: &FileEntry, path: &str, clipboard: Option<&String>, actions: &mut Vec<FileAction>) {
+        if let Some(problem) = &file_entry.problem {
+            ui.label(RichText::new(format!("⚠ {}", file_entry.name)).weak()).on_hover_text(problem);
+            return;
+        }
+        match &file_entry.file_type {
+            FileEntryType::File => {
+                let response = ui.dnd_drag_source(Id::new(("file_manager_drag", path)), path.to_string(), |ui| {
+                    ui.add(egui::Label::new(&file_entry.name).sense(Sense::click()))
+                }).inner;
+                if response.double_clicked() {
+                    actions.push(FileAction::Open(path.to_string()));
+                }
+                response.context_menu(|ui|{
+                    if ui.button("Open").clicked() {
+                        actions.push(FileAction::Open(path.to_string()));
+                        ui.close_menu();
+                    }
+                    Self::entry_menu(ui, path, parent_of(path), clipboard, actions);
                 });
             },
-            crate::backend::plugins::file_manager::FileEntryType::Folder(children) => {
-                CollapsingHeader::new(if is_root_dir {"project://"} else {&file_entry.name}).show(ui, |ui|{
-                    for entry in &*children {
-                        self.render_file_entry(ui, entry, false);
+            FileEntryType::Folder(_) | FileEntryType::UnloadedFolder => {
+                let is_root_dir = path.is_empty();
+                let header = CollapsingHeader::new(if is_root_dir {"project://"} else {&file_entry.name}).id_source(("file_manager_folder", path)).show(ui, |ui|{
+                    match &file_entry.file_type {
+                        FileEntryType::Folder(children) => for entry in children {
+                            let child = if is_root_dir {entry.name.clone()} else {format!("{}/{}", path, entry.name)};
+                            self.render_file_entry(ui, entry, &child, clipboard, actions);
+                        },
+                        _ => {
+                            ui.spinner();
+                            actions.push(FileAction::Load(path.to_string()));
+                        },
+                    }
+                }).header_response;
+                if let Some(dropped) = header.dnd_release_payload::<String>() {
+                    actions.push(FileAction::Move { path: (*dropped).clone(), destination: path.to_string() });
+                }
+                header.context_menu(|ui| {
+                    if !is_root_dir {
+                        Self::entry_menu(ui, path, path, clipboard, actions);
+                    } else {
+                        Self::folder_menu(ui, path, clipboard, actions);
                     }
-                }).header_response.context_menu(|ui| {
-                    ui.button("Open");
-                    ui.button("Delete");
-                    ui.button("Rename");
-                    ui.separator();
-                    ui.button("New Folder");
-                    ui.button("New File");
                 });
             },
         };
     }
+
+    /// Items shared by files and folders, `folder` receives new files and pasted items.
+    fn entry_menu(ui: &mut egui::Ui, path: &str, folder: &str, clipboard: Option<&String>, actions: &mut Vec<FileAction>) {
+        if ui.button("Delete").clicked() {
+            actions.push(FileAction::Delete(path.to_string()));
+            ui.close_menu();
+        }
+        if ui.button("Rename").clicked() {
+            let name = path.rsplit('/').next().unwrap_or_default().to_string();
+            actions.push(FileAction::Prompt(NamePrompt { kind: PromptKind::Rename, path: path.to_string(), text: name }));
+            ui.close_menu();
+        }
+        if ui.button("Cut").clicked() {
+            actions.push(FileAction::Cut(path.to_string()));
+            ui.close_menu();
+        }
+        Self::folder_menu(ui, folder, clipboard, actions);
+    }
+
+    fn folder_menu(ui: &mut egui::Ui, folder: &str, clipboard: Option<&String>, actions: &mut Vec<FileAction>) {
+        if let Some(cut) = clipboard {
+            if ui.button("Paste").on_hover_text(format!("Move \"{}\" here", cut)).clicked() {
+                actions.push(FileAction::Move { path: cut.clone(), destination: folder.to_string() });
+                ui.close_menu();
+            }
+        }
+        ui.separator();
+        if ui.button("New Folder").clicked() {
+            actions.push(FileAction::Prompt(NamePrompt { kind: PromptKind::NewFolder, path: folder.to_string(), text: String::new() }));
+            ui.close_menu();
+        }
+        if ui.button("New File").clicked() {
+            actions.push(FileAction::Prompt(NamePrompt { kind: PromptKind::NewFile, path: folder.to_string(), text: String::new() }));
+            ui.close_menu();
+        }
+    }
+}
+
+fn parent_of(path: &str) -> &str {
+    path.rsplit_once('/').map(|(parent, _)| parent).unwrap_or_default()
 }
@@ -51,48 +51,49 @@ impl<'a, 'b> Widget for LoggerWindow<'a, 'b> {
                     }
                 });
                 ui.add_space(10f32);
-                let logger = self.tab.app.core.get_plugin::<Logger>().unwrap();
-                TableBuilder::new(ui)
-                    .auto_shrink(Vec2b::new(false, false))
-                    .column(Column::exact(40f32).resizable(true))
-                    .column(Column::auto().at_least(50.0f32).resizable(true))
-                    .column(Column::auto().at_least(30.0f32).resizable(true))
-                    .column(Column::auto().at_least(100f32).resizable(true))
-                    .stick_to_bottom(*follow_to_bottom)
-                    .let_self(|mut me| {
-                        if *force_follow_to_bottom {
-                            me = me.scroll_to_row(logger.logs.len(), None);
-                        }
-                        me
-                    })
-                    .header(20.0f32, |mut header| {
-                        header.col(|ui| {
-                            ui.label("Index");
-                        });
-                        header.col(|ui| {
-                            ui.label("Sender");
-                        });
-                        header.col(|ui| {
-                            ui.label("Category");
-                        });
-                        header.col(|ui| {
-                            ui.label("Content");
-                        });
-                }).body(|mut body|{
-                    body.rows(20.0f32, logger.logs.len(), |mut rows|{
-                        let row_index = rows.index();
-                        let (sender, category, content) = &logger.logs[row_index];
-                        rows.col(|ui|{
-                            ui.label(format!("{}", row_index));
-                        });
-                        rows.col(|ui|{
-                            ui.label(sender);
-                        });
-                        rows.col(|ui|{
-                            ui.label(category);
-                        });
-                        rows.col(|ui|{
-                            ui.label(content);
+                let _ = self.tab.app.core.with_plugin(|logger: &Logger| {
+                    TableBuilder::new(ui)
+                        .auto_shrink(Vec2b::new(false, false))
+                        .column(Column::exact(40f32).resizable(true))
+                        .column(Column::auto().at_least(50.0f32).resizable(true))
+                        .column(Column::auto().at_least(30.0f32).resizable(true))
+                        .column(Column::auto().at_least(100f32).resizable(true))
+                        .stick_to_bottom(*follow_to_bottom)
+                        .let_self(|mut me| {
+                            if *force_follow_to_bottom {
+                                me = me.scroll_to_row(logger.logs.len(), None);
+                            }
+                            me
+                        })
+                        .header(20.0f32, |mut header| {
+                            header.col(|ui| {
+                                ui.label("Index");
+                            });
+                            header.col(|ui| {
+                                ui.label("Sender");
+                            });
+                            header.col(|ui| {
+                                ui.label("Category");
+                            });
+                            header.col(|ui| {
+                                ui.label("Content");
+                            });
+                    }).body(|body|{
+                        body.rows(20.0f32, logger.logs.len(), |mut rows|{
+                            let row_index = rows.index();
+                            let (sender, category, content) = &logger.logs[row_index];
+                            rows.col(|ui|{
+                                ui.label(format!("{}", row_index));
+                            });
+                            rows.col(|ui|{
+                                ui.label(sender);
+                            });
+                            rows.col(|ui|{
+                                ui.label(category);
+                            });
+                            rows.col(|ui|{
+                                ui.label(content);
+                            });
                         });
                     });
                 });
@@ -104,97 +105,99 @@ impl<'a, 'b> Widget for LoggerWindow<'a, 'b> {
                 //     }
                 // });
                 ui.add_space(10f32);
-                let logger = self.tab.app.core.get_plugin::<SystemEventInterceptor>().unwrap();
-                TableBuilder::new(ui)
-                    .auto_shrink(Vec2b::new(false, false))
-                    .column(Column::exact(40f32).resizable(true))
-                    .column(Column::auto().at_least(100.0f32).resizable(true))
-                    .column(Column::auto().at_least(60.0f32).resizable(true))
-                    .column(Column::auto().at_least(100f32).resizable(true))
-                    .stick_to_bottom(*follow_to_bottom)
-                    .let_self(|mut me| {
-                        if *force_follow_to_bottom {
-                            me = me.scroll_to_row(logger.collected_events.len(), None);
-                        }
-                        me
-                    })
-                    .header(20.0f32, |mut header| {
-                        header.col(|ui| {
-                            ui.label("Index");
-                        });
-                        header.col(|ui| {
-                            ui.label("Sender");
-                        });
-                        header.col(|ui| {
-                            ui.label("Action");
-                        });
-                        header.col(|ui| {
-                            ui.label("Aegs");
-                        });
-                }).body(|mut body|{
-                    body.rows(20.0f32, logger.collected_events.len(), |mut rows|{
-                        let row_index = rows.index();
-                        let event = &logger.collected_events[row_index];
-                        rows.col(|ui|{
-                            ui.label(format!("{}", row_index));
-                        });
-                        rows.col(|ui|{
-                            ui.label(&event.sender);
-                        });
-                        rows.col(|ui|{
-                            ui.label(&event.action);
-                        });
-                        rows.col(|ui|{
-                            ui.label(serde_json::to_string(&event.args).unwrap().as_str());
+                let _ = self.tab.app.core.with_plugin(|logger: &SystemEventInterceptor| {
+                    TableBuilder::new(ui)
+                        .auto_shrink(Vec2b::new(false, false))
+                        .column(Column::exact(40f32).resizable(true))
+                        .column(Column::auto().at_least(100.0f32).resizable(true))
+                        .column(Column::auto().at_least(60.0f32).resizable(true))
+                        .column(Column::auto().at_least(100f32).resizable(true))
+                        .stick_to_bottom(*follow_to_bottom)
+                        .let_self(|mut me| {
+                            if *force_follow_to_bottom {
+                                me = me.scroll_to_row(logger.collected_events.len(), None);
+                            }
+                            me
+                        })
+                        .header(20.0f32, |mut header| {
+                            header.col(|ui| {
+                                ui.label("Index");
+                            });
+                            header.col(|ui| {
+                                ui.label("Sender");
+                            });
+                            header.col(|ui| {
+                                ui.label("Action");
+                            });
+                            header.col(|ui| {
+                                ui.label("Aegs");
+                            });
+                    }).body(|body|{
+                        body.rows(20.0f32, logger.collected_events.len(), |mut rows|{
+                            let row_index = rows.index();
+                            let event = &logger.collected_events[row_index];
+                            rows.col(|ui|{
+                                ui.label(format!("{}", row_index));
+                            });
+                            rows.col(|ui|{
+                                ui.label(&event.sender);
+                            });
+                            rows.col(|ui|{
+                                ui.label(&event.action);
+                            });
+                            rows.col(|ui|{
+                                ui.label(serde_json::to_string(&event.args).unwrap().as_str());
+                            });
                         });
                     });
                 });
             }
             if *log_mode == 2 {
                 ui.add_space(10f32);
-                let logger = self.tab.app.core.get_plugin::<Logger>().unwrap();
-                let logs: Vec<_> = logger.logs.iter().filter(|item| item.0.as_str() == "JavaScriptEngine").collect();
-                TableBuilder::new(ui)
-                    .auto_shrink(Vec2b::new(false, false))
-                    .column(Column::exact(40f32).resizable(true))
-                    .column(Column::auto().at_least(50.0f32).resizable(true))
-                    .column(Column::auto().at_least(30.0f32).resizable(true))
-                    .column(Column::auto().at_least(100f32).resizable(true))
-                    .stick_to_bottom(*follow_to_bottom)
-                    .let_self(|mut me| {
-                        if *force_follow_to_bottom {
-                            me = me.scroll_to_row(logs.len(), None);
-                        }
-                        me
-                    })
-                    .header(20.0f32, |mut header| {
-                        header.col(|ui| {
-                            ui.label("Index");
-                        });
-                        header.col(|ui| {
-                            ui.label("Sender");
-                        });
-                        header.col(|ui| {
-                            ui.label("Category");
-                        });
-                        header.col(|ui| {
-                            ui.label("Content");
-                        });
-                }).body(|mut body|{
-                    body.rows(20.0f32, logs.len(), |mut rows|{
-                        let row_index = rows.index();
-                        let (sender, category, content) = &logs[row_index];
-                        rows.col(|ui|{
-                            ui.label(format!("{}", row_index));
-                        });
-                        rows.col(|ui|{
-                            ui.label(sender);
-                        });
-                        rows.col(|ui|{
-                            ui.label(category);
-                        });
-                        rows.col(|ui|{
-                            ui.label(content);
+                let _ = self.tab.app.core.with_plugin(|logger: &Logger| {
+                    let logs: Vec<_> = logger.logs.iter().filter(|item| item.0.as_str() == "JavaScriptEngine").collect();
+                    TableBuilder::new(ui)
+                        .auto_shrink(Vec2b::new(false, false))
+                        .column(Column::exact(40f32).resizable(true))
+                        .column(Column::auto().at_least(50.0f32).resizable(true))
+                        .column(Column::auto().at_least(30.0f32).resizable(true))
+                        .column(Column::auto().at_least(100f32).resizable(true))
+                        .stick_to_bottom(*follow_to_bottom)
+                        .let_self(|mut me| {
+                            if *force_follow_to_bottom {
+                                me = me.scroll_to_row(logs.len(), None);
+                            }
+                            me
+                        })
+                        .header(20.0f32, |mut header| {
+                            header.col(|ui| {
+                                ui.label("Index");
+                            });
+                            header.col(|ui| {
+                                ui.label("Sender");
+                            });
+                            header.col(|ui| {
+                                ui.label("Category");
+                            });
+                            header.col(|ui| {
+                                ui.label("Content");
+                            });
+                    }).body(|body|{
+                        body.rows(20.0f32, logs.len(), |mut rows|{
+                            let row_index = rows.index();
+                            let (sender, category, content) = &logs[row_index];
+                            rows.col(|ui|{
+                                ui.label(format!("{}", row_index));
+                            });
+                            rows.col(|ui|{
+                                ui.label(sender);
+                            });
+                            rows.col(|ui|{
+                                ui.label(category);
+                            });
+                            rows.col(|ui|{
+                                ui.label(content);
+                            });
                         });
                     });
                 });
@@ -9,4 +9,12 @@
 pub mod welcome_page;
 pub mod file_manager;
 pub mod logger_window;
-pub mod library_window;
\ No newline at end of file
+pub mod library_window;
+pub mod extensions_window;
+pub mod contributed_window;
+pub mod project_settings_window;
+pub mod preferences_window;
+pub mod code_editor_window;
+pub mod search_window;
+
+pub mod node_editor_window;
\ No newline at end of file
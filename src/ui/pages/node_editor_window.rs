@@ -0,0 +1,470 @@
+use egui::{ecolor::Hsva, epaint::CubicBezierShape, Align2, Color32, CursorIcon, Event, FontId, Key, PointerButton, Pos2, Rect, Rounding, Sense, Stroke, Vec2, Widget};
+use egui_hooks::UseHookExt;
+
+use crate::{backend::{node_graph::{Graph, GraphClipboard, GraphError, NodeId, NodeType, PortDefinition, PortRef, ANY_TYPE}, plugins::node_graph_manager::{GraphEdit, NodeGraphCommand, NodeGraphManager, NodeGraphRequest}}, dock_manager::MyTab};
+
+/// Sizes on the canvas, multiplied by the zoom when painted.
+const NODE_WIDTH: f32 = 170.;
+const HEADER_HEIGHT: f32 = 24.;
+const ROW_HEIGHT: f32 = 20.;
+const PORT_RADIUS: f32 = 5.;
+const GRID_SPACING: f32 = 32.;
+
+pub struct NodeEditorWindow<'a, 'b> {
+    pub tab: &'a mut MyTab<'b>,
+    pub file_name: &'a str,
+}
+
+/// Kept between frames in the temporary data of the editor.
+#[derive(Debug, Clone)]
+struct CanvasView {
+    /// Canvas position shown at the top left corner of the editor.
+    offset: Vec2,
+    zoom: f32,
+    selection: Vec<NodeId>,
+    drag: Option<CanvasDrag>,
+    /// Where the context menu was opened, on the canvas, and the input port under it.
+    menu: Option<(Vec2, Option<PortRef>)>,
+}
+
+impl Default for CanvasView {
+    fn default() -> Self {
+        Self { offset: Vec2::ZERO, zoom: 1., selection: vec![], drag: None, menu: None }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum CanvasDrag {
+    /// Moving the selected nodes, `delta` is on the canvas.
+    Nodes { delta: Vec2 },
+    /// A new connection started from a port, `output` tells which side of its node.
+    Connection { port: PortRef, output: bool, port_type: String },
+    /// Selecting the nodes in a rectangle, from a point on the screen.
+    Select { start: Pos2 },
+    Pan,
+}
+
+/// Maps canvas positions to the screen and back.
+#[derive(Debug, Clone, Copy)]
+struct Transform {
+    origin: Pos2,
+    offset: Vec2,
+    zoom: f32,
+}
+
+impl Transform {
+    fn to_screen(self, position: Vec2) -> Pos2 {
+        self.origin + (position - self.offset) * self.zoom
+    }
+
+    fn to_canvas(self, position: Pos2) -> Vec2 {
+        (position - self.origin) / self.zoom + self.offset
+    }
+}
+
+/// The connection being dragged from a port to the pointer.
+struct PendingConnection {
+    port: PortRef,
+    output: bool,
+    port_type: String,
+    pointer: Pos2,
+    /// The output and input it would connect once dropped on the port under the pointer, or why it cannot.
+    check: Option<Result<(PortRef, PortRef), GraphError>>,
+}
+
+/// A node placed on the screen for painting and hit testing.
+struct NodeLayout {
+    id: NodeId,
+    rect: Rect,
+    node_type: NodeType,
+    /// No enabled plugin defines the type, the node is shown with the ports its connections use.
+    known: bool,
+    inputs: Vec<Pos2>,
+    outputs: Vec<Pos2>,
+}
+
+impl NodeLayout {
+    fn input(&self, port: &str) -> Option<Pos2> {
+        self.node_type.inputs.iter().position(|definition| definition.id == port).map(|index| self.inputs[index])
+    }
+
+    fn output(&self, port: &str) -> Option<Pos2> {
+        self.node_type.outputs.iter().position(|definition| definition.id == port).map(|index| self.outputs[index])
+    }
+}
+
+impl<'a, 'b> Widget for NodeEditorWindow<'a, 'b> {
+    fn ui(mut self, ui: &mut egui::Ui) -> egui::Response {
+        let path = self.file_name.to_string();
+        let open_requested = ui.use_state(|| false, path.clone());
+        let status = self.tab.app.core.with_plugin(|graphs: &NodeGraphManager| graphs.graph(&path).map(|open| (open.graph.clone(), open.dirty()))).ok().flatten();
+        let Some((graph, dirty)) = status else {
+            // a tab restored with the dock layout, its graph is read the first time it is shown.
+            if !*open_requested {
+                open_requested.set_next(true);
+                self.tab.app.publish_command("NodeEditor", &NodeGraphCommand::Open { path });
+                return ui.spinner();
+            }
+            return ui.label(format!("\"{}\" cannot be opened, see the log window for the reason.", path));
+        };
+        let types = self.tab.app.core.request::<Vec<NodeType>>("NodeEditor", &NodeGraphRequest::NodeTypes).unwrap_or_default();
+        let id = egui::Id::new(("node_editor", path.as_str()));
+        let mut view = ui.data_mut(|data| data.remove_temp::<CanvasView>(id)).unwrap_or_default();
+        view.selection.retain(|selected| graph.node(*selected).is_some());
+
+        let response = ui.vertical(|ui| {
+            ui.horizontal(|ui| {
+                if ui.add_enabled(dirty, egui::Button::new("Save")).clicked() {
+                    self.tab.app.publish_command("NodeEditor", &NodeGraphCommand::Save { path: path.clone() });
+                }
+                if dirty {
+                    ui.label("●").on_hover_text("Unsaved changes");
+                }
+                ui.weak(&path);
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if ui.button("Reset view").clicked() {
+                        view.offset = Vec2::ZERO;
+                        view.zoom = 1.;
+                    }
+                    ui.weak(format!("{:.0}%", view.zoom * 100.));
+                    ui.weak(format!("{} nodes, {} selected", graph.nodes.len(), view.selection.len()));
+                });
+            });
+            ui.separator();
+            self.show_canvas(ui, id, &path, &graph, &types, &mut view);
+        }).response;
+        ui.data_mut(|data| data.insert_temp(id, view));
+        response
+    }
+}
+
+impl<'a, 'b> NodeEditorWindow<'a, 'b> {
+    fn show_canvas(&mut self, ui: &mut egui::Ui, id: egui::Id, path: &str, graph: &Graph, types: &[NodeType], view: &mut CanvasView) {
+        let (rect, _) = ui.allocate_exact_size(ui.available_size(), Sense::hover());
+        let response = ui.interact(rect, id, Sense::click_and_drag());
+        let pointer = response.hover_pos();
+
+        // wheel pans, Ctrl + wheel or pinching zooms around the pointer.
+        if let Some(pointer) = pointer {
+            let (zoom_delta, scroll) = ui.input(|input| (input.zoom_delta(), input.smooth_scroll_delta));
+            if zoom_delta != 1. {
+                let anchor = Transform { origin: rect.min, offset: view.offset, zoom: view.zoom }.to_canvas(pointer);
+                view.zoom = (view.zoom * zoom_delta).clamp(0.25, 3.);
+                view.offset = anchor - (pointer - rect.min) / view.zoom;
+            } else {
+                view.offset -= scroll / view.zoom;
+            }
+        }
+        let transform = Transform { origin: rect.min, offset: view.offset, zoom: view.zoom };
+        let moved = match view.drag {
+            Some(CanvasDrag::Nodes { delta }) => delta,
+            _ => Vec2::ZERO,
+        };
+        let layouts = graph.nodes.iter().map(|node| {
+            let delta = if view.selection.contains(&node.id) {moved} else {Vec2::ZERO};
+            layout_node(graph, types, node.id, transform, delta)
+        }).collect::<Vec<_>>();
+        let modifiers = ui.input(|input| input.modifiers);
+
+        // pointer: dragging a port connects, a node moves the selection, the background selects in a rectangle.
+        if response.drag_started_by(PointerButton::Primary) {
+            response.request_focus();
+            let origin = ui.input(|input| input.pointer.press_origin()).unwrap_or(rect.center());
+            if let Some((port, output, port_type)) = port_at(&layouts, origin, view.zoom) {
+                view.drag = Some(CanvasDrag::Connection { port, output, port_type });
+            } else if let Some(node) = node_at(&layouts, origin) {
+                if !view.selection.contains(&node) {
+                    if !(modifiers.command || modifiers.shift) {
+                        view.selection.clear();
+                    }
+                    view.selection.push(node);
+                }
+                view.drag = Some(CanvasDrag::Nodes { delta: Vec2::ZERO });
+            } else {
+                view.drag = Some(CanvasDrag::Select { start: origin });
+            }
+        } else if response.drag_started_by(PointerButton::Middle) {
+            view.drag = Some(CanvasDrag::Pan);
+        }
+        match &mut view.drag {
+            Some(CanvasDrag::Nodes { delta }) => *delta += response.drag_delta() / view.zoom,
+            Some(CanvasDrag::Pan) => view.offset -= response.drag_delta() / view.zoom,
+            _ => {},
+        }
+        if response.dragged() {
+            ui.ctx().set_cursor_icon(if matches!(view.drag, Some(CanvasDrag::Pan) | Some(CanvasDrag::Nodes { .. })) {CursorIcon::Grabbing} else {CursorIcon::Crosshair});
+        }
+        let mut pending = None;
+        if let (Some(CanvasDrag::Connection { port, output, port_type }), Some(pointer)) = (&view.drag, pointer) {
+            let target = port_at(&layouts, pointer, view.zoom).filter(|(_, target_output, _)| target_output != output);
+            let check = target.as_ref().map(|(target, _, _)| {
+                let (from, to) = if *output {(port.clone(), target.clone())} else {(target.clone(), port.clone())};
+                graph.check_connection(types, &from, &to).map(|_| (from, to))
+            });
+            pending = Some(PendingConnection { port: port.clone(), output: *output, port_type: port_type.clone(), pointer, check });
+        }
+        if response.drag_stopped() {
+            match view.drag.take() {
+                Some(CanvasDrag::Nodes { delta }) if delta != Vec2::ZERO => {
+                    self.edit(path, GraphEdit::MoveNodes { nodes: view.selection.clone(), delta: [delta.x, delta.y] });
+                },
+                Some(CanvasDrag::Connection { .. }) => {
+                    if let Some(PendingConnection { check: Some(Ok((from, to))), .. }) = pending.take() {
+                        self.edit(path, GraphEdit::Connect { from, to });
+                    }
+                },
+                Some(CanvasDrag::Select { start }) => {
+                    let end = pointer.or(ui.input(|input| input.pointer.interact_pos())).unwrap_or(start);
+                    let area = Rect::from_two_pos(start, end);
+                    if !(modifiers.command || modifiers.shift) {
+                        view.selection.clear();
+                    }
+                    let inside = layouts.iter().filter(|layout| layout.rect.intersects(area) && !view.selection.contains(&layout.id)).map(|layout| layout.id).collect::<Vec<_>>();
+                    view.selection.extend(inside);
+                },
+                _ => {},
+            }
+        }
+        if response.clicked() {
+            response.request_focus();
+            match pointer.and_then(|pointer| node_at(&layouts, pointer)) {
+                Some(node) if modifiers.command || modifiers.shift => {
+                    if view.selection.contains(&node) {
+                        view.selection.retain(|selected| *selected != node);
+                    } else {
+                        view.selection.push(node);
+                    }
+                },
+                Some(node) => view.selection = vec![node],
+                None => view.selection.clear(),
+            }
+        }
+        if response.secondary_clicked() {
+            if let Some(pointer) = pointer {
+                let input = port_at(&layouts, pointer, view.zoom).filter(|(_, output, _)| !*output).map(|(port, _, _)| port);
+                view.menu = Some((transform.to_canvas(pointer), input.filter(|port| graph.connections.iter().any(|connection| connection.to == *port))));
+            }
+        }
+
+        // keyboard: delete, select all, and copy or paste the selected nodes as JSON.
+        if response.has_focus() {
+            let events = ui.input(|input| input.events.clone());
+            for event in &events {
+                match event {
+                    Event::Copy | Event::Cut if !view.selection.is_empty() => {
+                        ui.ctx().copy_text(serde_json::to_string(&graph.copy(&view.selection)).unwrap());
+                        if *event == Event::Cut {
+                            self.edit(path, GraphEdit::RemoveNodes { nodes: std::mem::take(&mut view.selection) });
+                        }
+                    },
+                    Event::Paste(text) => {
+                        if let Ok(clipboard) = serde_json::from_str::<GraphClipboard>(text) {
+                            let target = pointer.map(|pointer| transform.to_canvas(pointer));
+                            self.paste(path, graph, clipboard, target, view);
+                        }
+                    },
+                    Event::Key { key: Key::Delete | Key::Backspace, pressed: true, .. } if !view.selection.is_empty() => {
+                        self.edit(path, GraphEdit::RemoveNodes { nodes: std::mem::take(&mut view.selection) });
+                    },
+                    Event::Key { key: Key::A, pressed: true, modifiers, .. } if modifiers.command => {
+                        view.selection = graph.nodes.iter().map(|node| node.id).collect();
+                    },
+                    Event::Key { key: Key::Escape, pressed: true, .. } => view.selection.clear(),
+                    _ => {},
+                }
+            }
+        }
+
+        response.context_menu(|ui| self.context_menu(ui, path, graph, types, view));
+        paint(ui, rect, graph, &layouts, view, transform, pending.as_ref());
+    }
+
+    fn context_menu(&mut self, ui: &mut egui::Ui, path: &str, graph: &Graph, types: &[NodeType], view: &mut CanvasView) {
+        let Some((position, input)) = view.menu.clone() else {
+            ui.close_menu();
+            return;
+        };
+        if let Some(to) = input {
+            if ui.button("Disconnect").clicked() {
+                self.edit(path, GraphEdit::Disconnect { to });
+                ui.close_menu();
+            }
+            ui.separator();
+        }
+        if ui.add_enabled(!view.selection.is_empty(), egui::Button::new("Copy")).clicked() {
+            ui.ctx().copy_text(serde_json::to_string(&graph.copy(&view.selection)).unwrap());
+            ui.close_menu();
+        }
+        if ui.add_enabled(!view.selection.is_empty(), egui::Button::new("Delete")).clicked() {
+            self.edit(path, GraphEdit::RemoveNodes { nodes: std::mem::take(&mut view.selection) });
+            ui.close_menu();
+        }
+        ui.separator();
+        if types.is_empty() {
+            ui.label("No plugin defines node types.");
+        }
+        let mut categories = types.iter().map(|node_type| node_type.category.as_str()).collect::<Vec<_>>();
+        categories.sort();
+        categories.dedup();
+        for category in categories {
+            let title = if category.is_empty() {"Other"} else {category};
+            ui.menu_button(title, |ui| {
+                for node_type in types.iter().filter(|node_type| node_type.category == category) {
+                    if ui.button(&node_type.title).clicked() {
+                        self.edit(path, GraphEdit::AddNode { type_id: node_type.id.clone(), position: [position.x, position.y] });
+                        ui.close_menu();
+                    }
+                }
+            });
+        }
+    }
+
+    /// Adds the copied nodes at `target`, or next to the copied ones, and selects them.
+    fn paste(&mut self, path: &str, graph: &Graph, clipboard: GraphClipboard, target: Option<Vec2>, view: &mut CanvasView) {
+        if clipboard.nodes.is_empty() {
+            return;
+        }
+        let corner = clipboard.nodes.iter().fold(Vec2::splat(f32::MAX), |corner, node| corner.min(Vec2::new(node.position[0], node.position[1])));
+        let offset = target.map(|target| target - corner).unwrap_or(Vec2::splat(GRID_SPACING));
+        // pasted nodes get the ids from `next_id` on, in the order of the clipboard.
+        let first = graph.next_id();
+        view.selection = (first..first + clipboard.nodes.len() as NodeId).collect();
+        self.edit(path, GraphEdit::Paste { clipboard, offset: [offset.x, offset.y] });
+    }
+
+    fn edit(&mut self, path: &str, edit: GraphEdit) {
+        self.tab.app.publish_command("NodeEditor", &NodeGraphCommand::Edit { path: path.to_string(), edit });
+    }
+}
+
+fn layout_node(graph: &Graph, types: &[NodeType], id: NodeId, transform: Transform, delta: Vec2) -> NodeLayout {
+    let node = graph.node(id).unwrap();
+    let (node_type, known) = match types.iter().find(|node_type| node_type.id == node.type_id) {
+        Some(node_type) => (node_type.clone(), true),
+        None => (unknown_node_type(graph, id, &node.type_id), false),
+    };
+    let rows = node_type.inputs.len().max(node_type.outputs.len()) as f32;
+    let min = transform.to_screen(Vec2::new(node.position[0], node.position[1]) + delta);
+    let rect = Rect::from_min_size(min, Vec2::new(NODE_WIDTH, HEADER_HEIGHT + rows * ROW_HEIGHT + 6.) * transform.zoom);
+    let row = |index: usize| rect.top() + (HEADER_HEIGHT + (index as f32 + 0.5) * ROW_HEIGHT) * transform.zoom;
+    NodeLayout {
+        id,
+        rect,
+        inputs: (0..node_type.inputs.len()).map(|index| Pos2::new(rect.left(), row(index))).collect(),
+        outputs: (0..node_type.outputs.len()).map(|index| Pos2::new(rect.right(), row(index))).collect(),
+        node_type,
+        known,
+    }
+}
+
+/// Ports of a node whose type is not defined, taken from its connections so they stay visible.
+fn unknown_node_type(graph: &Graph, id: NodeId, type_id: &str) -> NodeType {
+    // an input has a single connection, an output may have several.
+    let inputs = graph.connections.iter().filter(|connection| connection.to.node == id).map(|connection| connection.to.port.clone()).collect::<Vec<_>>();
+    let mut outputs = graph.connections.iter().filter(|connection| connection.from.node == id).map(|connection| connection.from.port.clone()).collect::<Vec<_>>();
+    outputs.sort();
+    outputs.dedup();
+    let ports = |ports: Vec<String>| ports.iter().map(|port| PortDefinition::new(port, port, ANY_TYPE)).collect();
+    NodeType { id: type_id.to_string(), title: type_id.to_string(), category: String::new(), inputs: ports(inputs), outputs: ports(outputs), owner: String::new() }
+}
+
+/// The port under `position`, whether it is an output and its type.
+fn port_at(layouts: &[NodeLayout], position: Pos2, zoom: f32) -> Option<(PortRef, bool, String)> {
+    let reach = (PORT_RADIUS + 4.) * zoom;
+    layouts.iter().rev().find_map(|layout| {
+        let side = |ports: &[Pos2], definitions: &[PortDefinition], output: bool| ports.iter().zip(definitions)
+            .find(|(port, _)| port.distance(position) <= reach)
+            .map(|(_, definition)| (PortRef::new(layout.id, &definition.id), output, definition.port_type.clone()));
+        side(&layout.inputs, &layout.node_type.inputs, false).or_else(|| side(&layout.outputs, &layout.node_type.outputs, true))
+    })
+}
+
+/// The topmost node under `position`, nodes painted later are on top.
+fn node_at(layouts: &[NodeLayout], position: Pos2) -> Option<NodeId> {
+    layouts.iter().rev().find(|layout| layout.rect.contains(position)).map(|layout| layout.id)
+}
+
+/// Every port type gets its own color, ports of `ANY_TYPE` are gray.
+fn type_color(ui: &egui::Ui, port_type: &str) -> Color32 {
+    if port_type == ANY_TYPE {
+        return ui.visuals().weak_text_color();
+    }
+    let hash = port_type.bytes().fold(7u32, |hash, byte| hash.wrapping_mul(31).wrapping_add(byte as u32));
+    Hsva::new((hash % 360) as f32 / 360., 0.55, 0.85, 1.).into()
+}
+
+fn connection_shape(from: Pos2, to: Pos2, zoom: f32, stroke: Stroke) -> CubicBezierShape {
+    let bend = ((to.x - from.x).abs() / 2.).max(40. * zoom);
+    CubicBezierShape::from_points_stroke([from, from + Vec2::new(bend, 0.), to - Vec2::new(bend, 0.), to], false, Color32::TRANSPARENT, stroke)
+}
+
+fn paint(ui: &egui::Ui, rect: Rect, graph: &Graph, layouts: &[NodeLayout], view: &CanvasView, transform: Transform, pending: Option<&PendingConnection>) {
+    let painter = ui.painter_at(rect);
+    let visuals = ui.visuals();
+    painter.rect_filled(rect, 0., visuals.extreme_bg_color);
+    let spacing = GRID_SPACING * transform.zoom;
+    let start = transform.to_screen((transform.offset / GRID_SPACING).floor() * GRID_SPACING);
+    let grid = Stroke::new(1., visuals.faint_bg_color);
+    let mut x = start.x;
+    while x < rect.right() {
+        painter.line_segment([Pos2::new(x, rect.top()), Pos2::new(x, rect.bottom())], grid);
+        x += spacing;
+    }
+    let mut y = start.y;
+    while y < rect.bottom() {
+        painter.line_segment([Pos2::new(rect.left(), y), Pos2::new(rect.right(), y)], grid);
+        y += spacing;
+    }
+
+    let width = 2. * transform.zoom.max(0.5);
+    for connection in &graph.connections {
+        let from = layouts.iter().find(|layout| layout.id == connection.from.node).and_then(|layout| layout.output(&connection.from.port).map(|position| (position, layout)));
+        let to = layouts.iter().find(|layout| layout.id == connection.to.node).and_then(|layout| layout.input(&connection.to.port));
+        if let (Some((from, layout)), Some(to)) = (from, to) {
+            let port_type = layout.node_type.output(&connection.from.port).map(|port| port.port_type.as_str()).unwrap_or(ANY_TYPE);
+            painter.add(connection_shape(from, to, transform.zoom, Stroke::new(width, type_color(ui, port_type))));
+        }
+    }
+
+    let title_font = FontId::proportional(13. * transform.zoom);
+    let port_font = FontId::proportional(12. * transform.zoom);
+    let rounding = 6. * transform.zoom;
+    for layout in layouts {
+        let selected = view.selection.contains(&layout.id);
+        let stroke = if selected {Stroke::new(2., visuals.selection.stroke.color)} else {visuals.window_stroke};
+        painter.rect(layout.rect, rounding, visuals.window_fill, stroke);
+        let header = Rect::from_min_size(layout.rect.min, Vec2::new(layout.rect.width(), HEADER_HEIGHT * transform.zoom));
+        painter.rect_filled(header.shrink(1.), Rounding { nw: rounding, ne: rounding, sw: 0., se: 0. }, visuals.faint_bg_color);
+        let title_color = if layout.known {visuals.strong_text_color()} else {visuals.error_fg_color};
+        painter.text(header.left_center() + Vec2::new(8. * transform.zoom, 0.), Align2::LEFT_CENTER, &layout.node_type.title, title_font.clone(), title_color);
+        for (position, definition) in layout.inputs.iter().zip(&layout.node_type.inputs) {
+            painter.circle(*position, PORT_RADIUS * transform.zoom, type_color(ui, &definition.port_type), Stroke::new(1., visuals.window_stroke.color));
+            painter.text(*position + Vec2::new(10. * transform.zoom, 0.), Align2::LEFT_CENTER, &definition.name, port_font.clone(), visuals.text_color());
+        }
+        for (position, definition) in layout.outputs.iter().zip(&layout.node_type.outputs) {
+            painter.circle(*position, PORT_RADIUS * transform.zoom, type_color(ui, &definition.port_type), Stroke::new(1., visuals.window_stroke.color));
+            painter.text(*position - Vec2::new(10. * transform.zoom, 0.), Align2::RIGHT_CENTER, &definition.name, port_font.clone(), visuals.text_color());
+        }
+    }
+
+    // green when the port under the pointer accepts the connection, red with the reason when it does not.
+    if let Some(pending) = pending {
+        let start = layouts.iter().find(|layout| layout.id == pending.port.node).and_then(|layout| if pending.output {layout.output(&pending.port.port)} else {layout.input(&pending.port.port)});
+        if let Some(start) = start {
+            let color = match &pending.check {
+                Some(Ok(_)) => visuals.selection.stroke.color,
+                Some(Err(_)) => visuals.error_fg_color,
+                None => type_color(ui, &pending.port_type),
+            };
+            let (from, to) = if pending.output {(start, pending.pointer)} else {(pending.pointer, start)};
+            painter.add(connection_shape(from, to, transform.zoom, Stroke::new(width, color)));
+            if let Some(Err(reason)) = &pending.check {
+                painter.text(pending.pointer + Vec2::new(14., 14.), Align2::LEFT_TOP, reason, FontId::proportional(12.), visuals.error_fg_color);
+            }
+        }
+    }
+    if let (Some(CanvasDrag::Select { start }), Some(pointer)) = (&view.drag, ui.input(|input| input.pointer.hover_pos())) {
+        let area = Rect::from_two_pos(*start, pointer);
+        painter.rect(area, 0., visuals.selection.bg_fill.gamma_multiply(0.3), visuals.selection.stroke);
+    }
+}
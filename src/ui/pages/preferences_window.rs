@@ -0,0 +1,110 @@
+use egui::{CollapsingHeader, DragValue, RichText, ScrollArea, Widget};
+use egui_hooks::UseHookExt;
+use serde_json::{json, Value};
+
+use crate::{backend::{plugin_settings::SettingScope, plugins::preferences::{PreferenceEntry, PreferenceKind, Preferences, PreferencesCommand}}, dock_manager::MyTab};
+
+pub struct PreferencesWindow<'a, 'b> {
+    pub tab: &'a mut MyTab<'b>
+}
+
+impl<'a, 'b> Widget for PreferencesWindow<'a, 'b> {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        let scope = ui.use_state(|| SettingScope::User, ());
+        let project_attached = self.tab.app.core.project_attached();
+
+        ui.vertical(|ui| {
+            ui.horizontal(|ui| {
+                ui.label("Apply changes to:");
+                if ui.selectable_label(*scope == SettingScope::User, "User").clicked() {
+                    scope.set_next(SettingScope::User);
+                }
+                ui.add_enabled_ui(project_attached, |ui| {
+                    if ui.selectable_label(*scope == SettingScope::Project, "Project").on_disabled_hover_text("Please open a project first.").clicked() {
+                        scope.set_next(SettingScope::Project);
+                    }
+                });
+            });
+            ui.separator();
+            let scope = if project_attached {*scope} else {SettingScope::User};
+
+            // (entry, effective value, value of the selected scope)
+            let sections = self.tab.app.core.with_plugin(|preferences: &Preferences| {
+                preferences.sections().iter().map(|section| {
+                    let entries = section.entries.iter().map(|entry| (entry.clone(), preferences.value(&entry.key).unwrap_or(Value::Null), preferences.value_in(&entry.key, scope).cloned())).collect::<Vec<_>>();
+                    (section.title.clone(), entries)
+                }).collect::<Vec<_>>()
+            }).unwrap_or_default();
+
+            let mut commands = vec![];
+            ScrollArea::vertical().auto_shrink([false, false]).show(ui, |ui| {
+                for (title, entries) in sections {
+                    CollapsingHeader::new(RichText::new(title).strong()).default_open(true).show(ui, |ui| {
+                        egui::Grid::new(ui.next_auto_id()).num_columns(3).striped(true).show(ui, |ui| {
+                            for (entry, value, overridden) in entries {
+                                ui.label(&entry.title).on_hover_text(&entry.description);
+                                if let Some(value) = value_editor(ui, &entry, value) {
+                                    commands.push(PreferencesCommand::Set { key: entry.key.clone(), value, scope });
+                                }
+                                if overridden.is_some() && ui.small_button("Reset").on_hover_text("Use the value inherited from the default or from the user preferences.").clicked() {
+                                    commands.push(PreferencesCommand::Reset { key: entry.key.clone(), scope });
+                                }
+                                ui.end_row();
+                            }
+                        });
+                    });
+                }
+            });
+            for command in commands {
+                self.tab.app.publish_command("PreferencesUI", &command);
+            }
+        }).response
+    }
+}
+
+/// Shows the widget matching the kind of the entry, returns the new value when it is edited.
+fn value_editor(ui: &mut egui::Ui, entry: &PreferenceEntry, value: Value) -> Option<Value> {
+    match &entry.kind {
+        PreferenceKind::Bool => {
+            let mut checked = value.as_bool().unwrap_or_default();
+            ui.checkbox(&mut checked, "").changed().then(|| json!(checked))
+        },
+        PreferenceKind::Integer { min, max } => {
+            let mut number = value.as_i64().unwrap_or(*min);
+            ui.add(DragValue::new(&mut number).range(*min..=*max)).changed().then(|| json!(number))
+        },
+        PreferenceKind::Float { min, max } => {
+            let mut number = value.as_f64().unwrap_or(*min);
+            ui.add(DragValue::new(&mut number).range(*min..=*max).speed(0.1)).changed().then(|| json!(number))
+        },
+        PreferenceKind::Text => {
+            let mut text = value.as_str().unwrap_or_default().to_string();
+            ui.text_edit_singleline(&mut text).changed().then(|| json!(text))
+        },
+        PreferenceKind::Keybinding => {
+            // a half typed shortcut is invalid, it is kept in memory and only sent once the field loses focus.
+            let draft_id = ui.id().with(&entry.key).with("draft");
+            let mut text = ui.data_mut(|data| data.get_temp::<String>(draft_id)).unwrap_or_else(|| value.as_str().unwrap_or_default().to_string());
+            let response = ui.text_edit_singleline(&mut text);
+            if response.lost_focus() {
+                ui.data_mut(|data| data.remove::<String>(draft_id));
+                let valid = entry.kind.validate(&json!(text)).is_ok();
+                return (valid && value.as_str() != Some(text.as_str())).then(|| json!(text));
+            }
+            if response.has_focus() {
+                ui.data_mut(|data| data.insert_temp(draft_id, text));
+            }
+            None
+        },
+        PreferenceKind::Choice(choices) => {
+            let mut selected = value.as_str().unwrap_or_default().to_string();
+            let before = selected.clone();
+            egui::ComboBox::from_id_source(&entry.key).selected_text(&selected).show_ui(ui, |ui| {
+                for choice in choices {
+                    ui.selectable_value(&mut selected, choice.clone(), choice);
+                }
+            });
+            (selected != before).then(|| json!(selected))
+        },
+    }
+}
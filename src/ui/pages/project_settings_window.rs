@@ -0,0 +1,64 @@
+use egui::{RichText, ScrollArea, Widget};
+use egui_hooks::UseHookExt;
+
+use crate::{backend::plugins::project_manager::{Project, ProjectManagerCommand, ProjectManagerRequest}, dock_manager::MyTab, project_window::ProjectEditorUi};
+
+/// `base` is the project the draft was started from, the draft is dirty while `edited` differs from it.
+#[derive(Clone)]
+struct ProjectDraft {
+    base: Project,
+    edited: Project,
+}
+
+pub struct ProjectSettingsWindow<'a, 'b> {
+    pub tab: &'a mut MyTab<'b>
+}
+
+impl<'a, 'b> Widget for ProjectSettingsWindow<'a, 'b> {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        let draft = ui.use_state(|| None::<ProjectDraft>, ());
+        let project = self.tab.app.core.request::<Option<Project>>("ProjectSettingsUI", &ProjectManagerRequest::Project).ok().flatten();
+        let Some(project) = project else {
+            if draft.is_some() {
+                draft.set_next(None);
+            }
+            return ui.label("Please open/or create a project first.");
+        };
+
+        let mut current = match (*draft).clone() {
+            // a saved or externally changed project replaces a draft without local changes.
+            Some(current) if current.base == project || (current.edited != current.base && current.edited != project) => current,
+            _ => ProjectDraft { base: project.clone(), edited: project.clone() },
+        };
+        let outdated = current.base != project;
+
+        let response = ui.vertical(|ui| {
+            ScrollArea::vertical().auto_shrink([false, true]).show(ui, |ui| {
+                let ctx = ui.ctx().clone();
+                current.edited.editor_ui(&ctx, ui);
+            });
+            ui.separator();
+            let problems = current.edited.validate();
+            for problem in &problems {
+                ui.colored_label(ui.visuals().error_fg_color, &problem.message);
+            }
+            if outdated {
+                ui.colored_label(ui.visuals().warn_fg_color, "The project file was changed elsewhere, saving overwrites those changes.");
+            }
+            let dirty = current.edited != current.base;
+            ui.horizontal(|ui| {
+                if ui.add_enabled(dirty && problems.is_empty(), egui::Button::new("Save")).clicked() {
+                    self.tab.app.publish_command("ProjectSettingsUI", &ProjectManagerCommand::UpdateProject { project: current.edited.clone() });
+                }
+                if ui.add_enabled(dirty || outdated, egui::Button::new("Revert")).clicked() {
+                    current = ProjectDraft { base: project.clone(), edited: project.clone() };
+                }
+                if dirty {
+                    ui.label(RichText::new("Unsaved changes").italics().weak());
+                }
+            });
+        }).response;
+        draft.set_next(Some(current));
+        response
+    }
+}
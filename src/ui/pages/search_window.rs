@@ -0,0 +1,163 @@
+use egui::{text::LayoutJob, CollapsingHeader, FontId, RichText, ScrollArea, TextEdit, TextFormat, TextStyle, Widget};
+use egui_hooks::UseHookExt;
+
+use crate::{backend::{plugins::{document_manager::DocumentManagerCommand, search_manager::{ReplacementPreview, SearchManager, SearchManagerCommand, SearchManagerRequest}}, search::{FileMatches, SearchOptions}}, dock_manager::MyTab};
+
+/// What the user typed, kept between frames. Globs are edited as comma separated text.
+#[derive(Debug, Clone, Default)]
+struct SearchForm {
+    options: SearchOptions,
+    include: String,
+    exclude: String,
+    replacement: String,
+    preview: bool,
+    /// Files left out of the replacement in the preview.
+    skipped: Vec<String>,
+}
+
+impl SearchForm {
+    fn new(options: SearchOptions) -> Self {
+        Self { include: options.include.join(", "), exclude: options.exclude.join(", "), options, ..Default::default() }
+    }
+
+    fn options(&self) -> SearchOptions {
+        let globs = |text: &str| text.split(',').map(str::trim).filter(|glob| !glob.is_empty()).map(str::to_string).collect();
+        SearchOptions { include: globs(&self.include), exclude: globs(&self.exclude), ..self.options.clone() }
+    }
+}
+
+pub struct SearchWindow<'a, 'b> {
+    pub tab: &'a mut MyTab<'b>
+}
+
+impl<'a, 'b> Widget for SearchWindow<'a, 'b> {
+    fn ui(mut self, ui: &mut egui::Ui) -> egui::Response {
+        let options = self.tab.app.core.with_plugin(|search: &SearchManager| search.options.clone()).unwrap_or_default();
+        let mut form = ui.use_state(|| SearchForm::new(options), ()).into_var();
+        // the preview is asked for again when the replacement or the results change.
+        let preview = ui.use_state(|| None::<((String, usize), Vec<ReplacementPreview>)>, ());
+        let Ok((results, searching, searched_files, truncated, match_count)) = self.tab.app.core.with_plugin(|search: &SearchManager| {
+            (search.results.clone(), search.searching, search.searched_files, search.truncated, search.match_count())
+        }) else {
+            return ui.label("The search manager is disabled.");
+        };
+
+        ui.vertical(|ui| {
+            let mut search = false;
+            ui.horizontal(|ui| {
+                let query = ui.add(TextEdit::singleline(&mut form.options.query).hint_text("Search").desired_width(ui.available_width() - 110.));
+                search |= query.lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter));
+                search |= ui.selectable_label(form.options.case_sensitive, "Aa").on_hover_text("Match case").clicked() && toggle(&mut form.options.case_sensitive);
+                search |= ui.selectable_label(form.options.whole_word, "W").on_hover_text("Match whole word").clicked() && toggle(&mut form.options.whole_word);
+                search |= ui.selectable_label(form.options.regex, ".*").on_hover_text("Use regular expression").clicked() && toggle(&mut form.options.regex);
+            });
+            ui.horizontal(|ui| {
+                ui.add(TextEdit::singleline(&mut form.replacement).hint_text("Replace").desired_width(ui.available_width() - 110.));
+                ui.toggle_value(&mut form.preview, "Preview");
+                if ui.add_enabled(match_count > 0 && !searching, egui::Button::new("Replace all")).clicked() {
+                    self.tab.app.publish_command("SearchUI", &SearchManagerCommand::Replace { replacement: form.replacement.clone(), skipped: form.skipped.clone() });
+                    form.preview = false;
+                    form.skipped.clear();
+                }
+            });
+            CollapsingHeader::new("Files to include and exclude").id_source("search_globs").show(ui, |ui| {
+                search |= ui.add(TextEdit::singleline(&mut form.include).hint_text("Include, such as src/**/*.js")).lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter));
+                search |= ui.add(TextEdit::singleline(&mut form.exclude).hint_text("Exclude, such as *.min.js, build/")).lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter));
+            });
+            if search && !form.options.query.is_empty() {
+                self.tab.app.publish_command("SearchUI", &SearchManagerCommand::Search(form.options()));
+                form.skipped.clear();
+            }
+            ui.horizontal(|ui| {
+                if searching {
+                    ui.spinner();
+                    ui.label(format!("{} matches in {} files, {} files searched", match_count, results.len(), searched_files));
+                    if ui.button("Cancel").clicked() {
+                        self.tab.app.publish_command("SearchUI", &SearchManagerCommand::Cancel);
+                    }
+                } else if !form.options.query.is_empty() {
+                    ui.weak(format!("{} matches in {} files", match_count, results.len()));
+                    if truncated {
+                        ui.colored_label(ui.visuals().warn_fg_color, "The search stopped early, narrow it down to see every match.");
+                    }
+                }
+            });
+            ui.separator();
+
+            let previews = if form.preview {
+                let key = (form.replacement.clone(), match_count);
+                match (*preview).clone() {
+                    Some((previous, lines)) if previous == key => lines,
+                    _ => {
+                        let lines = self.tab.app.core.request::<Vec<ReplacementPreview>>("SearchUI", &SearchManagerRequest::ReplacePreview { replacement: form.replacement.clone() }).unwrap_or_default();
+                        preview.set_next(Some((key, lines.clone())));
+                        lines
+                    },
+                }
+            } else {
+                vec![]
+            };
+            let font_id = TextStyle::Monospace.resolve(ui.style());
+            ScrollArea::both().auto_shrink([false, false]).show(ui, |ui| {
+                for file in &results {
+                    let header = RichText::new(format!("{}  ({})", file.path, file.matches.len()));
+                    CollapsingHeader::new(header).id_source(("search_result", file.path.as_str())).default_open(true).show(ui, |ui| {
+                        if form.preview {
+                            let mut replaced = !form.skipped.contains(&file.path);
+                            if ui.checkbox(&mut replaced, "Replace in this file").changed() {
+                                form.skipped.retain(|path| *path != file.path);
+                                if !replaced {
+                                    form.skipped.push(file.path.clone());
+                                }
+                            }
+                            for line in previews.iter().filter(|line| line.path == file.path) {
+                                ui.horizontal(|ui| {
+                                    ui.weak(format!("{:>5}", line.line + 1));
+                                    ui.label(RichText::new(line.before.trim()).font(font_id.clone()).strikethrough().color(ui.visuals().error_fg_color));
+                                    ui.label(RichText::new(line.after.trim()).font(font_id.clone()).color(ui.visuals().warn_fg_color));
+                                });
+                            }
+                        } else {
+                            self.show_matches(ui, file, &font_id);
+                        }
+                    });
+                }
+            });
+        }).response
+    }
+}
+
+impl<'a, 'b> SearchWindow<'a, 'b> {
+    /// One row per match, clicking it opens the file in a code editor with the match selected.
+    fn show_matches(&mut self, ui: &mut egui::Ui, file: &FileMatches, font_id: &FontId) {
+        for found in &file.matches {
+            let row = ui.horizontal(|ui| {
+                ui.weak(format!("{:>5}", found.line + 1));
+                ui.add(egui::Label::new(match_job(ui, &found.line_text, found.column, found.length, font_id)).selectable(false).sense(egui::Sense::click()))
+            }).inner;
+            if row.on_hover_cursor(egui::CursorIcon::PointingHand).clicked() {
+                self.tab.app.publish_command("SearchUI", &DocumentManagerCommand::Reveal { path: file.path.clone(), line: found.line, column: found.column, length: found.length });
+            }
+        }
+    }
+}
+
+/// Flips a flag that was clicked, always true so it can be chained after `clicked()`.
+fn toggle(flag: &mut bool) -> bool {
+    *flag = !*flag;
+    true
+}
+
+/// The line with the match highlighted, leading whitespace is left out.
+fn match_job(ui: &egui::Ui, line: &str, column: usize, length: usize, font_id: &FontId) -> LayoutJob {
+    let offset = |column: usize| line.char_indices().nth(column).map(|(index, _)| index).unwrap_or(line.len());
+    let (start, end) = (offset(column), offset(column + length));
+    let indent = line[..start].len() - line[..start].trim_start().len();
+    let normal = TextFormat { font_id: font_id.clone(), color: ui.visuals().text_color(), ..Default::default() };
+    let highlighted = TextFormat { background: ui.visuals().selection.bg_fill, color: ui.visuals().strong_text_color(), ..normal.clone() };
+    let mut job = LayoutJob::default();
+    job.append(&line[indent..start], 0., normal.clone());
+    job.append(&line[start..end], 0., highlighted);
+    job.append(&line[end..], 0., normal);
+    job
+}
@@ -1,10 +1,11 @@
-use std::ops::Deref;
+use std::time::{SystemTime, UNIX_EPOCH};
 
-use egui::{Layout, Response, Widget};
+use egui::{Layout, RichText, ScrollArea, Widget};
 use egui_extras::{Size, StripBuilder};
 use egui_hooks::UseHookExt;
+use serde_json::Value;
 
-use crate::dock_manager::MyTab;
+use crate::{backend::{plugin_settings::SettingScope, plugins::{preferences::{Preferences, PreferencesCommand, PreferencesRequest}, project_manager::{ProjectManagerCommand, ProjectManagerRequest, RecentProject}}, project_template::ProjectTemplate}, dock_manager::MyTab, project_window::ProjectManagerWindow};
 
 /**
  * @author مۇختەرجان مەخمۇت
@@ -19,19 +20,112 @@ pub struct WelcomePage<'a, 'b> {
 }
 impl<'a, 'b> Widget for WelcomePage<'a, 'b> {
     fn ui(self, ui: &mut egui::Ui) -> egui::Response {
-        let state = ui.use_state(|| 0.0f32, ());
-        let space = ui.available_size().y - *state;
-        ui.add_space(space / 2.0);
-        let response = ui.with_layout(Layout::top_down(egui::Align::Center), |ui| {
-            ui.heading("Welcome to BlueBird Integrated development environment!");
-            ui.add_space(20.);
-            ui.label("Create new project or open an existing one from upper [File] menu item.");
-        }).response;
-        ui.add_space(space / 2.0);
-        if *state == 0. {
-            state.set_next(response.interact_rect.height());
-            ui.ctx().request_repaint();
-        }
-        response
+        let templates = self.tab.app.core.request::<Vec<ProjectTemplate>>("WelcomePage", &ProjectManagerRequest::Templates).unwrap_or_default();
+        let reopen_last_project = self.tab.app.core.request::<Option<bool>>("WelcomePage", &PreferencesRequest::Value { key: String::from(Preferences::REOPEN_LAST_PROJECT) }).ok().flatten();
+
+        ScrollArea::vertical().auto_shrink([false, false]).show(ui, |ui| {
+            ui.add_space(40.);
+            ui.with_layout(Layout::top_down(egui::Align::Center), |ui| {
+                ui.heading("Welcome to BlueBird Integrated development environment!");
+                ui.add_space(20.);
+                ui.horizontal(|ui| {
+                    if ui.button(RichText::new("New project").size(16.)).clicked() {
+                        self.tab.app.show_new_project_dialog(None);
+                    }
+                    if ui.button(RichText::new("Open project").size(16.)).clicked() {
+                        self.tab.app.show_open_project_dialog();
+                    }
+                });
+                if let Some(mut reopen) = reopen_last_project {
+                    if ui.checkbox(&mut reopen, "Reopen last project on startup").changed() {
+                        let command = PreferencesCommand::Set { key: String::from(Preferences::REOPEN_LAST_PROJECT), value: Value::Bool(reopen), scope: SettingScope::User };
+                        self.tab.app.publish_command("WelcomePage", &command);
+                    }
+                }
+
+                ui.add_space(20.);
+                ui.strong("Start from a template");
+                ui.horizontal_wrapped(|ui| {
+                    ui.set_max_width(520.);
+                    for template in templates {
+                        if ui.button(&template.name).on_hover_text(&template.description).clicked() {
+                            self.tab.app.show_new_project_dialog(Some(template.id));
+                        }
+                    }
+                });
+
+                ui.add_space(20.);
+                ui.strong("Recent projects");
+                ui.add(RecentProjectList {tab: self.tab});
+            }).response
+        }).inner
+    }
+}
+
+/// Recent projects of ProjectManager with their description, pinned ones first.
+pub struct RecentProjectList<'a, 'b> {
+    pub tab: &'a mut MyTab<'b>
+}
+
+impl<'a, 'b> Widget for RecentProjectList<'a, 'b> {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        let search = ui.use_state(String::new, ());
+        let projects = self.tab.app.core.request::<Vec<RecentProject>>("WelcomePage", &ProjectManagerRequest::RecentProjects).unwrap_or_default();
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|duration| duration.as_secs()).unwrap_or_default();
+        ui.vertical(|ui| {
+            ui.set_max_width(520.);
+            if projects.is_empty() {
+                ui.weak("No recent projects yet.");
+            } else {
+                let mut text = (*search).clone();
+                if ui.add(egui::TextEdit::singleline(&mut text).hint_text("Search recent projects").desired_width(f32::INFINITY)).changed() {
+                    search.set_next(text.clone());
+                }
+            }
+            let query = search.to_lowercase();
+            let matches = |project: &RecentProject| {
+                let path = project.path.to_string_lossy();
+                [project.name.as_str(), project.description.as_str(), path.as_ref()].iter().any(|text| text.to_lowercase().contains(&query))
+            };
+            for project in projects.into_iter().filter(matches) {
+                ui.group(|ui| {
+                    ui.set_width(ui.available_width());
+                    ui.horizontal(|ui| {
+                        let name = RichText::new(&project.name).strong().size(16.);
+                        if ui.add_enabled(!project.missing, egui::Button::new(name).frame(false)).on_hover_text("Open project").clicked() {
+                            ProjectManagerWindow::open_project(self.tab.app, &project.path);
+                        }
+                        if project.missing {
+                            ui.colored_label(ui.visuals().warn_fg_color, "missing");
+                        }
+                        ui.with_layout(Layout::right_to_left(egui::Align::Center), |ui| {
+                            if ui.small_button("✖").on_hover_text("Remove from the list").clicked() {
+                                self.tab.app.publish_command("WelcomePage", &ProjectManagerCommand::ForgetRecentProject { path: project.path.clone() });
+                            }
+                            let pin = if project.pinned {"Unpin"} else {"Pin"};
+                            if ui.small_button(pin).clicked() {
+                                self.tab.app.publish_command("WelcomePage", &ProjectManagerCommand::PinRecentProject { path: project.path.clone(), pinned: !project.pinned });
+                            }
+                            if project.last_opened > 0 {
+                                ui.weak(format_elapsed(now.saturating_sub(project.last_opened)));
+                            }
+                        });
+                    });
+                    if !project.description.is_empty() {
+                        ui.label(&project.description);
+                    }
+                    ui.label(RichText::new(project.path.to_string_lossy()).small().weak());
+                });
+            }
+        }).response
+    }
+}
+
+fn format_elapsed(seconds: u64) -> String {
+    match seconds {
+        0..=59 => String::from("just now"),
+        60..=3599 => format!("{} minutes ago", seconds / 60),
+        3600..=86399 => format!("{} hours ago", seconds / 3600),
+        _ => format!("{} days ago", seconds / 86400),
     }
 }
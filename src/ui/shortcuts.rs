@@ -0,0 +1,25 @@
+use egui::{Key, KeyboardShortcut, Modifiers};
+
+/// Parses `Ctrl+Shift+Z` style bindings, `Ctrl` is the command key on macOS.
+pub fn parse_shortcut(binding: &str) -> Option<KeyboardShortcut> {
+    let parts = binding.split('+').collect::<Vec<_>>();
+    let (key, modifiers) = parts.split_last()?;
+    let mut result = Modifiers::NONE;
+    for modifier in modifiers {
+        result = result | match *modifier {
+            "Ctrl" | "Cmd" => Modifiers::COMMAND,
+            "Shift" => Modifiers::SHIFT,
+            "Alt" => Modifiers::ALT,
+            _ => return None,
+        };
+    }
+    Some(KeyboardShortcut::new(result, Key::from_name(key)?))
+}
+
+/// Consumes the shortcut of `binding` when it was pressed this frame.
+pub fn pressed(ctx: &egui::Context, binding: Option<&String>) -> bool {
+    let Some(shortcut) = binding.and_then(|binding| parse_shortcut(binding)) else {
+        return false;
+    };
+    ctx.input_mut(|input| input.consume_shortcut(&shortcut))
+}
@@ -0,0 +1,1358 @@
+use std::{any::Any, cell::{Cell, RefCell}, path::PathBuf, rc::Rc, time::{SystemTime, UNIX_EPOCH}};
+
+use serde_json::Value;
+
+use bluebird_ide::{backend::{commands::Command, extension::{discover_extensions, ExtensionError}, file_watcher::{ProjectWatcher, WatchMode}, node_graph::{Graph, GraphError, NodeType, PortDefinition, PortRef}, plugin::{IPlugin, IPluginStateStorage, PluginDependency, PluginError, PluginInfo, PluginStatus}, plugin_graph::PluginGraphError, plugins::{document_manager::{DiskState, DocumentManager, DocumentManagerCommand, DocumentManagerEvent, DocumentManagerRequest, DocumentSnapshot}, file_manager::{sandboxed_path, DiskChange, FileEntry, FileEntryType, FileManager, FileManagerCommand, FileManagerEvent, FileManagerRequest, FileOperationError, TrashedItem}, logger::{LogCommand, Logger}, node_graph_manager::{GraphEdit, NodeGraphCommand, NodeGraphManager, NodeGraphRequest}, notification_center::NotificationCenter, preferences::{EditorPreferences, PreferenceEntry, PreferenceKind, PreferenceSection, Preferences, PreferencesCommand, PreferencesRequest, Theme}, project_manager::{Project, ProjectManager, ProjectManagerCommand, ProjectManagerRequest, RecentProject}, search_manager::{ReplacementPreview, SearchManager, SearchManagerCommand, SearchManagerRequest}, window_manager::{FileTabChange, WindowManager, WindowManagerCommand}}, designer_core::{CoreCommand, PluginAccessError}, plugin_settings::SettingScope, project_file::{self, ProjectFileError}, project_template::ProjectTemplate, request::RequestError, search::{FileMatches, SearchMatch, SearchOptions}, syntax::{tokenize, tokenize_line, Language, LineState, TokenKind}, text_buffer::{Buffer, EditKind, Motion, Selection, TextChange}}, DesignerCore};
+
+fn temp_project_dir(name: &str) -> PathBuf {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+    let path = std::env::temp_dir().join(format!("bluebird_ide_{}_{}_{}", name, std::process::id(), nanos));
+    std::fs::create_dir_all(&path).unwrap();
+    path
+}
+
+fn sample_project() -> Project {
+    let mut project = Project::new_from_empty();
+    project.name = "Headless".into();
+    project.package_name = "com.example.headless".into();
+    project.author = "tester".into();
+    project.email = "tester@example.com".into();
+    project
+}
+
+#[test]
+fn create_and_open_project_without_window() {
+    let repaints = Rc::new(Cell::new(0usize));
+    let counter = repaints.clone();
+    let mut core = DesignerCore::new(move || counter.set(counter.get() + 1));
+    core.set_user_config_dir(None);
+    core.initialize_plugins().unwrap();
+
+    let dir = temp_project_dir("open");
+    let path = dir.to_str().unwrap().to_string();
+    core.publish_message("test", &ProjectManagerCommand::CreateProject { path: path.clone(), project: sample_project(), template: None });
+    assert!(dir.join("project.blueproj").exists());
+
+    core.publish_message("test", &ProjectManagerCommand::OpenProject { path: path.clone(), save_documents: false });
+    let project_name = core.with_plugin(|project_manager: &ProjectManager| project_manager.project.as_ref().map(|project| project.name.clone())).unwrap();
+    assert_eq!(project_name.as_deref(), Some("Headless"));
+
+    let project_files = core.with_plugin(|file_manager: &FileManager| file_manager.root.clone()).unwrap();
+    match &project_files.unwrap().file_type {
+        FileEntryType::Folder(children) => assert!(children.iter().any(|entry| entry.name == "project.blueproj")),
+        _ => panic!("project root should be a loaded folder"),
+    }
+    assert!(repaints.get() > 0);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn closing_a_project_releases_project_state() {
+    let mut core = DesignerCore::headless();
+    core.initialize_plugins().unwrap();
+    let first = temp_project_dir("close_first");
+    let second = temp_project_dir("close_second");
+    for dir in [&first, &second] {
+        core.publish_message("test", &ProjectManagerCommand::CreateProject { path: dir.to_str().unwrap().to_string(), project: sample_project(), template: None });
+    }
+
+    core.publish_message("test", &ProjectManagerCommand::OpenProject { path: first.to_str().unwrap().to_string(), save_documents: false });
+    core.publish_message("test", &WindowManagerCommand::UpdateLayout(serde_json::json!({"layout": "first"})));
+    core.publish_message("test", &ProjectManagerCommand::CloseProject { save_documents: false });
+    // the window manager stores the reported layout itself, closing through the core keeps it too.
+    let stored = std::fs::read_to_string(first.join(".designer").join("settings.json")).unwrap();
+    assert_eq!(serde_json::from_str::<Value>(&stored).unwrap(), serde_json::json!({"layout": "first"}));
+    assert_eq!(core.with_plugin(|window_manager: &WindowManager| window_manager.file_changes.clone()).unwrap(), vec![FileTabChange::ProjectClosed]);
+    assert!(!core.with_plugin(|project_manager: &ProjectManager| project_manager.project_is_available()).unwrap());
+    assert_eq!(core.request::<Option<PathBuf>>("test", &ProjectManagerRequest::ProjectPath).unwrap(), None);
+    assert!(core.with_plugin(|file_manager: &FileManager| file_manager.root.is_none()).unwrap());
+    assert!(core.with_plugin(|window_manager: &WindowManager| window_manager.dock_state.is_none()).unwrap());
+    assert!(!core.project_attached());
+
+    // closing twice is harmless and another project opens without restarting.
+    core.publish_message("test", &ProjectManagerCommand::CloseProject { save_documents: false });
+    core.publish_message("test", &ProjectManagerCommand::OpenProject { path: second.to_str().unwrap().to_string(), save_documents: false });
+    assert_eq!(core.request::<Option<PathBuf>>("test", &ProjectManagerRequest::ProjectPath).unwrap(), Some(second.clone()));
+    assert!(core.with_plugin(|window_manager: &WindowManager| window_manager.dock_state.is_none()).unwrap());
+    assert!(core.with_plugin(|file_manager: &FileManager| file_manager.root.is_some()).unwrap());
+
+    // without a reported layout the restored one is stored again.
+    core.publish_message("test", &ProjectManagerCommand::OpenProject { path: first.to_str().unwrap().to_string(), save_documents: false });
+    assert_eq!(core.with_plugin(|window_manager: &WindowManager| window_manager.dock_state.clone()).unwrap(), Some(serde_json::json!({"layout": "first"})));
+    std::fs::remove_file(first.join(".designer").join("settings.json")).unwrap();
+    core.publish_message("test", &ProjectManagerCommand::CloseProject { save_documents: false });
+    assert!(first.join(".designer").join("settings.json").exists());
+
+    std::fs::remove_dir_all(first).unwrap();
+    std::fs::remove_dir_all(second).unwrap();
+}
+
+#[test]
+fn opening_a_project_closes_the_open_one() {
+    let received = Rc::new(RefCell::new(vec![]));
+    let mut core = DesignerCore::headless();
+    core.register_dynamic_plugin(RecordingPlugin { uuid: "script-a", received: received.clone(), status: PluginStatus::Disabled }).unwrap();
+    core.initialize_plugins().unwrap();
+    let mut info = core.plugin_infos().iter().find(|info| info.uuid == "script-a").unwrap().clone();
+    info.subscriptions.push(String::from("designer.project_manager.event"));
+    core.update_plugin_info(info).unwrap();
+    let first = temp_project_dir("switch_first");
+    let second = temp_project_dir("switch_second");
+    for dir in [&first, &second] {
+        core.publish_message("test", &ProjectManagerCommand::CreateProject { path: dir.to_str().unwrap().to_string(), project: sample_project(), template: None });
+    }
+    std::fs::write(first.join("notes.txt"), "first").unwrap();
+
+    core.publish_message("test", &ProjectManagerCommand::OpenProject { path: first.to_str().unwrap().to_string(), save_documents: false });
+    core.publish_message("test", &FileManagerCommand::OpenFile(String::from("notes.txt")));
+    core.publish_message("test", &DocumentManagerCommand::Edit { path: String::from("notes.txt"), changes: vec![TextChange { start: 0, end: 5, text: String::from("changed") }], kind: EditKind::Other });
+    received.borrow_mut().clear();
+    core.publish_message("test", &ProjectManagerCommand::OpenProject { path: second.to_str().unwrap().to_string(), save_documents: true });
+
+    // the first project is closed, Closing and Closed come before Opened, and its documents are saved as asked.
+    assert_eq!(*received.borrow(), vec![String::from("script-a:designer.project_manager.event"); 3]);
+    assert_eq!(std::fs::read_to_string(first.join("notes.txt")).unwrap(), "changed");
+    assert!(core.with_plugin(|documents: &DocumentManager| documents.documents().is_empty()).unwrap());
+    assert_eq!(core.request::<Option<PathBuf>>("test", &ProjectManagerRequest::ProjectPath).unwrap(), Some(second.clone()));
+    let files = core.request::<Vec<FileEntry>>("test", &FileManagerRequest::ListDirectory { path: "".into() }).unwrap();
+    assert!(!files.iter().any(|entry| entry.name == "notes.txt"));
+
+    // the unsaved changes are dropped when the user chose not to save them.
+    core.publish_message("test", &ProjectManagerCommand::OpenProject { path: first.to_str().unwrap().to_string(), save_documents: false });
+    core.publish_message("test", &DocumentManagerCommand::Open { path: String::from("notes.txt") });
+    core.publish_message("test", &DocumentManagerCommand::Edit { path: String::from("notes.txt"), changes: vec![TextChange::insert(0, "un")], kind: EditKind::Other });
+    core.publish_message("test", &ProjectManagerCommand::OpenProject { path: second.to_str().unwrap().to_string(), save_documents: false });
+    assert_eq!(std::fs::read_to_string(first.join("notes.txt")).unwrap(), "changed");
+
+    // a buffer that cannot be saved keeps its project open instead of losing the changes.
+    core.publish_message("test", &ProjectManagerCommand::OpenProject { path: first.to_str().unwrap().to_string(), save_documents: false });
+    core.publish_message("test", &DocumentManagerCommand::Open { path: String::from("notes.txt") });
+    core.publish_message("test", &DocumentManagerCommand::Edit { path: String::from("notes.txt"), changes: vec![TextChange::insert(0, "un")], kind: EditKind::Other });
+    std::fs::remove_file(first.join("notes.txt")).unwrap();
+    std::fs::create_dir(first.join("notes.txt")).unwrap();
+    core.publish_message("test", &ProjectManagerCommand::OpenProject { path: second.to_str().unwrap().to_string(), save_documents: true });
+    assert_eq!(core.request::<Option<PathBuf>>("test", &ProjectManagerRequest::ProjectPath).unwrap(), Some(first.clone()));
+    assert_eq!(core.request::<Vec<String>>("test", &DocumentManagerRequest::UnsavedDocuments).unwrap(), vec![String::from("notes.txt")]);
+
+    std::fs::remove_dir_all(first).unwrap();
+    std::fs::remove_dir_all(second).unwrap();
+}
+
+#[test]
+fn headless_core_starts_without_project() {
+    let mut core = DesignerCore::headless();
+    core.initialize_plugins().unwrap();
+    assert!(!core.with_plugin(|project_manager: &ProjectManager| project_manager.project_is_available()).unwrap());
+    assert!(core.with_plugin(|file_manager: &FileManager| file_manager.root.is_none()).unwrap());
+}
+
+#[test]
+fn requests_are_answered_by_the_owning_plugin() {
+    let mut core = DesignerCore::headless();
+    core.initialize_plugins().unwrap();
+    assert_eq!(core.request::<Option<PathBuf>>("test", &ProjectManagerRequest::ProjectPath).unwrap(), None);
+
+    let dir = temp_project_dir("request");
+    let path = dir.to_str().unwrap().to_string();
+    core.publish_message("test", &ProjectManagerCommand::CreateProject { path: path.clone(), project: sample_project(), template: None });
+    core.publish_message("test", &ProjectManagerCommand::OpenProject { path: path.clone(), save_documents: false });
+    std::fs::write(dir.join("notes.txt"), "hello").unwrap();
+
+    assert_eq!(core.request::<Option<PathBuf>>("test", &ProjectManagerRequest::ProjectPath).unwrap(), Some(dir.clone()));
+    assert_eq!(core.request::<String>("test", &FileManagerRequest::ReadFile { path: "notes.txt".into() }).unwrap(), "hello");
+    let entries = core.request::<Vec<FileEntry>>("test", &FileManagerRequest::ListDirectory { path: "".into() }).unwrap();
+    assert!(entries.iter().any(|entry| entry.name == "notes.txt"));
+
+    let id = core.post_request("test", &FileManagerRequest::ReadFile { path: "missing.txt".into() });
+    assert!(matches!(core.take_reply::<String>(id), Some(Err(RequestError::Failed { .. }))));
+    assert!(core.take_reply::<String>(id).is_none());
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn unhandled_and_targeted_messages() {
+    let mut core = DesignerCore::headless();
+    core.initialize_plugins().unwrap();
+
+    core.publish_message("test", &("designer.file_manger.management", ()));
+    let logs = core.with_plugin(|logger: &Logger| logger.logs.clone()).unwrap();
+    assert!(logs.iter().any(|(sender, category, content)| sender == "designer.core" && category == "Warning" && content.contains("designer.file_manger.management")));
+
+    let logger_uuid = Logger::new().info().uuid;
+    let file_manager_uuid = FileManager::new().info().uuid;
+    core.publish_message_to("test", &file_manager_uuid, &LogCommand::Generate { category: "Info".into(), content: "not for the logger".into() });
+    core.publish_message_to("test", &logger_uuid, &LogCommand::Generate { category: "Info".into(), content: "for the logger".into() });
+    let logs = core.with_plugin(|logger: &Logger| logger.logs.clone()).unwrap();
+    assert!(!logs.iter().any(|(_, _, content)| content == "not for the logger"));
+    assert!(logs.iter().any(|(_, _, content)| content == "for the logger"));
+}
+
+#[test]
+fn plugin_errors_are_reported_instead_of_panicking() {
+    let mut core = DesignerCore::headless();
+    core.initialize_plugins().unwrap();
+
+    core.publish_message("test", &("designer.project_manager.management", "not a command"));
+    let missing = temp_project_dir("missing");
+    core.publish_message("test", &ProjectManagerCommand::OpenProject { path: missing.to_str().unwrap().to_string(), save_documents: false });
+
+    let logs = core.with_plugin(|logger: &Logger| logger.logs.clone()).unwrap();
+    let errors: Vec<_> = logs.iter().filter(|(_, category, _)| category == "Error").collect();
+    assert_eq!(errors.len(), 2);
+    assert!(errors.iter().all(|(_, _, content)| content.contains("designer.project_manager.management") && content.contains("\"test\"")));
+    assert_eq!(core.with_plugin(|center: &NotificationCenter| center.notifications.len()).unwrap(), 2);
+    assert!(!core.with_plugin(|project_manager: &ProjectManager| project_manager.project_is_available()).unwrap());
+
+    std::fs::remove_dir_all(missing).unwrap();
+}
+
+#[test]
+fn plugin_access_is_borrow_checked() {
+    let core = DesignerCore::headless();
+    core.with_plugin_mut(|logger: &mut Logger| logger.logs.push(("test".into(), "Info".into(), "direct".into()))).unwrap();
+    assert_eq!(core.with_plugin(|logger: &Logger| logger.logs.len()).unwrap(), 1);
+
+    let nested = core.with_plugin_mut(|_: &mut Logger| core.with_plugin(|logger: &Logger| logger.logs.len())).unwrap();
+    assert_eq!(nested, Err(PluginAccessError::Busy(std::any::type_name::<Logger>())));
+}
+
+struct DummyPlugin {
+    uuid: &'static str,
+    dependencies: Vec<&'static str>,
+    status: PluginStatus,
+}
+
+impl IPlugin for DummyPlugin {
+    fn info(&self) -> PluginInfo {
+        PluginInfo {
+            uuid: self.uuid.to_string(),
+            name: self.uuid.to_string(),
+            description: String::new(),
+            version: (0, 0, 1),
+            author: String::from("tester"),
+            subscriptions: vec![],
+            dependencies: self.dependencies.iter().map(|uuid| PluginDependency::new(*uuid)).collect(),
+            api_version: None,
+        }
+    }
+    fn status(&self) -> PluginStatus { self.status.clone() }
+    fn initialize(&mut self, _root: &mut DesignerCore) {}
+    fn enable(&mut self, _root: &mut DesignerCore) { self.status = PluginStatus::Enabled; }
+    fn disable(&mut self, _root: &mut DesignerCore) { self.status = PluginStatus::Disabled; }
+    fn load_state(&mut self, _storage: &mut dyn IPluginStateStorage) {}
+    fn store_state(&mut self, _storage: &mut dyn IPluginStateStorage) -> Result<(), PluginError> { Ok(()) }
+    fn get_state(&self) -> Value { Value::Null }
+    fn handle_message(&mut self, _root: &mut DesignerCore, _sender: &str, _action: &str, _args: Value) -> Result<(), PluginError> { Ok(()) }
+    fn as_any(&self) -> &dyn Any { self }
+    fn as_any_mut(&mut self) -> &mut dyn Any { self }
+}
+
+#[test]
+fn disabling_a_plugin_cascades_to_its_dependents() {
+    let mut core = DesignerCore::headless();
+    core.initialize_plugins().unwrap();
+
+    let mut disabled = core.disable_plugin(ProjectManager::UUID).unwrap();
+    disabled.sort();
+    let mut expected = vec![ProjectManager::UUID.to_string(), FileManager::UUID.to_string(), DocumentManager::UUID.to_string(), SearchManager::UUID.to_string(), NodeGraphManager::UUID.to_string(), WindowManager::UUID.to_string()];
+    expected.sort();
+    assert_eq!(disabled, expected);
+    assert_eq!(core.plugin_status(Logger::UUID), Some(PluginStatus::Enabled));
+
+    core.enable_plugin(FileManager::UUID).unwrap();
+    assert_eq!(core.plugin_status(ProjectManager::UUID), Some(PluginStatus::Enabled));
+    assert_eq!(core.plugin_status(FileManager::UUID), Some(PluginStatus::Enabled));
+    assert_eq!(core.plugin_status(WindowManager::UUID), Some(PluginStatus::Disabled));
+}
+
+#[test]
+fn missing_and_cyclic_dependencies_are_reported() {
+    let mut core = DesignerCore::headless();
+    core.register_dynamic_plugin(DummyPlugin { uuid: "dummy-missing", dependencies: vec!["not-registered"], status: PluginStatus::Disabled }).unwrap();
+    core.register_dynamic_plugin(DummyPlugin { uuid: "dummy-dependent", dependencies: vec!["dummy-missing"], status: PluginStatus::Disabled }).unwrap();
+    core.register_dynamic_plugin(DummyPlugin { uuid: "dummy-a", dependencies: vec!["dummy-b"], status: PluginStatus::Disabled }).unwrap();
+    core.register_dynamic_plugin(DummyPlugin { uuid: "dummy-b", dependencies: vec!["dummy-a"], status: PluginStatus::Disabled }).unwrap();
+
+    let errors = core.initialize_plugins().unwrap_err();
+    assert!(errors.contains(&PluginGraphError::MissingDependency { plugin: "dummy-missing".into(), dependency: "not-registered".into() }));
+    assert!(errors.contains(&PluginGraphError::DependencyFailed { plugin: "dummy-dependent".into(), dependency: "dummy-missing".into() }));
+    assert!(errors.contains(&PluginGraphError::Cycle(vec!["dummy-a".into(), "dummy-b".into()])));
+    for uuid in ["dummy-missing", "dummy-dependent", "dummy-a", "dummy-b"] {
+        assert_eq!(core.plugin_status(uuid), Some(PluginStatus::Disabled));
+    }
+    assert_eq!(core.plugin_status(ProjectManager::UUID), Some(PluginStatus::Enabled));
+    assert!(core.enable_plugin("dummy-a").is_err());
+}
+
+#[test]
+fn plugin_choices_persist_per_user_and_per_project() {
+    let config_dir = temp_project_dir("config");
+    let project_dir = temp_project_dir("plugins");
+    let path = project_dir.to_str().unwrap().to_string();
+
+    let mut core = DesignerCore::headless();
+    core.set_user_config_dir(Some(config_dir.clone()));
+    core.initialize_plugins().unwrap();
+    core.publish_message("test", &CoreCommand::SetPluginEnabled { uuid: NotificationCenter::UUID.into(), enabled: false, scope: SettingScope::User });
+    assert_eq!(core.plugin_status(NotificationCenter::UUID), Some(PluginStatus::Disabled));
+    assert!(config_dir.join("plugins.json").exists());
+
+    // project scope needs an opened project.
+    core.publish_message("test", &CoreCommand::SetPluginEnabled { uuid: WindowManager::UUID.into(), enabled: false, scope: SettingScope::Project });
+    assert_eq!(core.plugin_status(WindowManager::UUID), Some(PluginStatus::Enabled));
+
+    core.publish_message("test", &ProjectManagerCommand::CreateProject { path: path.clone(), project: sample_project(), template: None });
+    core.publish_message("test", &ProjectManagerCommand::OpenProject { path: path.clone(), save_documents: false });
+    core.publish_message("test", &CoreCommand::SetPluginEnabled { uuid: WindowManager::UUID.into(), enabled: false, scope: SettingScope::Project });
+    core.publish_message("test", &CoreCommand::SetPluginEnabled { uuid: NotificationCenter::UUID.into(), enabled: true, scope: SettingScope::Project });
+    assert_eq!(core.plugin_status(WindowManager::UUID), Some(PluginStatus::Disabled));
+    assert_eq!(core.plugin_status(NotificationCenter::UUID), Some(PluginStatus::Enabled));
+
+    // a fresh session restores the user choice, then the project choices once the project is opened.
+    let mut core = DesignerCore::headless();
+    core.set_user_config_dir(Some(config_dir.clone()));
+    core.initialize_plugins().unwrap();
+    assert_eq!(core.plugin_status(NotificationCenter::UUID), Some(PluginStatus::Disabled));
+    assert_eq!(core.plugin_status(WindowManager::UUID), Some(PluginStatus::Enabled));
+    core.publish_message("test", &ProjectManagerCommand::OpenProject { path: path.clone(), save_documents: false });
+    assert_eq!(core.plugin_status(NotificationCenter::UUID), Some(PluginStatus::Enabled));
+    assert_eq!(core.plugin_status(WindowManager::UUID), Some(PluginStatus::Disabled));
+
+    core.publish_message("test", &CoreCommand::ResetPluginEnabled { uuid: NotificationCenter::UUID.into(), scope: SettingScope::Project });
+    assert_eq!(core.plugin_status(NotificationCenter::UUID), Some(PluginStatus::Disabled));
+
+    std::fs::remove_dir_all(config_dir).unwrap();
+    std::fs::remove_dir_all(project_dir).unwrap();
+}
+
+#[test]
+fn plugin_state_survives_restarts_and_tolerates_bad_files() {
+    let config_dir = temp_project_dir("state");
+    let project_dir = temp_project_dir("state_project");
+    let path = project_dir.to_str().unwrap().to_string();
+
+    let mut core = DesignerCore::headless();
+    core.set_user_config_dir(Some(config_dir.clone()));
+    core.initialize_plugins().unwrap();
+    core.publish_message("test", &LogCommand::Generate { category: "Info".into(), content: "from the first session".into() });
+    core.publish_message("test", &ProjectManagerCommand::CreateProject { path: path.clone(), project: sample_project(), template: None });
+    core.publish_message("test", &ProjectManagerCommand::OpenProject { path: path.clone(), save_documents: false });
+    core.publish_message("test", &ProjectManagerCommand::CloseProject { save_documents: false });
+    core.shutdown();
+    assert!(config_dir.join("plugins").join(format!("{}.json", Logger::UUID)).exists());
+
+    let mut core = DesignerCore::headless();
+    core.set_user_config_dir(Some(config_dir.clone()));
+    core.initialize_plugins().unwrap();
+    let restored = core.with_plugin(|logger: &Logger| logger.logs.iter().any(|(_, _, content)| content == "from the first session")).unwrap();
+    assert!(restored);
+
+    // broken or outdated files are ignored instead of panicking.
+    std::fs::write(config_dir.join("plugins").join(format!("{}.json", Logger::UUID)), "{ not json").unwrap();
+    std::fs::write(config_dir.join("plugins").join(format!("{}.json", ProjectManager::UUID)), r#"{"recent_projects": 42, "removed_field": true}"#).unwrap();
+    let mut core = DesignerCore::headless();
+    core.set_user_config_dir(Some(config_dir.clone()));
+    core.initialize_plugins().unwrap();
+    assert_eq!(core.plugin_status(Logger::UUID), Some(PluginStatus::Enabled));
+    assert!(core.with_plugin(|project_manager: &ProjectManager| project_manager.recent_projects.is_empty()).unwrap());
+
+    std::fs::remove_dir_all(config_dir).unwrap();
+    std::fs::remove_dir_all(project_dir).unwrap();
+}
+
+struct RecordingPlugin {
+    uuid: &'static str,
+    received: Rc<RefCell<Vec<String>>>,
+    status: PluginStatus,
+}
+
+impl IPlugin for RecordingPlugin {
+    fn info(&self) -> PluginInfo {
+        PluginInfo {
+            uuid: self.uuid.to_string(),
+            name: self.uuid.to_string(),
+            description: String::new(),
+            version: (0, 0, 1),
+            author: String::from("tester"),
+            subscriptions: vec![],
+            dependencies: vec![],
+            api_version: None,
+        }
+    }
+    fn status(&self) -> PluginStatus { self.status.clone() }
+    fn initialize(&mut self, _root: &mut DesignerCore) {}
+    fn enable(&mut self, _root: &mut DesignerCore) { self.status = PluginStatus::Enabled; }
+    fn disable(&mut self, _root: &mut DesignerCore) { self.status = PluginStatus::Disabled; }
+    fn load_state(&mut self, _storage: &mut dyn IPluginStateStorage) {}
+    fn store_state(&mut self, _storage: &mut dyn IPluginStateStorage) -> Result<(), PluginError> { Ok(()) }
+    fn get_state(&self) -> Value { Value::Null }
+    fn handle_message(&mut self, _root: &mut DesignerCore, _sender: &str, action: &str, _args: Value) -> Result<(), PluginError> {
+        self.received.borrow_mut().push(format!("{}:{}", self.uuid, action));
+        Ok(())
+    }
+    fn as_any(&self) -> &dyn Any { self }
+    fn as_any_mut(&mut self) -> &mut dyn Any { self }
+}
+
+#[test]
+fn dynamic_plugins_are_registered_by_uuid_and_can_change_subscriptions() {
+    let received = Rc::new(RefCell::new(vec![]));
+    let mut core = DesignerCore::headless();
+    core.register_dynamic_plugin(RecordingPlugin { uuid: "script-a", received: received.clone(), status: PluginStatus::Disabled }).unwrap();
+    core.register_dynamic_plugin(RecordingPlugin { uuid: "script-b", received: received.clone(), status: PluginStatus::Disabled }).unwrap();
+    let duplicate = core.register_dynamic_plugin(RecordingPlugin { uuid: "script-a", received: received.clone(), status: PluginStatus::Disabled });
+    assert_eq!(duplicate, Err(PluginGraphError::DuplicatePlugin("script-a".into())));
+    core.initialize_plugins().unwrap();
+
+    let ping = Command { action: String::from("script.ping"), args: Value::Null };
+    core.publish_message("test", &ping);
+    assert!(received.borrow().is_empty());
+
+    let mut info = core.plugin_infos().iter().find(|info| info.uuid == "script-b").unwrap().clone();
+    info.subscriptions.push(String::from("script.*"));
+    core.update_plugin_info(info).unwrap();
+    core.publish_message("test", &ping);
+    assert_eq!(*received.borrow(), vec![String::from("script-b:script.ping")]);
+}
+
+/// Publishes to itself while it is being enabled, so it is busy when the message is dispatched.
+struct GreetingPlugin {
+    received: Rc<RefCell<Vec<String>>>,
+    status: PluginStatus,
+}
+
+impl IPlugin for GreetingPlugin {
+    fn info(&self) -> PluginInfo {
+        PluginInfo {
+            uuid: String::from("greeting"),
+            name: String::from("Greeting"),
+            description: String::new(),
+            version: (0, 0, 1),
+            author: String::from("tester"),
+            subscriptions: vec![String::from("greeting.*")],
+            dependencies: vec![],
+            api_version: None,
+        }
+    }
+    fn status(&self) -> PluginStatus { self.status.clone() }
+    fn initialize(&mut self, _root: &mut DesignerCore) {}
+    fn enable(&mut self, root: &mut DesignerCore) {
+        self.status = PluginStatus::Enabled;
+        root.publish_message("greeting", &Command { action: String::from("greeting.hello"), args: Value::Null });
+    }
+    fn disable(&mut self, _root: &mut DesignerCore) { self.status = PluginStatus::Disabled; }
+    fn load_state(&mut self, _storage: &mut dyn IPluginStateStorage) {}
+    fn store_state(&mut self, _storage: &mut dyn IPluginStateStorage) -> Result<(), PluginError> { Ok(()) }
+    fn get_state(&self) -> Value { Value::Null }
+    fn handle_message(&mut self, _root: &mut DesignerCore, _sender: &str, action: &str, _args: Value) -> Result<(), PluginError> {
+        self.received.borrow_mut().push(action.to_string());
+        Ok(())
+    }
+    fn as_any(&self) -> &dyn Any { self }
+    fn as_any_mut(&mut self) -> &mut dyn Any { self }
+}
+
+#[test]
+fn plugins_are_registered_once_and_busy_plugins_get_their_messages_later() {
+    let received = Rc::new(RefCell::new(vec![]));
+    let mut core = DesignerCore::headless();
+    assert_eq!(core.register_plugin(Logger::new()), Err(PluginGraphError::DuplicatePlugin(std::any::type_name::<Logger>().to_string())));
+    core.register_plugin(GreetingPlugin { received: received.clone(), status: PluginStatus::Disabled }).unwrap();
+    let same_uuid = core.register_dynamic_plugin(GreetingPlugin { received: received.clone(), status: PluginStatus::Disabled });
+    assert_eq!(same_uuid, Err(PluginGraphError::DuplicatePlugin(String::from("greeting"))));
+    assert_eq!(core.plugin_infos().iter().filter(|info| info.uuid == "greeting").count(), 1);
+
+    core.initialize_plugins().unwrap();
+    assert_eq!(*received.borrow(), vec![String::from("greeting.hello")]);
+    core.disable_plugin("greeting").unwrap();
+    core.enable_plugin("greeting").unwrap();
+    assert_eq!(received.borrow().len(), 2);
+}
+
+#[test]
+fn extension_packages_are_discovered_from_directories_and_zips() {
+    let extensions = temp_project_dir("extensions");
+    let unpack_root = temp_project_dir("unpacked");
+
+    let folder = extensions.join("folder-extension");
+    std::fs::create_dir_all(&folder).unwrap();
+    std::fs::write(folder.join("extension.json"), r#"{
+        "uuid": "folder-extension", "name": "Folder extension", "version": [1, 2, 0], "entry": "main.js",
+        "contributes": {"commands": [{"action": "folder.run", "title": "Run"}], "windows": [{"id": "panel", "title": "Panel"}]}
+    }"#).unwrap();
+    std::fs::write(folder.join("main.js"), "").unwrap();
+
+    let broken = extensions.join("broken-extension");
+    std::fs::create_dir_all(&broken).unwrap();
+    std::fs::write(broken.join("extension.json"), r#"{"uuid": "broken"}"#).unwrap();
+
+    let mut zip = zip::ZipWriter::new(std::fs::File::create(extensions.join("zipped.zip")).unwrap());
+    zip.start_file("extension.json", zip::write::FileOptions::default()).unwrap();
+    std::io::Write::write_all(&mut zip, br#"{"uuid": "zipped-extension", "name": "Zipped", "version": [0, 1, 0], "entry": "src/index.js"}"#).unwrap();
+    zip.start_file("src/index.js", zip::write::FileOptions::default()).unwrap();
+    std::io::Write::write_all(&mut zip, b"export const answer = 42").unwrap();
+    zip.finish().unwrap();
+
+    // a uuid or an entry leaving the unpack directory is rejected before anything is removed.
+    let outside = temp_project_dir("outside_extensions");
+    std::fs::write(outside.join("keep.txt"), "kept").unwrap();
+    for (name, manifest) in [("escaping.zip", format!(r#"{{"uuid": "../{}", "name": "Escaping", "version": [0, 1, 0], "entry": "main.js"}}"#, outside.file_name().unwrap().to_str().unwrap())),
+        ("absolute.zip", format!(r#"{{"uuid": {:?}, "name": "Absolute", "version": [0, 1, 0], "entry": "main.js"}}"#, outside.to_str().unwrap())),
+        ("outside_entry.zip", String::from(r#"{"uuid": "outside-entry", "name": "Outside entry", "version": [0, 1, 0], "entry": "../main.js"}"#))] {
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(extensions.join(name)).unwrap());
+        zip.start_file("extension.json", zip::write::FileOptions::default()).unwrap();
+        std::io::Write::write_all(&mut zip, manifest.as_bytes()).unwrap();
+        zip.finish().unwrap();
+    }
+
+    let (packages, errors) = discover_extensions(&extensions, SettingScope::User, &unpack_root);
+    assert_eq!(packages.len(), 2);
+    assert_eq!(errors.len(), 4);
+    assert!(errors.iter().all(|error| matches!(error, ExtensionError::InvalidManifest { .. })));
+    assert_eq!(std::fs::read_to_string(outside.join("keep.txt")).unwrap(), "kept");
+
+    let folder_package = packages.iter().find(|package| package.manifest.uuid == "folder-extension").unwrap();
+    let info = folder_package.manifest.plugin_info();
+    assert_eq!(info.version, (1, 2, 0));
+    assert_eq!(info.subscriptions, vec![String::from("folder.run")]);
+    assert_eq!(folder_package.manifest.contributes.windows[0].id, "panel");
+
+    let zipped = packages.iter().find(|package| package.manifest.uuid == "zipped-extension").unwrap();
+    assert_eq!(std::fs::read_to_string(zipped.entry_path().unwrap()).unwrap(), "export const answer = 42");
+    assert!(zipped.root.starts_with(&unpack_root));
+
+    std::fs::remove_dir_all(outside).unwrap();
+    std::fs::remove_dir_all(extensions).unwrap();
+    std::fs::remove_dir_all(unpack_root).unwrap();
+}
+
+#[test]
+fn older_project_files_are_migrated_and_invalid_ones_are_explained() {
+    let dir = temp_project_dir("migration");
+    std::fs::write(dir.join("project.blueproj"), r#"{"name": "Legacy", "version": [1, 0, 0], "package_name": "com.example.legacy", "author": "tester"}"#).unwrap();
+    let loaded = project_file::load(&dir).unwrap();
+    assert_eq!(loaded.migrated_from, Some(0));
+    assert_eq!(loaded.project.format_version, project_file::CURRENT_FORMAT_VERSION);
+    assert_eq!(loaded.project.email, "");
+    assert!(loaded.problems.is_empty());
+    assert!(std::fs::read_to_string(loaded.backup.unwrap()).unwrap().contains("Legacy"));
+    assert_eq!(project_file::load(&dir).unwrap().migrated_from, None);
+
+    let mut core = DesignerCore::headless();
+    core.initialize_plugins().unwrap();
+    core.publish_message("test", &ProjectManagerCommand::OpenProject { path: dir.to_str().unwrap().to_string(), save_documents: false });
+    assert!(core.with_plugin(|project_manager: &ProjectManager| project_manager.project_is_available()).unwrap());
+
+    let mut project = sample_project();
+    project.package_name = "1invalid".into();
+    project.email = "not an email".into();
+    let fields = project.validate().iter().map(|error| error.field).collect::<Vec<_>>();
+    assert_eq!(fields, vec!["package_name", "email"]);
+    let rejected = temp_project_dir("invalid");
+    core.publish_message("test", &ProjectManagerCommand::CreateProject { path: rejected.to_str().unwrap().to_string(), project, template: None });
+    assert!(!rejected.join("project.blueproj").exists());
+
+    std::fs::write(dir.join("project.blueproj"), r#"{"format_version": 99, "name": "Future"}"#).unwrap();
+    assert!(matches!(project_file::load(&dir), Err(ProjectFileError::UnsupportedVersion { version: 99, .. })));
+
+    std::fs::remove_dir_all(dir).unwrap();
+    std::fs::remove_dir_all(rejected).unwrap();
+}
+
+#[test]
+fn new_projects_are_created_from_builtin_and_user_templates() {
+    let config_dir = temp_project_dir("templates_config");
+    let user_template = ProjectTemplate::user_directory(&config_dir).join("company");
+    std::fs::create_dir_all(user_template.join("docs")).unwrap();
+    std::fs::write(user_template.join("template.json"), r#"{"name": "Company project", "description": "House style"}"#).unwrap();
+    std::fs::write(user_template.join("docs").join("{{package_name}}.md"), "Owned by {{author}}").unwrap();
+
+    let mut core = DesignerCore::headless();
+    core.set_user_config_dir(Some(config_dir.clone()));
+    core.initialize_plugins().unwrap();
+    let templates = core.request::<Vec<ProjectTemplate>>("test", &ProjectManagerRequest::Templates).unwrap();
+    let ids = templates.iter().map(|template| template.id.as_str()).collect::<Vec<_>>();
+    assert_eq!(ids, vec!["empty", "js_ui_extension", "node_graph", "company"]);
+    let company = templates.iter().find(|template| template.id == "company").unwrap();
+    assert_eq!(company.name, "Company project");
+    assert_eq!(company.files, vec!["docs/{{package_name}}.md".to_string(), "project.blueproj".to_string()]);
+
+    let extension_project = temp_project_dir("template_extension");
+    core.publish_message("test", &ProjectManagerCommand::CreateProject { path: extension_project.to_str().unwrap().to_string(), project: sample_project(), template: Some("js_ui_extension".into()) });
+    assert!(project_file::load(&extension_project).is_ok());
+    let script = std::fs::read_to_string(extension_project.join("src").join("main.js")).unwrap();
+    assert!(script.contains("uuid: \"com.example.headless\"") && !script.contains("{{"));
+    let package = bluebird_ide::backend::extension::ExtensionPackage::from_directory(&extension_project, SettingScope::User).unwrap();
+    assert_eq!(package.manifest.uuid, "com.example.headless");
+    assert_eq!(package.manifest.contributes.windows[0].title, "Headless");
+
+    let company_project = temp_project_dir("template_company");
+    core.publish_message("test", &ProjectManagerCommand::CreateProject { path: company_project.to_str().unwrap().to_string(), project: sample_project(), template: Some("company".into()) });
+    assert_eq!(std::fs::read_to_string(company_project.join("docs").join("com.example.headless.md")).unwrap(), "Owned by tester");
+    assert!(!company_project.join("template.json").exists());
+
+    // a file name that becomes an absolute path once the placeholders are replaced is refused.
+    let escaping = ProjectTemplate::user_directory(&config_dir).join("escaping");
+    std::fs::create_dir_all(&escaping).unwrap();
+    std::fs::write(escaping.join("{{name}}.txt"), "outside").unwrap();
+    let outside = std::env::temp_dir().join("bluebird_template_escape");
+    let templates = core.request::<Vec<ProjectTemplate>>("test", &ProjectManagerRequest::Templates).unwrap();
+    let template = templates.iter().find(|template| template.id == "escaping").unwrap();
+    let escaping_project = temp_project_dir("template_escaping");
+    assert!(template.instantiate(&escaping_project, &Project { name: outside.to_str().unwrap().to_string(), ..sample_project() }).is_err());
+    assert!(!outside.with_extension("txt").exists());
+
+    for dir in [config_dir, extension_project, company_project, escaping_project] {
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
+
+#[test]
+fn project_metadata_is_updated_after_validation() {
+    let dir = temp_project_dir("update");
+    let path = dir.to_str().unwrap().to_string();
+    let mut core = DesignerCore::headless();
+    core.initialize_plugins().unwrap();
+
+    let mut project = sample_project();
+    project.name = "Renamed".into();
+    core.publish_message("test", &ProjectManagerCommand::UpdateProject { project: project.clone() });
+    assert!(!dir.join("project.blueproj").exists());
+
+    core.publish_message("test", &ProjectManagerCommand::CreateProject { path: path.clone(), project: sample_project(), template: None });
+    core.publish_message("test", &ProjectManagerCommand::OpenProject { path, save_documents: false });
+    core.publish_message("test", &ProjectManagerCommand::UpdateProject { project: project.clone() });
+    assert_eq!(core.request::<Option<Project>>("test", &ProjectManagerRequest::Project).unwrap(), Some(project.clone()));
+    assert_eq!(project_file::load(&dir).unwrap().project, project);
+    assert!(!dir.join("project.blueproj.tmp").exists());
+
+    let mut invalid = project.clone();
+    invalid.email = "nobody@".into();
+    core.publish_message("test", &ProjectManagerCommand::UpdateProject { project: invalid });
+    assert_eq!(core.request::<Option<Project>>("test", &ProjectManagerRequest::Project).unwrap(), Some(project.clone()));
+    assert_eq!(project_file::load(&dir).unwrap().project, project);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn preferences_are_layered_validated_and_extensible() {
+    let config_dir = temp_project_dir("preferences");
+    let project_dir = temp_project_dir("preferences_project");
+    let path = project_dir.to_str().unwrap().to_string();
+    let mut core = DesignerCore::headless();
+    core.set_user_config_dir(Some(config_dir.clone()));
+    core.initialize_plugins().unwrap();
+
+    let editor = core.request::<EditorPreferences>("test", &PreferencesRequest::Editor).unwrap();
+    assert_eq!(editor.theme, Theme::System);
+    assert_eq!(editor.keybindings.get("save").map(String::as_str), Some("Ctrl+S"));
+
+    core.publish_message("test", &PreferencesCommand::Set { key: Preferences::FONT_SIZE.into(), value: serde_json::json!(18.0), scope: SettingScope::User });
+    core.publish_message("test", &PreferencesCommand::Set { key: Preferences::FONT_SIZE.into(), value: serde_json::json!(400.0), scope: SettingScope::User });
+    core.publish_message("test", &PreferencesCommand::Set { key: Preferences::THEME.into(), value: serde_json::json!("Purple"), scope: SettingScope::User });
+    core.publish_message("test", &PreferencesCommand::Set { key: Preferences::LOG_BUFFER_SIZE.into(), value: serde_json::json!(16), scope: SettingScope::User });
+    let editor = core.request::<EditorPreferences>("test", &PreferencesRequest::Editor).unwrap();
+    assert_eq!((editor.font_size, editor.theme, editor.log_buffer_size), (18.0, Theme::System, 16));
+    for index in 0..40 {
+        core.publish_message("test", &LogCommand::Generate { category: "Info".into(), content: format!("log {}", index) });
+    }
+    assert_eq!(core.with_plugin(|logger: &Logger| logger.logs.len()).unwrap(), 16);
+
+    // a project override wins while the project is open and is forgotten once it is closed.
+    core.publish_message("test", &ProjectManagerCommand::CreateProject { path: path.clone(), project: sample_project(), template: None });
+    core.publish_message("test", &ProjectManagerCommand::OpenProject { path: path.clone(), save_documents: false });
+    core.publish_message("test", &PreferencesCommand::Set { key: Preferences::FONT_SIZE.into(), value: serde_json::json!(12.0), scope: SettingScope::Project });
+    assert_eq!(core.request::<Option<f32>>("test", &PreferencesRequest::Value { key: Preferences::FONT_SIZE.into() }).unwrap(), Some(12.0));
+    core.publish_message("test", &ProjectManagerCommand::CloseProject { save_documents: false });
+    assert_eq!(core.request::<Option<f32>>("test", &PreferencesRequest::Value { key: Preferences::FONT_SIZE.into() }).unwrap(), Some(18.0));
+    core.publish_message("test", &ProjectManagerCommand::OpenProject { path: path.clone(), save_documents: false });
+    assert_eq!(core.request::<Option<f32>>("test", &PreferencesRequest::Value { key: Preferences::FONT_SIZE.into() }).unwrap(), Some(12.0));
+
+    let section = PreferenceSection { id: "formatter".into(), title: "Formatter".into(), owner: String::new(), entries: vec![
+        PreferenceEntry::new("formatter.on_save", "Format on save", PreferenceKind::Bool, serde_json::json!(false)),
+    ]};
+    core.publish_message("formatter-plugin", &PreferencesCommand::RegisterSection(section));
+    core.publish_message("test", &PreferencesCommand::Set { key: "formatter.on_save".into(), value: serde_json::json!(true), scope: SettingScope::User });
+    let sections = core.request::<Vec<PreferenceSection>>("test", &PreferencesRequest::Sections).unwrap();
+    assert_eq!(sections.last().unwrap().owner, "formatter-plugin");
+    core.shutdown();
+
+    let mut core = DesignerCore::headless();
+    core.set_user_config_dir(Some(config_dir.clone()));
+    core.initialize_plugins().unwrap();
+    assert_eq!(core.request::<EditorPreferences>("test", &PreferencesRequest::Editor).unwrap().font_size, 18.0);
+    // values of sections registered later are kept until their owner registers again.
+    assert_eq!(core.request::<Option<bool>>("test", &PreferencesRequest::Value { key: "formatter.on_save".into() }).unwrap(), None);
+    assert!(core.with_plugin(|preferences: &Preferences| preferences.value_in("formatter.on_save", SettingScope::User).is_some()).unwrap());
+
+    std::fs::remove_dir_all(config_dir).unwrap();
+    std::fs::remove_dir_all(project_dir).unwrap();
+}
+
+#[test]
+fn recent_projects_are_tracked_pinned_and_pruned() {
+    let config_dir = temp_project_dir("recent_config");
+    let first = temp_project_dir("recent_first");
+    let second = temp_project_dir("recent_second");
+    let mut core = DesignerCore::headless();
+    core.set_user_config_dir(Some(config_dir.clone()));
+    core.initialize_plugins().unwrap();
+
+    for dir in [&first, &second] {
+        core.publish_message("test", &ProjectManagerCommand::CreateProject { path: dir.to_str().unwrap().to_string(), project: sample_project(), template: None });
+    }
+    core.publish_message("test", &ProjectManagerCommand::OpenProject { path: first.to_str().unwrap().to_string(), save_documents: false });
+    // the same directory written differently is still one entry.
+    core.publish_message("test", &ProjectManagerCommand::OpenProject { path: format!("{}/.", first.to_str().unwrap()), save_documents: false });
+    core.publish_message("test", &ProjectManagerCommand::PinRecentProject { path: second.clone(), pinned: true });
+    let recent = core.request::<Vec<RecentProject>>("test", &ProjectManagerRequest::RecentProjects).unwrap();
+    assert_eq!(recent.len(), 2);
+    assert_eq!((recent[0].path.clone(), recent[0].pinned), (second.clone(), true));
+    assert_eq!(recent[1].name, "Headless");
+
+    std::fs::remove_dir_all(&first).unwrap();
+    let recent = core.request::<Vec<RecentProject>>("test", &ProjectManagerRequest::RecentProjects).unwrap();
+    assert!(recent[1].missing);
+    core.publish_message("test", &ProjectManagerCommand::PruneRecentProjects);
+    core.publish_message("test", &ProjectManagerCommand::ClearRecentProjects);
+    let recent = core.request::<Vec<RecentProject>>("test", &ProjectManagerRequest::RecentProjects).unwrap();
+    assert_eq!(recent.iter().map(|project| project.path.clone()).collect::<Vec<_>>(), vec![second.clone()]);
+    core.shutdown();
+
+    // lists of older builds hold bare paths.
+    std::fs::write(config_dir.join("plugins").join(format!("{}.json", ProjectManager::UUID)), serde_json::json!({"recent_projects": [second]}).to_string()).unwrap();
+    let mut core = DesignerCore::headless();
+    core.set_user_config_dir(Some(config_dir.clone()));
+    core.initialize_plugins().unwrap();
+    let recent = core.request::<Vec<RecentProject>>("test", &ProjectManagerRequest::RecentProjects).unwrap();
+    assert_eq!((recent[0].name.as_str(), recent[0].missing), ("Headless", false));
+
+    std::fs::remove_dir_all(config_dir).unwrap();
+    std::fs::remove_dir_all(second).unwrap();
+}
+
+#[test]
+fn file_operations_stay_in_the_project_and_deleted_files_can_be_restored() {
+    let dir = temp_project_dir("file_operations");
+    let mut core = DesignerCore::headless();
+    core.initialize_plugins().unwrap();
+    core.publish_message("test", &ProjectManagerCommand::CreateProject { path: dir.to_str().unwrap().to_string(), project: sample_project(), template: None });
+    core.publish_message("test", &ProjectManagerCommand::OpenProject { path: dir.to_str().unwrap().to_string(), save_documents: false });
+
+    core.publish_message("test", &FileManagerCommand::CreateFolder { path: String::from("src") });
+    core.publish_message("test", &FileManagerCommand::CreateFile { path: String::from("src/main.js") });
+    core.publish_message("test", &FileManagerCommand::Rename { path: String::from("src"), new_name: String::from("scripts") });
+    assert!(dir.join("scripts").join("main.js").exists());
+    assert_eq!(core.with_plugin(|wm: &WindowManager| wm.file_changes.clone()).unwrap(), vec![FileTabChange::Moved { from: String::from("src"), to: String::from("scripts") }]);
+    assert_eq!(FileTabChange::Moved { from: String::from("src"), to: String::from("scripts") }.apply("src/main.js").as_deref(), Some("scripts/main.js"));
+    assert_eq!(FileTabChange::Removed { path: String::from("src") }.apply("src_old/main.js").as_deref(), Some("src_old/main.js"));
+
+    core.publish_message("test", &FileManagerCommand::CreateFolder { path: String::from("lib") });
+    core.publish_message("test", &FileManagerCommand::Move { path: String::from("scripts/main.js"), destination: String::from("lib") });
+    core.publish_message("test", &FileManagerCommand::Delete { path: String::from("lib/main.js") });
+    assert!(!dir.join("lib").join("main.js").exists());
+    let trash = core.request::<Vec<TrashedItem>>("test", &FileManagerRequest::Trash).unwrap();
+    assert_eq!(trash.iter().map(|item| item.original_path.as_str()).collect::<Vec<_>>(), vec!["lib/main.js"]);
+    core.publish_message("test", &FileManagerCommand::RestoreDeleted { id: None });
+    assert!(dir.join("lib").join("main.js").exists());
+    assert!(core.request::<Vec<TrashedItem>>("test", &FileManagerRequest::Trash).unwrap().is_empty());
+
+    // every rejected operation is logged as an error and leaves the disk untouched.
+    for command in [
+        FileManagerCommand::CreateFile { path: String::from("../escaped.txt") },
+        FileManagerCommand::Move { path: String::from("lib"), destination: String::from("lib") },
+        FileManagerCommand::Rename { path: String::from("lib"), new_name: String::from("../lib") },
+        FileManagerCommand::Delete { path: String::from("project.blueproj") },
+        FileManagerCommand::CreateFolder { path: String::from("scripts") },
+    ] {
+        core.publish_message("test", &command);
+    }
+    let errors = core.with_plugin(|logger: &Logger| logger.logs.iter().filter(|(_, category, _)| category == "Error").count()).unwrap();
+    assert_eq!(errors, 5);
+    assert!(!dir.parent().unwrap().join("escaped.txt").exists());
+    assert!(dir.join("project.blueproj").exists());
+    assert!(matches!(sandboxed_path(&dir, "scripts/../../outside"), Err(FileOperationError::OutsideProject { .. })));
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+/// Keeps the arguments of every message of one action.
+struct EventRecorder {
+    action: &'static str,
+    events: Rc<RefCell<Vec<Value>>>,
+    status: PluginStatus,
+}
+
+impl IPlugin for EventRecorder {
+    fn info(&self) -> PluginInfo {
+        PluginInfo {
+            uuid: format!("recorder:{}", self.action),
+            name: String::from("Event recorder"),
+            description: String::new(),
+            version: (0, 0, 1),
+            author: String::from("tester"),
+            subscriptions: vec![self.action.to_string()],
+            dependencies: vec![],
+            api_version: None,
+        }
+    }
+    fn status(&self) -> PluginStatus { self.status.clone() }
+    fn initialize(&mut self, _root: &mut DesignerCore) {}
+    fn enable(&mut self, _root: &mut DesignerCore) { self.status = PluginStatus::Enabled; }
+    fn disable(&mut self, _root: &mut DesignerCore) { self.status = PluginStatus::Disabled; }
+    fn load_state(&mut self, _storage: &mut dyn IPluginStateStorage) {}
+    fn store_state(&mut self, _storage: &mut dyn IPluginStateStorage) -> Result<(), PluginError> { Ok(()) }
+    fn get_state(&self) -> Value { Value::Null }
+    fn handle_message(&mut self, _root: &mut DesignerCore, _sender: &str, _action: &str, args: Value) -> Result<(), PluginError> {
+        self.events.borrow_mut().push(args);
+        Ok(())
+    }
+    fn as_any(&self) -> &dyn Any { self }
+    fn as_any_mut(&mut self) -> &mut dyn Any { self }
+}
+
+/// Applies watcher changes the way the window does every frame until `done` holds, notifications arrive on another thread.
+fn wait_for_disk_changes(core: &mut DesignerCore, mut done: impl FnMut(&mut DesignerCore) -> bool) -> bool {
+    for _ in 0..200 {
+        if core.with_plugin(|file_manager: &FileManager| file_manager.has_pending_changes()).unwrap() {
+            core.publish_message("test", &FileManagerCommand::ApplyFileChanges);
+        }
+        if done(core) {
+            return true;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(50));
+    }
+    false
+}
+
+#[test]
+fn project_tree_follows_changes_made_outside_of_the_editor() {
+    let dir = temp_project_dir("watcher");
+    let events = Rc::new(RefCell::new(vec![]));
+    let mut core = DesignerCore::headless();
+    core.register_dynamic_plugin(EventRecorder { action: "designer.file_manager.event", events: events.clone(), status: PluginStatus::Disabled }).unwrap();
+    core.initialize_plugins().unwrap();
+    core.publish_message("test", &ProjectManagerCommand::CreateProject { path: dir.to_str().unwrap().to_string(), project: sample_project(), template: None });
+    core.publish_message("test", &ProjectManagerCommand::OpenProject { path: dir.to_str().unwrap().to_string(), save_documents: false });
+    assert!(core.with_plugin(|file_manager: &FileManager| file_manager.watch_mode()).unwrap().is_some());
+    let disk_changes = |events: &Rc<RefCell<Vec<Value>>>| events.borrow().iter().filter_map(|event| serde_json::from_value::<FileManagerEvent>(event.clone()).ok()).filter_map(|event| match event {
+        FileManagerEvent::Disk(change) => Some(change),
+        _ => None,
+    }).collect::<Vec<_>>();
+    let entry_exists = |core: &mut DesignerCore, path: &str| core.with_plugin(|file_manager: &FileManager| file_manager.root.as_ref().and_then(|root| root.get(path)).is_some()).unwrap();
+
+    std::fs::create_dir_all(dir.join("assets").join("images")).unwrap();
+    std::fs::write(dir.join("assets").join("images").join("logo.svg"), "<svg/>").unwrap();
+    std::fs::write(dir.join("notes.txt"), "first").unwrap();
+    assert!(wait_for_disk_changes(&mut core, |core| entry_exists(core, "assets") && entry_exists(core, "notes.txt")));
+    // folders are read when they are expanded.
+    assert!(!entry_exists(&mut core, "assets/images"));
+    core.publish_message("test", &FileManagerCommand::LoadFolder { path: String::from("assets") });
+    core.publish_message("test", &FileManagerCommand::LoadFolder { path: String::from("assets/images") });
+    assert!(entry_exists(&mut core, "assets/images/logo.svg"));
+    let created = disk_changes(&events);
+    assert!(created.contains(&DiskChange::Created { path: String::from("assets"), folder: true }));
+    assert!(!created.iter().any(|change| matches!(change, DiskChange::Created { path, .. } if path.starts_with("assets/"))));
+
+    events.borrow_mut().clear();
+    std::fs::write(dir.join("notes.txt"), "second").unwrap();
+    assert!(wait_for_disk_changes(&mut core, |_| disk_changes(&events).contains(&DiskChange::Modified { path: String::from("notes.txt") })));
+
+    std::fs::remove_file(dir.join("notes.txt")).unwrap();
+    assert!(wait_for_disk_changes(&mut core, |core| !entry_exists(core, "notes.txt")));
+    assert!(disk_changes(&events).contains(&DiskChange::Removed { path: String::from("notes.txt") }));
+
+    // an open document is followed even when its folder is not loaded in the tree.
+    std::fs::create_dir_all(dir.join("docs").join("inner")).unwrap();
+    std::fs::write(dir.join("docs").join("inner").join("guide.md"), "first").unwrap();
+    core.publish_message("test", &DocumentManagerCommand::Open { path: String::from("docs/inner/guide.md") });
+    events.borrow_mut().clear();
+    std::fs::write(dir.join("docs").join("inner").join("guide.md"), "second").unwrap();
+    assert!(wait_for_disk_changes(&mut core, |_| disk_changes(&events).contains(&DiskChange::Modified { path: String::from("docs/inner/guide.md") })));
+    let text = core.with_plugin(|documents: &DocumentManager| documents.document("docs/inner/guide.md").unwrap().buffer.text()).unwrap();
+    assert_eq!(text, "second");
+    assert!(!entry_exists(&mut core, "docs/inner"));
+    std::fs::remove_file(dir.join("docs").join("inner").join("guide.md")).unwrap();
+    assert!(wait_for_disk_changes(&mut core, |_| disk_changes(&events).contains(&DiskChange::Removed { path: String::from("docs/inner/guide.md") })));
+    core.publish_message("test", &DocumentManagerCommand::Close { path: String::from("docs/inner/guide.md") });
+
+    // the fallback notices changes by scanning.
+    let watcher = ProjectWatcher::watch(&dir, WatchMode::Polling, None).unwrap();
+    std::fs::write(dir.join("polled.txt"), "").unwrap();
+    assert!((0..100).any(|_| {
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        watcher.take_changes().iter().any(|change| change.path.ends_with("polled.txt"))
+    }));
+
+    core.publish_message("test", &ProjectManagerCommand::CloseProject { save_documents: false });
+    assert!(core.with_plugin(|file_manager: &FileManager| file_manager.watch_mode()).unwrap().is_none());
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn ignored_files_are_hidden_and_folders_are_read_on_demand() {
+    let dir = temp_project_dir("ignore_rules");
+    let mut project = sample_project();
+    project.exclude = vec![String::from("docs/"), String::from("[z-a]")];
+    let mut core = DesignerCore::headless();
+    core.initialize_plugins().unwrap();
+    core.publish_message("test", &ProjectManagerCommand::CreateProject { path: dir.to_str().unwrap().to_string(), project, template: None });
+    std::fs::write(dir.join(".gitignore"), "*.log\nbuild/\n!keep.log\n").unwrap();
+    for file in ["a.log", "keep.log", "build/out.bin", "target/debug/app", "node_modules/left-pad/index.js", ".git/HEAD", "docs/readme.md", "src/nested/secret.txt", "src/nested/ok.txt"] {
+        let path = dir.join(file);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, "").unwrap();
+    }
+    std::fs::write(dir.join("src").join("nested").join(".gitignore"), "secret.txt\n").unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        std::fs::write(dir.join("src").join(std::ffi::OsStr::from_bytes(b"latin1-\xe9.txt")), "").unwrap();
+        std::os::unix::fs::symlink(dir.join("src"), dir.join("src").join("loop")).unwrap();
+    }
+
+    core.publish_message("test", &ProjectManagerCommand::OpenProject { path: dir.to_str().unwrap().to_string(), save_documents: false });
+    let names = |core: &mut DesignerCore, folder: &str| core.with_plugin(|file_manager: &FileManager| match &file_manager.root.as_ref().unwrap().get(folder).unwrap().file_type {
+        FileEntryType::Folder(children) => children.iter().map(|child| child.name.clone()).collect::<Vec<_>>(),
+        _ => vec![],
+    }).unwrap();
+    assert_eq!(names(&mut core, ""), vec!["src", ".gitignore", "keep.log", "project.blueproj"]);
+    let tree = core.with_plugin(|file_manager: &FileManager| file_manager.root.clone().unwrap()).unwrap();
+    assert!(matches!(tree.get("src").unwrap().file_type, FileEntryType::UnloadedFolder));
+    // the invalid exclusion is reported and skipped.
+    assert!(core.with_plugin(|logger: &Logger| logger.logs.iter().any(|(_, category, content)| category == "Warning" && content.contains("\"[z-a]\""))).unwrap());
+
+    core.publish_message("test", &FileManagerCommand::LoadFolder { path: String::from("src") });
+    core.publish_message("test", &FileManagerCommand::LoadFolder { path: String::from("src/nested") });
+    assert_eq!(names(&mut core, "src/nested"), vec![".gitignore", "ok.txt"]);
+    let listed = core.request::<Vec<FileEntry>>("test", &FileManagerRequest::ListDirectory { path: String::from("src/nested") }).unwrap();
+    assert_eq!(listed.len(), 2);
+    #[cfg(unix)]
+    {
+        let tree = core.with_plugin(|file_manager: &FileManager| file_manager.root.clone().unwrap()).unwrap();
+        assert!(tree.get("src/loop").unwrap().problem.as_deref().unwrap().contains("loop"));
+        let latin1 = names(&mut core, "src").into_iter().find(|name| name.starts_with("latin1-")).unwrap();
+        assert!(tree.get(&format!("src/{}", latin1)).unwrap().problem.as_deref().unwrap().contains("UTF-8"));
+        core.publish_message("test", &FileManagerCommand::LoadFolder { path: String::from("src/loop") });
+        assert!(core.with_plugin(|file_manager: &FileManager| matches!(file_manager.root.as_ref().unwrap().get("src/loop").unwrap().file_type, FileEntryType::UnloadedFolder)).unwrap());
+    }
+
+    // exclusions can bring a default one back, the tree keeps the loaded folders.
+    let mut project = core.request::<Option<Project>>("test", &ProjectManagerRequest::Project).unwrap().unwrap();
+    project.exclude = vec![String::from("!target/")];
+    core.publish_message("test", &ProjectManagerCommand::UpdateProject { project });
+    assert!(names(&mut core, "").contains(&String::from("target")));
+    assert!(names(&mut core, "").contains(&String::from("docs")));
+    assert_eq!(names(&mut core, "src/nested"), vec![".gitignore", "ok.txt"]);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn files_open_in_buffers_that_track_dirty_state_and_follow_the_disk() {
+    let dir = temp_project_dir("documents");
+    let mut core = DesignerCore::headless();
+    core.initialize_plugins().unwrap();
+    core.publish_message("test", &ProjectManagerCommand::CreateProject { path: dir.to_str().unwrap().to_string(), project: sample_project(), template: None });
+    core.publish_message("test", &ProjectManagerCommand::OpenProject { path: dir.to_str().unwrap().to_string(), save_documents: false });
+    std::fs::create_dir_all(dir.join("src")).unwrap();
+    std::fs::write(dir.join("src").join("main.js"), "let a = 1;\n").unwrap();
+    let document = |core: &mut DesignerCore, path: &str| core.request::<Option<DocumentSnapshot>>("test", &DocumentManagerRequest::Document { path: path.to_string() }).unwrap();
+
+    core.publish_message("test", &FileManagerCommand::OpenFile(String::from("./src/main.js")));
+    assert_eq!(core.with_plugin(|wm: &WindowManager| wm.opened_documents.clone()).unwrap(), vec![String::from("src/main.js")]);
+    let opened = document(&mut core, "src/main.js").unwrap();
+    assert_eq!((opened.language, opened.dirty, opened.text.as_str()), (Language::JavaScript, false, "let a = 1;\n"));
+
+    core.publish_message("test", &DocumentManagerCommand::Edit { path: String::from("src/main.js"), changes: vec![TextChange { start: 8, end: 9, text: String::from("42") }], kind: EditKind::Other });
+    assert!(document(&mut core, "src/main.js").unwrap().dirty);
+    assert_eq!(core.request::<Vec<String>>("test", &DocumentManagerRequest::UnsavedDocuments).unwrap(), vec![String::from("src/main.js")]);
+    #[cfg(unix)]
+    std::fs::set_permissions(dir.join("src").join("main.js"), std::os::unix::fs::PermissionsExt::from_mode(0o755)).unwrap();
+    core.publish_message("test", &DocumentManagerCommand::SaveAll);
+    assert_eq!(std::fs::read_to_string(dir.join("src").join("main.js")).unwrap(), "let a = 42;\n");
+    assert!(!document(&mut core, "src/main.js").unwrap().dirty);
+    // the file is replaced by a temporary one that does not stay behind.
+    assert_eq!(std::fs::read_dir(dir.join("src")).unwrap().count(), 1);
+    #[cfg(unix)]
+    assert_eq!(std::os::unix::fs::PermissionsExt::mode(&std::fs::metadata(dir.join("src").join("main.js")).unwrap().permissions()) & 0o777, 0o755);
+
+    // buffers follow renames, a clean buffer takes changes made by other programs and a modified one is flagged.
+    core.publish_message("test", &FileManagerCommand::Rename { path: String::from("src"), new_name: String::from("lib") });
+    assert!(document(&mut core, "src/main.js").is_none());
+    std::fs::write(dir.join("lib").join("main.js"), "let b = 2;\n").unwrap();
+    core.publish_message("test", &FileManagerEvent::Disk(DiskChange::Modified { path: String::from("lib/main.js") }));
+    assert_eq!(document(&mut core, "lib/main.js").unwrap().text, "let b = 2;\n");
+    core.publish_message("test", &DocumentManagerCommand::Edit { path: String::from("lib/main.js"), changes: vec![TextChange::insert(0, "// edited\n")], kind: EditKind::Other });
+    std::fs::write(dir.join("lib").join("main.js"), "let c = 3;\n").unwrap();
+    core.publish_message("test", &FileManagerEvent::Disk(DiskChange::Modified { path: String::from("lib/main.js") }));
+    let conflicted = document(&mut core, "lib/main.js").unwrap();
+    assert_eq!((conflicted.disk_state, conflicted.text.as_str()), (DiskState::Modified, "// edited\nlet b = 2;\n"));
+    core.publish_message("test", &DocumentManagerCommand::Autosave);
+    assert_eq!(std::fs::read_to_string(dir.join("lib").join("main.js")).unwrap(), "let c = 3;\n");
+    core.publish_message("test", &DocumentManagerCommand::Reload { path: String::from("lib/main.js") });
+    assert!(!document(&mut core, "lib/main.js").unwrap().dirty);
+
+    std::fs::write(dir.join("data.bin"), [0xff, 0xfe, 0x00]).unwrap();
+    core.publish_message("test", &FileManagerCommand::OpenFile(String::from("data.bin")));
+    assert!(document(&mut core, "data.bin").is_none());
+    // deleting a folder closes the clean buffers of its files, the ones with unsaved changes stay open.
+    std::fs::write(dir.join("lib").join("other.js"), "").unwrap();
+    core.publish_message("test", &DocumentManagerCommand::Open { path: String::from("lib/other.js") });
+    core.publish_message("test", &DocumentManagerCommand::Edit { path: String::from("lib/other.js"), changes: vec![TextChange::insert(0, "unsaved")], kind: EditKind::Other });
+    core.publish_message("test", &FileManagerCommand::Delete { path: String::from("lib") });
+    assert_eq!(core.request::<Vec<String>>("test", &DocumentManagerRequest::OpenDocuments).unwrap(), vec![String::from("lib/other.js")]);
+    assert_eq!(document(&mut core, "lib/other.js").unwrap().disk_state, DiskState::Removed);
+
+    let source = "const s = 'a\\'b'; // note\n{\"key\": [1.5, true]}";
+    let kinds = |language, text: &str| tokenize(language, text).into_iter().filter(|token| token.kind != TokenKind::Plain).map(|token| (token.kind, text[token.range].to_string())).collect::<Vec<_>>();
+    assert_eq!(kinds(Language::JavaScript, source)[..3], [(TokenKind::Keyword, String::from("const")), (TokenKind::Punctuation, String::from("=")), (TokenKind::String, String::from("'a\\'b'"))]);
+    assert!(kinds(Language::JavaScript, source).contains(&(TokenKind::Comment, String::from("// note"))));
+    assert_eq!(kinds(Language::Json, "{\"key\": [1.5, true]}")[1..3], [(TokenKind::Property, String::from("\"key\"")), (TokenKind::Punctuation, String::from(":"))]);
+    assert!(kinds(Language::Python, "def f():\n    return f\"{x}\" # done").contains(&(TokenKind::String, String::from("f\"{x}\""))));
+    assert_eq!(Language::from_path("src/app.TSX"), Language::TypeScript);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn buffers_edit_at_every_cursor_and_undo_in_groups() {
+    let mut buffer = Buffer::new("alpha\nbeta\ngamma");
+    buffer.set_selections(vec![Selection::cursor(0), Selection::cursor(6), Selection::cursor(11)]);
+    for character in ["- ", "x"] {
+        buffer.apply(buffer.insert_changes(character), EditKind::Typing).unwrap();
+    }
+    assert_eq!(buffer.text(), "- xalpha\n- xbeta\n- xgamma");
+    assert_eq!(buffer.selections().iter().map(|selection| buffer.line_column(selection.head)).collect::<Vec<_>>(), vec![(0, 3), (1, 3), (2, 3)]);
+    buffer.apply(buffer.delete_changes(false, false), EditKind::Deleting).unwrap();
+    assert_eq!(buffer.text(), "- alpha\n- beta\n- gamma");
+
+    // typing is undone as one step, deleting as another, the text is clean again at the saved point.
+    buffer.undo().unwrap();
+    assert_eq!(buffer.text(), "- xalpha\n- xbeta\n- xgamma");
+    let changes = buffer.undo().unwrap();
+    assert_eq!(buffer.text(), "alpha\nbeta\ngamma");
+    assert!(!buffer.is_dirty() && !buffer.can_undo());
+    assert_eq!(changes.len(), 6);
+    buffer.redo().unwrap();
+    assert!(buffer.is_dirty());
+    buffer.mark_saved();
+    buffer.apply(vec![TextChange::insert(0, "!")], EditKind::Typing).unwrap();
+    buffer.undo().unwrap();
+    assert_eq!((buffer.text().as_str(), buffer.is_dirty()), ("- xalpha\n- xbeta\n- xgamma", false));
+    assert!(buffer.apply(vec![TextChange::delete(0..1000)], EditKind::Other).is_err());
+
+    // cursors move by word and line, and merge when they meet.
+    buffer.set_selections(vec![Selection::cursor(0)]);
+    buffer.set_selections(buffer.moved_selections(Motion::WordRight, false));
+    assert_eq!(buffer.selections(), [Selection::cursor(1)]);
+    buffer.set_selections(buffer.with_cursor_on_adjacent_line(true));
+    buffer.set_selections(buffer.moved_selections(Motion::LineEnd, true));
+    assert_eq!(buffer.selected_text(), " xalpha\n xbeta");
+    buffer.set_selections(buffer.moved_selections(Motion::DocumentStart, false));
+    assert_eq!(buffer.selections().len(), 1);
+
+    // a multi-megabyte text is edited in place.
+    let line = "const value = compute(index, \"text\"); // comment\n";
+    let mut large = Buffer::new(&line.repeat(100_000));
+    let middle = large.position(50_000, 6);
+    large.apply(vec![TextChange { start: middle, end: middle + 5, text: String::from("other") }], EditKind::Other).unwrap();
+    assert_eq!(large.line(50_000), "const other = compute(index, \"text\"); // comment");
+    assert_eq!(large.len_lines(), 100_001);
+
+    let (_, state) = tokenize_line(Language::JavaScript, "let a = 1; /* open", LineState::Normal);
+    assert_eq!(state, LineState::BlockComment);
+    let (tokens, state) = tokenize_line(Language::JavaScript, "still */ let", state);
+    assert_eq!((tokens[0].kind, tokens[0].range.clone(), state), (TokenKind::Comment, 0..8, LineState::Normal));
+}
+
+#[test]
+fn document_edits_publish_changes_and_follow_undo() {
+    let dir = temp_project_dir("document_edits");
+    let events = Rc::new(RefCell::new(vec![]));
+    let mut core = DesignerCore::headless();
+    core.register_dynamic_plugin(EventRecorder { action: "designer.document_manager.event", events: events.clone(), status: PluginStatus::Disabled }).unwrap();
+    core.initialize_plugins().unwrap();
+    core.publish_message("test", &ProjectManagerCommand::CreateProject { path: dir.to_str().unwrap().to_string(), project: sample_project(), template: None });
+    core.publish_message("test", &ProjectManagerCommand::OpenProject { path: dir.to_str().unwrap().to_string(), save_documents: false });
+    std::fs::write(dir.join("notes.txt"), "one\ntwo\n").unwrap();
+    core.publish_message("test", &DocumentManagerCommand::Open { path: String::from("notes.txt") });
+
+    core.publish_message("test", &DocumentManagerCommand::Edit { path: String::from("notes.txt"), changes: vec![TextChange::insert(4, "and ")], kind: EditKind::Typing });
+    core.publish_message("test", &DocumentManagerCommand::Undo { path: String::from("notes.txt") });
+    core.publish_message("test", &DocumentManagerCommand::Redo { path: String::from("notes.txt") });
+    // reloading the file only sends the part that differs from the buffer.
+    std::fs::write(dir.join("notes.txt"), "one\nand three\n").unwrap();
+    core.publish_message("test", &DocumentManagerCommand::Reload { path: String::from("notes.txt") });
+    let changed = events.borrow().iter().filter_map(|event| serde_json::from_value::<DocumentManagerEvent>(event.clone()).ok()).filter_map(|event| match event {
+        DocumentManagerEvent::Changed { version, changes, .. } => Some((version, changes)),
+        _ => None,
+    }).collect::<Vec<_>>();
+    assert_eq!(changed, vec![(1, vec![TextChange::insert(4, "and ")]), (2, vec![TextChange::delete(4..8)]), (3, vec![TextChange::insert(4, "and ")]), (4, vec![TextChange { start: 9, end: 11, text: String::from("hree") }])]);
+    let lines = core.with_plugin_mut(|documents: &mut DocumentManager| documents.highlight_lines("notes.txt", 1..5)).unwrap();
+    assert_eq!(lines.into_iter().map(|(text, _)| text).collect::<Vec<_>>(), vec!["and three", ""]);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn file_operations_layouts_and_project_settings_are_undone_from_the_history() {
+    let dir = temp_project_dir("history");
+    let mut core = DesignerCore::headless();
+    core.initialize_plugins().unwrap();
+    core.publish_message("test", &ProjectManagerCommand::CreateProject { path: dir.to_str().unwrap().to_string(), project: sample_project(), template: None });
+    core.publish_message("test", &ProjectManagerCommand::OpenProject { path: dir.to_str().unwrap().to_string(), save_documents: false });
+    assert_eq!(core.history().undo_label(), None);
+
+    core.publish_message("test", &FileManagerCommand::CreateFile { path: String::from("main.js") });
+    core.publish_message("test", &FileManagerCommand::Rename { path: String::from("main.js"), new_name: String::from("app.js") });
+    assert_eq!(core.history().undo_label(), Some("Rename \"main.js\" to \"app.js\""));
+    core.publish_message("test", &CoreCommand::Undo);
+    assert!(dir.join("main.js").exists() && !dir.join("app.js").exists());
+    assert_eq!(core.history().redo_label(), Some("Rename \"main.js\" to \"app.js\""));
+    core.publish_message("test", &CoreCommand::Redo);
+    assert!(dir.join("app.js").exists());
+
+    // a redone delete goes to a new trash item, undoing it again restores that one.
+    core.publish_message("test", &FileManagerCommand::Delete { path: String::from("app.js") });
+    core.publish_message("test", &CoreCommand::Undo);
+    assert!(dir.join("app.js").exists());
+    core.publish_message("test", &CoreCommand::Redo);
+    assert!(!dir.join("app.js").exists());
+    core.publish_message("test", &CoreCommand::Undo);
+    assert!(dir.join("app.js").exists());
+    assert!(core.request::<Vec<TrashedItem>>("test", &FileManagerRequest::Trash).unwrap().is_empty());
+
+    // a new action forgets what could be redone.
+    core.publish_message("test", &CoreCommand::Redo);
+    core.publish_message("test", &FileManagerCommand::CreateFolder { path: String::from("lib") });
+    assert_eq!(core.history().redo_label(), None);
+
+    let mut project = sample_project();
+    project.name = "Renamed".into();
+    core.publish_message("test", &ProjectManagerCommand::UpdateProject { project: project.clone() });
+    core.publish_message("test", &CoreCommand::Undo);
+    assert_eq!(project_file::load(&dir).unwrap().project, sample_project());
+
+    let layout = |tabs: &[&str]| serde_json::json!({ "surfaces": [{ "tabs": tabs, "rect": { "min": [0, 0] }, "active": 0 }] });
+    assert_eq!(WindowManager::layout_structure(&layout(&["a"])), WindowManager::layout_structure(&serde_json::json!({ "surfaces": [{ "tabs": ["a"], "rect": null, "active": 3 }] })));
+    core.publish_message("test", &WindowManagerCommand::LayoutChanged { before: layout(&["a"]), after: layout(&["a", "b"]) });
+    let recorded = serde_json::to_string(core.history().undo_entries().last().unwrap()).unwrap();
+    assert!(!recorded.contains("rect"));
+    core.publish_message("test", &CoreCommand::Undo);
+    let restored = core.with_plugin(|wm: &WindowManager| wm.dock_state.clone()).unwrap().unwrap();
+    assert_eq!(WindowManager::layout_structure(&restored), WindowManager::layout_structure(&layout(&["a"])));
+
+    // a structure turns back into a dock state egui_dock can read.
+    let mut dock = egui_dock::DockState::new(vec![String::from("a"), String::from("b")]);
+    let [_, right] = dock.main_surface_mut().split_right(egui_dock::NodeIndex::root(), 0.3, vec![String::from("c")]);
+    dock.main_surface_mut().split_below(right, 0.5, vec![String::from("d")]);
+    dock.add_window(vec![String::from("e")]);
+    let structure = WindowManager::layout_structure(&serde_json::to_value(&dock).unwrap());
+    let rebuilt = serde_json::from_value::<egui_dock::DockState<String>>(WindowManager::layout_from_structure(&structure)).unwrap();
+    assert_eq!(WindowManager::layout_structure(&serde_json::to_value(&rebuilt).unwrap()), structure);
+
+    let logs = core.with_plugin(|logger: &Logger| logger.logs.iter().filter(|(_, category, _)| category == "History").map(|(_, _, content)| content.clone()).collect::<Vec<_>>()).unwrap();
+    assert!(logs.contains(&String::from("Undo: Rename \"main.js\" to \"app.js\"")));
+    assert!(logs.contains(&String::from("Undo: Change the window layout")));
+
+    // a step that cannot be replayed is dropped with an error.
+    std::fs::remove_dir_all(dir.join("lib")).unwrap();
+    let errors = core.with_plugin(|logger: &Logger| logger.logs.iter().filter(|(_, category, _)| category == "Error").count()).unwrap();
+    while core.history().undo_label().is_some_and(|label| label != "Create \"lib\"") {
+        core.publish_message("test", &CoreCommand::Undo);
+    }
+    core.publish_message("test", &CoreCommand::Undo);
+    assert_eq!(core.with_plugin(|logger: &Logger| logger.logs.iter().filter(|(_, category, _)| category == "Error").count()).unwrap(), errors + 1);
+    assert_ne!(core.history().redo_label(), Some("Create \"lib\""));
+
+    core.publish_message("test", &ProjectManagerCommand::CloseProject { save_documents: false });
+    assert_eq!(core.history().undo_label(), None);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+/// Applies search results the way the window does every frame until the search finished.
+fn wait_for_search(core: &mut DesignerCore) -> bool {
+    for _ in 0..200 {
+        if core.with_plugin(|search: &SearchManager| search.has_pending_results()).unwrap() {
+            core.publish_message("test", &SearchManagerCommand::ApplyResults);
+        }
+        if !core.with_plugin(|search: &SearchManager| search.searching).unwrap() {
+            return true;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(20));
+    }
+    false
+}
+
+#[test]
+fn project_search_streams_matches_and_replacements_are_previewed_and_undone() {
+    let dir = temp_project_dir("search");
+    let mut core = DesignerCore::headless();
+    core.initialize_plugins().unwrap();
+    core.publish_message("test", &ProjectManagerCommand::CreateProject { path: dir.to_str().unwrap().to_string(), project: sample_project(), template: None });
+    core.publish_message("test", &ProjectManagerCommand::OpenProject { path: dir.to_str().unwrap().to_string(), save_documents: false });
+    std::fs::create_dir_all(dir.join("src")).unwrap();
+    std::fs::create_dir_all(dir.join("node_modules")).unwrap();
+    std::fs::write(dir.join("main.js"), "let foo = 1;\nfoo(foo);\n").unwrap();
+    std::fs::write(dir.join("src").join("util.js"), "// Foo\nfoobar();\n").unwrap();
+    std::fs::write(dir.join("src").join("util.min.js"), "foo").unwrap();
+    std::fs::write(dir.join("node_modules").join("lib.js"), "foo").unwrap();
+    std::fs::write(dir.join("notes.txt"), "foo").unwrap();
+
+    let options = SearchOptions { query: String::from("foo"), whole_word: true, include: vec![String::from("*.js")], exclude: vec![String::from("*.min.js")], ..Default::default() };
+    core.publish_message("test", &SearchManagerCommand::Search(options.clone()));
+    assert!(wait_for_search(&mut core));
+    let results = core.request::<Vec<FileMatches>>("test", &SearchManagerRequest::Results).unwrap();
+    assert_eq!(results.iter().map(|file| (file.path.as_str(), file.matches.len())).collect::<Vec<_>>(), vec![("main.js", 3), ("src/util.js", 1)]);
+    assert_eq!(results[0].matches[2], SearchMatch { line: 1, column: 4, length: 3, line_text: String::from("foo(foo);") });
+
+    core.publish_message("test", &SearchManagerCommand::Search(SearchOptions { case_sensitive: true, ..options.clone() }));
+    assert!(wait_for_search(&mut core));
+    assert_eq!(core.with_plugin(|search: &SearchManager| search.match_count()).unwrap(), 3);
+    core.publish_message("test", &SearchManagerCommand::Search(SearchOptions { query: String::from(r"(\w+)\(foo\)"), regex: true, whole_word: false, ..options.clone() }));
+    assert!(wait_for_search(&mut core));
+    let preview = core.request::<Vec<ReplacementPreview>>("test", &SearchManagerRequest::ReplacePreview { replacement: String::from("$1(bar)") }).unwrap();
+    assert_eq!(preview, vec![ReplacementPreview { path: String::from("main.js"), line: 1, before: String::from("foo(foo);"), after: String::from("foo(bar);") }]);
+    // a match running over a line break previews every line it joins.
+    core.publish_message("test", &SearchManagerCommand::Search(SearchOptions { query: String::from(r"1;\nfoo"), regex: true, whole_word: false, ..options.clone() }));
+    assert!(wait_for_search(&mut core));
+    let preview = core.request::<Vec<ReplacementPreview>>("test", &SearchManagerRequest::ReplacePreview { replacement: String::from("2;\nbar") }).unwrap();
+    assert_eq!(preview, vec![ReplacementPreview { path: String::from("main.js"), line: 0, before: String::from("let foo = 1;\nfoo(foo);"), after: String::from("let foo = 2;\nbar(foo);") }]);
+    core.publish_message("test", &SearchManagerCommand::Search(SearchOptions { query: String::from("(unclosed"), regex: true, ..options.clone() }));
+    assert!(!core.with_plugin(|search: &SearchManager| search.searching).unwrap());
+
+    // an open buffer is searched and replaced instead of its file, it is saved when it had no other changes.
+    core.publish_message("test", &DocumentManagerCommand::Open { path: String::from("main.js") });
+    core.publish_message("test", &SearchManagerCommand::Search(options.clone()));
+    assert!(wait_for_search(&mut core));
+    core.publish_message("test", &SearchManagerCommand::Replace { replacement: String::from("bar"), skipped: vec![] });
+    assert_eq!(std::fs::read_to_string(dir.join("main.js")).unwrap(), "let bar = 1;\nbar(bar);\n");
+    assert_eq!(std::fs::read_to_string(dir.join("src").join("util.js")).unwrap(), "// bar\nfoobar();\n");
+    assert_eq!(std::fs::read_to_string(dir.join("src").join("util.min.js")).unwrap(), "foo");
+    assert_eq!(core.history().undo_label(), Some("Replace \"foo\" with \"bar\" in 2 file(s)"));
+    // the undo step keeps the replaced ranges, not the texts of the files.
+    let recorded = serde_json::to_string(core.history().undo_entries().last().unwrap()).unwrap();
+    assert!(!recorded.contains("let foo = 1;") && recorded.contains("\"changes\""));
+    assert!(wait_for_search(&mut core));
+    assert_eq!(core.with_plugin(|search: &SearchManager| search.match_count()).unwrap(), 0);
+
+    core.publish_message("test", &CoreCommand::Undo);
+    assert_eq!(std::fs::read_to_string(dir.join("main.js")).unwrap(), "let foo = 1;\nfoo(foo);\n");
+    assert_eq!(std::fs::read_to_string(dir.join("src").join("util.js")).unwrap(), "// Foo\nfoobar();\n");
+    let snapshot = core.request::<Option<DocumentSnapshot>>("test", &DocumentManagerRequest::Document { path: String::from("main.js") }).unwrap().unwrap();
+    assert!(!snapshot.dirty && snapshot.text == "let foo = 1;\nfoo(foo);\n");
+    // a file changed since then is not overwritten by the redo.
+    std::fs::write(dir.join("src").join("util.js"), "changed").unwrap();
+    core.publish_message("test", &CoreCommand::Redo);
+    assert_eq!(std::fs::read_to_string(dir.join("main.js")).unwrap(), "let foo = 1;\nfoo(foo);\n");
+    assert_eq!(core.history().redo_label(), None);
+
+    core.publish_message("test", &DocumentManagerCommand::Reveal { path: String::from("main.js"), line: 1, column: 4, length: 3 });
+    let selections = core.with_plugin(|documents: &DocumentManager| documents.document("main.js").unwrap().buffer.selections().to_vec()).unwrap();
+    assert_eq!(selections, vec![Selection { anchor: 17, head: 20 }]);
+    assert_eq!(core.with_plugin(|wm: &WindowManager| wm.revealed_lines.clone()).unwrap(), vec![(String::from("main.js"), 1)]);
+    assert_eq!(TextChange::between("let foo = 1;", "let bar = 1;"), TextChange { start: 4, end: 7, text: String::from("bar") });
+    assert_eq!(TextChange::between("aaa", "aa"), TextChange::delete(2..3));
+
+    // a file that cannot be written stops the replacement, the files changed before it can still be undone.
+    std::fs::write(dir.join("src").join("util.js"), "// Foo\nfoobar();\n").unwrap();
+    std::fs::create_dir(dir.join("src").join("util.js.tmp")).unwrap();
+    core.publish_message("test", &SearchManagerCommand::Search(options.clone()));
+    assert!(wait_for_search(&mut core));
+    core.publish_message("test", &SearchManagerCommand::Replace { replacement: String::from("bar"), skipped: vec![] });
+    assert_eq!(std::fs::read_to_string(dir.join("main.js")).unwrap(), "let bar = 1;\nbar(bar);\n");
+    assert_eq!(std::fs::read_to_string(dir.join("src").join("util.js")).unwrap(), "// Foo\nfoobar();\n");
+    assert_eq!(core.history().undo_label(), Some("Replace \"foo\" with \"bar\" in 1 file(s)"));
+    core.publish_message("test", &CoreCommand::Undo);
+    assert_eq!(std::fs::read_to_string(dir.join("main.js")).unwrap(), "let foo = 1;\nfoo(foo);\n");
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn node_graphs_check_port_types_copy_nodes_and_undo_edits() {
+    let dir = temp_project_dir("node_graph");
+    let received = Rc::new(RefCell::new(vec![]));
+    let mut core = DesignerCore::headless();
+    core.register_dynamic_plugin(RecordingPlugin { uuid: "script-a", received: received.clone(), status: PluginStatus::Disabled }).unwrap();
+    core.initialize_plugins().unwrap();
+    core.publish_message("test", &ProjectManagerCommand::CreateProject { path: dir.to_str().unwrap().to_string(), project: sample_project(), template: None });
+    core.publish_message("test", &ProjectManagerCommand::OpenProject { path: dir.to_str().unwrap().to_string(), save_documents: false });
+    let number = NodeType { id: String::from("script.number"), title: String::from("Number"), category: String::from("Script"), inputs: vec![], outputs: vec![PortDefinition::new("value", "Value", "number")], owner: String::new() };
+    core.publish_message("test", &NodeGraphCommand::RegisterNodeTypes { owner: String::from("script-a"), types: vec![number.clone()] });
+    let taken = NodeType { id: String::from("core.add"), ..number.clone() };
+    core.publish_message("test", &NodeGraphCommand::RegisterNodeTypes { owner: String::from("script-a"), types: vec![taken] });
+    let types = core.request::<Vec<NodeType>>("test", &NodeGraphRequest::NodeTypes).unwrap();
+    assert!(types.iter().any(|node_type| node_type.id == "script.number" && node_type.owner == "script-a"));
+    assert_eq!(types.iter().find(|node_type| node_type.id == "core.add").unwrap().owner, NodeGraphManager::UUID);
+
+    // a new empty file is an empty graph, opening it asks the window for a node editor.
+    core.publish_message("test", &FileManagerCommand::CreateFile { path: String::from("logic.bluegraph") });
+    core.publish_message("test", &FileManagerCommand::OpenFile(String::from("logic.bluegraph")));
+    assert_eq!(core.with_plugin(|wm: &WindowManager| wm.opened_graphs.clone()).unwrap(), vec![String::from("logic.bluegraph")]);
+    assert!(core.with_plugin(|documents: &DocumentManager| documents.documents().is_empty()).unwrap());
+    let edit = |core: &mut DesignerCore, edit: GraphEdit| core.publish_message("test", &NodeGraphCommand::Edit { path: String::from("logic.bluegraph"), edit });
+    edit(&mut core, GraphEdit::AddNode { type_id: String::from("script.number"), position: [0., 0.] });
+    edit(&mut core, GraphEdit::AddNode { type_id: String::from("core.join"), position: [200., 0.] });
+    edit(&mut core, GraphEdit::AddNode { type_id: String::from("core.to_text"), position: [200., 100.] });
+    edit(&mut core, GraphEdit::AddNode { type_id: String::from("missing.type"), position: [0., 0.] });
+    let graph = |core: &mut DesignerCore| core.request::<Option<Graph>>("test", &NodeGraphRequest::Graph { path: String::from("logic.bluegraph") }).unwrap().unwrap();
+    assert_eq!(graph(&mut core).nodes.iter().map(|node| node.id).collect::<Vec<_>>(), vec![1, 2, 3]);
+
+    // a number does not go into text, but into a port of any type, and an input keeps its last connection.
+    let types = core.request::<Vec<NodeType>>("test", &NodeGraphRequest::NodeTypes).unwrap();
+    assert_eq!(graph(&mut core).check_connection(&types, &PortRef::new(1, "value"), &PortRef::new(2, "a")), Err(GraphError::TypeMismatch { output: String::from("number"), input: String::from("string") }));
+    edit(&mut core, GraphEdit::Connect { from: PortRef::new(1, "value"), to: PortRef::new(2, "a") });
+    edit(&mut core, GraphEdit::Connect { from: PortRef::new(1, "value"), to: PortRef::new(3, "value") });
+    edit(&mut core, GraphEdit::Connect { from: PortRef::new(3, "text"), to: PortRef::new(2, "a") });
+    edit(&mut core, GraphEdit::Connect { from: PortRef::new(3, "text"), to: PortRef::new(2, "b") });
+    edit(&mut core, GraphEdit::Connect { from: PortRef::new(1, "value"), to: PortRef::new(2, "b") });
+    let connections = graph(&mut core).connections.iter().map(|connection| (connection.from.node, connection.to.node, connection.to.port.clone())).collect::<Vec<_>>();
+    assert_eq!(connections, vec![(1, 3, String::from("value")), (3, 2, String::from("a")), (3, 2, String::from("b"))]);
+    assert!(core.with_plugin(|graphs: &NodeGraphManager| graphs.has_unsaved_graphs()).unwrap());
+
+    // copying keeps the connections between the copied nodes only, pasted nodes get new ids.
+    let clipboard = graph(&mut core).copy(&[1, 3]);
+    assert_eq!((clipboard.nodes.len(), clipboard.connections.len()), (2, 1));
+    edit(&mut core, GraphEdit::Paste { clipboard, offset: [0., 300.] });
+    let pasted = graph(&mut core);
+    assert_eq!(pasted.nodes.iter().map(|node| (node.id, node.position)).skip(3).collect::<Vec<_>>(), vec![(4, [0., 300.]), (5, [200., 400.])]);
+    assert!(pasted.connections.iter().any(|connection| connection.from == PortRef::new(4, "value") && connection.to == PortRef::new(5, "value")));
+    edit(&mut core, GraphEdit::RemoveNodes { nodes: vec![3] });
+    assert_eq!(graph(&mut core).connections.len(), 1);
+    assert_eq!(core.history().undo_label(), Some("Remove 1 node(s)"));
+    core.publish_message("test", &CoreCommand::Undo);
+    core.publish_message("test", &CoreCommand::Undo);
+    assert_eq!(graph(&mut core), Graph { nodes: pasted.nodes[..3].to_vec(), connections: pasted.connections[..3].to_vec(), ..pasted.clone() });
+    core.publish_message("test", &CoreCommand::Redo);
+    assert_eq!(graph(&mut core), pasted);
+
+    // the file round trips, node types of a disabled plugin are hidden but their nodes are kept.
+    core.publish_message("test", &NodeGraphCommand::Save { path: String::from("logic.bluegraph") });
+    assert!(!core.with_plugin(|graphs: &NodeGraphManager| graphs.has_unsaved_graphs()).unwrap());
+    assert_eq!(Graph::from_json(&std::fs::read_to_string(dir.join("logic.bluegraph")).unwrap()).unwrap(), pasted);
+    assert!(!dir.join("logic.bluegraph.tmp").exists());
+    assert_eq!(Graph::from_json("{\"version\": 9}"), Err(GraphError::UnsupportedVersion(9)));
+    core.disable_plugin("script-a").unwrap();
+    let types = core.request::<Vec<NodeType>>("test", &NodeGraphRequest::NodeTypes).unwrap();
+    assert!(!types.iter().any(|node_type| node_type.id == "script.number") && types.iter().any(|node_type| node_type.id == "core.log"));
+    assert_eq!(graph(&mut core).nodes.len(), 5);
+
+    // graphs follow renamed files.
+    core.publish_message("test", &FileManagerCommand::Rename { path: String::from("logic.bluegraph"), new_name: String::from("flow.bluegraph") });
+    assert!(core.with_plugin(|graphs: &NodeGraphManager| graphs.graph("flow.bluegraph").is_some()).unwrap());
+
+    // a graph that cannot be saved keeps the project open.
+    core.publish_message("test", &NodeGraphCommand::Edit { path: String::from("flow.bluegraph"), edit: GraphEdit::RemoveNodes { nodes: vec![1] } });
+    std::fs::remove_file(dir.join("flow.bluegraph")).unwrap();
+    std::fs::create_dir(dir.join("flow.bluegraph")).unwrap();
+    core.publish_message("test", &ProjectManagerCommand::CloseProject { save_documents: true });
+    assert_eq!(core.request::<Option<PathBuf>>("test", &ProjectManagerRequest::ProjectPath).unwrap(), Some(dir.clone()));
+    assert_eq!(core.request::<Vec<String>>("test", &NodeGraphRequest::UnsavedGraphs).unwrap(), vec![String::from("flow.bluegraph")]);
+    std::fs::remove_dir_all(dir).unwrap();
+}